@@ -9,6 +9,8 @@ type G1 = pasta_curves::pallas::Point;
 type G2 = pasta_curves::vesta::Point;
 type S1 = nova_snark::spartan_with_ipa_pc::RelaxedR1CSSNARK<G1>;
 type S2 = nova_snark::spartan_with_ipa_pc::RelaxedR1CSSNARK<G2>;
+type S1LowMemory = nova_snark::spartan_with_ipa_pc::LowMemoryRelaxedR1CSSNARK<G1>;
+type S2LowMemory = nova_snark::spartan_with_ipa_pc::LowMemoryRelaxedR1CSSNARK<G2>;
 
 use bellperson::{gadgets::num::AllocatedNum, ConstraintSystem, SynthesisError};
 use core::marker::PhantomData;
@@ -71,6 +73,18 @@ fn bench_compressed_snark(c: &mut Criterion, num_samples: usize, num_steps: usiz
       .is_ok());
     })
   });
+  // Bench time to produce a compressed SNARK with the low-memory prover
+  group.bench_function("ProveLowMemory", |b| {
+    b.iter(|| {
+      assert!(
+        CompressedSNARK::<_, _, _, _, S1LowMemory, S2LowMemory>::prove(
+          black_box(&pp),
+          black_box(&recursive_snark)
+        )
+        .is_ok()
+      );
+    })
+  });
   let res = CompressedSNARK::<_, _, _, _, S1, S2>::prove(&pp, &recursive_snark);
   assert!(res.is_ok());
   let compressed_snark = res.unwrap();
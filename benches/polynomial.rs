@@ -0,0 +1,109 @@
+#![allow(non_snake_case)]
+
+use criterion::*;
+use ff::{Field, PrimeField};
+use nova_snark::polynomial::{EqPolynomial, MultilinearPolynomial, SparsePolynomial};
+use rand::rngs::OsRng;
+use rayon::prelude::*;
+use std::time::Duration;
+
+type S = pasta_curves::pallas::Scalar;
+
+fn random_point(num_vars: usize) -> Vec<S> {
+  (0..num_vars).map(|_| S::random(&mut OsRng)).collect()
+}
+
+/// The evaluations that the polynomials replaced, which the benchmarks use as baselines
+mod baseline {
+  use super::*;
+
+  /// Evaluates a sparse polynomial by computing eq(r, i) from the bits of each index i,
+  /// in O(n log n) time for n entries
+  pub fn sparse_evaluate<Scalar: PrimeField>(Z: &[(usize, Scalar)], r: &[Scalar]) -> Scalar {
+    let compute_chi = |i: usize| {
+      (0..r.len()).fold(Scalar::one(), |chi, j| {
+        if i & (1 << (r.len() - j - 1)) > 0 {
+          chi * r[j]
+        } else {
+          chi * (Scalar::one() - r[j])
+        }
+      })
+    };
+    Z.par_iter()
+      .map(|(i, z)| compute_chi(*i) * z)
+      .reduce(Scalar::zero, |x, y| x + y)
+  }
+
+  /// Evaluates a multilinear polynomial with the full table of eq(r, .)
+  pub fn multilinear_evaluate<Scalar: PrimeField>(Z: &[Scalar], r: &[Scalar]) -> Scalar {
+    let chis = EqPolynomial::new(r.to_vec()).evals();
+    (0..chis.len())
+      .into_par_iter()
+      .map(|i| chis[i] * Z[i])
+      .reduce(Scalar::zero, |x, y| x + y)
+  }
+}
+
+fn polynomial_benchmark(c: &mut Criterion) {
+  bench_sparse_evaluate(c, 20, &[1 << 10, 1 << 16]);
+  bench_multilinear_evaluate(c, &[16, 20]);
+}
+
+fn set_duration() -> Criterion {
+  Criterion::default().warm_up_time(Duration::from_millis(3000))
+}
+
+criterion_group! {
+name = polynomial;
+config = set_duration();
+targets = polynomial_benchmark
+}
+
+criterion_main!(polynomial);
+
+fn bench_sparse_evaluate(c: &mut Criterion, num_vars: usize, num_entries: &[usize]) {
+  let mut group = c.benchmark_group("SparsePolynomial");
+  group.sample_size(10);
+  let r = random_point(num_vars);
+  for n in num_entries {
+    // entries in a prefix of the hypercube, as the public IO of an R1CS instance, and entries
+    // spread over the whole hypercube
+    let stride = (1 << num_vars) / n;
+    for (layout, step) in [("prefix", 1), ("spread", stride)] {
+      let Z = (0..*n)
+        .map(|i| (i * step, S::random(&mut OsRng)))
+        .collect::<Vec<(usize, S)>>();
+      let poly = SparsePolynomial::new(num_vars, Z.clone());
+      assert_eq!(poly.evaluate(&r), baseline::sparse_evaluate(&Z, &r));
+
+      let name = format!("Evaluate-{}-vars-{}-{}-entries", num_vars, n, layout);
+      group.bench_function(&name, |b| {
+        b.iter(|| black_box(&poly).evaluate(black_box(&r)));
+      });
+      group.bench_function(format!("{}-baseline", name), |b| {
+        b.iter(|| baseline::sparse_evaluate(black_box(&Z), black_box(&r)));
+      });
+    }
+  }
+  group.finish();
+}
+
+fn bench_multilinear_evaluate(c: &mut Criterion, num_vars: &[usize]) {
+  let mut group = c.benchmark_group("MultilinearPolynomial");
+  group.sample_size(10);
+  for ell in num_vars {
+    let Z = random_point(1 << ell);
+    let poly = MultilinearPolynomial::new(Z.clone());
+    let r = random_point(*ell);
+    assert_eq!(poly.evaluate(&r), baseline::multilinear_evaluate(&Z, &r));
+
+    let name = format!("Evaluate-2^{}-entries", ell);
+    group.bench_function(&name, |b| {
+      b.iter(|| black_box(&poly).evaluate(black_box(&r)));
+    });
+    group.bench_function(format!("{}-baseline", name), |b| {
+      b.iter(|| baseline::multilinear_evaluate(black_box(&Z), black_box(&r)));
+    });
+  }
+  group.finish();
+}
@@ -0,0 +1,157 @@
+#![allow(non_snake_case)]
+
+//! Reports the peak memory that the provers of `RelaxedR1CSSNARK` and `LowMemoryRelaxedR1CSSNARK`
+//! allocate for relaxed R1CS instances of growing sizes. Run with
+//! `cargo bench --bench spartan-memory`, and set `NUM_CONS_LOG` (e.g., `NUM_CONS_LOG=20`) to
+//! measure a single size.
+
+use ff::Field;
+use nova_snark::{
+  nifs::NIFS,
+  r1cs::{R1CSGens, R1CSInstance, R1CSShape, R1CSWitness, RelaxedR1CSInstance, RelaxedR1CSWitness},
+  snark::RelaxedR1CSSNARKTrait,
+  spartan_with_ipa_pc::{LowMemoryRelaxedR1CSSNARK, RelaxedR1CSSNARK},
+  traits::{Group, HashFuncConstantsTrait, HashFuncTrait},
+};
+use rand::rngs::OsRng;
+use std::{
+  alloc::{GlobalAlloc, Layout, System},
+  sync::atomic::{AtomicUsize, Ordering},
+  time::Instant,
+};
+
+type G = pasta_curves::pallas::Point;
+type S = <G as Group>::Scalar;
+type ROConstants =
+  <<G as Group>::HashFunc as HashFuncTrait<<G as Group>::Base, <G as Group>::Scalar>>::Constants;
+
+/// An allocator that keeps track of the bytes allocated now and at the peak
+struct CountingAllocator;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+  unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+    let ptr = System.alloc(layout);
+    if !ptr.is_null() {
+      let allocated = ALLOCATED.fetch_add(layout.size(), Ordering::SeqCst) + layout.size();
+      PEAK.fetch_max(allocated, Ordering::SeqCst);
+    }
+    ptr
+  }
+
+  unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+    System.dealloc(ptr, layout);
+    ALLOCATED.fetch_sub(layout.size(), Ordering::SeqCst);
+  }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// Runs `f` and returns its result along with the peak number of bytes that it allocated
+/// on top of those allocated before the call
+fn peak_allocation<T>(f: impl FnOnce() -> T) -> (T, usize) {
+  let before = ALLOCATED.load(Ordering::SeqCst);
+  PEAK.store(before, Ordering::SeqCst);
+  let res = f();
+  (res, PEAK.load(Ordering::SeqCst) - before)
+}
+
+/// Returns a shape with `n` constraints and `n` variables, whose first half of constraints
+/// multiply pairs of variables and whose second half multiply variables by `u`,
+/// along with a satisfying witness
+fn shape_and_witness(n: usize) -> (R1CSShape<G>, Vec<S>) {
+  let half = n / 2;
+  let (mut A, mut B, mut C) = (Vec::new(), Vec::new(), Vec::new());
+  let mut W = (0..n).map(|_| S::random(&mut OsRng)).collect::<Vec<S>>();
+  for i in 0..half {
+    A.push((i, i, S::one()));
+    B.push((i, (i + 1) % half, S::one()));
+    C.push((i, half + i, S::one()));
+    W[half + i] = W[i] * W[(i + 1) % half];
+  }
+  for i in half..n {
+    A.push((i, i, S::one()));
+    B.push((i, n, S::one()));
+    C.push((i, i, S::one()));
+  }
+  (R1CSShape::new(n, n, 2, &A, &B, &C).unwrap(), W)
+}
+
+/// Returns a relaxed instance and witness of `S` with a non-zero error vector,
+/// obtained by folding two instances
+fn relaxed_instance(
+  gens: &R1CSGens<G>,
+  S: &R1CSShape<G>,
+  W: &[S],
+) -> (RelaxedR1CSInstance<G>, RelaxedR1CSWitness<G>) {
+  let W = R1CSWitness::new(S, W).unwrap();
+  let U = R1CSInstance::new(S, &W.commit(gens), &[S::zero(), S::zero()]).unwrap();
+  let (U_relaxed, W_relaxed) = (
+    RelaxedR1CSInstance::from_r1cs_instance(gens, S, &U),
+    RelaxedR1CSWitness::from_r1cs_witness(S, &W),
+  );
+  let (_, (U_folded, W_folded)) = NIFS::prove(
+    gens,
+    &ROConstants::new(),
+    &S::zero(),
+    S,
+    &U_relaxed,
+    &W_relaxed,
+    &U,
+    &W,
+  )
+  .unwrap();
+  (U_folded, W_folded)
+}
+
+fn main() {
+  let sizes = match std::env::var("NUM_CONS_LOG") {
+    Ok(log) => vec![log.parse::<usize>().expect("NUM_CONS_LOG must be a number")],
+    Err(_) => vec![12, 14, 16],
+  };
+
+  println!(
+    "{:>10} {:>14} {:>14} {:>14} {:>10}",
+    "num_cons", "witness (MB)", "standard (MB)", "low mem (MB)", "ratio"
+  );
+  for log in sizes {
+    let n = 1 << log;
+    let (S, W) = shape_and_witness(n);
+    let gens = R1CSGens::<G>::new(S.num_cons(), S.num_vars());
+    let (U, W) = relaxed_instance(&gens, &S, &W);
+    let pk = RelaxedR1CSSNARK::<G>::prover_key(&gens, &S, &S::zero());
+    let vk = RelaxedR1CSSNARK::<G>::verifier_key(&gens, &S, &S::zero());
+
+    // run each prover once before measuring, so that the thread pool and lazily
+    // initialized tables are already allocated
+    let _ = RelaxedR1CSSNARK::<G>::prove(&pk, &U, &W).unwrap();
+    let _ = LowMemoryRelaxedR1CSSNARK::<G>::prove(&pk, &U, &W).unwrap();
+
+    let start = Instant::now();
+    let (snark, standard) = peak_allocation(|| RelaxedR1CSSNARK::<G>::prove(&pk, &U, &W).unwrap());
+    let time_standard = start.elapsed();
+    assert!(snark.verify(&vk, &U).is_ok());
+
+    let start = Instant::now();
+    let (snark, low_memory) =
+      peak_allocation(|| LowMemoryRelaxedR1CSSNARK::<G>::prove(&pk, &U, &W).unwrap());
+    let time_low_memory = start.elapsed();
+    assert!(snark.verify(&vk, &U).is_ok());
+
+    let mb = |bytes: usize| bytes as f64 / (1 << 20) as f64;
+    let witness = (W.W().len() + W.E().len()) * std::mem::size_of::<S>();
+    println!(
+      "{:>10} {:>14.1} {:>14.1} {:>14.1} {:>10.2}   ({:?} vs {:?})",
+      n,
+      mb(witness),
+      mb(standard),
+      mb(low_memory),
+      standard as f64 / low_memory as f64,
+      time_standard,
+      time_low_memory,
+    );
+  }
+}
@@ -0,0 +1,98 @@
+//! A `ConstraintSystem` for tests that checks each constraint as it is enforced, for circuits
+//! whose shape is too large to be collected by `ShapeCS`.
+
+use crate::errors::NovaError;
+use bellperson::{ConstraintSystem, Index, LinearCombination, SynthesisError, Variable};
+use ff::PrimeField;
+
+/// A `ConstraintSystem` that records the assignment of the variables and the first constraint
+/// that the assignment does not satisfy, without storing the constraints
+pub struct CheckingCS<F: PrimeField> {
+  inputs: Vec<F>,
+  aux: Vec<F>,
+  num_constraints: usize,
+  unsatisfied: Option<usize>,
+}
+
+impl<F: PrimeField> CheckingCS<F> {
+  /// Returns the public inputs, without the constant one
+  pub fn inputs(&self) -> &[F] {
+    &self.inputs[1..]
+  }
+
+  /// Checks that the assignment satisfies every constraint enforced so far
+  pub fn is_sat(&self) -> Result<(), NovaError> {
+    match self.unsatisfied {
+      Some(i) => Err(NovaError::UnSat {
+        constraint: Some(i),
+      }),
+      None => Ok(()),
+    }
+  }
+}
+
+impl<F: PrimeField> ConstraintSystem<F> for CheckingCS<F> {
+  type Root = Self;
+
+  fn new() -> Self {
+    Self {
+      inputs: vec![F::one()],
+      aux: Vec::new(),
+      num_constraints: 0,
+      unsatisfied: None,
+    }
+  }
+
+  fn alloc<FN, A, AR>(&mut self, _annotation: A, f: FN) -> Result<Variable, SynthesisError>
+  where
+    FN: FnOnce() -> Result<F, SynthesisError>,
+    A: FnOnce() -> AR,
+    AR: Into<String>,
+  {
+    self.aux.push(f()?);
+    Ok(Variable::new_unchecked(Index::Aux(self.aux.len() - 1)))
+  }
+
+  fn alloc_input<FN, A, AR>(&mut self, _annotation: A, f: FN) -> Result<Variable, SynthesisError>
+  where
+    FN: FnOnce() -> Result<F, SynthesisError>,
+    A: FnOnce() -> AR,
+    AR: Into<String>,
+  {
+    self.inputs.push(f()?);
+    Ok(Variable::new_unchecked(Index::Input(self.inputs.len() - 1)))
+  }
+
+  fn enforce<A, AR, LA, LB, LC>(&mut self, _annotation: A, a: LA, b: LB, c: LC)
+  where
+    A: FnOnce() -> AR,
+    AR: Into<String>,
+    LA: FnOnce(LinearCombination<F>) -> LinearCombination<F>,
+    LB: FnOnce(LinearCombination<F>) -> LinearCombination<F>,
+    LC: FnOnce(LinearCombination<F>) -> LinearCombination<F>,
+  {
+    let eval = |lc: LinearCombination<F>| lc.eval(None, None, &self.inputs, &self.aux);
+    let (a, b, c) = (
+      eval(a(LinearCombination::zero())),
+      eval(b(LinearCombination::zero())),
+      eval(c(LinearCombination::zero())),
+    );
+    if a * b != c && self.unsatisfied.is_none() {
+      self.unsatisfied = Some(self.num_constraints);
+    }
+    self.num_constraints += 1;
+  }
+
+  fn push_namespace<NR, N>(&mut self, _name_fn: N)
+  where
+    NR: Into<String>,
+    N: FnOnce() -> NR,
+  {
+  }
+
+  fn pop_namespace(&mut self) {}
+
+  fn get_root(&mut self) -> &mut Self::Root {
+    self
+  }
+}
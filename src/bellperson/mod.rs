@@ -2,6 +2,8 @@
 //!
 //! [Bellperson]: https://github.com/filecoin-project/bellperson
 
+#[cfg(test)]
+pub(crate) mod checking_cs;
 pub mod r1cs;
 pub mod shape_cs;
 pub mod solver;
@@ -159,7 +161,7 @@ mod tests {
       n_limbs,
     )?;
 
-    let _ = a1.equal_when_carried(cs.namespace(|| "check equal"), &a2)?;
+    a1.equal_when_carried(cs.namespace(|| "check equal"), &a2)?;
     Ok(())
   }
 
@@ -183,7 +185,7 @@ mod tests {
       limb_width,
       n_limbs,
     )?;
-    let _ = m.inputize(cs.namespace(|| "modulus m"))?;
+    m.inputize(cs.namespace(|| "modulus m"))?;
 
     let a = BigNat::from_num(
       cs.namespace(|| "allocate a_limbs"),
@@ -229,14 +231,14 @@ mod tests {
       limb_width,
       n_limbs,
     )?;
-    let _ = a.inputize(cs.namespace(|| "input a"))?;
+    a.inputize(cs.namespace(|| "input a"))?;
     let b = BigNat::alloc_from_nat(
       cs.namespace(|| "b"),
       || Ok(b_val.clone()),
       limb_width,
       n_limbs,
     )?;
-    let _ = b.inputize(cs.namespace(|| "input b"))?;
+    b.inputize(cs.namespace(|| "input b"))?;
     let c = BigNat::alloc_from_nat(
       cs.namespace(|| "c"),
       || Ok(c_val.clone()),
@@ -263,14 +265,14 @@ mod tests {
       limb_width,
       n_limbs,
     )?;
-    let _ = a.inputize(cs.namespace(|| "input a"))?;
+    a.inputize(cs.namespace(|| "input a"))?;
     let b = BigNat::alloc_from_nat(
       cs.namespace(|| "b"),
       || Ok(b_val.clone()),
       limb_width,
       n_limbs,
     )?;
-    let _ = b.inputize(cs.namespace(|| "input b"))?;
+    b.inputize(cs.namespace(|| "input b"))?;
     let c = BigNat::alloc_from_nat(
       cs.namespace(|| "c"),
       || Ok(c_val.clone()),
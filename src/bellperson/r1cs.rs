@@ -3,23 +3,44 @@
 #![allow(non_snake_case)]
 
 use super::{shape_cs::ShapeCS, solver::SatisfyingAssignment};
-use bellperson::{Index, LinearCombination};
+use bellperson::{gadgets::num::AllocatedNum, Index, LinearCombination};
 
 use ff::PrimeField;
 
 use crate::{
   errors::NovaError,
+  lookup::{LookupShape, ROConstants, NUM_LOOKUP_IO},
   r1cs::{R1CSGens, R1CSInstance, R1CSShape, R1CSWitness},
-  traits::Group,
+  traits::{Group, HashFuncConstantsTrait},
 };
 
 /// `NovaWitness` provide a method for acquiring an `R1CSInstance` and `R1CSWitness` from implementers.
 pub trait NovaWitness<G: Group> {
-  /// Return an instance and witness, given a shape and gens.
+  /// Return an instance and witness, given a shape and gens. The challenge of a lookup
+  /// argument is derived with the default constants from the digest of the shape, as when
+  /// the instance is folded on its own rather than as part of an IVC.
   fn r1cs_instance_and_witness(
     &self,
     shape: &R1CSShape<G>,
     gens: &R1CSGens<G>,
+  ) -> Result<(R1CSInstance<G>, R1CSWitness<G>), NovaError> {
+    self.r1cs_instance_and_witness_with_key(
+      shape,
+      gens,
+      &ROConstants::<G>::new(),
+      &shape.get_digest(),
+    )
+  }
+
+  /// Return an instance and witness, given a shape and gens, where the challenge of a lookup
+  /// argument is derived with `ro_consts` from `pp_digest`, which must be those that the
+  /// instance is folded with.
+  fn r1cs_instance_and_witness_with_key(
+    &self,
+    shape: &R1CSShape<G>,
+    gens: &R1CSGens<G>,
+    ro_consts: &ROConstants<G>,
+    pp_digest: &G::Scalar,
   ) -> Result<(R1CSInstance<G>, R1CSWitness<G>), NovaError>;
 }
 
@@ -27,6 +48,13 @@ pub trait NovaWitness<G: Group> {
 pub trait NovaShape<G: Group> {
   /// Return an appropriate `R1CSShape` struct.
   fn r1cs_shape(&self) -> R1CSShape<G>;
+  /// Return an appropriate `R1CSShape` struct with a lookup argument that checks that
+  /// the values of `queries` are in `table`.
+  fn r1cs_shape_with_lookup(
+    &self,
+    table: &[G::Scalar],
+    queries: &[AllocatedNum<G::Scalar>],
+  ) -> R1CSShape<G>;
   /// Return an appropriate `R1CSGens` struct.
   fn r1cs_gens(&self) -> R1CSGens<G>;
 }
@@ -35,11 +63,24 @@ impl<G: Group> NovaWitness<G> for SatisfyingAssignment<G>
 where
   G::Scalar: PrimeField,
 {
-  fn r1cs_instance_and_witness(
+  fn r1cs_instance_and_witness_with_key(
     &self,
     shape: &R1CSShape<G>,
     gens: &R1CSGens<G>,
+    ro_consts: &ROConstants<G>,
+    pp_digest: &G::Scalar,
   ) -> Result<(R1CSInstance<G>, R1CSWitness<G>), NovaError> {
+    if let Some(lookup) = &shape.lookup {
+      return lookup.r1cs_instance_and_witness(
+        shape,
+        gens,
+        ro_consts,
+        pp_digest,
+        &self.aux_assignment,
+        &self.input_assignment[1..],
+      );
+    }
+
     let W = R1CSWitness::<G>::new(shape, &self.aux_assignment)?;
     let X = &self.input_assignment[1..];
 
@@ -56,28 +97,11 @@ where
   G::Scalar: PrimeField,
 {
   fn r1cs_shape(&self) -> R1CSShape<G> {
-    let mut A: Vec<(usize, usize, G::Scalar)> = Vec::new();
-    let mut B: Vec<(usize, usize, G::Scalar)> = Vec::new();
-    let mut C: Vec<(usize, usize, G::Scalar)> = Vec::new();
-
-    let mut num_cons_added = 0;
-    let mut X = (&mut A, &mut B, &mut C, &mut num_cons_added);
-
     let num_inputs = self.num_inputs();
     let num_constraints = self.num_constraints();
     let num_vars = self.num_aux();
 
-    for constraint in self.constraints.iter() {
-      add_constraint(
-        &mut X,
-        num_vars,
-        &constraint.0,
-        &constraint.1,
-        &constraint.2,
-      );
-    }
-
-    assert_eq!(num_cons_added, num_constraints);
+    let (A, B, C) = matrices(self, num_vars);
 
     let S: R1CSShape<G> = {
       // Don't count One as an input for shape's purposes.
@@ -88,11 +112,80 @@ where
     S
   }
 
+  fn r1cs_shape_with_lookup(
+    &self,
+    table: &[G::Scalar],
+    queries: &[AllocatedNum<G::Scalar>],
+  ) -> R1CSShape<G> {
+    let num_io = self.num_inputs() - 1 + NUM_LOOKUP_IO;
+    let num_vars = LookupShape::<G>::num_vars(self.num_aux(), table.len(), queries.len());
+
+    let (mut A, mut B, mut C) = matrices(self, num_vars);
+
+    // the lookup argument reads the queries from the same columns as the constraints of the
+    // circuit; its challenge does not bind the public IO, so the queries must be auxiliary
+    let columns = queries
+      .iter()
+      .map(|q| match q.get_variable().get_unchecked() {
+        Index::Input(_) => {
+          panic!("a lookup query must be an auxiliary variable, not a public input")
+        }
+        Index::Aux(idx) => idx,
+      })
+      .collect::<Vec<usize>>();
+    let lookup = LookupShape::new(table, &columns, self.num_aux());
+    let num_constraints = self.num_constraints()
+      + lookup.append_constraints(
+        self.num_constraints(),
+        num_vars,
+        num_io,
+        &mut A,
+        &mut B,
+        &mut C,
+      );
+
+    R1CSShape::new_with_lookup(num_constraints, num_vars, num_io, &A, &B, &C, Some(lookup)).unwrap()
+  }
+
   fn r1cs_gens(&self) -> R1CSGens<G> {
     R1CSGens::<G>::new(self.num_constraints(), self.num_aux())
   }
 }
 
+/// Collects the constraints of `cs` into matrices over `num_vars` variables
+fn matrices<G: Group>(
+  cs: &ShapeCS<G>,
+  num_vars: usize,
+) -> (
+  Vec<(usize, usize, G::Scalar)>,
+  Vec<(usize, usize, G::Scalar)>,
+  Vec<(usize, usize, G::Scalar)>,
+)
+where
+  G::Scalar: PrimeField,
+{
+  let mut A: Vec<(usize, usize, G::Scalar)> = Vec::new();
+  let mut B: Vec<(usize, usize, G::Scalar)> = Vec::new();
+  let mut C: Vec<(usize, usize, G::Scalar)> = Vec::new();
+
+  let mut num_cons_added = 0;
+  let mut X = (&mut A, &mut B, &mut C, &mut num_cons_added);
+
+  for constraint in cs.constraints.iter() {
+    add_constraint(
+      &mut X,
+      num_vars,
+      &constraint.0,
+      &constraint.1,
+      &constraint.2,
+    );
+  }
+
+  assert_eq!(num_cons_added, cs.num_constraints());
+
+  (A, B, C)
+}
+
 fn add_constraint<S: PrimeField>(
   X: &mut (
     &mut Vec<(usize, usize, S)>,
@@ -5,7 +5,7 @@ use std::{
   collections::{BTreeMap, HashMap},
 };
 
-use crate::traits::Group;
+use crate::{profile::CircuitProfile, traits::Group};
 use ff::{Field, PrimeField};
 
 use bellperson::{ConstraintSystem, Index, LinearCombination, SynthesisError, Variable};
@@ -13,6 +13,7 @@ use bellperson::{ConstraintSystem, Index, LinearCombination, SynthesisError, Var
 #[derive(Clone, Copy)]
 struct OrderedVariable(Variable);
 
+#[allow(dead_code)]
 #[derive(Debug)]
 enum NamedObject {
   Constraint(usize),
@@ -116,6 +117,17 @@ where
     self.aux.len()
   }
 
+  /// Returns the constraints and the aux inputs of each namespace.
+  pub fn profile(&self) -> CircuitProfile {
+    CircuitProfile::from_paths(
+      self
+        .constraints
+        .iter()
+        .map(|(_a, _b, _c, name)| name.as_str()),
+      self.aux.iter().map(|aux| aux.as_str()),
+    )
+  }
+
   /// Print all public inputs, aux inputs, and constraint names.
   #[allow(dead_code)]
   pub fn pretty_print_list(&self) -> Vec<String> {
@@ -128,7 +140,7 @@ where
       result.push(format!("AUX {}", aux));
     }
 
-    for &(ref _a, ref _b, ref _c, ref name) in &self.constraints {
+    for (_a, _b, _c, name) in &self.constraints {
       result.push(name.to_string());
     }
 
@@ -147,7 +159,7 @@ where
     let negone = -<G::Scalar>::one();
 
     let powers_of_two = (0..G::Scalar::NUM_BITS)
-      .map(|i| G::Scalar::from(2u64).pow_vartime(&[u64::from(i)]))
+      .map(|i| G::Scalar::from(2u64).pow_vartime([u64::from(i)]))
       .collect::<Vec<_>>();
 
     let pp = |s: &mut String, lc: &LinearCombination<G::Scalar>| {
@@ -188,7 +200,7 @@ where
       s.push(')');
     };
 
-    for &(ref a, ref b, ref c, ref name) in &self.constraints {
+    for (a, b, c, name) in &self.constraints {
       s.push('\n');
 
       s.push_str(&format!("{}: ", name));
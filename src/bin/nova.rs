@@ -0,0 +1,559 @@
+//! A command-line front end to Nova that runs the setup, the prover and the verifier
+//! of a step circuit with file-based artifacts.
+//!
+//! ```text
+//! nova setup --circuit <trivial|cubic|circom> [--r1cs <file> --input <wire> --output <wire>] [--tag <tag>] [--security <default|conservative|fast-test>] --params <file> --pk <file> --vk <file>
+//! nova prove --params <file> --steps <n> --z0 <decimal> [--witness <file>]... --proof <file>
+//! nova compress --params <file> --pk <file> --proof <file> --out <file>
+//! nova verify (--params <file> | --vk <file>) --proof <file>
+//! ```
+//!
+//! The generators of the public parameters are derived deterministically from the circuit,
+//! so the parameters file holds the description of the circuit, the tag and the security
+//! configuration along with the digest of the parameters, and the prover recreates the
+//! parameters from it and checks the digest. The setup also writes the prover and the verifier
+//! keys of compressed proofs, so a compressed proof is verified with the verifier key alone,
+//! without the circuit. Keys and proofs are written with bincode.
+//!
+//! `verify` trusts the verifier key that it is given, and prints the digest so that operators
+//! can pin it; a recursive proof is verified against the parameters recreated from the circuit
+//! description in the parameters file. The secondary circuit is always the trivial one, the
+//! circuits are fixed at compile time, and the files are not versioned, so they are only
+//! readable by the same build.
+#![allow(non_snake_case)]
+use bellperson::{gadgets::num::AllocatedNum, ConstraintSystem, SynthesisError};
+use ff::PrimeField;
+use nova_snark::{
+  circom::{witness_from_file, CircomCircuit, CircomR1CS, CircomStepConfig},
+  errors::NovaError,
+  security::SecurityConfig,
+  spartan_with_ipa_pc::RelaxedR1CSSNARK,
+  traits::{Group, StepCircuit},
+  CompressedSNARK, ProverKey, PublicParams, RecursiveSNARK, VerifierKey,
+};
+use num_bigint::BigUint;
+use serde::{Deserialize, Serialize};
+use std::{
+  collections::HashMap,
+  env, fs,
+  io::BufWriter,
+  marker::PhantomData,
+  path::{Path, PathBuf},
+  process,
+  time::Instant,
+};
+
+type G1 = pasta_curves::pallas::Point;
+type G2 = pasta_curves::vesta::Point;
+type F1 = <G1 as Group>::Scalar;
+type F2 = <G2 as Group>::Scalar;
+type C1 = ExampleCircuit<F1>;
+type C2 = ExampleCircuit<F2>;
+type S1 = RelaxedR1CSSNARK<G1>;
+type S2 = RelaxedR1CSSNARK<G2>;
+type Params = PublicParams<G1, G2, C1, C2>;
+type PK = ProverKey<G1, G2, C1, C2, S1, S2>;
+type VK = VerifierKey<G1, G2, C1, C2, S1, S2>;
+
+const USAGE: &str = "usage:
+  nova setup --circuit <trivial|cubic|circom> [--r1cs <file> --input <wire> --output <wire>] [--tag <tag>] [--security <default|conservative|fast-test>] --params <file> --pk <file> --vk <file>
+  nova prove --params <file> --steps <n> --z0 <decimal> [--witness <file>]... --proof <file>
+  nova compress --params <file> --pk <file> --proof <file> --out <file>
+  nova verify (--params <file> | --vk <file>) --proof <file>";
+
+/// The step circuits that the command line can run
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "name", rename_all = "lowercase")]
+enum CircuitSpec {
+  /// outputs its input
+  Trivial,
+  /// outputs `x^3 + x + 5` for an input `x`
+  Cubic,
+  /// a Circom circuit over the scalar field of pallas, with the wires of the input and the output
+  Circom {
+    r1cs: PathBuf,
+    input: usize,
+    output: usize,
+  },
+}
+
+/// The contents of a parameters file
+#[derive(Debug, Serialize, Deserialize)]
+struct ParamsFile {
+  circuit: CircuitSpec,
+  /// the domain-separation tag of the application, which is empty by default
+  #[serde(default)]
+  tag: String,
+  /// the security configuration, which is the default one if absent
+  #[serde(default)]
+  security: SecurityConfig,
+  digest: String,
+}
+
+/// The contents of a proof file
+#[derive(Serialize, Deserialize)]
+struct ProofFile {
+  num_steps: usize,
+  z0: String,
+  proof: Proof,
+}
+
+#[derive(Serialize, Deserialize)]
+enum Proof {
+  Recursive(Box<RecursiveSNARK<G1, G2, C1, C2>>),
+  Compressed(Box<CompressedSNARK<G1, G2, C1, C2, S1, S2>>),
+}
+
+/// A step circuit chosen at run time among the circuits of `CircuitSpec`
+#[derive(Clone, Debug)]
+enum ExampleCircuit<F: PrimeField> {
+  Trivial(PhantomData<F>),
+  Cubic(PhantomData<F>),
+  Circom(CircomCircuit<F>),
+}
+
+impl<F: PrimeField> StepCircuit<F> for ExampleCircuit<F> {
+  fn synthesize<CS: ConstraintSystem<F>>(
+    &self,
+    cs: &mut CS,
+    z: AllocatedNum<F>,
+  ) -> Result<AllocatedNum<F>, SynthesisError> {
+    match self {
+      ExampleCircuit::Trivial(_) => Ok(z),
+      ExampleCircuit::Cubic(_) => {
+        let x = z;
+        let x_sq = x.square(cs.namespace(|| "x_sq"))?;
+        let x_cu = x_sq.mul(cs.namespace(|| "x_cu"), &x)?;
+        let y = AllocatedNum::alloc(cs.namespace(|| "y"), || {
+          Ok(
+            x_cu.get_value().ok_or(SynthesisError::AssignmentMissing)?
+              + x.get_value().ok_or(SynthesisError::AssignmentMissing)?
+              + F::from(5u64),
+          )
+        })?;
+        cs.enforce(
+          || "y = x^3 + x + 5",
+          |lc| lc + x_cu.get_variable() + x.get_variable() + (F::from(5u64), CS::one()),
+          |lc| lc + CS::one(),
+          |lc| lc + y.get_variable(),
+        );
+        Ok(y)
+      }
+      ExampleCircuit::Circom(c) => c.synthesize(cs, z),
+    }
+  }
+
+  fn compute(&self, z: &F) -> F {
+    match self {
+      ExampleCircuit::Trivial(_) => *z,
+      ExampleCircuit::Cubic(_) => *z * *z * *z + z + F::from(5u64),
+      ExampleCircuit::Circom(c) => c.compute(z),
+    }
+  }
+
+  fn try_compute(&self, z: &F) -> Result<F, NovaError> {
+    match self {
+      ExampleCircuit::Circom(c) => c.try_compute(z),
+      _ => Ok(self.compute(z)),
+    }
+  }
+}
+
+/// Creates the primary circuit of `spec`, with the witnesses of a Circom circuit read from `witnesses`
+fn primary_circuit(spec: &CircuitSpec, witnesses: &[PathBuf]) -> Result<C1, String> {
+  if !witnesses.is_empty() && !matches!(spec, CircuitSpec::Circom { .. }) {
+    return Err("only Circom circuits take witness files".to_string());
+  }
+  match spec {
+    CircuitSpec::Trivial => Ok(ExampleCircuit::Trivial(PhantomData)),
+    CircuitSpec::Cubic => Ok(ExampleCircuit::Cubic(PhantomData)),
+    CircuitSpec::Circom {
+      r1cs,
+      input,
+      output,
+    } => {
+      let r1cs = CircomR1CS::from_file(r1cs)
+        .map_err(|e| format!("cannot read {}: {}", r1cs.display(), e))?;
+      let mut circuit = CircomCircuit::new(r1cs, CircomStepConfig::new(*input, *output))
+        .map_err(|e| format!("invalid wires of the input or the output: {}", e))?;
+      for path in witnesses {
+        let witness =
+          witness_from_file(path).map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+        circuit
+          .add_witness(witness)
+          .map_err(|e| format!("invalid witness {}: {}", path.display(), e))?;
+      }
+      Ok(ExampleCircuit::Circom(circuit))
+    }
+  }
+}
+
+/// Creates the public parameters of `spec` with the domain-separation tag `tag` and the
+/// security configuration `config`, and checks them against the digest in `file` if any
+fn public_params(
+  spec: &CircuitSpec,
+  tag: &str,
+  config: &SecurityConfig,
+  witnesses: &[PathBuf],
+  file: Option<&ParamsFile>,
+) -> Result<Params, String> {
+  let start = Instant::now();
+  let pp = Params::setup_with_config(
+    primary_circuit(spec, witnesses)?,
+    ExampleCircuit::Trivial(PhantomData),
+    tag.as_bytes(),
+    config,
+  )
+  .map_err(|e| format!("cannot set up the parameters: {}", e))?;
+  eprintln!("public parameters generated in {:?}", start.elapsed());
+
+  if let Some(file) = file {
+    if file.digest != to_decimal(&pp.digest()) {
+      return Err("the parameters do not match the digest in the parameters file".to_string());
+    }
+  }
+  Ok(pp)
+}
+
+/// Parses the flags of a command, each of which is followed by a value and may be repeated
+fn parse_flags(args: &[String]) -> Result<HashMap<String, Vec<String>>, String> {
+  let mut flags: HashMap<String, Vec<String>> = HashMap::new();
+  let mut args = args.iter();
+  while let Some(flag) = args.next() {
+    let name = flag
+      .strip_prefix("--")
+      .ok_or_else(|| format!("unexpected argument {}", flag))?;
+    let value = args
+      .next()
+      .ok_or_else(|| format!("missing value for {}", flag))?;
+    flags
+      .entry(name.to_string())
+      .or_default()
+      .push(value.clone());
+  }
+  Ok(flags)
+}
+
+fn flag<'a>(flags: &'a HashMap<String, Vec<String>>, name: &str) -> Result<&'a str, String> {
+  match flags.get(name).map(|v| &v[..]) {
+    Some([value]) => Ok(value),
+    Some(_) => Err(format!("--{} is given more than once", name)),
+    None => Err(format!("missing --{}", name)),
+  }
+}
+
+fn parse<T: std::str::FromStr>(
+  flags: &HashMap<String, Vec<String>>,
+  name: &str,
+) -> Result<T, String> {
+  flag(flags, name)?
+    .parse()
+    .map_err(|_| format!("invalid value for --{}", name))
+}
+
+/// Parses a field element from its decimal representation
+fn parse_scalar<F: PrimeField>(s: &str) -> Result<F, String> {
+  let n: BigUint = s.parse().map_err(|_| format!("invalid number {}", s))?;
+  let bytes = n.to_bytes_le();
+  let mut repr = F::Repr::default();
+  if bytes.len() > repr.as_ref().len() {
+    return Err(format!("{} is not in the field", s));
+  }
+  repr.as_mut()[..bytes.len()].copy_from_slice(&bytes);
+  Option::from(F::from_repr(repr)).ok_or_else(|| format!("{} is not in the field", s))
+}
+
+fn to_decimal<F: PrimeField>(f: &F) -> String {
+  BigUint::from_bytes_le(f.to_repr().as_ref()).to_str_radix(10)
+}
+
+fn read_params(path: &str) -> Result<ParamsFile, String> {
+  let bytes = fs::read(path).map_err(|e| format!("cannot read {}: {}", path, e))?;
+  serde_json::from_slice(&bytes).map_err(|e| format!("invalid parameters file {}: {}", path, e))
+}
+
+fn read_proof(path: &str) -> Result<ProofFile, String> {
+  let bytes = fs::read(path).map_err(|e| format!("cannot read {}: {}", path, e))?;
+  bincode::deserialize(&bytes).map_err(|e| format!("invalid proof file {}: {}", path, e))
+}
+
+fn read_pk(path: &str) -> Result<PK, String> {
+  let bytes = fs::read(path).map_err(|e| format!("cannot read {}: {}", path, e))?;
+  PK::from_bytes(&bytes).map_err(|e| format!("invalid prover key {}: {}", path, e))
+}
+
+fn read_vk(path: &str) -> Result<VK, String> {
+  let bytes = fs::read(path).map_err(|e| format!("cannot read {}: {}", path, e))?;
+  VK::from_bytes(&bytes).map_err(|e| format!("invalid verifier key {}: {}", path, e))
+}
+
+fn write_bytes(path: &str, bytes: &[u8]) -> Result<(), String> {
+  fs::write(path, bytes).map_err(|e| format!("cannot write {}: {}", path, e))?;
+  report_size(path);
+  Ok(())
+}
+
+fn write_proof(path: &str, proof: &ProofFile) -> Result<(), String> {
+  let file = fs::File::create(path).map_err(|e| format!("cannot create {}: {}", path, e))?;
+  bincode::serialize_into(BufWriter::new(file), proof)
+    .map_err(|e| format!("cannot write {}: {}", path, e))?;
+  report_size(path);
+  Ok(())
+}
+
+fn report_size(path: &str) {
+  if let Ok(metadata) = fs::metadata(path) {
+    eprintln!("wrote {} ({} bytes)", path, metadata.len());
+  }
+}
+
+fn setup(flags: &HashMap<String, Vec<String>>) -> Result<(), String> {
+  let circuit = match flag(flags, "circuit")? {
+    "trivial" => CircuitSpec::Trivial,
+    "cubic" => CircuitSpec::Cubic,
+    "circom" => {
+      let r1cs = Path::new(flag(flags, "r1cs")?);
+      CircuitSpec::Circom {
+        // the other commands may run in other directories
+        r1cs: fs::canonicalize(r1cs)
+          .map_err(|e| format!("cannot read {}: {}", r1cs.display(), e))?,
+        input: parse(flags, "input")?,
+        output: parse(flags, "output")?,
+      }
+    }
+    name => return Err(format!("unknown circuit {}", name)),
+  };
+
+  let tag = match flags.get("tag") {
+    Some(_) => flag(flags, "tag")?.to_string(),
+    None => String::new(),
+  };
+  let security = match flags.get("security") {
+    None => SecurityConfig::default(),
+    Some(_) => match flag(flags, "security")? {
+      "default" => SecurityConfig::default(),
+      "conservative" => SecurityConfig::conservative(),
+      "fast-test" => SecurityConfig::fast_test(),
+      name => return Err(format!("unknown security configuration {}", name)),
+    },
+  };
+  let pp = public_params(&circuit, &tag, &security, &[], None)?;
+  let (num_cons_primary, num_cons_secondary) = pp.num_constraints();
+  let (num_vars_primary, num_vars_secondary) = pp.num_variables();
+  println!(
+    "primary circuit: {} constraints, {} variables",
+    num_cons_primary, num_vars_primary
+  );
+  println!(
+    "secondary circuit: {} constraints, {} variables",
+    num_cons_secondary, num_vars_secondary
+  );
+  println!("digest: {}", to_decimal(&pp.digest()));
+
+  let file = ParamsFile {
+    circuit,
+    tag,
+    security,
+    digest: to_decimal(&pp.digest()),
+  };
+  let json = serde_json::to_string_pretty(&file).map_err(|e| e.to_string())?;
+  write_bytes(flag(flags, "params")?, json.as_bytes())?;
+
+  let start = Instant::now();
+  let (pk, vk) = CompressedSNARK::<_, _, _, _, S1, S2>::setup(&pp);
+  eprintln!("keys generated in {:?}", start.elapsed());
+  write_bytes(flag(flags, "pk")?, &pk.to_bytes())?;
+  write_bytes(flag(flags, "vk")?, &vk.to_bytes())
+}
+
+fn prove(flags: &HashMap<String, Vec<String>>) -> Result<(), String> {
+  let file = read_params(flag(flags, "params")?)?;
+  let witnesses = flags
+    .get("witness")
+    .map(|v| v.iter().map(PathBuf::from).collect::<Vec<_>>())
+    .unwrap_or_default();
+  let pp = public_params(
+    &file.circuit,
+    &file.tag,
+    &file.security,
+    &witnesses,
+    Some(&file),
+  )?;
+
+  let num_steps: usize = parse(flags, "steps")?;
+  let z0 = flag(flags, "z0")?;
+  let z0_primary = parse_scalar::<F1>(z0)?;
+
+  let start = Instant::now();
+  let snark = RecursiveSNARK::prove(&pp, num_steps, z0_primary, F2::zero())
+    .map_err(|e| format!("cannot prove: {}", e))?;
+  eprintln!("{} steps proven in {:?}", num_steps, start.elapsed());
+
+  write_proof(
+    flag(flags, "proof")?,
+    &ProofFile {
+      num_steps,
+      z0: z0.to_string(),
+      proof: Proof::Recursive(Box::new(snark)),
+    },
+  )
+}
+
+fn compress(flags: &HashMap<String, Vec<String>>) -> Result<(), String> {
+  let file = read_params(flag(flags, "params")?)?;
+  let pk = read_pk(flag(flags, "pk")?)?;
+  let proof = read_proof(flag(flags, "proof")?)?;
+  let pp = public_params(&file.circuit, &file.tag, &file.security, &[], Some(&file))?;
+  let snark = match proof.proof {
+    Proof::Recursive(snark) => snark,
+    Proof::Compressed(_) => return Err("the proof is already compressed".to_string()),
+  };
+
+  let start = Instant::now();
+  let compressed = CompressedSNARK::prove_with_key(&pp, &pk, &snark)
+    .map_err(|e| format!("cannot compress: {}", e))?;
+  eprintln!("proof compressed in {:?}", start.elapsed());
+
+  write_proof(
+    flag(flags, "out")?,
+    &ProofFile {
+      num_steps: proof.num_steps,
+      z0: proof.z0,
+      proof: Proof::Compressed(Box::new(compressed)),
+    },
+  )
+}
+
+fn verify(flags: &HashMap<String, Vec<String>>) -> Result<(), String> {
+  let proof = read_proof(flag(flags, "proof")?)?;
+  let z0_primary = parse_scalar::<F1>(&proof.z0)?;
+
+  // a recursive proof is verified with the public parameters, and a compressed one with the verifier key
+  let (res, start, digest) = match &proof.proof {
+    Proof::Recursive(snark) => {
+      let file = read_params(flag(flags, "params")?)?;
+      let pp = public_params(&file.circuit, &file.tag, &file.security, &[], Some(&file))?;
+      let start = Instant::now();
+      let res = snark.verify(&pp, proof.num_steps, z0_primary, F2::zero());
+      (res, start, pp.digest())
+    }
+    Proof::Compressed(snark) => {
+      let vk = read_vk(flag(flags, "vk")?)?;
+      let start = Instant::now();
+      let res = snark.verify_with_key(&vk, proof.num_steps, z0_primary, F2::zero());
+      (res, start, vk.digest())
+    }
+  };
+  let (zn_primary, _) = res.map_err(|e| format!("the proof is invalid: {}", e))?;
+  eprintln!("proof verified in {:?}", start.elapsed());
+
+  println!("digest: {}", to_decimal(&digest));
+  println!("num_steps: {}", proof.num_steps);
+  println!("z0: {}", proof.z0);
+  println!("zn: {}", to_decimal(&zn_primary));
+  Ok(())
+}
+
+fn run(args: &[String]) -> Result<(), String> {
+  let (command, flags) = args.split_first().ok_or_else(|| USAGE.to_string())?;
+  let flags = parse_flags(flags)?;
+  match command.as_str() {
+    "setup" => setup(&flags),
+    "prove" => prove(&flags),
+    "compress" => compress(&flags),
+    "verify" => verify(&flags),
+    _ => Err(USAGE.to_string()),
+  }
+}
+
+fn main() {
+  let args = env::args().skip(1).collect::<Vec<_>>();
+  if let Err(e) = run(&args) {
+    eprintln!("error: {}", e);
+    process::exit(1);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_cli_cubic() {
+    let dir = env::temp_dir().join(format!("nova-cli-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = |name: &str| dir.join(name).to_str().unwrap().to_string();
+    let run_args = |args: &[&str]| run(&args.iter().map(|a| a.to_string()).collect::<Vec<_>>());
+
+    run_args(&[
+      "setup",
+      "--circuit",
+      "cubic",
+      "--params",
+      &path("pp.json"),
+      "--pk",
+      &path("pk.bin"),
+      "--vk",
+      &path("vk.bin"),
+    ])
+    .unwrap();
+    run_args(&[
+      "prove",
+      "--params",
+      &path("pp.json"),
+      "--steps",
+      "3",
+      "--z0",
+      "1",
+      "--proof",
+      &path("proof.bin"),
+    ])
+    .unwrap();
+    run_args(&[
+      "compress",
+      "--params",
+      &path("pp.json"),
+      "--pk",
+      &path("pk.bin"),
+      "--proof",
+      &path("proof.bin"),
+      "--out",
+      &path("compressed.bin"),
+    ])
+    .unwrap();
+
+    // the recursive proof verifies with the parameters, and the compressed one with the verifier key alone
+    run_args(&[
+      "verify",
+      "--params",
+      &path("pp.json"),
+      "--proof",
+      &path("proof.bin"),
+    ])
+    .unwrap();
+    run_args(&[
+      "verify",
+      "--vk",
+      &path("vk.bin"),
+      "--proof",
+      &path("compressed.bin"),
+    ])
+    .unwrap();
+
+    // the last 32 bytes are the final output of the secondary circuit, which the proof commits to
+    let mut bytes = fs::read(path("compressed.bin")).unwrap();
+    let len = bytes.len();
+    bytes[len - 32] ^= 1;
+    fs::write(path("tampered.bin"), &bytes).unwrap();
+    let res = run_args(&[
+      "verify",
+      "--vk",
+      &path("vk.bin"),
+      "--proof",
+      &path("tampered.bin"),
+    ]);
+    assert!(res.unwrap_err().starts_with("the proof is invalid"));
+
+    fs::remove_dir_all(&dir).unwrap();
+  }
+}
@@ -0,0 +1,443 @@
+//! This module defines customizable constraint systems (CCS), which generalize R1CS to constraints
+//! of higher degree. A CCS is given by matrices `M_0, ..., M_{t-1}`, multisets `S_0, ..., S_{q-1}`
+//! of indices of these matrices, and constants `c_0, ..., c_{q-1}`. It is satisfied by
+//! `z = (W, 1, X)` if `sum_i c_i * prod_{j in S_i} (M_j * z) = 0`, where the products are entry-wise.
+//! R1CS is the special case with `M = (A, B, C)`, `S = ({0, 1}, {2})` and `c = (1, -1)`.
+//!
+//! The multi-folding scheme in `nimfs` folds instances of CCS into linearized instances,
+//! which claim the evaluations `v_j` of the multilinear extensions of `M_j * z` at a point `r_x`.
+use super::{
+  commitments::{CommitTrait, Commitment},
+  constants::NUM_HASH_BITS,
+  errors::NovaError,
+  parallel::prelude::*,
+  polynomial::EqPolynomial,
+  r1cs::{R1CSGens, R1CSShape},
+  traits::Group,
+};
+use ff::{Field, PrimeField};
+use flate2::{write::ZlibEncoder, Compression};
+use itertools::concat;
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Sha3_256};
+
+/// A type that holds the shape of a CCS
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CCSShape<G: Group> {
+  pub(crate) num_cons: usize,
+  pub(crate) num_vars: usize,
+  pub(crate) num_io: usize,
+  pub(crate) M: Vec<Vec<(usize, usize, G::Scalar)>>,
+  pub(crate) S: Vec<Vec<usize>>,
+  pub(crate) c: Vec<G::Scalar>,
+  digest: G::Scalar, // digest of the rest of CCSShape
+}
+
+/// A type that holds a witness for a given CCS instance
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CCSWitness<G: Group> {
+  pub(crate) W: Vec<G::Scalar>,
+}
+
+/// A type that holds a committed CCS instance
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CCCSInstance<G: Group> {
+  pub(crate) comm_W: Commitment<G>,
+  pub(crate) X: Vec<G::Scalar>,
+}
+
+/// A type that holds a linearized committed CCS instance, which claims that
+/// `v_j` is the evaluation of the multilinear extension of `M_j * (W, u, X)` at `r_x`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LCCCSInstance<G: Group> {
+  pub(crate) comm_W: Commitment<G>,
+  pub(crate) X: Vec<G::Scalar>,
+  pub(crate) u: G::Scalar,
+  pub(crate) r_x: Vec<G::Scalar>,
+  pub(crate) v: Vec<G::Scalar>,
+}
+
+impl<G: Group> CCSShape<G> {
+  /// Create an object of type `CCSShape` from the explicitly specified matrices, multisets and constants
+  pub fn new(
+    num_cons: usize,
+    num_vars: usize,
+    num_io: usize,
+    M: &[Vec<(usize, usize, G::Scalar)>],
+    S: &[Vec<usize>],
+    c: &[G::Scalar],
+  ) -> Result<CCSShape<G>, NovaError> {
+    if let Some((matrix, (row, col, _val))) = M.iter().enumerate().find_map(|(j, M_j)| {
+      M_j
+        .iter()
+        .find(|(row, col, _val)| *row >= num_cons || *col > num_io + num_vars)
+        .map(|entry| (j, entry))
+    }) {
+      return Err(NovaError::InvalidIndex {
+        matrix,
+        row: *row,
+        col: *col,
+      });
+    }
+    if num_cons == 0 {
+      return Err(NovaError::InvalidShape {
+        reason: "the shape has no constraints",
+      });
+    }
+    if S.len() != c.len() {
+      return Err(NovaError::InvalidShape {
+        reason: "the numbers of multisets and constants differ",
+      });
+    }
+    if !S
+      .iter()
+      .all(|S_i| !S_i.is_empty() && S_i.iter().all(|j| *j < M.len()))
+    {
+      return Err(NovaError::InvalidShape {
+        reason: "a multiset is empty or refers to a missing matrix",
+      });
+    }
+
+    let digest = Self::compute_digest(num_cons, num_vars, num_io, M, S, c);
+
+    Ok(CCSShape {
+      num_cons,
+      num_vars,
+      num_io,
+      M: M.to_vec(),
+      S: S.to_vec(),
+      c: c.to_vec(),
+      digest,
+    })
+  }
+
+  /// Converts an R1CS shape into the equivalent CCS shape
+  pub fn from_r1cs(S: &R1CSShape<G>) -> CCSShape<G> {
+    Self::new(
+      S.num_cons,
+      S.num_vars,
+      S.num_io,
+      &[S.A.clone(), S.B.clone(), S.C.clone()],
+      &[vec![0, 1], vec![2]],
+      &[G::Scalar::one(), -G::Scalar::one()],
+    )
+    .unwrap()
+  }
+
+  /// Samples public parameters for committing to witnesses of the CCS
+  pub fn gens(&self) -> R1CSGens<G> {
+    R1CSGens::new(self.num_cons, self.num_vars)
+  }
+
+  /// Returns the degree of the constraints, which is the size of the largest multiset
+  pub fn degree(&self) -> usize {
+    self.S.iter().map(|S_i| S_i.len()).max().unwrap_or(0)
+  }
+
+  /// Returns the number of variables of the multilinear extensions of `M_j * z`
+  pub fn num_vars_x(&self) -> usize {
+    self.num_cons.next_power_of_two().trailing_zeros() as usize
+  }
+
+  /// returned the digest of the `CCSShape`
+  pub fn get_digest(&self) -> G::Scalar {
+    self.digest
+  }
+
+  /// Computes `M_j * z` for every matrix, padded with zeros to a power-of-two length
+  pub(crate) fn multiply_vec(&self, z: &[G::Scalar]) -> Result<Vec<Vec<G::Scalar>>, NovaError> {
+    if z.len() != self.num_io + self.num_vars + 1 {
+      return Err(NovaError::InvalidWitnessLength {
+        expected: self.num_io + self.num_vars + 1,
+        actual: z.len(),
+      });
+    }
+
+    Ok(
+      self
+        .M
+        .par_iter()
+        .map(|M_j| {
+          let mut Mz = vec![G::Scalar::zero(); self.num_cons.next_power_of_two()];
+          for (row, col, val) in M_j {
+            Mz[*row] += *val * z[*col];
+          }
+          Mz
+        })
+        .collect(),
+    )
+  }
+
+  /// Checks if the CCS instance is satisfiable given a witness and its shape
+  pub fn is_sat(
+    &self,
+    gens: &R1CSGens<G>,
+    U: &CCCSInstance<G>,
+    W: &CCSWitness<G>,
+  ) -> Result<(), NovaError> {
+    assert_eq!(W.W.len(), self.num_vars);
+    assert_eq!(U.X.len(), self.num_io);
+
+    // verify if sum_i c_i * prod_{j in S_i} M_j z = 0
+    let unsat_constraint: Option<usize> = {
+      let z = concat(vec![W.W.clone(), vec![G::Scalar::one()], U.X.clone()]);
+      let Mz = self.multiply_vec(&z)?;
+      (0..self.num_cons).into_par_iter().find_first(|row| {
+        self
+          .S
+          .iter()
+          .zip(self.c.iter())
+          .map(|(S_i, c_i)| S_i.iter().fold(*c_i, |acc, j| acc * Mz[*j][*row]))
+          .fold(G::Scalar::zero(), |acc, x| acc + x)
+          != G::Scalar::zero()
+      })
+    };
+
+    // verify if comm_W is a commitment to W
+    let res_comm: bool = U.comm_W == W.W.commit(&gens.gens);
+
+    if unsat_constraint.is_some() || !res_comm {
+      Err(NovaError::UnSat {
+        constraint: unsat_constraint,
+      })
+    } else {
+      Ok(())
+    }
+  }
+
+  /// Checks if the linearized CCS instance is satisfiable given a witness and its shape
+  pub fn is_sat_linearized(
+    &self,
+    gens: &R1CSGens<G>,
+    U: &LCCCSInstance<G>,
+    W: &CCSWitness<G>,
+  ) -> Result<(), NovaError> {
+    assert_eq!(W.W.len(), self.num_vars);
+    assert_eq!(U.X.len(), self.num_io);
+    NovaError::check_lengths(&[(self.num_vars_x(), U.r_x.len()), (self.M.len(), U.v.len())])?;
+
+    // verify if v_j is the evaluation of the multilinear extension of M_j z at r_x
+    let res_eq: bool = {
+      let z = concat(vec![W.W.clone(), vec![U.u], U.X.clone()]);
+      let Mz = self.multiply_vec(&z)?;
+      let eq_rx = EqPolynomial::new(U.r_x.clone()).evals();
+      Mz.par_iter().zip(U.v.par_iter()).all(|(Mz_j, v_j)| {
+        Mz_j
+          .iter()
+          .zip(eq_rx.iter())
+          .map(|(a, b)| *a * b)
+          .fold(G::Scalar::zero(), |acc, x| acc + x)
+          == *v_j
+      })
+    };
+
+    // verify if comm_W is a commitment to W
+    let res_comm: bool = U.comm_W == W.W.commit(&gens.gens);
+
+    if res_eq && res_comm {
+      Ok(())
+    } else {
+      Err(NovaError::UnSat { constraint: None })
+    }
+  }
+
+  fn compute_digest(
+    num_cons: usize,
+    num_vars: usize,
+    num_io: usize,
+    M: &[Vec<(usize, usize, G::Scalar)>],
+    S: &[Vec<usize>],
+    c: &[G::Scalar],
+  ) -> G::Scalar {
+    let shape_serialized = CCSShapeSerialized {
+      num_cons,
+      num_vars,
+      num_io,
+      M: M
+        .iter()
+        .map(|M_j| {
+          M_j
+            .par_iter()
+            .map(|(i, j, v)| (*i, *j, v.to_repr().as_ref().to_vec()))
+            .collect()
+        })
+        .collect(),
+      S: S.to_vec(),
+      c: c
+        .iter()
+        .map(|c_i| c_i.to_repr().as_ref().to_vec())
+        .collect(),
+    };
+
+    // obtain a vector of bytes representing the CCS shape
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    bincode::serialize_into(&mut encoder, &shape_serialized).unwrap();
+    let shape_bytes = encoder.finish().unwrap();
+
+    // convert shape_bytes into a short digest
+    let mut hasher = Sha3_256::new();
+    hasher.input(&shape_bytes);
+    let digest = hasher.result();
+
+    // truncate the digest to 250 bits
+    let bv = (0..NUM_HASH_BITS).map(|i| {
+      let (byte_pos, bit_pos) = (i / 8, i % 8);
+      let bit = (digest[byte_pos] >> bit_pos) & 1;
+      bit == 1
+    });
+
+    // turn the bit vector into a scalar
+    let mut res = G::Scalar::zero();
+    let mut coeff = G::Scalar::one();
+    for bit in bv {
+      if bit {
+        res += coeff;
+      }
+      coeff += coeff;
+    }
+    res
+  }
+}
+
+#[derive(Serialize, Deserialize)]
+struct CCSShapeSerialized {
+  num_cons: usize,
+  num_vars: usize,
+  num_io: usize,
+  M: Vec<Vec<(usize, usize, Vec<u8>)>>,
+  S: Vec<Vec<usize>>,
+  c: Vec<Vec<u8>>,
+}
+
+impl<G: Group> CCSWitness<G> {
+  /// A method to create a witness object using a vector of scalars
+  pub fn new(S: &CCSShape<G>, W: &[G::Scalar]) -> Result<CCSWitness<G>, NovaError> {
+    if S.num_vars != W.len() {
+      Err(NovaError::InvalidWitnessLength {
+        expected: S.num_vars,
+        actual: W.len(),
+      })
+    } else {
+      Ok(CCSWitness { W: W.to_owned() })
+    }
+  }
+
+  /// Produces a default witness of zeros given a CCSShape
+  pub fn default(S: &CCSShape<G>) -> CCSWitness<G> {
+    CCSWitness {
+      W: vec![G::Scalar::zero(); S.num_vars],
+    }
+  }
+
+  /// Commits to the witness using the supplied generators
+  pub fn commit(&self, gens: &R1CSGens<G>) -> Commitment<G> {
+    self.W.commit(&gens.gens)
+  }
+
+  /// Folds an incoming witness into the current one
+  pub fn fold(&self, W2: &CCSWitness<G>, r: &G::Scalar) -> Result<CCSWitness<G>, NovaError> {
+    if self.W.len() != W2.W.len() {
+      return Err(NovaError::InvalidWitnessLength {
+        expected: self.W.len(),
+        actual: W2.W.len(),
+      });
+    }
+    let W = self
+      .W
+      .par_iter()
+      .zip(W2.W.par_iter())
+      .map(|(a, b)| *a + *r * *b)
+      .collect();
+    Ok(CCSWitness { W })
+  }
+}
+
+impl<G: Group> CCCSInstance<G> {
+  /// A method to create an instance object using consitituent elements
+  pub fn new(
+    S: &CCSShape<G>,
+    comm_W: &Commitment<G>,
+    X: &[G::Scalar],
+  ) -> Result<CCCSInstance<G>, NovaError> {
+    if S.num_io != X.len() {
+      Err(NovaError::InvalidInputLength {
+        expected: S.num_io,
+        actual: X.len(),
+      })
+    } else {
+      Ok(CCCSInstance {
+        comm_W: *comm_W,
+        X: X.to_owned(),
+      })
+    }
+  }
+}
+
+impl<G: Group> LCCCSInstance<G> {
+  /// Produces a default linearized instance given R1CSGens and CCSShape,
+  /// which is satisfied by the default witness
+  pub fn default(gens: &R1CSGens<G>, S: &CCSShape<G>) -> LCCCSInstance<G> {
+    LCCCSInstance {
+      comm_W: CCSWitness::default(S).commit(gens),
+      X: vec![G::Scalar::zero(); S.num_io],
+      u: G::Scalar::zero(),
+      r_x: vec![G::Scalar::zero(); S.num_vars_x()],
+      v: vec![G::Scalar::zero(); S.M.len()],
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::bellperson::{
+    r1cs::{NovaShape, NovaWitness},
+    shape_cs::ShapeCS,
+    solver::SatisfyingAssignment,
+  };
+  use ::bellperson::{gadgets::num::AllocatedNum, ConstraintSystem};
+
+  type G = pasta_curves::pallas::Point;
+  type S = pasta_curves::pallas::Scalar;
+
+  fn synthesize_square<CS: ConstraintSystem<S>>(cs: &mut CS, x: Option<u64>) {
+    let x = AllocatedNum::alloc(cs.namespace(|| "x"), || Ok(S::from(x.unwrap()))).unwrap();
+    let y = x.square(cs.namespace(|| "x^2")).unwrap();
+    x.inputize(cs.namespace(|| "input")).unwrap();
+    y.inputize(cs.namespace(|| "output")).unwrap();
+  }
+
+  #[test]
+  fn test_ccs_from_r1cs() {
+    let mut cs: ShapeCS<G> = ShapeCS::new();
+    synthesize_square(&mut cs, None);
+    let shape = cs.r1cs_shape();
+    let gens = cs.r1cs_gens();
+    let ccs = CCSShape::from_r1cs(&shape);
+    assert_eq!(ccs.degree(), 2);
+
+    let mut cs: SatisfyingAssignment<G> = SatisfyingAssignment::new();
+    synthesize_square(&mut cs, Some(7));
+    let (U, W) = cs.r1cs_instance_and_witness(&shape, &gens).unwrap();
+    let U_ccs = CCCSInstance::new(&ccs, &U.comm_W, &U.X).unwrap();
+    let W_ccs = CCSWitness::new(&ccs, &W.W).unwrap();
+    assert!(ccs.is_sat(&gens, &U_ccs, &W_ccs).is_ok());
+
+    // a wrong output does not satisfy the CCS
+    let mut X = U.X.clone();
+    X[1] += S::one();
+    let U_ccs = CCCSInstance::new(&ccs, &U.comm_W, &X).unwrap();
+    assert!(matches!(
+      ccs.is_sat(&gens, &U_ccs, &W_ccs),
+      Err(NovaError::UnSat {
+        constraint: Some(_)
+      })
+    ));
+
+    // the default linearized instance is satisfied by the default witness
+    let U_default = LCCCSInstance::default(&gens, &ccs);
+    assert!(ccs
+      .is_sat_linearized(&gens, &U_default, &CCSWitness::default(&ccs))
+      .is_ok());
+  }
+}
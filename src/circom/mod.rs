@@ -0,0 +1,569 @@
+//! This module imports circuits written in Circom. It reads the constraints of a circuit
+//! from a `.r1cs` file and its witnesses from `.wtns` files, and provides `CircomCircuit`,
+//! a step circuit that synthesizes the constraints with bellperson.
+//!
+//! The constraints are enforced exactly as Circom emits them, so a Circom circuit that is
+//! under-constrained remains so in Nova. Both readers check that the prime in the header is
+//! the modulus of the field before they allocate anything. The state of a step is a single
+//! wire, the public signals of Circom become private wires, and the witness of a step is the
+//! first one whose input wire holds the input of the step, so two steps with the same input
+//! cannot have different witnesses. There is no witness generator: `.wtns` files must be
+//! produced with the tooling of Circom.
+//!
+//! It also exports the shapes and the witnesses of Nova circuits to the same formats,
+//! and to the JSON form of snarkjs, so that they can be inspected with external tools.
+//! Wires are labelled by their index, since the names of namespaces are not exported; the
+//! profiler of `profile` attributes constraints to namespaces instead. A relaxed instance is
+//! exported as `(u, X, W)` with its error vector apart, so tools that check plain R1CS accept
+//! it only if `u = 1` and `E = 0`. The export of a shape with a lookup argument includes its
+//! lookup constraints and reserved IO.
+mod r1cs;
+mod wtns;
+
+pub use r1cs::{CircomLC, CircomR1CS};
+pub use wtns::{read_witness, witness_from_file, witness_to_json, write_witness};
+
+use crate::{
+  errors::NovaError,
+  r1cs::{R1CSInstance, R1CSWitness, RelaxedR1CSInstance, RelaxedR1CSWitness},
+  traits::{Group, StepCircuit},
+};
+use bellperson::{gadgets::num::AllocatedNum, ConstraintSystem, LinearCombination, SynthesisError};
+use ff::{Field, PrimeField};
+use num_bigint::BigUint;
+use std::{
+  io::{self, Read, Write},
+  sync::Arc,
+};
+
+/// Returns the assignment of the wires of `CircomR1CS::from_shape(S)` for an instance `U`
+/// and a witness `W` of the shape `S`, which is `(1, X, W)`
+pub fn r1cs_assignment<G: Group>(U: &R1CSInstance<G>, W: &R1CSWitness<G>) -> Vec<G::Scalar> {
+  [vec![G::Scalar::one()], U.X.clone(), W.W.clone()].concat()
+}
+
+/// Returns the assignment of the wires of `CircomR1CS::from_shape(S)` for a relaxed instance `U`
+/// and a relaxed witness `W` of the shape `S`, which is `(u, X, W)`, along with the error vector.
+/// The constraints then hold up to the error: `A z * B z = u * C z + E`.
+pub fn relaxed_r1cs_assignment<G: Group>(
+  U: &RelaxedR1CSInstance<G>,
+  W: &RelaxedR1CSWitness<G>,
+) -> (Vec<G::Scalar>, Vec<G::Scalar>) {
+  ([vec![U.u], U.X.clone(), W.W.clone()].concat(), W.E.clone())
+}
+
+/// Returns the modulus of `F`
+fn modulus<F: PrimeField>() -> BigUint {
+  BigUint::from_bytes_le((-F::one()).to_repr().as_ref()) + 1u32
+}
+
+/// Returns a field element as a decimal string, as in the JSON files of snarkjs
+fn to_decimal<F: PrimeField>(f: &F) -> String {
+  BigUint::from_bytes_le(f.to_repr().as_ref()).to_str_radix(10)
+}
+
+/// Writes a file in the binary format of iden3 from its sections
+fn write_sections<W: Write>(
+  mut writer: W,
+  magic: &[u8; 4],
+  version: u32,
+  sections: &[(u32, Vec<u8>)],
+) -> io::Result<()> {
+  writer.write_all(magic)?;
+  writer.write_all(&version.to_le_bytes())?;
+  writer.write_all(&(sections.len() as u32).to_le_bytes())?;
+  for (section_type, bytes) in sections {
+    writer.write_all(&section_type.to_le_bytes())?;
+    writer.write_all(&(bytes.len() as u64).to_le_bytes())?;
+    writer.write_all(bytes)?;
+  }
+  writer.flush()
+}
+
+/// Appends the size of the representation of `F` and the modulus of `F` to a section
+fn write_prime<F: PrimeField>(section: &mut Vec<u8>) {
+  let n8 = F::Repr::default().as_ref().len();
+  let mut prime = modulus::<F>().to_bytes_le();
+  prime.resize(n8, 0);
+  section.extend_from_slice(&(n8 as u32).to_le_bytes());
+  section.extend_from_slice(&prime);
+}
+
+/// Returns an error for a malformed file
+fn invalid_file<S: Into<String>>(reason: S) -> NovaError {
+  NovaError::InvalidCircomFile {
+    reason: reason.into(),
+  }
+}
+
+/// Reads the sections of a file in the binary format of iden3: a magic string, a version,
+/// and a list of sections, each made of a type, a size and the bytes of the section
+fn read_sections<R: Read>(
+  mut reader: R,
+  magic: &[u8; 4],
+) -> Result<Vec<(u32, Vec<u8>)>, NovaError> {
+  let mut bytes = Vec::new();
+  reader
+    .read_to_end(&mut bytes)
+    .map_err(|e| invalid_file(format!("cannot read the file: {}", e)))?;
+  let mut bytes = &bytes[..];
+
+  if bytes.len() < magic.len() || &bytes[..magic.len()] != magic {
+    return Err(invalid_file(format!(
+      "the file does not start with {:?}",
+      String::from_utf8_lossy(magic)
+    )));
+  }
+  bytes = &bytes[magic.len()..];
+  let _version = read_u32(&mut bytes)?;
+  let num_sections = read_u32(&mut bytes)?;
+
+  (0..num_sections)
+    .map(|_| {
+      let section_type = read_u32(&mut bytes)?;
+      let size = read_u64(&mut bytes)? as usize;
+      if size > bytes.len() {
+        return Err(invalid_file(format!(
+          "section {} is truncated",
+          section_type
+        )));
+      }
+      let (section, rest) = bytes.split_at(size);
+      bytes = rest;
+      Ok((section_type, section.to_vec()))
+    })
+    .collect()
+}
+
+/// Returns the first section of the given type
+fn section(sections: &[(u32, Vec<u8>)], section_type: u32) -> Result<&[u8], NovaError> {
+  sections
+    .iter()
+    .find(|(t, _)| *t == section_type)
+    .map(|(_, bytes)| &bytes[..])
+    .ok_or_else(|| invalid_file(format!("section {} is missing", section_type)))
+}
+
+fn read_u32(reader: &mut &[u8]) -> Result<u32, NovaError> {
+  let mut buf = [0u8; 4];
+  reader
+    .read_exact(&mut buf)
+    .map_err(|_| invalid_file("unexpected end of the file"))?;
+  Ok(u32::from_le_bytes(buf))
+}
+
+fn read_u64(reader: &mut &[u8]) -> Result<u64, NovaError> {
+  let mut buf = [0u8; 8];
+  reader
+    .read_exact(&mut buf)
+    .map_err(|_| invalid_file("unexpected end of the file"))?;
+  Ok(u64::from_le_bytes(buf))
+}
+
+/// Checks that the prime of a file, in `n8` little-endian bytes, is the modulus of `F`
+fn check_prime<F: PrimeField>(reader: &mut &[u8], n8: usize) -> Result<(), NovaError> {
+  // the length is checked before anything is allocated, as it comes from the file
+  let len = F::Repr::default().as_ref().len();
+  if n8 != len {
+    return Err(invalid_file(format!(
+      "field elements are {} bytes instead of {}",
+      n8, len
+    )));
+  }
+  let mut prime = vec![0u8; n8];
+  reader
+    .read_exact(&mut prime)
+    .map_err(|_| invalid_file("unexpected end of the file"))?;
+  if BigUint::from_bytes_le(&prime) == modulus::<F>() {
+    Ok(())
+  } else {
+    Err(invalid_file(format!(
+      "the file is defined over the prime {}, not over the scalar field",
+      BigUint::from_bytes_le(&prime)
+    )))
+  }
+}
+
+/// Reads a field element in `n8` little-endian bytes
+fn read_field<F: PrimeField>(reader: &mut &[u8], n8: usize) -> Result<F, NovaError> {
+  let mut repr = F::Repr::default();
+  if repr.as_ref().len() != n8 {
+    return Err(invalid_file(format!(
+      "field elements are {} bytes instead of {}",
+      n8,
+      repr.as_ref().len()
+    )));
+  }
+  reader
+    .read_exact(repr.as_mut())
+    .map_err(|_| invalid_file("unexpected end of the file"))?;
+  Option::from(F::from_repr(repr)).ok_or_else(|| invalid_file("a field element is not reduced"))
+}
+
+/// The wires of a Circom circuit that hold the input and the output of a step.
+/// Wire 0 is the constant one, and is followed by the public outputs, the public inputs
+/// and the private inputs, so in a circuit with one output and one input,
+/// the output is wire 1 and the input is wire 2.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CircomStepConfig {
+  /// the wire that holds the input of a step
+  pub input: usize,
+  /// the wire that holds the output of a step
+  pub output: usize,
+}
+
+impl CircomStepConfig {
+  /// Creates a mapping of the input and the output of a step to wires
+  pub fn new(input: usize, output: usize) -> Self {
+    Self { input, output }
+  }
+}
+
+/// A step circuit made of the constraints of a Circom circuit. Since Circom computes witnesses
+/// outside of Rust, the circuit holds the witnesses of the steps, and in each step uses
+/// the witness whose input wire holds the input of the step.
+#[derive(Clone, Debug)]
+pub struct CircomCircuit<F: PrimeField> {
+  r1cs: Arc<CircomR1CS<F>>,
+  config: CircomStepConfig,
+  witnesses: Vec<Vec<F>>,
+}
+
+impl<F: PrimeField> CircomCircuit<F> {
+  /// Creates a step circuit from the constraints of a Circom circuit and a mapping
+  /// of the input and the output of a step to wires
+  pub fn new(r1cs: CircomR1CS<F>, config: CircomStepConfig) -> Result<Self, NovaError> {
+    // wire 0 is the constant one
+    let num_wires = r1cs.num_wires();
+    for wire in [config.input, config.output] {
+      if wire == 0 || wire >= num_wires {
+        return Err(NovaError::InvalidWire { wire, num_wires });
+      }
+    }
+    Ok(Self {
+      r1cs: Arc::new(r1cs),
+      config,
+      witnesses: Vec::new(),
+    })
+  }
+
+  /// Adds the witness of a step, which must satisfy the constraints
+  pub fn add_witness(&mut self, witness: Vec<F>) -> Result<(), NovaError> {
+    self.r1cs.is_sat(&witness)?;
+    self.witnesses.push(witness);
+    Ok(())
+  }
+
+  /// Returns the witness whose input wire holds `z`
+  fn find_witness(&self, z: &F) -> Option<&[F]> {
+    self
+      .witnesses
+      .iter()
+      .find(|w| w[self.config.input] == *z)
+      .map(|w| &w[..])
+  }
+}
+
+impl<F: PrimeField> StepCircuit<F> for CircomCircuit<F> {
+  fn synthesize<CS: ConstraintSystem<F>>(
+    &self,
+    cs: &mut CS,
+    z: AllocatedNum<F>,
+  ) -> Result<AllocatedNum<F>, SynthesisError> {
+    // without values, as when synthesizing the shape, no witness is needed
+    let witness = match z.get_value() {
+      Some(z) => Some(
+        self
+          .find_witness(&z)
+          .ok_or(SynthesisError::AssignmentMissing)?,
+      ),
+      None => None,
+    };
+
+    // the input wire is z, and every other wire but the constant one is allocated
+    let mut vars = vec![CS::one()];
+    let mut output = None;
+    for i in 1..self.r1cs.num_wires() {
+      let wire = if i == self.config.input {
+        z.clone()
+      } else {
+        AllocatedNum::alloc(cs.namespace(|| format!("wire {}", i)), || {
+          witness
+            .map(|w| w[i])
+            .ok_or(SynthesisError::AssignmentMissing)
+        })?
+      };
+      vars.push(wire.get_variable());
+      if i == self.config.output {
+        output = Some(wire);
+      }
+    }
+
+    let lc = |terms: &CircomLC<F>| {
+      terms
+        .iter()
+        .fold(LinearCombination::zero(), |lc, (wire, coeff)| {
+          lc + (*coeff, vars[*wire])
+        })
+    };
+    for (i, (A, B, C)) in self.r1cs.constraints.iter().enumerate() {
+      cs.enforce(
+        || format!("constraint {}", i),
+        |_| lc(A),
+        |_| lc(B),
+        |_| lc(C),
+      );
+    }
+
+    output.ok_or(SynthesisError::Unsatisfiable)
+  }
+
+  /// Panics if no witness has the input `z`; the prover calls `try_compute` instead
+  fn compute(&self, z: &F) -> F {
+    self.try_compute(z).unwrap()
+  }
+
+  fn try_compute(&self, z: &F) -> Result<F, NovaError> {
+    self
+      .find_witness(z)
+      .map(|w| w[self.config.output])
+      .ok_or_else(|| SynthesisError::AssignmentMissing.into())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    bellperson::{
+      r1cs::{NovaShape, NovaWitness},
+      shape_cs::ShapeCS,
+      solver::SatisfyingAssignment,
+    },
+    nifs::NIFS,
+    traits::{HashFuncConstantsTrait, HashFuncTrait},
+    PublicParams, RecursiveSNARK,
+  };
+  use std::marker::PhantomData;
+
+  type G1 = pasta_curves::pallas::Point;
+  type G2 = pasta_curves::vesta::Point;
+  type S = <G1 as Group>::Scalar;
+
+  const CUBIC_R1CS: &[u8] = include_bytes!("fixtures/cubic.r1cs");
+  const CUBIC_WTNS: [&[u8]; 2] = [
+    include_bytes!("fixtures/cubic_0.wtns"),
+    include_bytes!("fixtures/cubic_1.wtns"),
+  ];
+
+  // the fixtures compute y = x^3 + x + 5, with wires one, y, x, x^2 and x^3
+  fn cubic_circuit() -> CircomCircuit<S> {
+    let r1cs = CircomR1CS::read(CUBIC_R1CS).unwrap();
+    let mut circuit = CircomCircuit::new(r1cs, CircomStepConfig::new(2, 1)).unwrap();
+    for wtns in CUBIC_WTNS {
+      circuit.add_witness(read_witness(wtns).unwrap()).unwrap();
+    }
+    circuit
+  }
+
+  #[derive(Clone, Debug)]
+  struct TrivialTestCircuit<F: PrimeField> {
+    _p: PhantomData<F>,
+  }
+
+  impl<F: PrimeField> StepCircuit<F> for TrivialTestCircuit<F> {
+    fn synthesize<CS: ConstraintSystem<F>>(
+      &self,
+      _cs: &mut CS,
+      z: AllocatedNum<F>,
+    ) -> Result<AllocatedNum<F>, SynthesisError> {
+      Ok(z)
+    }
+
+    fn compute(&self, z: &F) -> F {
+      *z
+    }
+  }
+
+  #[test]
+  fn test_circom_files() {
+    let r1cs = CircomR1CS::<S>::read(CUBIC_R1CS).unwrap();
+    assert_eq!(r1cs.num_wires(), 5);
+    assert_eq!(r1cs.num_constraints(), 3);
+    assert_eq!(r1cs.num_signals(), (1, 1, 0));
+
+    let witness = read_witness::<S, _>(CUBIC_WTNS[0]).unwrap();
+    let expected = [1u64, 35, 3, 9, 27].map(S::from).to_vec();
+    assert_eq!(witness, expected);
+    assert!(r1cs.is_sat(&witness).is_ok());
+
+    let mut tampered = witness;
+    tampered[1] += S::one();
+    assert!(matches!(
+      r1cs.is_sat(&tampered),
+      Err(NovaError::UnSat {
+        constraint: Some(_)
+      })
+    ));
+
+    // malformed files and files over another field are rejected
+    assert!(matches!(
+      CircomR1CS::<S>::read(CUBIC_WTNS[0]),
+      Err(NovaError::InvalidCircomFile { .. })
+    ));
+    assert!(matches!(
+      CircomR1CS::<S>::read(&CUBIC_R1CS[..CUBIC_R1CS.len() - 1]),
+      Err(NovaError::InvalidCircomFile { .. })
+    ));
+    assert!(matches!(
+      CircomR1CS::<<G2 as Group>::Scalar>::read(CUBIC_R1CS),
+      Err(NovaError::InvalidCircomFile { .. })
+    ));
+
+    // the length of the prime is checked before a buffer of that length is allocated
+    assert!(matches!(
+      check_prime::<S>(&mut &CUBIC_R1CS[..], u32::MAX as usize),
+      Err(NovaError::InvalidCircomFile { .. })
+    ));
+
+    // the input and the output must be wires other than the constant one
+    assert!(CircomCircuit::new(r1cs.clone(), CircomStepConfig::new(0, 1)).is_err());
+    assert!(CircomCircuit::new(r1cs, CircomStepConfig::new(2, 5)).is_err());
+  }
+
+  #[test]
+  fn test_circom_circuit() {
+    let circuit = cubic_circuit();
+
+    let mut cs: ShapeCS<G1> = ShapeCS::new();
+    let z = AllocatedNum::alloc(cs.namespace(|| "z"), || Ok(S::zero())).unwrap();
+    let _ = circuit.synthesize(&mut cs, z).unwrap();
+    let shape = cs.r1cs_shape();
+    let gens = cs.r1cs_gens();
+    assert_eq!(shape.num_cons, 3);
+
+    let mut cs: SatisfyingAssignment<G1> = SatisfyingAssignment::new();
+    let z = AllocatedNum::alloc(cs.namespace(|| "z"), || Ok(S::from(3))).unwrap();
+    let y = circuit.synthesize(&mut cs, z).unwrap();
+    assert_eq!(y.get_value(), Some(S::from(35)));
+    assert_eq!(circuit.compute(&S::from(3)), S::from(35));
+    let (inst, witness) = cs.r1cs_instance_and_witness(&shape, &gens).unwrap();
+    assert!(shape.is_sat(&gens, &inst, &witness).is_ok());
+
+    // an input without a witness can neither be synthesized nor computed
+    let mut cs: SatisfyingAssignment<G1> = SatisfyingAssignment::new();
+    let z = AllocatedNum::alloc(cs.namespace(|| "z"), || Ok(S::from(4))).unwrap();
+    assert!(circuit.synthesize(&mut cs, z).is_err());
+    assert!(matches!(
+      circuit.try_compute(&S::from(4)),
+      Err(NovaError::Synthesis { .. })
+    ));
+  }
+
+  #[test]
+  fn test_circom_export() {
+    // the fixtures survive a round trip through the writers
+    let r1cs = CircomR1CS::<S>::read(CUBIC_R1CS).unwrap();
+    let mut bytes = Vec::new();
+    r1cs.write(&mut bytes).unwrap();
+    assert_eq!(CircomR1CS::read(&bytes[..]), Ok(r1cs.clone()));
+
+    let witness = read_witness::<S, _>(CUBIC_WTNS[0]).unwrap();
+    let mut bytes = Vec::new();
+    write_witness(&witness, &mut bytes).unwrap();
+    assert_eq!(read_witness::<S, _>(&bytes[..]), Ok(witness.clone()));
+    assert_eq!(
+      witness_to_json(&witness),
+      serde_json::json!(["1", "35", "3", "9", "27"])
+    );
+    assert_eq!(r1cs.to_json()["nConstraints"], 3);
+
+    // export a Nova circuit whose input and output are public
+    let circuit = cubic_circuit();
+    let mut cs: ShapeCS<G1> = ShapeCS::new();
+    let z = AllocatedNum::alloc(cs.namespace(|| "z"), || Ok(S::zero())).unwrap();
+    z.inputize(cs.namespace(|| "input")).unwrap();
+    let y = circuit.synthesize(&mut cs, z).unwrap();
+    y.inputize(cs.namespace(|| "output")).unwrap();
+    let shape = cs.r1cs_shape();
+    let gens = cs.r1cs_gens();
+    let exported = CircomR1CS::from_shape(&shape);
+    assert_eq!(exported.num_signals(), (0, 2, 0));
+    assert_eq!(exported.num_constraints(), shape.num_cons);
+
+    let instances = [3u64, 35]
+      .iter()
+      .map(|x| {
+        let mut cs: SatisfyingAssignment<G1> = SatisfyingAssignment::new();
+        let z = AllocatedNum::alloc(cs.namespace(|| "z"), || Ok(S::from(*x))).unwrap();
+        z.inputize(cs.namespace(|| "input")).unwrap();
+        let y = circuit.synthesize(&mut cs, z).unwrap();
+        y.inputize(cs.namespace(|| "output")).unwrap();
+        cs.r1cs_instance_and_witness(&shape, &gens).unwrap()
+      })
+      .collect::<Vec<_>>();
+    for (U, W) in &instances {
+      let assignment = r1cs_assignment(U, W);
+      assert!(exported.is_sat(&assignment).is_ok());
+      assert_eq!(assignment[1..3], U.X[..]);
+    }
+
+    // a folded instance satisfies the exported constraints up to its error vector
+    let ro_consts =
+      <<G1 as Group>::HashFunc as HashFuncTrait<<G1 as Group>::Base, S>>::Constants::new();
+    let (U1, W1) = &instances[0];
+    let (U2, W2) = &instances[1];
+    let (_, (U, W)) = NIFS::prove(
+      &gens,
+      &ro_consts,
+      &shape.get_digest(),
+      &shape,
+      &RelaxedR1CSInstance::from_r1cs_instance(&gens, &shape, U1),
+      &RelaxedR1CSWitness::from_r1cs_witness(&shape, W1),
+      U2,
+      W2,
+    )
+    .unwrap();
+    let (z, E) = relaxed_r1cs_assignment(&U, &W);
+    assert_eq!(z[0], U.u);
+    let eval = |lc: &CircomLC<S>| {
+      lc.iter()
+        .fold(S::zero(), |acc, (wire, coeff)| acc + *coeff * z[*wire])
+    };
+    for (i, (A, B, C)) in exported.constraints.iter().enumerate() {
+      assert_eq!(eval(A) * eval(B), U.u * eval(C) + E[i]);
+    }
+  }
+
+  #[test]
+  fn test_circom_recursive_snark() {
+    let circuit_primary = cubic_circuit();
+    let circuit_secondary = TrivialTestCircuit {
+      _p: Default::default(),
+    };
+
+    let pp = PublicParams::<
+      G1,
+      G2,
+      CircomCircuit<<G1 as Group>::Scalar>,
+      TrivialTestCircuit<<G2 as Group>::Scalar>,
+    >::setup(circuit_primary, circuit_secondary);
+
+    // the exported primary circuit holds the verifier circuit and the constraints of the step
+    let (r1cs_primary, _) = pp.circom_r1cs();
+    assert_eq!(
+      r1cs_primary.num_constraints(),
+      pp.r1cs_shape_primary.num_cons
+    );
+    assert!(r1cs_primary.num_constraints() > 3);
+
+    let num_steps = 2;
+    let recursive_snark =
+      RecursiveSNARK::prove(&pp, num_steps, S::from(3), <G2 as Group>::Scalar::zero()).unwrap();
+
+    let res = recursive_snark.verify(&pp, num_steps, S::from(3), <G2 as Group>::Scalar::zero());
+    assert!(res.is_ok());
+    let (zn_primary, _) = res.unwrap();
+    assert_eq!(zn_primary, S::from(42915));
+  }
+}
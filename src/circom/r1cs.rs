@@ -0,0 +1,248 @@
+//! This module reads and writes the constraints of a Circom circuit in the binary `.r1cs` format.
+//! The file holds a header section with the prime and the numbers of wires and constraints,
+//! and a section with the constraints `A * B - C = 0`, where `A`, `B` and `C` are
+//! linear combinations of the wires. Wire 0 is the constant one.
+use super::{
+  check_prime, invalid_file, modulus, read_field, read_sections, read_u32, read_u64, section,
+  to_decimal, write_prime, write_sections,
+};
+use crate::{errors::NovaError, r1cs::R1CSShape, traits::Group};
+use ff::PrimeField;
+use serde_json::json;
+use std::{
+  collections::BTreeMap,
+  fs::File,
+  io::{self, Read, Write},
+  path::Path,
+};
+
+/// A linear combination of wires, as pairs of a wire index and a coefficient
+pub type CircomLC<F> = Vec<(usize, F)>;
+
+const HEADER_SECTION: u32 = 1;
+const CONSTRAINTS_SECTION: u32 = 2;
+const WIRE_TO_LABEL_SECTION: u32 = 3;
+const VERSION: u32 = 1;
+
+/// The constraints of a Circom circuit
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CircomR1CS<F: PrimeField> {
+  pub(crate) num_wires: usize,
+  pub(crate) num_pub_out: usize,
+  pub(crate) num_pub_in: usize,
+  pub(crate) num_prv_in: usize,
+  pub(crate) constraints: Vec<(CircomLC<F>, CircomLC<F>, CircomLC<F>)>,
+}
+
+impl<F: PrimeField> CircomR1CS<F> {
+  /// Reads the constraints from a reader over a `.r1cs` file
+  pub fn read<R: Read>(reader: R) -> Result<Self, NovaError> {
+    let sections = read_sections(reader, b"r1cs")?;
+
+    // the header: the field, the numbers of wires and signals, and the number of constraints
+    let mut header = section(&sections, HEADER_SECTION)?;
+    let n8 = read_u32(&mut header)? as usize;
+    check_prime::<F>(&mut header, n8)?;
+    let num_wires = read_u32(&mut header)? as usize;
+    let num_pub_out = read_u32(&mut header)? as usize;
+    let num_pub_in = read_u32(&mut header)? as usize;
+    let num_prv_in = read_u32(&mut header)? as usize;
+    let _num_labels = read_u64(&mut header)?;
+    let num_constraints = read_u32(&mut header)? as usize;
+    if num_wires == 0 || 1 + num_pub_out + num_pub_in + num_prv_in > num_wires {
+      return Err(invalid_file("the header has more signals than wires"));
+    }
+
+    let mut body = section(&sections, CONSTRAINTS_SECTION)?;
+    let mut read_lc = || -> Result<CircomLC<F>, NovaError> {
+      let num_terms = read_u32(&mut body)? as usize;
+      (0..num_terms)
+        .map(|_| {
+          let wire = read_u32(&mut body)? as usize;
+          if wire >= num_wires {
+            return Err(NovaError::InvalidWire { wire, num_wires });
+          }
+          Ok((wire, read_field::<F>(&mut body, n8)?))
+        })
+        .collect()
+    };
+    let constraints = (0..num_constraints)
+      .map(|_| Ok((read_lc()?, read_lc()?, read_lc()?)))
+      .collect::<Result<Vec<_>, NovaError>>()?;
+
+    Ok(Self {
+      num_wires,
+      num_pub_out,
+      num_pub_in,
+      num_prv_in,
+      constraints,
+    })
+  }
+
+  /// Reads the constraints from a `.r1cs` file
+  pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, NovaError> {
+    let file =
+      File::open(path).map_err(|e| invalid_file(format!("cannot open the file: {}", e)))?;
+    Self::read(file)
+  }
+
+  /// Converts the shape of a Nova circuit, whose variables are `(W, u, X)`, to the wires
+  /// `(1, X, W)`: the public IO become public inputs, and the rest are internal wires
+  pub fn from_shape<G: Group<Scalar = F>>(S: &R1CSShape<G>) -> Self {
+    let wire = |col: usize| {
+      if col < S.num_vars {
+        1 + S.num_io + col
+      } else {
+        col - S.num_vars
+      }
+    };
+
+    // merge the entries of a row that refer to the same wire, as the JSON form keys terms by wire
+    let lcs = |M: &[(usize, usize, F)]| {
+      let mut rows = vec![BTreeMap::new(); S.num_cons];
+      for (row, col, val) in M {
+        *rows[*row].entry(wire(*col)).or_insert_with(F::zero) += val;
+      }
+      rows
+        .into_iter()
+        .map(|terms| {
+          terms
+            .into_iter()
+            .filter(|(_, coeff)| !bool::from(coeff.is_zero()))
+            .collect::<CircomLC<F>>()
+        })
+        .collect::<Vec<_>>()
+    };
+    let (A, B, C) = (lcs(&S.A), lcs(&S.B), lcs(&S.C));
+
+    Self {
+      num_wires: 1 + S.num_io + S.num_vars,
+      num_pub_out: 0,
+      num_pub_in: S.num_io,
+      num_prv_in: 0,
+      constraints: A
+        .into_iter()
+        .zip(B)
+        .zip(C)
+        .map(|((a, b), c)| (a, b, c))
+        .collect(),
+    }
+  }
+
+  /// Writes the constraints in the `.r1cs` format, with each wire labelled by its index
+  pub fn write<W: Write>(&self, writer: W) -> io::Result<()> {
+    let mut header = Vec::new();
+    write_prime::<F>(&mut header);
+    for n in [
+      self.num_wires,
+      self.num_pub_out,
+      self.num_pub_in,
+      self.num_prv_in,
+    ] {
+      header.extend_from_slice(&(n as u32).to_le_bytes());
+    }
+    header.extend_from_slice(&(self.num_wires as u64).to_le_bytes());
+    header.extend_from_slice(&(self.constraints.len() as u32).to_le_bytes());
+
+    let mut body = Vec::new();
+    for (A, B, C) in &self.constraints {
+      for lc in [A, B, C] {
+        body.extend_from_slice(&(lc.len() as u32).to_le_bytes());
+        for (wire, coeff) in lc {
+          body.extend_from_slice(&(*wire as u32).to_le_bytes());
+          body.extend_from_slice(coeff.to_repr().as_ref());
+        }
+      }
+    }
+
+    let labels = (0..self.num_wires as u64)
+      .flat_map(|label| label.to_le_bytes())
+      .collect();
+
+    write_sections(
+      writer,
+      b"r1cs",
+      VERSION,
+      &[
+        (HEADER_SECTION, header),
+        (CONSTRAINTS_SECTION, body),
+        (WIRE_TO_LABEL_SECTION, labels),
+      ],
+    )
+  }
+
+  /// Writes the constraints to a `.r1cs` file
+  pub fn to_file<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+    self.write(File::create(path)?)
+  }
+
+  /// Returns the constraints in the JSON form of snarkjs, where each linear combination maps
+  /// wires to coefficients and field elements are decimal strings
+  pub fn to_json(&self) -> serde_json::Value {
+    let lc = |terms: &CircomLC<F>| {
+      terms
+        .iter()
+        .map(|(wire, coeff)| (wire.to_string(), json!(to_decimal(coeff))))
+        .collect::<serde_json::Map<_, _>>()
+    };
+    json!({
+      "n8": F::Repr::default().as_ref().len(),
+      "prime": modulus::<F>().to_str_radix(10),
+      "nVars": self.num_wires,
+      "nOutputs": self.num_pub_out,
+      "nPubInputs": self.num_pub_in,
+      "nPrvInputs": self.num_prv_in,
+      "nLabels": self.num_wires,
+      "nConstraints": self.constraints.len(),
+      "constraints": self
+        .constraints
+        .iter()
+        .map(|(A, B, C)| json!([lc(A), lc(B), lc(C)]))
+        .collect::<Vec<_>>(),
+    })
+  }
+
+  /// Returns the number of wires, including the constant one
+  pub fn num_wires(&self) -> usize {
+    self.num_wires
+  }
+
+  /// Returns the number of constraints
+  pub fn num_constraints(&self) -> usize {
+    self.constraints.len()
+  }
+
+  /// Returns the number of public outputs, public inputs and private inputs
+  pub fn num_signals(&self) -> (usize, usize, usize) {
+    (self.num_pub_out, self.num_pub_in, self.num_prv_in)
+  }
+
+  /// Checks if the assignment of the wires satisfies the constraints
+  pub fn is_sat(&self, witness: &[F]) -> Result<(), NovaError> {
+    if witness.len() != self.num_wires {
+      return Err(NovaError::InvalidWitnessLength {
+        expected: self.num_wires,
+        actual: witness.len(),
+      });
+    }
+    // the first wire is the constant one
+    if witness[0] != F::one() {
+      return Err(NovaError::UnSat { constraint: None });
+    }
+    let eval = |lc: &CircomLC<F>| {
+      lc.iter().fold(F::zero(), |acc, (wire, coeff)| {
+        acc + *coeff * witness[*wire]
+      })
+    };
+    match self
+      .constraints
+      .iter()
+      .position(|(A, B, C)| eval(A) * eval(B) != eval(C))
+    {
+      Some(constraint) => Err(NovaError::UnSat {
+        constraint: Some(constraint),
+      }),
+      None => Ok(()),
+    }
+  }
+}
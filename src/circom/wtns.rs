@@ -0,0 +1,69 @@
+//! This module reads and writes the assignment of the wires of a Circom circuit in the binary
+//! `.wtns` format, which holds a header section with the prime and the number of wires,
+//! and a section with the value of each wire.
+use super::{
+  check_prime, invalid_file, read_field, read_sections, read_u32, section, to_decimal, write_prime,
+  write_sections,
+};
+use crate::errors::NovaError;
+use ff::PrimeField;
+use std::{
+  fs::File,
+  io::{self, Read, Write},
+  path::Path,
+};
+
+const VERSION: u32 = 2;
+
+const HEADER_SECTION: u32 = 1;
+const WITNESS_SECTION: u32 = 2;
+
+/// Reads the value of each wire from a reader over a `.wtns` file
+pub fn read_witness<F: PrimeField, R: Read>(reader: R) -> Result<Vec<F>, NovaError> {
+  let sections = read_sections(reader, b"wtns")?;
+
+  let mut header = section(&sections, HEADER_SECTION)?;
+  let n8 = read_u32(&mut header)? as usize;
+  check_prime::<F>(&mut header, n8)?;
+  let num_wires = read_u32(&mut header)? as usize;
+
+  let mut body = section(&sections, WITNESS_SECTION)?;
+  if body.len() != num_wires * n8 {
+    return Err(invalid_file(
+      "the witness section does not match the number of wires",
+    ));
+  }
+  (0..num_wires)
+    .map(|_| read_field::<F>(&mut body, n8))
+    .collect()
+}
+
+/// Reads the value of each wire from a `.wtns` file
+pub fn witness_from_file<F: PrimeField, P: AsRef<Path>>(path: P) -> Result<Vec<F>, NovaError> {
+  let file = File::open(path).map_err(|e| invalid_file(format!("cannot open the file: {}", e)))?;
+  read_witness(file)
+}
+
+/// Writes the value of each wire in the `.wtns` format
+pub fn write_witness<F: PrimeField, W: Write>(witness: &[F], writer: W) -> io::Result<()> {
+  let mut header = Vec::new();
+  write_prime::<F>(&mut header);
+  header.extend_from_slice(&(witness.len() as u32).to_le_bytes());
+
+  let body = witness
+    .iter()
+    .flat_map(|w| w.to_repr().as_ref().to_vec())
+    .collect();
+
+  write_sections(
+    writer,
+    b"wtns",
+    VERSION,
+    &[(HEADER_SECTION, header), (WITNESS_SECTION, body)],
+  )
+}
+
+/// Returns the value of each wire in the JSON form of snarkjs, a list of decimal strings
+pub fn witness_to_json<F: PrimeField>(witness: &[F]) -> serde_json::Value {
+  witness.iter().map(to_decimal).collect()
+}
@@ -3,7 +3,7 @@
 //! only the primary executes the next step of the computation.
 //! We have two running instances. Each circuit takes as input 2 hashes: one for each
 //! of the running instances. Each of these hashes is
-//! H(params = H(shape, gens), i, z0, zi, U). Each circuit folds the last invocation of
+//! H(params, i, z0, zi, U), where params is the digest of the public parameters. Each circuit folds the last invocation of
 //! the other into the running instance
 
 use super::{
@@ -28,27 +28,30 @@ use bellperson::{
   Circuit, ConstraintSystem, SynthesisError,
 };
 use ff::Field;
+use serde::Serialize;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct NIFSVerifierCircuitParams {
   limb_width: usize,
   n_limbs: usize,
   is_primary_circuit: bool, // A boolean indicating if this is the primary circuit
+  lookup: bool,             // A boolean indicating if the folded instances carry a lookup argument
 }
 
 impl NIFSVerifierCircuitParams {
-  pub fn new(limb_width: usize, n_limbs: usize, is_primary_circuit: bool) -> Self {
+  pub fn new(limb_width: usize, n_limbs: usize, is_primary_circuit: bool, lookup: bool) -> Self {
     Self {
       limb_width,
       n_limbs,
       is_primary_circuit,
+      lookup,
     }
   }
 }
 
 #[derive(Debug)]
 pub struct NIFSVerifierCircuitInputs<G: Group> {
-  params: G::Scalar, // Hash(Shape of u2, Gens for u2). Needed for computing the challenge.
+  params: G::Scalar, // The digest of the public parameters. Needed for computing the challenge.
   i: G::Base,
   z0: G::Base,
   zi: Option<G::Base>,
@@ -155,6 +158,7 @@ where
       self.inputs.get().map_or(None, |inputs| {
         inputs.U.get().map_or(None, |U| Some(U.clone()))
       }),
+      self.params.lookup,
       self.params.limb_width,
       self.params.n_limbs,
     )?;
@@ -165,6 +169,7 @@ where
       self.inputs.get().map_or(None, |inputs| {
         inputs.u.get().map_or(None, |u| Some(u.clone()))
       }),
+      self.params.lookup.then_some((&params, &self.ro_consts)),
     )?;
 
     // Allocate T
@@ -191,6 +196,7 @@ where
       // The primary circuit just returns the default R1CS instance
       AllocatedRelaxedR1CSInstance::default(
         cs.namespace(|| "Allocate U_default"),
+        self.params.lookup,
         self.params.limb_width,
         self.params.n_limbs,
       )?
@@ -226,7 +232,7 @@ where
     ro.absorb(i);
     ro.absorb(z_0);
     ro.absorb(z_i);
-    let _ = U.absorb_in_ro(cs.namespace(|| "absorb U"), &mut ro)?;
+    U.absorb_in_ro(cs.namespace(|| "absorb U"), &mut ro)?;
 
     let hash_bits = ro.get_hash(cs.namespace(|| "Input hash"))?;
     let hash = le_bits_to_num(cs.namespace(|| "bits to hash"), hash_bits)?;
@@ -249,17 +255,12 @@ where
 
     Ok((U_fold, check_pass))
   }
-}
 
-impl<G, SC> Circuit<<G as Group>::Base> for NIFSVerifierCircuit<G, SC>
-where
-  G: Group,
-  SC: StepCircuit<G::Base>,
-{
-  fn synthesize<CS: ConstraintSystem<<G as Group>::Base>>(
+  /// Synthesizes the circuit and returns the variables that the step circuit looks up in its table
+  pub fn synthesize_with_lookups<CS: ConstraintSystem<<G as Group>::Base>>(
     self,
     cs: &mut CS,
-  ) -> Result<(), SynthesisError> {
+  ) -> Result<Vec<AllocatedNum<G::Base>>, SynthesisError> {
     // Allocate all witnesses
     let (params, i, z_0, z_i, U, u, T) =
       self.alloc_witness(cs.namespace(|| "allocate the circuit witness"))?;
@@ -323,9 +324,12 @@ where
       &Boolean::from(is_base_case),
     )?;
 
-    let z_next = self
-      .step_circuit
-      .synthesize(&mut cs.namespace(|| "F"), z_input)?;
+    let mut lookups = Vec::new();
+    let z_next = self.step_circuit.synthesize_with_lookups(
+      &mut cs.namespace(|| "F"),
+      z_input,
+      &mut lookups,
+    )?;
 
     // Compute the new hash H(params, Unew, i+1, z0, z_{i+1})
     let mut ro: PoseidonROGadget<G::Base> = PoseidonROGadget::new(self.ro_consts);
@@ -333,17 +337,29 @@ where
     ro.absorb(i_new.clone());
     ro.absorb(z_0);
     ro.absorb(z_next);
-    let _ = Unew.absorb_in_ro(cs.namespace(|| "absorb U_new"), &mut ro)?;
+    Unew.absorb_in_ro(cs.namespace(|| "absorb U_new"), &mut ro)?;
     let hash_bits = ro.get_hash(cs.namespace(|| "output hash bits"))?;
     let hash = le_bits_to_num(cs.namespace(|| "convert hash to num"), hash_bits)?;
 
     // Outputs the computed hash and u.X[1] that corresponds to the hash of the other circuit
-    let _ = u
-      .X1
+    u.X1
       .inputize(cs.namespace(|| "Output unmodified hash of the other circuit"))?;
-    let _ = hash.inputize(cs.namespace(|| "output new hash of this circuit"))?;
+    hash.inputize(cs.namespace(|| "output new hash of this circuit"))?;
+
+    Ok(lookups)
+  }
+}
 
-    Ok(())
+impl<G, SC> Circuit<<G as Group>::Base> for NIFSVerifierCircuit<G, SC>
+where
+  G: Group,
+  SC: StepCircuit<G::Base>,
+{
+  fn synthesize<CS: ConstraintSystem<<G as Group>::Base>>(
+    self,
+    cs: &mut CS,
+  ) -> Result<(), SynthesisError> {
+    self.synthesize_with_lookups(cs).map(|_| ())
   }
 }
 
@@ -386,8 +402,8 @@ mod tests {
   #[test]
   fn test_verification_circuit() {
     // In the following we use 1 to refer to the primary, and 2 to refer to the secondary circuit
-    let params1 = NIFSVerifierCircuitParams::new(BN_LIMB_WIDTH, BN_N_LIMBS, true);
-    let params2 = NIFSVerifierCircuitParams::new(BN_LIMB_WIDTH, BN_N_LIMBS, false);
+    let params1 = NIFSVerifierCircuitParams::new(BN_LIMB_WIDTH, BN_N_LIMBS, true, false);
+    let params2 = NIFSVerifierCircuitParams::new(BN_LIMB_WIDTH, BN_N_LIMBS, false, false);
     let ro_consts1: ROConstantsCircuit<<G2 as Group>::Base> = ROConstantsCircuit::new();
     let ro_consts2: ROConstantsCircuit<<G1 as Group>::Base> = ROConstantsCircuit::new();
 
@@ -409,6 +425,16 @@ mod tests {
       cs.num_constraints()
     );
 
+    // the profile attributes most constraints to folding and hashing
+    let profile = cs.profile();
+    assert_eq!(profile.num_constraints, cs.num_constraints());
+    assert_eq!(profile.num_aux, cs.num_aux());
+    let non_base_case = profile.get("synthesize non base case").unwrap();
+    let fold = non_base_case.get("compute fold of U and u").unwrap();
+    let hash = non_base_case.get("Input hash").unwrap();
+    assert!(non_base_case.get("absorb U").is_some());
+    assert!(fold.num_constraints + hash.num_constraints > cs.num_constraints() / 2);
+
     // Initialize the shape and gens for the secondary
     let circuit2: NIFSVerifierCircuit<G1, TestCircuit<<G1 as Group>::Base>> =
       NIFSVerifierCircuit::new(
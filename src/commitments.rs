@@ -1,34 +1,66 @@
+//! This module defines Pedersen commitments to vectors of scalars, which commit to the witnesses
+//! and the error vectors of R1CS instances.
 use super::{
+  constants::{BN_LIMB_WIDTH, BN_N_LIMBS},
   errors::NovaError,
-  traits::{AbsorbInROTrait, AppendToTranscriptTrait, CompressedGroup, Group, HashFuncTrait},
+  parallel::prelude::*,
+  traits::{
+    AbsorbInROTrait, AppendToTranscriptTrait, CompressedGroup, Group, HashFuncTrait,
+    TranscriptEngineTrait,
+  },
 };
+use bellperson_nonnative::{mp::bignat::nat_to_limbs, util::convert::f_to_nat};
 use core::{
   fmt::Debug,
   marker::PhantomData,
   ops::{Add, AddAssign, Mul, MulAssign},
 };
-use ff::Field;
-use merlin::Transcript;
-use rayon::prelude::*;
+use ff::{Field, PrimeFieldBits};
+use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
 
+/// Returns the x-coordinate of the point, the parity of its y-coordinate, and whether it is
+/// the point at infinity. The point at infinity has x = 0 and an even y.
+pub(crate) fn compress_point<G: Group>(comm: &Commitment<G>) -> (G::Base, bool, bool) {
+  let (x, y, is_infinity) = comm.comm.to_coordinates();
+  if is_infinity {
+    (G::Base::zero(), false, true)
+  } else {
+    (x, y.to_le_bits()[0], false)
+  }
+}
+
+/// Returns the elements of `G::Scalar` that represent the point in compressed form in a RO over
+/// `G::Scalar`: the two halves of its x-coordinate, then `sign + 2 * is_infinity`
+pub(crate) fn compressed_point_elements<G: Group>(comm: &Commitment<G>) -> Vec<G::Scalar> {
+  let (x, sign, is_infinity) = compress_point(comm);
+  let mut elements: Vec<G::Scalar> =
+    nat_to_limbs(&f_to_nat(&x), BN_LIMB_WIDTH * BN_N_LIMBS / 2, 2).unwrap();
+  elements.push(G::Scalar::from(sign as u64 + 2 * is_infinity as u64));
+  elements
+}
+
+/// Generators to commit to vectors of scalars
 #[derive(Clone, Debug)]
 pub struct CommitGens<G: Group> {
   gens: Vec<G::PreprocessedGroupElement>,
   _p: PhantomData<G>,
 }
 
+/// A commitment to a vector of scalars
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Commitment<G: Group> {
   pub(crate) comm: G,
 }
 
+/// A commitment in its compressed form
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct CompressedCommitment<C: CompressedGroup> {
   comm: C,
 }
 
 impl<G: Group> CommitGens<G> {
-  pub fn new(label: &'static [u8], n: usize) -> Self {
+  /// Creates at least `n` generators, derived from `label`
+  pub fn new(label: &[u8], n: usize) -> Self {
     CommitGens {
       gens: G::from_label(label, n.next_power_of_two()),
       _p: Default::default(),
@@ -39,6 +71,16 @@ impl<G: Group> CommitGens<G> {
     self.gens.len()
   }
 
+  /// Returns the compressed generators, one after the other
+  pub(crate) fn to_bytes(&self) -> Vec<u8> {
+    self
+      .gens
+      .iter()
+      .flat_map(|g| G::compress_preprocessed(g).as_bytes().to_vec())
+      .collect()
+  }
+
+  /// Splits the generators into the first `n` and the rest
   pub fn split_at(&self, n: usize) -> (CommitGens<G>, CommitGens<G>) {
     (
       CommitGens {
@@ -52,6 +94,7 @@ impl<G: Group> CommitGens<G> {
     )
   }
 
+  /// Returns the generators of `self` followed by those of `other`
   pub fn combine(&self, other: &CommitGens<G>) -> CommitGens<G> {
     let gens = {
       let mut c = self.gens.clone();
@@ -64,20 +107,20 @@ impl<G: Group> CommitGens<G> {
     }
   }
 
-  // combines the left and right halves of `self` using `w1` and `w2` as the weights
+  /// Combines the left and right halves of `self` using `w1` and `w2` as the weights
   pub fn fold(&self, w1: &G::Scalar, w2: &G::Scalar) -> CommitGens<G> {
-    let w = vec![*w1, *w2];
-    let (L, R) = self.split_at(self.len() / 2);
+    self.fold_prefix(self.len(), w1, w2)
+  }
 
-    let gens = (0..self.len() / 2)
+  /// Combines the left and right halves of the first `n` generators of `self` using `w1` and
+  /// `w2` as the weights, reading the generators in place
+  pub(crate) fn fold_prefix(&self, n: usize, w1: &G::Scalar, w2: &G::Scalar) -> CommitGens<G> {
+    let w = [*w1, *w2];
+    let (L, R) = self.gens[..n].split_at(n / 2);
+
+    let gens = (0..n / 2)
       .into_par_iter()
-      .map(|i| {
-        let gens = CommitGens::<G> {
-          gens: [L.gens[i].clone(), R.gens[i].clone()].to_vec(),
-          _p: Default::default(),
-        };
-        w.commit(&gens).comm.preprocessed()
-      })
+      .map(|i| G::vartime_multiscalar_mul(&w, &[L[i].clone(), R[i].clone()]).preprocessed())
       .collect();
 
     CommitGens {
@@ -86,6 +129,14 @@ impl<G: Group> CommitGens<G> {
     }
   }
 
+  /// Commits to `scalars` with the generators of `self` that start at `offset`
+  pub(crate) fn commit_at(&self, offset: usize, scalars: &[G::Scalar]) -> Commitment<G> {
+    assert!(self.gens.len() >= offset + scalars.len());
+    Commitment {
+      comm: G::vartime_multiscalar_mul(scalars, &self.gens[offset..offset + scalars.len()]),
+    }
+  }
+
   /// Scales each element in `self` by `r`
   pub fn scale(&self, r: &G::Scalar) -> Self {
     let gens_scaled = self
@@ -107,7 +158,7 @@ impl<G: Group> CommitGens<G> {
     }
   }
 
-  /// reinterprets a vector of commitments as a set of generators
+  /// Reinterprets a vector of commitments as a set of generators
   pub fn reinterpret_commitments_as_gens(
     c: &[CompressedCommitment<G::CompressedGroupElement>],
   ) -> Result<Self, NovaError> {
@@ -127,6 +178,7 @@ impl<G: Group> CommitGens<G> {
 }
 
 impl<G: Group> Commitment<G> {
+  /// Compresses the commitment
   pub fn compress(&self) -> CompressedCommitment<G::CompressedGroupElement> {
     CompressedCommitment {
       comm: self.comm.compress(),
@@ -135,6 +187,7 @@ impl<G: Group> Commitment<G> {
 }
 
 impl<C: CompressedGroup> CompressedCommitment<C> {
+  /// Decompresses the commitment, which fails if it is not a valid group element
   pub fn decompress(&self) -> Result<Commitment<C::GroupElement>, NovaError> {
     let comm = self.comm.decompress();
     if comm.is_none() {
@@ -146,7 +199,68 @@ impl<C: CompressedGroup> CompressedCommitment<C> {
   }
 }
 
+// commitments are serialized in their compressed form
+impl<G: Group> Serialize for Commitment<G> {
+  fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+    self.compress().serialize(s)
+  }
+}
+
+impl<'de, G: Group> Deserialize<'de> for Commitment<G> {
+  fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+    CompressedCommitment::<G::CompressedGroupElement>::deserialize(d)?
+      .decompress()
+      .map_err(|_| D::Error::custom("invalid commitment"))
+  }
+}
+
+// generators are serialized in their compressed form
+impl<G: Group> Serialize for CommitGens<G> {
+  fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+    self
+      .gens
+      .iter()
+      .map(|g| G::compress_preprocessed(g).as_bytes().to_vec())
+      .collect::<Vec<_>>()
+      .serialize(s)
+  }
+}
+
+impl<'de, G: Group> Deserialize<'de> for CommitGens<G> {
+  fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+    let gens = Vec::<Vec<u8>>::deserialize(d)?
+      .par_iter()
+      .map(|bytes| {
+        G::CompressedGroupElement::from_bytes(bytes)
+          .and_then(|g| g.decompress())
+          .map(|g| g.preprocessed())
+      })
+      .collect::<Option<Vec<_>>>()
+      .ok_or_else(|| D::Error::custom("invalid generator"))?;
+    Ok(CommitGens {
+      gens,
+      _p: Default::default(),
+    })
+  }
+}
+
+impl<C: CompressedGroup> Serialize for CompressedCommitment<C> {
+  fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+    self.comm.as_bytes().serialize(s)
+  }
+}
+
+impl<'de, C: CompressedGroup> Deserialize<'de> for CompressedCommitment<C> {
+  fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+    let bytes = Vec::<u8>::deserialize(d)?;
+    let comm = C::from_bytes(&bytes).ok_or_else(|| D::Error::custom("invalid commitment"))?;
+    Ok(CompressedCommitment { comm })
+  }
+}
+
+/// A trait for committing to a vector of scalars
 pub trait CommitTrait<G: Group> {
+  /// Commits to `self` with the provided generators
   fn commit(&self, gens: &CommitGens<G>) -> Commitment<G>;
 }
 
@@ -159,9 +273,13 @@ impl<G: Group> CommitTrait<G> for [G::Scalar] {
   }
 }
 
-impl<G: Group> AppendToTranscriptTrait for Commitment<G> {
-  fn append_to_transcript(&self, label: &'static [u8], transcript: &mut Transcript) {
-    transcript.append_message(label, self.comm.compress().as_bytes());
+impl<G: Group> AppendToTranscriptTrait<G> for Commitment<G> {
+  fn append_to_transcript<T: TranscriptEngineTrait<G>>(
+    &self,
+    label: &'static [u8],
+    transcript: &mut T,
+  ) {
+    transcript.absorb_commitment(label, self);
   }
 }
 
@@ -178,9 +296,18 @@ impl<G: Group> AbsorbInROTrait<G> for Commitment<G> {
   }
 }
 
-impl<C: CompressedGroup> AppendToTranscriptTrait for CompressedCommitment<C> {
-  fn append_to_transcript(&self, label: &'static [u8], transcript: &mut Transcript) {
-    transcript.append_message(label, self.comm.as_bytes());
+impl<C: CompressedGroup> AppendToTranscriptTrait<C::GroupElement> for CompressedCommitment<C> {
+  /// Absorbs the decompressed commitment, or its bytes if it is not a valid group element,
+  /// in which case the verifier rejects the proof when it decompresses the commitment
+  fn append_to_transcript<T: TranscriptEngineTrait<C::GroupElement>>(
+    &self,
+    label: &'static [u8],
+    transcript: &mut T,
+  ) {
+    match self.decompress() {
+      Ok(comm) => transcript.absorb_commitment(label, &comm),
+      Err(_) => transcript.absorb_bytes(label, self.comm.as_bytes()),
+    }
   }
 }
 
@@ -191,7 +318,7 @@ impl<'b, G: Group> MulAssign<&'b G::Scalar> for Commitment<G> {
   }
 }
 
-impl<'a, 'b, G: Group> Mul<&'b G::Scalar> for &'a Commitment<G> {
+impl<'b, G: Group> Mul<&'b G::Scalar> for &Commitment<G> {
   type Output = Commitment<G>;
   fn mul(self, scalar: &'b G::Scalar) -> Commitment<G> {
     Commitment {
@@ -217,7 +344,7 @@ impl<'b, G: Group> AddAssign<&'b Commitment<G>> for Commitment<G> {
   }
 }
 
-impl<'a, 'b, G: Group> Add<&'b Commitment<G>> for &'a Commitment<G> {
+impl<'b, G: Group> Add<&'b Commitment<G>> for &Commitment<G> {
   type Output = Commitment<G>;
   fn add(self, other: &'b Commitment<G>) -> Commitment<G> {
     Commitment {
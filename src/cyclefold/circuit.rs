@@ -0,0 +1,611 @@
+//! There are two circuits in the CycleFold recursion. The primary circuit executes the
+//! step circuit and folds its own previous instance into a running instance. Scalars are
+//! folded natively, but the folded commitments are only provided as witnesses: the
+//! CycleFold circuit, defined over the other curve of the cycle, checks the two group
+//! operations W + r * w and E + r * T, and the primary circuit folds its instance into a
+//! running instance of the CycleFold circuit. The hash output by the primary circuit is
+//! H(params, i, z0, zi, U, U_cyclefold).
+use super::gadgets::{
+  AllocatedCompressedPoint, AllocatedCycleFoldInstance, AllocatedNativeR1CSInstance,
+  AllocatedNativeRelaxedR1CSInstance, SCALAR_MUL_WINDOW,
+};
+use crate::{
+  commitments::{compress_point, Commitment},
+  gadgets::{
+    ecc::AllocatedPoint,
+    utils::{alloc_num_equals, alloc_zero, bits_to_bignat, conditionally_select, le_bits_to_num},
+  },
+  poseidon::{PoseidonROGadget, ROConstantsCircuit},
+  r1cs::{R1CSInstance, RelaxedR1CSInstance},
+  traits::{Group, StepCircuit},
+};
+use bellperson::{
+  gadgets::{
+    boolean::{AllocatedBit, Boolean},
+    num::AllocatedNum,
+    Assignment,
+  },
+  Circuit, ConstraintSystem, LinearCombination, SynthesisError,
+};
+use bellperson_nonnative::{
+  mp::bignat::{BigNat, BigNatParams},
+  util::convert::f_to_nat,
+};
+use ff::{Field, PrimeFieldBits};
+use num_bigint::BigInt;
+use serde::Serialize;
+
+/// The number of points whose group operations are checked by the CycleFold circuit
+const NUM_POINTS: usize = 6;
+
+/// The number of public IO of the CycleFold circuit: the x-coordinates of the points,
+/// the challenge, and the signs and infinity flags of the points packed in one number
+pub(crate) const NUM_IO_CYCLEFOLD: usize = NUM_POINTS + 2;
+
+/// The number of public IO of the primary circuit
+pub(crate) const NUM_IO_PRIMARY: usize = 2;
+
+/// The inputs of the CycleFold circuit
+#[derive(Debug)]
+pub struct CycleFoldCircuitInputs<G: Group> {
+  W: Commitment<G>,
+  w: Commitment<G>,
+  W_fold: Commitment<G>,
+  E: Commitment<G>,
+  T: Commitment<G>,
+  E_fold: Commitment<G>,
+  r: G::Scalar,
+}
+
+impl<G: Group> CycleFoldCircuitInputs<G> {
+  /// Create new inputs for the CycleFold circuit, which checks that
+  /// W_fold = W + r * w and E_fold = E + r * T
+  pub fn new(
+    U: &RelaxedR1CSInstance<G>,
+    u: &R1CSInstance<G>,
+    T: &Commitment<G>,
+    U_fold: &RelaxedR1CSInstance<G>,
+    r: G::Scalar,
+  ) -> Self {
+    Self {
+      W: U.comm_W,
+      w: u.comm_W,
+      W_fold: U_fold.comm_W,
+      E: U.comm_E,
+      T: *T,
+      E_fold: U_fold.comm_E,
+      r,
+    }
+  }
+}
+
+/// Circuit that checks the group operations of a fold of the primary circuit
+pub struct CycleFoldCircuit<G: Group> {
+  num_challenge_bits: usize,
+  inputs: Option<CycleFoldCircuitInputs<G>>,
+}
+
+impl<G: Group> CycleFoldCircuit<G> {
+  /// Create a new CycleFold circuit for challenges of `num_challenge_bits` bits
+  pub fn new(num_challenge_bits: usize, inputs: Option<CycleFoldCircuitInputs<G>>) -> Self {
+    Self {
+      num_challenge_bits,
+      inputs,
+    }
+  }
+
+  /// Allocates a point given in compressed form and checks that it is on the curve.
+  /// Returns the point along with the linear combination `sign + 2 * is_infinity` of its flags.
+  pub(crate) fn alloc_compressed_point<CS: ConstraintSystem<G::Base>>(
+    mut cs: CS,
+    comm: Option<&Commitment<G>>,
+  ) -> Result<(AllocatedPoint<G::Base>, LinearCombination<G::Base>), SynthesisError> {
+    let coords = comm.map(|comm| comm.comm.to_coordinates());
+    let compressed = comm.map(compress_point);
+
+    let x = AllocatedNum::alloc(cs.namespace(|| "x"), || Ok(compressed.get()?.0))?;
+    let y = AllocatedNum::alloc(cs.namespace(|| "y"), || Ok(coords.get()?.1))?;
+    let sign = AllocatedBit::alloc(cs.namespace(|| "sign"), compressed.map(|c| c.1))?;
+    let is_infinity = AllocatedBit::alloc(cs.namespace(|| "is_infinity"), compressed.map(|c| c.2))?;
+
+    // the point at infinity is (0, 0)
+    cs.enforce(
+      || "is_infinity * x = 0",
+      |lc| lc + is_infinity.get_variable(),
+      |lc| lc + x.get_variable(),
+      |lc| lc,
+    );
+    cs.enforce(
+      || "is_infinity * y = 0",
+      |lc| lc + is_infinity.get_variable(),
+      |lc| lc + y.get_variable(),
+      |lc| lc,
+    );
+
+    // any other point satisfies y^2 = x^3 + A * x + B
+    let (a, b) = G::get_curve_params();
+    let y_sq = y.square(cs.namespace(|| "y_sq"))?;
+    let x_sq = x.square(cs.namespace(|| "x_sq"))?;
+    let x_cu = x_sq.mul(cs.namespace(|| "x_cu"), &x)?;
+    cs.enforce(
+      || "(y^2 - x^3 - A * x - B) * (1 - is_infinity) = 0",
+      |lc| lc + y_sq.get_variable() - x_cu.get_variable() - (a, x.get_variable()) - (b, CS::one()),
+      |lc| lc + CS::one() - is_infinity.get_variable(),
+      |lc| lc,
+    );
+
+    // the sign is the parity of y
+    let y_bits = y.to_bits_le_strict(cs.namespace(|| "y bits"))?;
+    cs.enforce(
+      || "sign = y_bits[0]",
+      |lc| lc + &y_bits[0].lc(CS::one(), G::Base::one()) - sign.get_variable(),
+      |lc| lc + CS::one(),
+      |lc| lc,
+    );
+
+    let is_infinity_num = AllocatedNum::alloc(cs.namespace(|| "is_infinity num"), || {
+      Ok(if *is_infinity.get_value().get()? {
+        G::Base::one()
+      } else {
+        G::Base::zero()
+      })
+    })?;
+    cs.enforce(
+      || "is_infinity num = is_infinity",
+      |lc| lc + is_infinity_num.get_variable() - is_infinity.get_variable(),
+      |lc| lc + CS::one(),
+      |lc| lc,
+    );
+
+    let flags = LinearCombination::zero()
+      + sign.get_variable()
+      + (G::Base::from(2u64), is_infinity.get_variable());
+
+    Ok((
+      AllocatedPoint {
+        x,
+        y,
+        is_infinity: is_infinity_num,
+      },
+      flags,
+    ))
+  }
+}
+
+impl<G: Group> Circuit<G::Base> for CycleFoldCircuit<G> {
+  fn synthesize<CS: ConstraintSystem<G::Base>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+    let inputs = self.inputs.as_ref();
+    let comms = [
+      inputs.map(|inputs| &inputs.W),
+      inputs.map(|inputs| &inputs.w),
+      inputs.map(|inputs| &inputs.W_fold),
+      inputs.map(|inputs| &inputs.E),
+      inputs.map(|inputs| &inputs.T),
+      inputs.map(|inputs| &inputs.E_fold),
+    ];
+
+    // Allocate the points and output their x-coordinates
+    let mut points = Vec::new();
+    let mut flags = LinearCombination::zero();
+    let mut flags_shift = G::Base::one();
+    for (i, comm) in comms.into_iter().enumerate() {
+      let (point, point_flags) =
+        Self::alloc_compressed_point(cs.namespace(|| format!("point {}", i)), comm)?;
+      point
+        .x
+        .inputize(cs.namespace(|| format!("output x of point {}", i)))?;
+      points.push(point);
+      flags = flags + (flags_shift, &point_flags);
+      flags_shift = flags_shift.double().double();
+    }
+
+    // Allocate the challenge as bits and output it
+    let r_bits = inputs.map(|inputs| inputs.r.to_le_bits());
+    let r_bits = (0..self.num_challenge_bits)
+      .map(|i| {
+        AllocatedBit::alloc(
+          cs.namespace(|| format!("r bit {}", i)),
+          r_bits.as_ref().map(|bits| bits[i]),
+        )
+      })
+      .collect::<Result<Vec<_>, _>>()?;
+    let r = le_bits_to_num(cs.namespace(|| "r"), r_bits.clone())?;
+    r.inputize(cs.namespace(|| "output r"))?;
+
+    // Output the signs and infinity flags
+    let flags_num = AllocatedNum::alloc(cs.namespace(|| "flags"), || {
+      let mut value = G::Base::zero();
+      let mut shift = G::Base::one();
+      for comm in comms {
+        let (_, sign, is_infinity) = compress_point(comm.get()?);
+        if sign {
+          value += shift;
+        }
+        if is_infinity {
+          value += shift.double();
+        }
+        shift = shift.double().double();
+      }
+      Ok(value)
+    })?;
+    cs.enforce(
+      || "check flags",
+      |lc| lc + flags_num.get_variable(),
+      |lc| lc + CS::one(),
+      |_| flags,
+    );
+    flags_num.inputize(cs.namespace(|| "output flags"))?;
+
+    // Check W_fold = W + r * w
+    let rw =
+      points[1].scalar_mul_windowed(cs.namespace(|| "r * w"), r_bits.clone(), SCALAR_MUL_WINDOW)?;
+    let W_fold = points[0].add(cs.namespace(|| "W + r * w"), &rw)?;
+    W_fold.enforce_equal(cs.namespace(|| "check W_fold"), &points[2]);
+
+    // Check E_fold = E + r * T
+    let rT = points[4].scalar_mul_windowed(cs.namespace(|| "r * T"), r_bits, SCALAR_MUL_WINDOW)?;
+    let E_fold = points[3].add(cs.namespace(|| "E + r * T"), &rT)?;
+    E_fold.enforce_equal(cs.namespace(|| "check E_fold"), &points[5]);
+
+    Ok(())
+  }
+}
+
+/// Parameters of the primary circuit
+#[derive(Debug, Clone, Serialize)]
+pub struct PrimaryCircuitParams {
+  limb_width: usize,
+  n_limbs: usize,
+}
+
+impl PrimaryCircuitParams {
+  pub fn new(limb_width: usize, n_limbs: usize) -> Self {
+    Self {
+      limb_width,
+      n_limbs,
+    }
+  }
+}
+
+/// The inputs of the primary circuit
+#[derive(Debug)]
+pub struct PrimaryCircuitInputs<G1, G2>
+where
+  G1: Group<Base = <G2 as Group>::Scalar>,
+  G2: Group<Base = <G1 as Group>::Scalar>,
+{
+  params: G1::Scalar, // Hash(Shape of the primary circuit, Shape of the CycleFold circuit)
+  i: G1::Scalar,
+  z0: G1::Scalar,
+  zi: Option<G1::Scalar>,
+  U: Option<RelaxedR1CSInstance<G1>>,
+  u: Option<R1CSInstance<G1>>,
+  T: Option<Commitment<G1>>,
+  U_fold: Option<RelaxedR1CSInstance<G1>>,
+  U_cyclefold: Option<RelaxedR1CSInstance<G2>>,
+  u_cyclefold: Option<R1CSInstance<G2>>,
+  T_cyclefold: Option<Commitment<G2>>,
+}
+
+impl<G1, G2> PrimaryCircuitInputs<G1, G2>
+where
+  G1: Group<Base = <G2 as Group>::Scalar>,
+  G2: Group<Base = <G1 as Group>::Scalar>,
+{
+  /// Create new inputs/witness for the primary circuit. `U_fold` is the fold of `U` and `u`,
+  /// and `u_cyclefold` is the instance of the CycleFold circuit that checks it.
+  #[allow(clippy::too_many_arguments)]
+  pub fn new(
+    params: G1::Scalar,
+    i: G1::Scalar,
+    z0: G1::Scalar,
+    zi: Option<G1::Scalar>,
+    U: Option<RelaxedR1CSInstance<G1>>,
+    u: Option<R1CSInstance<G1>>,
+    T: Option<Commitment<G1>>,
+    U_fold: Option<RelaxedR1CSInstance<G1>>,
+    U_cyclefold: Option<RelaxedR1CSInstance<G2>>,
+    u_cyclefold: Option<R1CSInstance<G2>>,
+    T_cyclefold: Option<Commitment<G2>>,
+  ) -> Self {
+    Self {
+      params,
+      i,
+      z0,
+      zi,
+      U,
+      u,
+      T,
+      U_fold,
+      U_cyclefold,
+      u_cyclefold,
+      T_cyclefold,
+    }
+  }
+}
+
+/// Circuit that executes a step and folds the previous step into a running instance
+pub struct PrimaryCircuit<G1, G2, SC>
+where
+  G1: Group<Base = <G2 as Group>::Scalar>,
+  G2: Group<Base = <G1 as Group>::Scalar>,
+  SC: StepCircuit<G1::Scalar>,
+{
+  params: PrimaryCircuitParams,
+  ro_consts: ROConstantsCircuit<G1::Scalar>,
+  inputs: Option<PrimaryCircuitInputs<G1, G2>>,
+  step_circuit: SC, // The function that is applied for each step
+}
+
+impl<G1, G2, SC> PrimaryCircuit<G1, G2, SC>
+where
+  G1: Group<Base = <G2 as Group>::Scalar>,
+  G2: Group<Base = <G1 as Group>::Scalar>,
+  SC: StepCircuit<G1::Scalar>,
+{
+  /// Create a new primary circuit for the given inputs
+  pub fn new(
+    params: PrimaryCircuitParams,
+    inputs: Option<PrimaryCircuitInputs<G1, G2>>,
+    step_circuit: SC,
+    ro_consts: ROConstantsCircuit<G1::Scalar>,
+  ) -> Self {
+    Self {
+      params,
+      inputs,
+      step_circuit,
+      ro_consts,
+    }
+  }
+
+  /// Builds the public IO of the CycleFold instance that checks the fold of `U` and `u`
+  fn cyclefold_io(
+    &self,
+    U: &AllocatedNativeRelaxedR1CSInstance<G1>,
+    u: &AllocatedNativeR1CSInstance<G1>,
+    T: &AllocatedCompressedPoint<G1>,
+    U_fold: &AllocatedNativeRelaxedR1CSInstance<G1>,
+    r_bits: &[AllocatedBit],
+  ) -> Vec<BigNat<G1::Scalar>> {
+    // the points are in the order in which the CycleFold circuit allocates them
+    let points = [&U.W, &u.W, &U_fold.W, &U.E, T, &U_fold.E];
+
+    let mut X = points
+      .iter()
+      .map(|p| p.x.clone())
+      .collect::<Vec<BigNat<G1::Scalar>>>();
+
+    X.push(bits_to_bignat(
+      r_bits,
+      self.params.limb_width,
+      self.params.n_limbs,
+    ));
+
+    let mut flags = LinearCombination::zero();
+    let mut flags_value = Some(G1::Scalar::zero());
+    let mut shift = G1::Scalar::one();
+    for p in points {
+      flags = flags + (shift, &p.flags());
+      flags_value = flags_value.and_then(|v| Some(v + shift * p.flags_value()?));
+      shift = shift.double().double();
+    }
+    let mut limbs = vec![LinearCombination::zero(); self.params.n_limbs];
+    limbs[0] = flags;
+    let flags_bn = BigNat {
+      limbs,
+      limb_values: flags_value.map(|v| {
+        let mut values = vec![G1::Scalar::zero(); self.params.n_limbs];
+        values[0] = v;
+        values
+      }),
+      value: flags_value.map(|v| f_to_nat(&v)),
+      params: BigNatParams {
+        min_bits: 0,
+        max_word: (BigInt::from(1) << (2 * NUM_POINTS)) - 1,
+        limb_width: self.params.limb_width,
+        n_limbs: self.params.n_limbs,
+      },
+    };
+    X.push(flags_bn);
+    X
+  }
+}
+
+impl<G1, G2, SC> Circuit<G1::Scalar> for PrimaryCircuit<G1, G2, SC>
+where
+  G1: Group<Base = <G2 as Group>::Scalar>,
+  G2: Group<Base = <G1 as Group>::Scalar>,
+  SC: StepCircuit<G1::Scalar>,
+{
+  fn synthesize<CS: ConstraintSystem<G1::Scalar>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+    let (limb_width, n_limbs) = (self.params.limb_width, self.params.n_limbs);
+    let inputs = self.inputs.as_ref();
+
+    // Allocate all witnesses
+    let params = AllocatedNum::alloc(cs.namespace(|| "params"), || Ok(inputs.get()?.params))?;
+    let i = AllocatedNum::alloc(cs.namespace(|| "i"), || Ok(inputs.get()?.i))?;
+    let z_0 = AllocatedNum::alloc(cs.namespace(|| "z0"), || Ok(inputs.get()?.z0))?;
+    let z_i = AllocatedNum::alloc(cs.namespace(|| "zi"), || {
+      Ok(inputs.get()?.zi.unwrap_or_else(G1::Scalar::zero))
+    })?;
+    let U = AllocatedNativeRelaxedR1CSInstance::alloc(
+      cs.namespace(|| "allocate U"),
+      inputs.and_then(|inputs| inputs.U.as_ref()),
+      NUM_IO_PRIMARY,
+      limb_width,
+      n_limbs,
+    )?;
+    let u = AllocatedNativeR1CSInstance::alloc(
+      cs.namespace(|| "allocate instance u to fold"),
+      inputs.and_then(|inputs| inputs.u.as_ref()),
+      NUM_IO_PRIMARY,
+      limb_width,
+      n_limbs,
+    )?;
+    let T = AllocatedCompressedPoint::alloc(
+      cs.namespace(|| "allocate T"),
+      inputs.and_then(|inputs| inputs.T.as_ref()),
+      limb_width,
+      n_limbs,
+    )?;
+    let W_fold = AllocatedCompressedPoint::alloc(
+      cs.namespace(|| "allocate W_fold"),
+      inputs.and_then(|inputs| inputs.U_fold.as_ref().map(|U| &U.comm_W)),
+      limb_width,
+      n_limbs,
+    )?;
+    let E_fold = AllocatedCompressedPoint::alloc(
+      cs.namespace(|| "allocate E_fold"),
+      inputs.and_then(|inputs| inputs.U_fold.as_ref().map(|U| &U.comm_E)),
+      limb_width,
+      n_limbs,
+    )?;
+    let U_cyclefold = AllocatedCycleFoldInstance::alloc(
+      cs.namespace(|| "allocate U_cyclefold"),
+      inputs.and_then(|inputs| inputs.U_cyclefold.as_ref()),
+      NUM_IO_CYCLEFOLD,
+      limb_width,
+      n_limbs,
+    )?;
+    let W_cyclefold = AllocatedPoint::alloc(
+      cs.namespace(|| "allocate W_cyclefold"),
+      inputs.and_then(|inputs| {
+        inputs
+          .u_cyclefold
+          .as_ref()
+          .map(|u| u.comm_W.comm.to_coordinates())
+      }),
+    )?;
+    let T_cyclefold = AllocatedPoint::alloc(
+      cs.namespace(|| "allocate T_cyclefold"),
+      inputs.and_then(|inputs| inputs.T_cyclefold.as_ref().map(|T| T.comm.to_coordinates())),
+    )?;
+
+    // The x-coordinates that did not come out of a hash become IO of the CycleFold circuit,
+    // so their limbs must be range checked
+    u.W.assert_well_formed(cs.namespace(|| "u.W rangecheck"))?;
+    T.assert_well_formed(cs.namespace(|| "T rangecheck"))?;
+    W_fold.assert_well_formed(cs.namespace(|| "W_fold rangecheck"))?;
+    E_fold.assert_well_formed(cs.namespace(|| "E_fold rangecheck"))?;
+
+    // Compute variable indicating if this is the base case
+    let zero = alloc_zero(cs.namespace(|| "zero"))?;
+    let is_base_case = alloc_num_equals(cs.namespace(|| "Check if base case"), &i, &zero)?;
+
+    // Check that u.x[1] = Hash(params, i, z0, zi, U, U_cyclefold)
+    let mut ro: PoseidonROGadget<G1::Scalar> = PoseidonROGadget::new(self.ro_consts.clone());
+    ro.absorb(params.clone());
+    ro.absorb(i.clone());
+    ro.absorb(z_0.clone());
+    ro.absorb(z_i.clone());
+    U.absorb_in_ro(cs.namespace(|| "absorb U"), &mut ro)?;
+    U_cyclefold.absorb_in_ro(cs.namespace(|| "absorb U_cyclefold"), &mut ro)?;
+    let hash_bits = ro.get_hash(cs.namespace(|| "Input hash"))?;
+    let hash = le_bits_to_num(cs.namespace(|| "bits to hash"), hash_bits)?;
+    let check_pass = alloc_num_equals(
+      cs.namespace(|| "check consistency of u.X[1] with H(params, i, z0, zi, U, U_cyclefold)"),
+      &u.X[1],
+      &hash,
+    )?;
+
+    // Fold u into U. The folded commitments are checked by the CycleFold circuit.
+    let (U_fold, r_bits) = U.fold_with_r1cs(
+      cs.namespace(|| "compute fold of U and u"),
+      params.clone(),
+      &u,
+      &T,
+      W_fold,
+      E_fold,
+      self.ro_consts.clone(),
+    )?;
+
+    // Fold the CycleFold instance that checks the above into the running CycleFold instance
+    let X_cyclefold = self.cyclefold_io(&U, &u, &T, &U_fold, &r_bits);
+    let U_cyclefold_fold = U_cyclefold.fold_with_r1cs(
+      cs.namespace(|| "compute fold of U_cyclefold and u_cyclefold"),
+      params.clone(),
+      &W_cyclefold,
+      &X_cyclefold,
+      &T_cyclefold,
+      self.ro_consts.clone(),
+      limb_width,
+      n_limbs,
+    )?;
+
+    // Either check_pass=true or we are in the base case
+    let should_be_false = AllocatedBit::nor(
+      cs.namespace(|| "check_pass nor base_case"),
+      &check_pass,
+      &is_base_case,
+    )?;
+    cs.enforce(
+      || "check_pass nor base_case = false",
+      |lc| lc + should_be_false.get_variable(),
+      |lc| lc + CS::one(),
+      |lc| lc,
+    );
+
+    // In the base case, both running instances are the default ones
+    let U_default = AllocatedNativeRelaxedR1CSInstance::default(
+      cs.namespace(|| "Allocate U_default"),
+      NUM_IO_PRIMARY,
+      limb_width,
+      n_limbs,
+    )?;
+    let U_new = U_default.conditionally_select(
+      cs.namespace(|| "compute U_new"),
+      &U_fold,
+      &Boolean::from(is_base_case.clone()),
+    )?;
+    let U_cyclefold_default = AllocatedCycleFoldInstance::default(
+      cs.namespace(|| "Allocate U_cyclefold_default"),
+      NUM_IO_CYCLEFOLD,
+      limb_width,
+      n_limbs,
+    )?;
+    let U_cyclefold_new = U_cyclefold_default.conditionally_select(
+      cs.namespace(|| "compute U_cyclefold_new"),
+      &U_cyclefold_fold,
+      &Boolean::from(is_base_case.clone()),
+    )?;
+
+    // Compute i + 1
+    let i_new = AllocatedNum::alloc(cs.namespace(|| "i + 1"), || {
+      Ok(*i.get_value().get()? + G1::Scalar::one())
+    })?;
+    cs.enforce(
+      || "check i + 1",
+      |lc| lc,
+      |lc| lc,
+      |lc| lc + i_new.get_variable() - CS::one() - i.get_variable(),
+    );
+
+    // Compute z_{i+1}
+    let z_input = conditionally_select(
+      cs.namespace(|| "select input to F"),
+      &z_0,
+      &z_i,
+      &Boolean::from(is_base_case),
+    )?;
+    let z_next = self
+      .step_circuit
+      .synthesize(&mut cs.namespace(|| "F"), z_input)?;
+
+    // Compute the new hash H(params, i+1, z0, z_{i+1}, U_new, U_cyclefold_new)
+    let mut ro: PoseidonROGadget<G1::Scalar> = PoseidonROGadget::new(self.ro_consts);
+    ro.absorb(params);
+    ro.absorb(i_new);
+    ro.absorb(z_0);
+    ro.absorb(z_next);
+    U_new.absorb_in_ro(cs.namespace(|| "absorb U_new"), &mut ro)?;
+    U_cyclefold_new.absorb_in_ro(cs.namespace(|| "absorb U_cyclefold_new"), &mut ro)?;
+    let hash_bits = ro.get_hash(cs.namespace(|| "output hash bits"))?;
+    let hash = le_bits_to_num(cs.namespace(|| "convert hash to num"), hash_bits)?;
+
+    // Outputs u.X[1], the hash of the previous step, and the hash of this step
+    u.X[1].inputize(cs.namespace(|| "Output hash of the previous step"))?;
+    hash.inputize(cs.namespace(|| "output new hash of this circuit"))?;
+
+    Ok(())
+  }
+}
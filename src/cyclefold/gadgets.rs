@@ -0,0 +1,620 @@
+//! This module implements the gadgets used by the CycleFold circuits: instances of the
+//! primary circuit whose commitments are non-native, instances of the CycleFold circuit
+//! whose scalars are non-native, and the non-native arithmetic needed to fold them.
+use crate::commitments::compress_point;
+use crate::{
+  commitments::Commitment,
+  gadgets::{
+    ecc::AllocatedPoint,
+    utils::{
+      alloc_bignat_constant, alloc_one, alloc_zero, bits_to_bignat, conditionally_select,
+      conditionally_select_bignat, le_bits_to_num,
+    },
+  },
+  poseidon::{PoseidonROGadget, ROConstantsCircuit},
+  r1cs::{R1CSInstance, RelaxedR1CSInstance},
+  traits::Group,
+};
+use bellperson::{
+  gadgets::{
+    boolean::{AllocatedBit, Boolean},
+    num::AllocatedNum,
+    Assignment,
+  },
+  ConstraintSystem, LinearCombination, SynthesisError,
+};
+use bellperson_nonnative::{
+  mp::bignat::BigNat,
+  util::{convert::f_to_nat, num::Num},
+};
+use ff::{Field, PrimeField, PrimeFieldBits};
+use num_bigint::BigInt;
+
+/// The number of bits of the challenge processed at a time by the scalar multiplications
+/// of both circuits, which trades a table of 16 multiples for a quarter of the additions
+pub(crate) const SCALAR_MUL_WINDOW: usize = 4;
+
+/// Allocates a number that is constrained to be a bit
+fn alloc_bit_num<F: PrimeField, CS: ConstraintSystem<F>>(
+  mut cs: CS,
+  value: Option<bool>,
+) -> Result<AllocatedNum<F>, SynthesisError> {
+  let bit = AllocatedNum::alloc(cs.namespace(|| "alloc"), || {
+    Ok(if *value.get()? { F::one() } else { F::zero() })
+  })?;
+  cs.enforce(
+    || "check bit",
+    |lc| lc + bit.get_variable(),
+    |lc| lc + CS::one() - bit.get_variable(),
+    |lc| lc,
+  );
+  Ok(bit)
+}
+
+/// Absorbs a `BigNat` in the RO as two numbers, each holding half of its limbs
+fn absorb_bignat_in_ro<F, CS>(
+  mut cs: CS,
+  n: &BigNat<F>,
+  ro: &mut PoseidonROGadget<F>,
+) -> Result<(), SynthesisError>
+where
+  F: PrimeField + PrimeFieldBits,
+  CS: ConstraintSystem<F>,
+{
+  let grouped = n.group_limbs(n.params.n_limbs / 2);
+  for (i, limb) in grouped.as_limbs::<CS>().iter().enumerate() {
+    ro.absorb(limb.as_sapling_allocated_num(cs.namespace(|| format!("group {}", i)))?);
+  }
+  Ok(())
+}
+
+/// Computes `(a + r * b) mod m` for well-formed `a`, `r` and `b`.
+/// The result is range checked, so it is well-formed as well.
+pub fn fold_bignat<F: PrimeField, CS: ConstraintSystem<F>>(
+  mut cs: CS,
+  a: &BigNat<F>,
+  r: &BigNat<F>,
+  b: &BigNat<F>,
+  m: &BigNat<F>,
+) -> Result<BigNat<F>, SynthesisError> {
+  let (_, rb) = b.mult_mod(cs.namespace(|| "r * b"), r, m)?;
+  let sum = a.add::<CS>(&rb)?;
+  sum.red_mod(cs.namespace(|| "reduce"), m)
+}
+
+/// A point of `G` allocated in a circuit over `G::Scalar`, where its coordinates are non-native.
+/// The point is kept compressed, as the x-coordinate, the parity of the y-coordinate and
+/// a flag for the point at infinity. The CycleFold circuit checks that it lies on the curve.
+#[derive(Clone)]
+pub struct AllocatedCompressedPoint<G: Group> {
+  pub(crate) x: BigNat<G::Scalar>,
+  pub(crate) sign: AllocatedNum<G::Scalar>,
+  pub(crate) is_infinity: AllocatedNum<G::Scalar>,
+}
+
+impl<G: Group> AllocatedCompressedPoint<G> {
+  /// Allocates the given commitment. If it is None, it allocates the point at infinity
+  pub fn alloc<CS: ConstraintSystem<G::Scalar>>(
+    mut cs: CS,
+    comm: Option<&Commitment<G>>,
+    limb_width: usize,
+    n_limbs: usize,
+  ) -> Result<Self, SynthesisError> {
+    let (x, sign, is_infinity) = comm.map_or((G::Base::zero(), false, true), compress_point);
+
+    let x = BigNat::alloc_from_nat(
+      cs.namespace(|| "allocate x"),
+      || Ok(f_to_nat(&x)),
+      limb_width,
+      n_limbs,
+    )?;
+    let sign = alloc_bit_num(cs.namespace(|| "allocate sign"), Some(sign))?;
+    let is_infinity = alloc_bit_num(cs.namespace(|| "allocate is_infinity"), Some(is_infinity))?;
+
+    Ok(Self {
+      x,
+      sign,
+      is_infinity,
+    })
+  }
+
+  /// Allocates the point at infinity
+  pub fn default<CS: ConstraintSystem<G::Scalar>>(
+    mut cs: CS,
+    limb_width: usize,
+    n_limbs: usize,
+  ) -> Result<Self, SynthesisError> {
+    let x = alloc_bignat_constant(
+      cs.namespace(|| "allocate x"),
+      &BigInt::from(0),
+      limb_width,
+      n_limbs,
+    )?;
+    let sign = alloc_zero(cs.namespace(|| "allocate sign"))?;
+    let is_infinity = alloc_one(cs.namespace(|| "allocate is_infinity"))?;
+
+    Ok(Self {
+      x,
+      sign,
+      is_infinity,
+    })
+  }
+
+  /// Checks that the limbs of the x-coordinate fit in the limb width
+  pub fn assert_well_formed<CS: ConstraintSystem<G::Scalar>>(
+    &self,
+    mut cs: CS,
+  ) -> Result<(), SynthesisError> {
+    self.x.assert_well_formed(cs.namespace(|| "x rangecheck"))
+  }
+
+  /// Returns the linear combination `sign + 2 * is_infinity`
+  pub fn flags(&self) -> LinearCombination<G::Scalar> {
+    LinearCombination::zero()
+      + self.sign.get_variable()
+      + (G::Scalar::from(2u64), self.is_infinity.get_variable())
+  }
+
+  /// Returns the value of `sign + 2 * is_infinity`
+  pub fn flags_value(&self) -> Option<G::Scalar> {
+    Some(self.sign.get_value()? + self.is_infinity.get_value()?.double())
+  }
+
+  /// Absorb the point in the RO
+  pub fn absorb_in_ro<CS: ConstraintSystem<G::Scalar>>(
+    &self,
+    mut cs: CS,
+    ro: &mut PoseidonROGadget<G::Scalar>,
+  ) -> Result<(), SynthesisError> {
+    absorb_bignat_in_ro(cs.namespace(|| "absorb x"), &self.x, ro)?;
+    let flags = Num::new(self.flags_value(), self.flags());
+    ro.absorb(flags.as_sapling_allocated_num(cs.namespace(|| "flags"))?);
+    Ok(())
+  }
+
+  /// If condition outputs a otherwise outputs b
+  pub fn conditionally_select<CS: ConstraintSystem<G::Scalar>>(
+    mut cs: CS,
+    a: &Self,
+    b: &Self,
+    condition: &Boolean,
+  ) -> Result<Self, SynthesisError> {
+    let x = conditionally_select_bignat(cs.namespace(|| "select x"), &a.x, &b.x, condition)?;
+    let sign = conditionally_select(cs.namespace(|| "select sign"), &a.sign, &b.sign, condition)?;
+    let is_infinity = conditionally_select(
+      cs.namespace(|| "select is_infinity"),
+      &a.is_infinity,
+      &b.is_infinity,
+      condition,
+    )?;
+    Ok(Self {
+      x,
+      sign,
+      is_infinity,
+    })
+  }
+}
+
+/// An R1CS instance of the primary circuit allocated in the primary circuit
+pub struct AllocatedNativeR1CSInstance<G: Group> {
+  pub(crate) W: AllocatedCompressedPoint<G>,
+  pub(crate) X: Vec<AllocatedNum<G::Scalar>>,
+}
+
+impl<G: Group> AllocatedNativeR1CSInstance<G> {
+  /// Takes the r1cs instance and creates a new allocated r1cs instance
+  pub fn alloc<CS: ConstraintSystem<G::Scalar>>(
+    mut cs: CS,
+    u: Option<&R1CSInstance<G>>,
+    num_io: usize,
+    limb_width: usize,
+    n_limbs: usize,
+  ) -> Result<Self, SynthesisError> {
+    let W = AllocatedCompressedPoint::alloc(
+      cs.namespace(|| "allocate W"),
+      u.map(|u| &u.comm_W),
+      limb_width,
+      n_limbs,
+    )?;
+    let X = (0..num_io)
+      .map(|i| {
+        AllocatedNum::alloc(cs.namespace(|| format!("allocate X[{}]", i)), || {
+          Ok(u.map_or(G::Scalar::zero(), |u| u.X[i]))
+        })
+      })
+      .collect::<Result<Vec<_>, _>>()?;
+    Ok(Self { W, X })
+  }
+
+  /// Absorb the provided instance in the RO
+  pub fn absorb_in_ro<CS: ConstraintSystem<G::Scalar>>(
+    &self,
+    mut cs: CS,
+    ro: &mut PoseidonROGadget<G::Scalar>,
+  ) -> Result<(), SynthesisError> {
+    self.W.absorb_in_ro(cs.namespace(|| "absorb W"), ro)?;
+    for x in &self.X {
+      ro.absorb(x.clone());
+    }
+    Ok(())
+  }
+}
+
+/// A relaxed R1CS instance of the primary circuit allocated in the primary circuit.
+/// Its scalars are native and its commitments are compressed non-native points.
+pub struct AllocatedNativeRelaxedR1CSInstance<G: Group> {
+  pub(crate) W: AllocatedCompressedPoint<G>,
+  pub(crate) E: AllocatedCompressedPoint<G>,
+  pub(crate) u: AllocatedNum<G::Scalar>,
+  pub(crate) X: Vec<AllocatedNum<G::Scalar>>,
+}
+
+impl<G: Group> AllocatedNativeRelaxedR1CSInstance<G> {
+  /// Allocates the given RelaxedR1CSInstance as a witness of the circuit
+  pub fn alloc<CS: ConstraintSystem<G::Scalar>>(
+    mut cs: CS,
+    inst: Option<&RelaxedR1CSInstance<G>>,
+    num_io: usize,
+    limb_width: usize,
+    n_limbs: usize,
+  ) -> Result<Self, SynthesisError> {
+    let W = AllocatedCompressedPoint::alloc(
+      cs.namespace(|| "allocate W"),
+      inst.map(|inst| &inst.comm_W),
+      limb_width,
+      n_limbs,
+    )?;
+    let E = AllocatedCompressedPoint::alloc(
+      cs.namespace(|| "allocate E"),
+      inst.map(|inst| &inst.comm_E),
+      limb_width,
+      n_limbs,
+    )?;
+    let u = AllocatedNum::alloc(cs.namespace(|| "allocate u"), || {
+      Ok(inst.map_or(G::Scalar::zero(), |inst| inst.u))
+    })?;
+    let X = (0..num_io)
+      .map(|i| {
+        AllocatedNum::alloc(cs.namespace(|| format!("allocate X[{}]", i)), || {
+          Ok(inst.map_or(G::Scalar::zero(), |inst| inst.X[i]))
+        })
+      })
+      .collect::<Result<Vec<_>, _>>()?;
+    Ok(Self { W, E, u, X })
+  }
+
+  /// Allocates the default RelaxedR1CSInstance: W = E = 0, u = 0, X = 0
+  pub fn default<CS: ConstraintSystem<G::Scalar>>(
+    mut cs: CS,
+    num_io: usize,
+    limb_width: usize,
+    n_limbs: usize,
+  ) -> Result<Self, SynthesisError> {
+    let W = AllocatedCompressedPoint::default(cs.namespace(|| "allocate W"), limb_width, n_limbs)?;
+    let E = W.clone();
+    let u = alloc_zero(cs.namespace(|| "allocate u"))?;
+    let X = vec![u.clone(); num_io];
+    Ok(Self { W, E, u, X })
+  }
+
+  /// Absorb the provided instance in the RO
+  pub fn absorb_in_ro<CS: ConstraintSystem<G::Scalar>>(
+    &self,
+    mut cs: CS,
+    ro: &mut PoseidonROGadget<G::Scalar>,
+  ) -> Result<(), SynthesisError> {
+    self.W.absorb_in_ro(cs.namespace(|| "absorb W"), ro)?;
+    self.E.absorb_in_ro(cs.namespace(|| "absorb E"), ro)?;
+    ro.absorb(self.u.clone());
+    for x in &self.X {
+      ro.absorb(x.clone());
+    }
+    Ok(())
+  }
+
+  /// Folds self with an R1CS instance. The scalars are folded natively, while the folded
+  /// commitments `W_fold` and `E_fold` are provided by the prover and must be checked by
+  /// the CycleFold circuit. Returns the folded instance and the bits of the challenge.
+  #[allow(clippy::too_many_arguments)]
+  pub fn fold_with_r1cs<CS: ConstraintSystem<G::Scalar>>(
+    &self,
+    mut cs: CS,
+    params: AllocatedNum<G::Scalar>,
+    u: &AllocatedNativeR1CSInstance<G>,
+    T: &AllocatedCompressedPoint<G>,
+    W_fold: AllocatedCompressedPoint<G>,
+    E_fold: AllocatedCompressedPoint<G>,
+    ro_consts: ROConstantsCircuit<G::Scalar>,
+  ) -> Result<(Self, Vec<AllocatedBit>), SynthesisError> {
+    // Compute r:
+    let mut ro: PoseidonROGadget<G::Scalar> = PoseidonROGadget::new(ro_consts);
+    ro.absorb(params);
+    self.absorb_in_ro(cs.namespace(|| "absorb running instance"), &mut ro)?;
+    u.absorb_in_ro(cs.namespace(|| "absorb instance"), &mut ro)?;
+    T.absorb_in_ro(cs.namespace(|| "absorb T"), &mut ro)?;
+    let r_bits = ro.get_challenge(cs.namespace(|| "r bits"))?;
+    let r = le_bits_to_num(cs.namespace(|| "r"), r_bits.clone())?;
+
+    // u_fold = self.u + r
+    let u_fold = AllocatedNum::alloc(cs.namespace(|| "u_fold"), || {
+      Ok(*self.u.get_value().get()? + r.get_value().get()?)
+    })?;
+    cs.enforce(
+      || "Check u_fold",
+      |lc| lc,
+      |lc| lc,
+      |lc| lc + u_fold.get_variable() - self.u.get_variable() - r.get_variable(),
+    );
+
+    // X_fold[i] = self.X[i] + r * u.X[i]
+    let X_fold = self
+      .X
+      .iter()
+      .zip(&u.X)
+      .enumerate()
+      .map(|(i, (X_r, X))| {
+        let X_fold = AllocatedNum::alloc(cs.namespace(|| format!("X_fold[{}]", i)), || {
+          Ok(*X_r.get_value().get()? + *r.get_value().get()? * X.get_value().get()?)
+        })?;
+        cs.enforce(
+          || format!("Check X_fold[{}]", i),
+          |lc| lc + r.get_variable(),
+          |lc| lc + X.get_variable(),
+          |lc| lc + X_fold.get_variable() - X_r.get_variable(),
+        );
+        Ok(X_fold)
+      })
+      .collect::<Result<Vec<_>, SynthesisError>>()?;
+
+    Ok((
+      Self {
+        W: W_fold,
+        E: E_fold,
+        u: u_fold,
+        X: X_fold,
+      },
+      r_bits,
+    ))
+  }
+
+  /// If the condition is true then returns this otherwise it returns the other
+  pub fn conditionally_select<CS: ConstraintSystem<G::Scalar>>(
+    &self,
+    mut cs: CS,
+    other: &Self,
+    condition: &Boolean,
+  ) -> Result<Self, SynthesisError> {
+    let W = AllocatedCompressedPoint::conditionally_select(
+      cs.namespace(|| "W = cond ? self.W : other.W"),
+      &self.W,
+      &other.W,
+      condition,
+    )?;
+    let E = AllocatedCompressedPoint::conditionally_select(
+      cs.namespace(|| "E = cond ? self.E : other.E"),
+      &self.E,
+      &other.E,
+      condition,
+    )?;
+    let u = conditionally_select(
+      cs.namespace(|| "u = cond ? self.u : other.u"),
+      &self.u,
+      &other.u,
+      condition,
+    )?;
+    let X = self
+      .X
+      .iter()
+      .zip(&other.X)
+      .enumerate()
+      .map(|(i, (a, b))| {
+        conditionally_select(
+          cs.namespace(|| format!("X[{}] = cond ? self.X[{}] : other.X[{}]", i, i, i)),
+          a,
+          b,
+          condition,
+        )
+      })
+      .collect::<Result<Vec<_>, _>>()?;
+    Ok(Self { W, E, u, X })
+  }
+}
+
+/// A relaxed R1CS instance of the CycleFold circuit allocated in the primary circuit.
+/// Its commitments are native points while its scalars are non-native and kept as `BigNat`s.
+pub struct AllocatedCycleFoldInstance<G: Group> {
+  pub(crate) W: AllocatedPoint<G::Base>,
+  pub(crate) E: AllocatedPoint<G::Base>,
+  pub(crate) u: BigNat<G::Base>,
+  pub(crate) X: Vec<BigNat<G::Base>>,
+}
+
+impl<G: Group> AllocatedCycleFoldInstance<G> {
+  /// Allocates the given RelaxedR1CSInstance as a witness of the circuit
+  pub fn alloc<CS: ConstraintSystem<G::Base>>(
+    mut cs: CS,
+    inst: Option<&RelaxedR1CSInstance<G>>,
+    num_io: usize,
+    limb_width: usize,
+    n_limbs: usize,
+  ) -> Result<Self, SynthesisError> {
+    let W = AllocatedPoint::alloc(
+      cs.namespace(|| "allocate W"),
+      inst.map(|inst| inst.comm_W.comm.to_coordinates()),
+    )?;
+    let E = AllocatedPoint::alloc(
+      cs.namespace(|| "allocate E"),
+      inst.map(|inst| inst.comm_E.comm.to_coordinates()),
+    )?;
+    let u = BigNat::alloc_from_nat(
+      cs.namespace(|| "allocate u"),
+      || Ok(f_to_nat(&inst.map_or(G::Scalar::zero(), |inst| inst.u))),
+      limb_width,
+      n_limbs,
+    )?;
+    let X = (0..num_io)
+      .map(|i| {
+        BigNat::alloc_from_nat(
+          cs.namespace(|| format!("allocate X[{}]", i)),
+          || Ok(f_to_nat(&inst.map_or(G::Scalar::zero(), |inst| inst.X[i]))),
+          limb_width,
+          n_limbs,
+        )
+      })
+      .collect::<Result<Vec<_>, _>>()?;
+    Ok(Self { W, E, u, X })
+  }
+
+  /// Allocates the default RelaxedR1CSInstance: W = E = 0, u = 0, X = 0
+  pub fn default<CS: ConstraintSystem<G::Base>>(
+    mut cs: CS,
+    num_io: usize,
+    limb_width: usize,
+    n_limbs: usize,
+  ) -> Result<Self, SynthesisError> {
+    let W = AllocatedPoint::default(cs.namespace(|| "allocate W"))?;
+    let E = W.clone();
+    let u = alloc_bignat_constant(
+      cs.namespace(|| "allocate u"),
+      &BigInt::from(0),
+      limb_width,
+      n_limbs,
+    )?;
+    let X = vec![u.clone(); num_io];
+    Ok(Self { W, E, u, X })
+  }
+
+  /// Absorb the provided instance in the RO
+  pub fn absorb_in_ro<CS: ConstraintSystem<G::Base>>(
+    &self,
+    mut cs: CS,
+    ro: &mut PoseidonROGadget<G::Base>,
+  ) -> Result<(), SynthesisError> {
+    ro.absorb(self.W.x.clone());
+    ro.absorb(self.W.y.clone());
+    ro.absorb(self.W.is_infinity.clone());
+    ro.absorb(self.E.x.clone());
+    ro.absorb(self.E.y.clone());
+    ro.absorb(self.E.is_infinity.clone());
+    absorb_bignat_in_ro(cs.namespace(|| "absorb u"), &self.u, ro)?;
+    for (i, x) in self.X.iter().enumerate() {
+      absorb_bignat_in_ro(cs.namespace(|| format!("absorb X[{}]", i)), x, ro)?;
+    }
+    Ok(())
+  }
+
+  /// Folds self with an R1CS instance of the CycleFold circuit, given by its commitment
+  /// `W` and its (well-formed) public IO `X`, and returns the result
+  #[allow(clippy::too_many_arguments)]
+  pub fn fold_with_r1cs<CS: ConstraintSystem<G::Base>>(
+    &self,
+    mut cs: CS,
+    params: AllocatedNum<G::Base>,
+    W: &AllocatedPoint<G::Base>,
+    X: &[BigNat<G::Base>],
+    T: &AllocatedPoint<G::Base>,
+    ro_consts: ROConstantsCircuit<G::Base>,
+    limb_width: usize,
+    n_limbs: usize,
+  ) -> Result<Self, SynthesisError> {
+    // Compute r:
+    let mut ro: PoseidonROGadget<G::Base> = PoseidonROGadget::new(ro_consts);
+    ro.absorb(params);
+    self.absorb_in_ro(cs.namespace(|| "absorb running instance"), &mut ro)?;
+    ro.absorb(W.x.clone());
+    ro.absorb(W.y.clone());
+    ro.absorb(W.is_infinity.clone());
+    for (i, x) in X.iter().enumerate() {
+      absorb_bignat_in_ro(cs.namespace(|| format!("absorb x[{}]", i)), x, &mut ro)?;
+    }
+    ro.absorb(T.x.clone());
+    ro.absorb(T.y.clone());
+    ro.absorb(T.is_infinity.clone());
+    let r_bits = ro.get_challenge(cs.namespace(|| "r bits"))?;
+
+    // W_fold = self.W + r * W
+    let rW = W.scalar_mul_windowed(cs.namespace(|| "r * W"), r_bits.clone(), SCALAR_MUL_WINDOW)?;
+    let W_fold = self.W.add(cs.namespace(|| "self.W + r * W"), &rW)?;
+
+    // E_fold = self.E + r * T
+    let rT = T.scalar_mul_windowed(cs.namespace(|| "r * T"), r_bits.clone(), SCALAR_MUL_WINDOW)?;
+    let E_fold = self.E.add(cs.namespace(|| "self.E + r * T"), &rT)?;
+
+    // Fold the scalars modulo the order of the group
+    let r_bn = bits_to_bignat(&r_bits, limb_width, r_bits.len().div_ceil(limb_width));
+    let m = G::get_order();
+    let m_bn = alloc_bignat_constant(cs.namespace(|| "alloc m"), &m, limb_width, n_limbs)?;
+
+    // u_fold = self.u + r, which needs no multiplication since the incoming instance has u = 1
+    let u_fold = self
+      .u
+      .add::<CS>(&r_bn)?
+      .red_mod(cs.namespace(|| "u_fold"), &m_bn)?;
+
+    // X_fold[i] = self.X[i] + r * X[i]
+    let X_fold = self
+      .X
+      .iter()
+      .zip(X)
+      .enumerate()
+      .map(|(i, (X_r, X))| {
+        fold_bignat(
+          cs.namespace(|| format!("X_fold[{}]", i)),
+          X_r,
+          &r_bn,
+          X,
+          &m_bn,
+        )
+      })
+      .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(Self {
+      W: W_fold,
+      E: E_fold,
+      u: u_fold,
+      X: X_fold,
+    })
+  }
+
+  /// If the condition is true then returns this otherwise it returns the other
+  pub fn conditionally_select<CS: ConstraintSystem<G::Base>>(
+    &self,
+    mut cs: CS,
+    other: &Self,
+    condition: &Boolean,
+  ) -> Result<Self, SynthesisError> {
+    let W = AllocatedPoint::conditionally_select(
+      cs.namespace(|| "W = cond ? self.W : other.W"),
+      &self.W,
+      &other.W,
+      condition,
+    )?;
+    let E = AllocatedPoint::conditionally_select(
+      cs.namespace(|| "E = cond ? self.E : other.E"),
+      &self.E,
+      &other.E,
+      condition,
+    )?;
+    let u = conditionally_select_bignat(
+      cs.namespace(|| "u = cond ? self.u : other.u"),
+      &self.u,
+      &other.u,
+      condition,
+    )?;
+    let X = self
+      .X
+      .iter()
+      .zip(&other.X)
+      .enumerate()
+      .map(|(i, (a, b))| {
+        conditionally_select_bignat(
+          cs.namespace(|| format!("X[{}] = cond ? self.X[{}] : other.X[{}]", i, i, i)),
+          a,
+          b,
+          condition,
+        )
+      })
+      .collect::<Result<Vec<_>, _>>()?;
+    Ok(Self { W, E, u, X })
+  }
+}
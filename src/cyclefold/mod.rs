@@ -0,0 +1,745 @@
+//! This module implements an alternative recursion in the style of CycleFold.
+//! Only the primary circuit executes the step circuit, and it folds its own instances
+//! with native field operations. The group operations of each fold are proven by a small
+//! CycleFold circuit over the secondary curve, whose instances are in turn folded
+//! by the primary circuit.
+pub(crate) mod circuit;
+pub(crate) mod gadgets;
+
+use crate::{
+  bellperson::{
+    r1cs::{NovaShape, NovaWitness},
+    shape_cs::ShapeCS,
+    solver::SatisfyingAssignment,
+  },
+  commitments::{compressed_point_elements, Commitment},
+  errors::{Curve, NovaError, VerificationFailure},
+  gadgets::utils::scalar_as_base,
+  parallel,
+  poseidon::ROConstantsCircuit,
+  r1cs::{
+    digest_to_scalar, R1CSGens, R1CSInstance, R1CSShape, R1CSWitness, RelaxedR1CSInstance,
+    RelaxedR1CSWitness,
+  },
+  security::SecurityConfig,
+  traits::{AbsorbInROTrait, Group, HashFuncConstantsTrait, HashFuncTrait, StepCircuit},
+  PublicParamsSerialized,
+};
+use ::bellperson::{Circuit, ConstraintSystem};
+use bellperson_nonnative::{mp::bignat::nat_to_limbs, util::convert::f_to_nat};
+use circuit::{
+  CycleFoldCircuit, CycleFoldCircuitInputs, PrimaryCircuit, PrimaryCircuitInputs,
+  PrimaryCircuitParams, NUM_IO_CYCLEFOLD, NUM_IO_PRIMARY,
+};
+use core::marker::PhantomData;
+use ff::{Field, PrimeField};
+
+type ROConstants<G> =
+  <<G as Group>::HashFunc as HashFuncTrait<<G as Group>::Base, <G as Group>::Scalar>>::Constants;
+
+/// Absorbs an element of `G2::Scalar` as elements of `G2::Base` that each hold half of the
+/// limbs of the configuration, the way the primary circuit absorbs a `BigNat`
+fn absorb_non_native<G2: Group>(ro: &mut G2::HashFunc, e: &G2::Scalar, config: &SecurityConfig) {
+  let limbs_per_group = config.n_limbs / 2;
+  let limbs: Vec<G2::Base> = nat_to_limbs(
+    &f_to_nat(e),
+    config.limb_width * limbs_per_group,
+    config.n_limbs.div_ceil(limbs_per_group),
+  )
+  .unwrap();
+  for limb in limbs {
+    ro.absorb(limb);
+  }
+}
+
+/// Absorbs a commitment of the primary circuit in compressed form
+fn absorb_compressed<G1, G2>(ro: &mut G2::HashFunc, comm: &Commitment<G1>)
+where
+  G1: Group<Base = <G2 as Group>::Scalar>,
+  G2: Group<Base = <G1 as Group>::Scalar>,
+{
+  for e in compressed_point_elements(comm) {
+    ro.absorb(e);
+  }
+}
+
+/// Absorbs a relaxed R1CS instance of the primary circuit
+fn absorb_primary_instance<G1, G2>(ro: &mut G2::HashFunc, U: &RelaxedR1CSInstance<G1>)
+where
+  G1: Group<Base = <G2 as Group>::Scalar>,
+  G2: Group<Base = <G1 as Group>::Scalar>,
+{
+  absorb_compressed::<G1, G2>(ro, &U.comm_W);
+  absorb_compressed::<G1, G2>(ro, &U.comm_E);
+  ro.absorb(U.u);
+  for x in &U.X {
+    ro.absorb(*x);
+  }
+}
+
+/// Absorbs a relaxed R1CS instance of the CycleFold circuit
+fn absorb_cyclefold_instance<G2: Group>(
+  ro: &mut G2::HashFunc,
+  U: &RelaxedR1CSInstance<G2>,
+  config: &SecurityConfig,
+) {
+  U.comm_W.absorb_in_ro(ro);
+  U.comm_E.absorb_in_ro(ro);
+  absorb_non_native::<G2>(ro, &U.u, config);
+  for x in &U.X {
+    absorb_non_native::<G2>(ro, x, config);
+  }
+}
+
+/// A type that holds public parameters of the CycleFold recursion
+pub struct PublicParams<G1, G2, C1>
+where
+  G1: Group<Base = <G2 as Group>::Scalar>,
+  G2: Group<Base = <G1 as Group>::Scalar>,
+  C1: StepCircuit<G1::Scalar> + Clone,
+{
+  ro_consts: ROConstants<G2>,
+  ro_consts_circuit: ROConstantsCircuit<G1::Scalar>,
+  r1cs_gens_primary: R1CSGens<G1>,
+  r1cs_shape_primary: R1CSShape<G1>,
+  r1cs_gens_cyclefold: R1CSGens<G2>,
+  r1cs_shape_cyclefold: R1CSShape<G2>,
+  c_primary: C1,
+  params_primary: PrimaryCircuitParams,
+  config: SecurityConfig,
+  digest: G1::Scalar,
+}
+
+impl<G1, G2, C1> PublicParams<G1, G2, C1>
+where
+  G1: Group<Base = <G2 as Group>::Scalar>,
+  G2: Group<Base = <G1 as Group>::Scalar>,
+  C1: StepCircuit<G1::Scalar> + Clone,
+{
+  /// Create a new `PublicParams`
+  ///
+  /// # Panics
+  ///
+  /// Panics if the step circuit fails to synthesize; use `setup_with_config` to get the error.
+  pub fn setup(c_primary: C1) -> Self {
+    Self::setup_with_tag(c_primary, b"")
+  }
+
+  /// Create a new `PublicParams` for an application with the domain-separation tag `tag`,
+  /// which extends the labels of the generators and is bound in the digest of the parameters,
+  /// as in `crate::PublicParams::setup_with_tag`
+  ///
+  /// # Panics
+  ///
+  /// Panics if the step circuit fails to synthesize; use `setup_with_config` to get the error.
+  pub fn setup_with_tag(c_primary: C1, tag: &[u8]) -> Self {
+    Self::setup_inner(c_primary, tag, &SecurityConfig::default())
+      .expect("the step circuit must synthesize without witnesses")
+  }
+
+  /// Create a new `PublicParams` for the domain-separation tag `tag` with the security
+  /// configuration `config`, which is bound in the digest of the parameters.
+  /// Returns an error if `config` is out of the supported bounds, or `NovaError::Synthesis`
+  /// if the step circuit fails to synthesize.
+  pub fn setup_with_config(
+    c_primary: C1,
+    tag: &[u8],
+    config: &SecurityConfig,
+  ) -> Result<Self, NovaError> {
+    config.validate()?;
+    Self::setup_inner(c_primary, tag, config)
+  }
+
+  fn setup_inner(c_primary: C1, tag: &[u8], config: &SecurityConfig) -> Result<Self, NovaError> {
+    let params_primary = PrimaryCircuitParams::new(config.limb_width, config.n_limbs);
+    let ro_consts: ROConstants<G2> = ROConstants::<G2>::new_with_config(config);
+    let ro_consts_circuit: ROConstantsCircuit<G1::Scalar> =
+      ROConstantsCircuit::new_with_config(config);
+
+    // Initialize gens for the primary
+    let circuit_primary: PrimaryCircuit<G1, G2, C1> = PrimaryCircuit::new(
+      params_primary.clone(),
+      None,
+      c_primary.clone(),
+      ro_consts_circuit.clone(),
+    );
+    let mut cs: ShapeCS<G1> = ShapeCS::new();
+    circuit_primary.synthesize(&mut cs)?;
+    let r1cs_shape_primary = cs.r1cs_shape();
+    let r1cs_gens_primary = R1CSGens::new_with_tag(
+      r1cs_shape_primary.num_cons,
+      r1cs_shape_primary.num_vars,
+      tag,
+    );
+
+    // Initialize gens for the CycleFold circuit
+    let circuit_cyclefold: CycleFoldCircuit<G1> =
+      CycleFoldCircuit::new(config.num_challenge_bits, None);
+    let mut cs: ShapeCS<G2> = ShapeCS::new();
+    circuit_cyclefold.synthesize(&mut cs)?;
+    let r1cs_shape_cyclefold = cs.r1cs_shape();
+    let r1cs_gens_cyclefold = R1CSGens::new_with_tag(
+      r1cs_shape_cyclefold.num_cons,
+      r1cs_shape_cyclefold.num_vars,
+      tag,
+    );
+
+    // The primary circuit receives the digest as a witness
+    let pp_serialized = PublicParamsSerialized {
+      tag: tag.to_vec(),
+      config: *config,
+      shapes: vec![
+        r1cs_shape_primary.get_digest().to_repr().as_ref().to_vec(),
+        r1cs_shape_cyclefold
+          .get_digest()
+          .to_repr()
+          .as_ref()
+          .to_vec(),
+      ],
+      gens: vec![r1cs_gens_primary.to_bytes(), r1cs_gens_cyclefold.to_bytes()],
+      ro_consts: vec![ro_consts.to_bytes(), ro_consts_circuit.to_bytes()],
+      params: vec![params_primary.clone()],
+    };
+    let digest = digest_to_scalar(&bincode::serialize(&pp_serialized).unwrap());
+
+    Ok(Self {
+      ro_consts,
+      ro_consts_circuit,
+      r1cs_gens_primary,
+      r1cs_shape_primary,
+      r1cs_gens_cyclefold,
+      r1cs_shape_cyclefold,
+      c_primary,
+      params_primary,
+      config: *config,
+      digest,
+    })
+  }
+
+  /// Returns the number of constraints in the primary circuit and in the CycleFold circuit
+  pub fn num_constraints(&self) -> (usize, usize) {
+    (
+      self.r1cs_shape_primary.num_cons,
+      self.r1cs_shape_cyclefold.num_cons,
+    )
+  }
+
+  /// Computes H(params, i, z0, zi, U, U_cyclefold), the hash output by the primary circuit
+  fn hash_state(
+    &self,
+    i: usize,
+    z0: G1::Scalar,
+    zi: G1::Scalar,
+    U: &RelaxedR1CSInstance<G1>,
+    U_cyclefold: &RelaxedR1CSInstance<G2>,
+  ) -> G1::Scalar {
+    let mut hasher = G2::HashFunc::new(self.ro_consts.clone());
+    hasher.absorb(self.digest);
+    hasher.absorb(G1::Scalar::from(i as u64));
+    hasher.absorb(z0);
+    hasher.absorb(zi);
+    absorb_primary_instance::<G1, G2>(&mut hasher, U);
+    absorb_cyclefold_instance::<G2>(&mut hasher, U_cyclefold, &self.config);
+    scalar_as_base::<G2>(hasher.get_hash())
+  }
+
+  /// Folds an instance of the primary circuit into a running instance with a challenge
+  /// that the primary circuit can compute natively. Returns the commitment to the cross-term
+  /// along with the challenge, which is less than both scalar fields.
+  fn fold_primary(
+    &self,
+    U: &RelaxedR1CSInstance<G1>,
+    W: &RelaxedR1CSWitness<G1>,
+    u: &R1CSInstance<G1>,
+    w: &R1CSWitness<G1>,
+  ) -> Result<
+    (
+      Commitment<G1>,
+      G1::Scalar,
+      RelaxedR1CSInstance<G1>,
+      RelaxedR1CSWitness<G1>,
+    ),
+    NovaError,
+  > {
+    let (T, comm_T) = self
+      .r1cs_shape_primary
+      .commit_T(&self.r1cs_gens_primary, U, W, u, w)?;
+
+    let mut ro = G2::HashFunc::new(self.ro_consts.clone());
+    ro.absorb(self.digest);
+    absorb_primary_instance::<G1, G2>(&mut ro, U);
+    absorb_compressed::<G1, G2>(&mut ro, &u.comm_W);
+    for x in &u.X {
+      ro.absorb(*x);
+    }
+    absorb_compressed::<G1, G2>(&mut ro, &comm_T);
+    let r = scalar_as_base::<G2>(ro.get_challenge());
+
+    let U_fold = U.fold(u, &comm_T, &r)?;
+    let W_fold = W.fold(w, &T, &r)?;
+    Ok((comm_T, r, U_fold, W_fold))
+  }
+
+  /// Folds an instance of the CycleFold circuit into a running instance
+  fn fold_cyclefold(
+    &self,
+    U: &RelaxedR1CSInstance<G2>,
+    W: &RelaxedR1CSWitness<G2>,
+    u: &R1CSInstance<G2>,
+    w: &R1CSWitness<G2>,
+  ) -> Result<
+    (
+      Commitment<G2>,
+      RelaxedR1CSInstance<G2>,
+      RelaxedR1CSWitness<G2>,
+    ),
+    NovaError,
+  > {
+    let (T, comm_T) = self
+      .r1cs_shape_cyclefold
+      .commit_T(&self.r1cs_gens_cyclefold, U, W, u, w)?;
+
+    let mut ro = G2::HashFunc::new(self.ro_consts.clone());
+    ro.absorb(self.digest);
+    absorb_cyclefold_instance::<G2>(&mut ro, U, &self.config);
+    u.comm_W.absorb_in_ro(&mut ro);
+    for x in &u.X {
+      absorb_non_native::<G2>(&mut ro, x, &self.config);
+    }
+    comm_T.absorb_in_ro(&mut ro);
+    let r = ro.get_challenge();
+
+    let U_fold = U.fold(u, &comm_T, &r)?;
+    let W_fold = W.fold(w, &T, &r)?;
+    Ok((comm_T, U_fold, W_fold))
+  }
+}
+
+/// A SNARK that proves the correct execution of an incremental computation
+/// using the CycleFold recursion
+pub struct RecursiveSNARK<G1, G2, C1>
+where
+  G1: Group<Base = <G2 as Group>::Scalar>,
+  G2: Group<Base = <G1 as Group>::Scalar>,
+  C1: StepCircuit<G1::Scalar> + Clone,
+{
+  r_W_primary: RelaxedR1CSWitness<G1>,
+  r_U_primary: RelaxedR1CSInstance<G1>,
+  l_w_primary: R1CSWitness<G1>,
+  l_u_primary: R1CSInstance<G1>,
+  r_W_cyclefold: RelaxedR1CSWitness<G2>,
+  r_U_cyclefold: RelaxedR1CSInstance<G2>,
+  num_steps: usize,
+  z0_primary: G1::Scalar,
+  zn_primary: G1::Scalar,
+  _p_c1: PhantomData<C1>,
+}
+
+impl<G1, G2, C1> RecursiveSNARK<G1, G2, C1>
+where
+  G1: Group<Base = <G2 as Group>::Scalar>,
+  G2: Group<Base = <G1 as Group>::Scalar>,
+  C1: StepCircuit<G1::Scalar> + Clone,
+{
+  /// Create a new `RecursiveSNARK`
+  pub fn prove(
+    pp: &PublicParams<G1, G2, C1>,
+    num_steps: usize,
+    z0_primary: G1::Scalar,
+  ) -> Result<Self, NovaError> {
+    if num_steps == 0 {
+      return Err(NovaError::InvalidNumSteps);
+    }
+
+    // Execute the base case for the primary
+    let mut cs_primary: SatisfyingAssignment<G1> = SatisfyingAssignment::new();
+    let inputs_primary: PrimaryCircuitInputs<G1, G2> = PrimaryCircuitInputs::new(
+      pp.digest,
+      G1::Scalar::zero(),
+      z0_primary,
+      None,
+      None,
+      None,
+      None,
+      None,
+      None,
+      None,
+      None,
+    );
+    let circuit_primary: PrimaryCircuit<G1, G2, C1> = PrimaryCircuit::new(
+      pp.params_primary.clone(),
+      Some(inputs_primary),
+      pp.c_primary.clone(),
+      pp.ro_consts_circuit.clone(),
+    );
+    let (mut l_u_primary, mut l_w_primary) = circuit_primary
+      .synthesize(&mut cs_primary)
+      .map_err(NovaError::from)
+      .and_then(|_| {
+        cs_primary.r1cs_instance_and_witness(&pp.r1cs_shape_primary, &pp.r1cs_gens_primary)
+      })
+      .map_err(|e| e.in_step(Curve::Primary, 0))?;
+
+    // The base case outputs the default running instances
+    let mut r_W_primary = RelaxedR1CSWitness::<G1>::default(&pp.r1cs_shape_primary);
+    let mut r_U_primary =
+      RelaxedR1CSInstance::<G1>::default(&pp.r1cs_gens_primary, &pp.r1cs_shape_primary);
+    let mut r_W_cyclefold = RelaxedR1CSWitness::<G2>::default(&pp.r1cs_shape_cyclefold);
+    let mut r_U_cyclefold =
+      RelaxedR1CSInstance::<G2>::default(&pp.r1cs_gens_cyclefold, &pp.r1cs_shape_cyclefold);
+
+    let mut z_next_primary = pp
+      .c_primary
+      .try_compute(&z0_primary)
+      .map_err(|e| e.in_step(Curve::Primary, 0))?;
+
+    for i in 1..num_steps {
+      // fold the last instance of the primary circuit
+      let (comm_T, r, r_U_next_primary, r_W_next_primary) = pp
+        .fold_primary(&r_U_primary, &r_W_primary, &l_u_primary, &l_w_primary)
+        .map_err(|e| e.in_step(Curve::Primary, i))?;
+
+      // prove the group operations of that fold with the CycleFold circuit
+      let mut cs_cyclefold: SatisfyingAssignment<G2> = SatisfyingAssignment::new();
+      let inputs_cyclefold =
+        CycleFoldCircuitInputs::new(&r_U_primary, &l_u_primary, &comm_T, &r_U_next_primary, r);
+      let circuit_cyclefold: CycleFoldCircuit<G1> =
+        CycleFoldCircuit::new(pp.config.num_challenge_bits, Some(inputs_cyclefold));
+      let (l_u_cyclefold, l_w_cyclefold) = circuit_cyclefold
+        .synthesize(&mut cs_cyclefold)
+        .map_err(NovaError::from)
+        .and_then(|_| {
+          cs_cyclefold.r1cs_instance_and_witness(&pp.r1cs_shape_cyclefold, &pp.r1cs_gens_cyclefold)
+        })
+        .map_err(|e| e.in_step(Curve::Secondary, i))?;
+
+      // fold the instance of the CycleFold circuit
+      let (comm_T_cyclefold, r_U_next_cyclefold, r_W_next_cyclefold) = pp
+        .fold_cyclefold(
+          &r_U_cyclefold,
+          &r_W_cyclefold,
+          &l_u_cyclefold,
+          &l_w_cyclefold,
+        )
+        .map_err(|e| e.in_step(Curve::Secondary, i))?;
+
+      // run the primary circuit, which checks both folds
+      let mut cs_primary: SatisfyingAssignment<G1> = SatisfyingAssignment::new();
+      let inputs_primary: PrimaryCircuitInputs<G1, G2> = PrimaryCircuitInputs::new(
+        pp.digest,
+        G1::Scalar::from(i as u64),
+        z0_primary,
+        Some(z_next_primary),
+        Some(r_U_primary),
+        Some(l_u_primary),
+        Some(comm_T),
+        Some(r_U_next_primary.clone()),
+        Some(r_U_cyclefold),
+        Some(l_u_cyclefold),
+        Some(comm_T_cyclefold),
+      );
+      let circuit_primary: PrimaryCircuit<G1, G2, C1> = PrimaryCircuit::new(
+        pp.params_primary.clone(),
+        Some(inputs_primary),
+        pp.c_primary.clone(),
+        pp.ro_consts_circuit.clone(),
+      );
+      (l_u_primary, l_w_primary) = circuit_primary
+        .synthesize(&mut cs_primary)
+        .map_err(NovaError::from)
+        .and_then(|_| {
+          cs_primary.r1cs_instance_and_witness(&pp.r1cs_shape_primary, &pp.r1cs_gens_primary)
+        })
+        .map_err(|e| e.in_step(Curve::Primary, i))?;
+
+      // update the running instances and witnesses
+      r_U_primary = r_U_next_primary;
+      r_W_primary = r_W_next_primary;
+      r_U_cyclefold = r_U_next_cyclefold;
+      r_W_cyclefold = r_W_next_cyclefold;
+      z_next_primary = pp
+        .c_primary
+        .try_compute(&z_next_primary)
+        .map_err(|e| e.in_step(Curve::Primary, i))?;
+    }
+
+    Ok(Self {
+      r_W_primary,
+      r_U_primary,
+      l_w_primary,
+      l_u_primary,
+      r_W_cyclefold,
+      r_U_cyclefold,
+      num_steps,
+      z0_primary,
+      zn_primary: z_next_primary,
+      _p_c1: Default::default(),
+    })
+  }
+
+  /// Verify the correctness of the `RecursiveSNARK`
+  pub fn verify(
+    &self,
+    pp: &PublicParams<G1, G2, C1>,
+    num_steps: usize,
+    z0_primary: G1::Scalar,
+  ) -> Result<G1::Scalar, NovaError> {
+    // number of steps cannot be zero
+    if num_steps == 0 {
+      return Err(NovaError::InvalidNumSteps);
+    }
+
+    // check if the proof is for the supplied statement, before checking the proof itself
+    if self.num_steps != num_steps {
+      return Err(NovaError::verify_failure(
+        VerificationFailure::NumStepsMismatch {
+          claimed: self.num_steps,
+          supplied: num_steps,
+        },
+      ));
+    }
+    if self.z0_primary != z0_primary {
+      return Err(NovaError::verify_failure(
+        VerificationFailure::InitialInputMismatch {
+          curve: Curve::Primary,
+        },
+      ));
+    }
+
+    // check if the instances have the right number of public IO; the CycleFold circuit is
+    // over the secondary curve
+    let io_length = |curve, expected, actual| {
+      NovaError::verify_failure(VerificationFailure::IOLengthMismatch {
+        curve,
+        expected,
+        actual,
+      })
+    };
+    for len in [self.l_u_primary.X.len(), self.r_U_primary.X.len()] {
+      if len != NUM_IO_PRIMARY {
+        return Err(io_length(Curve::Primary, NUM_IO_PRIMARY, len));
+      }
+    }
+    if self.r_U_cyclefold.X.len() != NUM_IO_CYCLEFOLD {
+      return Err(io_length(
+        Curve::Secondary,
+        NUM_IO_CYCLEFOLD,
+        self.r_U_cyclefold.X.len(),
+      ));
+    }
+
+    // check if the output hash in the R1CS instance points to the right running instances
+    let hash = pp.hash_state(
+      num_steps,
+      z0_primary,
+      self.zn_primary,
+      &self.r_U_primary,
+      &self.r_U_cyclefold,
+    );
+    if hash != self.l_u_primary.X[1] {
+      return Err(NovaError::verify_failure(
+        VerificationFailure::HashMismatch {
+          curve: Curve::Primary,
+        },
+      ));
+    }
+
+    // check the satisfiability of the provided instances
+    let ((res_r_primary, res_l_primary), res_r_cyclefold) = parallel::join(
+      || {
+        parallel::join(
+          || {
+            pp.r1cs_shape_primary.is_sat_relaxed(
+              &pp.r1cs_gens_primary,
+              &self.r_U_primary,
+              &self.r_W_primary,
+            )
+          },
+          || {
+            pp.r1cs_shape_primary.is_sat(
+              &pp.r1cs_gens_primary,
+              &self.l_u_primary,
+              &self.l_w_primary,
+            )
+          },
+        )
+      },
+      || {
+        pp.r1cs_shape_cyclefold.is_sat_relaxed(
+          &pp.r1cs_gens_cyclefold,
+          &self.r_U_cyclefold,
+          &self.r_W_cyclefold,
+        )
+      },
+    );
+
+    // check the returned res objects
+    let relaxed_unsat = |curve| {
+      move |e| {
+        NovaError::verify_failure(VerificationFailure::RelaxedInstanceUnSat {
+          curve,
+          source: Box::new(e),
+        })
+      }
+    };
+    res_r_primary.map_err(relaxed_unsat(Curve::Primary))?;
+    res_l_primary.map_err(|e| {
+      NovaError::verify_failure(VerificationFailure::FreshInstanceUnSat {
+        curve: Curve::Primary,
+        source: Box::new(e),
+      })
+    })?;
+    res_r_cyclefold.map_err(relaxed_unsat(Curve::Secondary))?;
+
+    Ok(self.zn_primary)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    circuit::{NIFSVerifierCircuit, NIFSVerifierCircuitParams},
+    constants::{BN_LIMB_WIDTH, BN_N_LIMBS},
+    traits::HashFuncConstantsTrait,
+  };
+  use ::bellperson::{gadgets::num::AllocatedNum, SynthesisError};
+  use ff::PrimeField;
+  type G1 = pasta_curves::pallas::Point;
+  type G2 = pasta_curves::vesta::Point;
+
+  #[derive(Clone, Debug, Default)]
+  struct CubicCircuit<F: PrimeField> {
+    _p: PhantomData<F>,
+  }
+
+  impl<F> StepCircuit<F> for CubicCircuit<F>
+  where
+    F: PrimeField,
+  {
+    fn synthesize<CS: ConstraintSystem<F>>(
+      &self,
+      cs: &mut CS,
+      z: AllocatedNum<F>,
+    ) -> Result<AllocatedNum<F>, SynthesisError> {
+      // Consider a cubic equation: `x^3 + x + 5 = y`, where `x` and `y` are respectively the input and output.
+      let x = z;
+      let x_sq = x.square(cs.namespace(|| "x_sq"))?;
+      let x_cu = x_sq.mul(cs.namespace(|| "x_cu"), &x)?;
+      let y = AllocatedNum::alloc(cs.namespace(|| "y"), || {
+        Ok(x_cu.get_value().unwrap() + x.get_value().unwrap() + F::from(5u64))
+      })?;
+      cs.enforce(
+        || "y = x^3 + x + 5",
+        |lc| lc + x_cu.get_variable() + x.get_variable() + (F::from(5u64), CS::one()),
+        |lc| lc + CS::one(),
+        |lc| lc + y.get_variable(),
+      );
+      Ok(y)
+    }
+
+    fn compute(&self, z: &F) -> F {
+      *z * *z * *z + z + F::from(5u64)
+    }
+  }
+
+  #[test]
+  fn test_cyclefold_num_constraints() {
+    let pp =
+      PublicParams::<G1, G2, CubicCircuit<<G1 as Group>::Scalar>>::setup(CubicCircuit::default());
+    let (num_cons_primary, num_cons_cyclefold) = pp.num_constraints();
+
+    // the circuits of the default recursion, with the same step circuit on both curves
+    let circuit1: NIFSVerifierCircuit<G2, CubicCircuit<<G2 as Group>::Base>> =
+      NIFSVerifierCircuit::new(
+        NIFSVerifierCircuitParams::new(BN_LIMB_WIDTH, BN_N_LIMBS, true, false),
+        None,
+        CubicCircuit::default(),
+        ROConstantsCircuit::new(),
+      );
+    let mut cs: ShapeCS<G1> = ShapeCS::new();
+    let _ = circuit1.synthesize(&mut cs);
+    let num_cons_nifs_primary = cs.num_constraints();
+
+    let circuit2: NIFSVerifierCircuit<G1, CubicCircuit<<G1 as Group>::Base>> =
+      NIFSVerifierCircuit::new(
+        NIFSVerifierCircuitParams::new(BN_LIMB_WIDTH, BN_N_LIMBS, false, false),
+        None,
+        CubicCircuit::default(),
+        ROConstantsCircuit::new(),
+      );
+    let mut cs: ShapeCS<G2> = ShapeCS::new();
+    let _ = circuit2.synthesize(&mut cs);
+    let num_cons_nifs_secondary = cs.num_constraints();
+
+    println!(
+      "CycleFold: primary {} + cyclefold {}; NIFSVerifierCircuit: primary {} + secondary {}",
+      num_cons_primary, num_cons_cyclefold, num_cons_nifs_primary, num_cons_nifs_secondary
+    );
+    assert!(num_cons_cyclefold < num_cons_nifs_secondary);
+    assert!(
+      num_cons_primary + num_cons_cyclefold < num_cons_nifs_primary + num_cons_nifs_secondary
+    );
+  }
+
+  #[test]
+  fn test_cyclefold_recursive_snark() {
+    let pp =
+      PublicParams::<G1, G2, CubicCircuit<<G1 as Group>::Scalar>>::setup(CubicCircuit::default());
+
+    let num_steps = 3;
+    let z0 = <G1 as Group>::Scalar::one();
+    let recursive_snark = RecursiveSNARK::prove(&pp, num_steps, z0).unwrap();
+
+    let res = recursive_snark.verify(&pp, num_steps, z0);
+    assert!(res.is_ok());
+
+    let mut zn = z0;
+    for _ in 0..num_steps {
+      zn = zn * zn * zn + zn + <G1 as Group>::Scalar::from(5u64);
+    }
+    assert_eq!(res.unwrap(), zn);
+
+    // the proof does not verify for a different number of steps
+    assert!(recursive_snark.verify(&pp, num_steps + 1, z0).is_err());
+  }
+
+  #[test]
+  fn test_cyclefold_with_tag_and_config() {
+    type PP = PublicParams<G1, G2, CubicCircuit<<G1 as Group>::Scalar>>;
+    let num_steps = 2;
+    let z0 = <G1 as Group>::Scalar::one();
+
+    // the empty tag gives the parameters of setup
+    let pp = PP::setup(CubicCircuit::default());
+    assert_eq!(
+      pp.digest,
+      PP::setup_with_tag(CubicCircuit::default(), b"").digest
+    );
+
+    // a proof only verifies with the parameters of its application
+    let pp_a = PP::setup_with_tag(CubicCircuit::default(), b"app-a");
+    let pp_b = PP::setup_with_tag(CubicCircuit::default(), b"app-b");
+    assert_ne!(pp_a.digest, pp_b.digest);
+    let recursive_snark = RecursiveSNARK::prove(&pp_a, num_steps, z0).unwrap();
+    assert!(recursive_snark.verify(&pp_a, num_steps, z0).is_ok());
+    assert!(recursive_snark.verify(&pp_b, num_steps, z0).is_err());
+
+    // the configuration sets the width of the challenges of the CycleFold circuit and the
+    // limbs with which the primary circuit absorbs its instances
+    for config in [SecurityConfig::fast_test(), SecurityConfig::conservative()] {
+      let pp = PP::setup_with_config(CubicCircuit::default(), b"", &config).unwrap();
+      let recursive_snark = RecursiveSNARK::prove(&pp, num_steps, z0).unwrap();
+      assert!(recursive_snark.verify(&pp, num_steps, z0).is_ok());
+    }
+
+    let invalid = SecurityConfig {
+      num_challenge_bits: 16,
+      ..SecurityConfig::default()
+    };
+    assert!(matches!(
+      PP::setup_with_config(CubicCircuit::default(), b"", &invalid),
+      Err(NovaError::InvalidSecurityConfig { .. })
+    ));
+  }
+}
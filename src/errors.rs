@@ -1,27 +1,557 @@
-//! This module defines errors returned by the library.
-use core::fmt::Debug;
+//! This module defines errors returned by the library. A `NovaError` carries the context of
+//! a failure, such as the position of an invalid matrix entry, the expected and actual lengths,
+//! the first unsatisfied constraint or the check that fails, but never a field element.
+//! `NovaError::Step` wraps the error of a step with its curve and index, and the errors of
+//! bellperson and bincode are kept behind a `SharedError`, so `source` reaches them while
+//! `NovaError` remains `Clone` and `Eq`. The enum is `non_exhaustive`, so a match on it needs
+//! a wildcard arm.
+use bellperson::SynthesisError;
+use core::{
+  fmt::{self, Debug, Display},
+  ops::Deref,
+};
+use std::sync::Arc;
+
+/// The curve of the cycle, or equivalently the circuit over its scalar field, to which an
+/// error relates
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Curve {
+  /// the primary curve, whose circuit runs the step circuit `C1`
+  Primary,
+  /// the secondary curve, whose circuit runs the step circuit `C2`
+  Secondary,
+}
+
+impl Display for Curve {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Curve::Primary => write!(f, "primary"),
+      Curve::Secondary => write!(f, "secondary"),
+    }
+  }
+}
+
+/// The reason why the verifier of an incremental computation rejects a proof. The verifiers
+/// of `RecursiveSNARK`, `CompressedSNARK` and the CycleFold recursion return the same reasons.
+/// The four satisfiability checks of a `RecursiveSNARK` run in parallel and are reported in a
+/// fixed order (relaxed then fresh, primary then secondary), so only the first failure in
+/// that order is returned.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum VerificationFailure {
+  /// the supplied number of steps is not the one that the proof claims
+  NumStepsMismatch {
+    /// the number of steps that the proof claims
+    claimed: usize,
+    /// the supplied number of steps
+    supplied: usize,
+  },
+  /// the supplied initial input is not the one that the proof claims
+  InitialInputMismatch {
+    /// the curve of the input
+    curve: Curve,
+  },
+  /// an instance does not have the number of public IO of its shape
+  IOLengthMismatch {
+    /// the curve of the instance
+    curve: Curve,
+    /// the number of public IO of the shape
+    expected: usize,
+    /// the number of public IO of the instance
+    actual: usize,
+  },
+  /// the hash in the public IO of the last instance does not match the running instance,
+  /// the number of steps, and the initial and final values
+  HashMismatch {
+    /// the curve of the last instance
+    curve: Curve,
+  },
+  /// the running relaxed instance is not satisfied by its witness
+  RelaxedInstanceUnSat {
+    /// the curve of the instance
+    curve: Curve,
+    /// the error of the check
+    source: Box<NovaError>,
+  },
+  /// the last, fresh instance is not satisfied by its witness
+  FreshInstanceUnSat {
+    /// the curve of the instance
+    curve: Curve,
+    /// the error of the check
+    source: Box<NovaError>,
+  },
+  /// the last instance cannot be folded into the running instance
+  Folding {
+    /// the curve of the instances
+    curve: Curve,
+    /// the error of the folding verifier
+    source: Box<NovaError>,
+  },
+  /// the SNARK of the folded instance does not verify
+  SNARK {
+    /// the curve of the SNARK
+    curve: Curve,
+    /// the error of the SNARK verifier
+    source: Box<NovaError>,
+  },
+}
+
+impl VerificationFailure {
+  /// Returns true if the proof is rejected because the supplied number of steps or
+  /// initial inputs differ from the ones that the proof claims, rather than because
+  /// the proof is invalid. The claims of a proof are not trusted: a proof whose claims
+  /// match the supplied values must still pass the other checks, and a malicious prover
+  /// chooses its claims, so this only diagnoses honestly produced proofs.
+  pub fn is_statement_mismatch(&self) -> bool {
+    matches!(
+      self,
+      VerificationFailure::NumStepsMismatch { .. }
+        | VerificationFailure::InitialInputMismatch { .. }
+    )
+  }
+
+  fn source(&self) -> Option<&NovaError> {
+    match self {
+      VerificationFailure::RelaxedInstanceUnSat { source, .. }
+      | VerificationFailure::FreshInstanceUnSat { source, .. }
+      | VerificationFailure::Folding { source, .. }
+      | VerificationFailure::SNARK { source, .. } => Some(source.as_ref()),
+      _ => None,
+    }
+  }
+}
+
+impl Display for VerificationFailure {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      VerificationFailure::NumStepsMismatch { claimed, supplied } => write!(
+        f,
+        "the proof is for {} steps, not for the supplied {}",
+        claimed, supplied
+      ),
+      VerificationFailure::InitialInputMismatch { curve } => write!(
+        f,
+        "the proof is not for the supplied initial input of the {} circuit",
+        curve
+      ),
+      VerificationFailure::IOLengthMismatch {
+        curve,
+        expected,
+        actual,
+      } => write!(
+        f,
+        "an instance of the {} circuit has {} public IO instead of {}",
+        curve, actual, expected
+      ),
+      VerificationFailure::HashMismatch { curve } => write!(
+        f,
+        "the hash in the last instance of the {} circuit does not match the running instance",
+        curve
+      ),
+      VerificationFailure::RelaxedInstanceUnSat { curve, source } => write!(
+        f,
+        "the running instance of the {} circuit is not satisfied: {}",
+        curve, source
+      ),
+      VerificationFailure::FreshInstanceUnSat { curve, source } => write!(
+        f,
+        "the last instance of the {} circuit is not satisfied: {}",
+        curve, source
+      ),
+      VerificationFailure::Folding { curve, source } => write!(
+        f,
+        "the instances of the {} circuit cannot be folded: {}",
+        curve, source
+      ),
+      VerificationFailure::SNARK { curve, source } => write!(
+        f,
+        "the SNARK of the {} circuit does not verify: {}",
+        curve, source
+      ),
+    }
+  }
+}
+
+/// The check of a sum-check proof that fails
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SumcheckFailure {
+  /// the proof does not have one polynomial per round
+  NumRounds,
+  /// the polynomial of a round does not have the expected degree
+  Degree {
+    /// the index of the round, starting from 0
+    round: usize,
+  },
+  /// the evaluations of the polynomial of a round at 0 and 1 do not sum to the claim of the
+  /// previous round
+  RoundSum {
+    /// the index of the round, starting from 0
+    round: usize,
+  },
+  /// the claim of the last round does not match the evaluation that the verifier computes
+  FinalClaim,
+}
+
+impl Display for SumcheckFailure {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      SumcheckFailure::NumRounds => write!(f, "wrong number of rounds"),
+      SumcheckFailure::Degree { round } => write!(f, "wrong degree in round {}", round),
+      SumcheckFailure::RoundSum { round } => write!(f, "wrong sum in round {}", round),
+      SumcheckFailure::FinalClaim => write!(f, "wrong final claim"),
+    }
+  }
+}
+
+/// The check of an inner product argument that fails
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum IPAFailure {
+  /// the proof does not have as many left as right commitments, or has too many
+  NumRounds,
+  /// a challenge of the argument is zero, so it has no inverse
+  ZeroChallenge,
+  /// the commitments of the proof do not open to the claimed inner product
+  Commitments,
+  /// the public IO of the circuits of a deferred check are not consistent with each other
+  DeferredIO,
+  /// the generator that a circuit folds is not the inner product of the generators with the
+  /// tensor of the challenges
+  FoldedGenerator,
+}
+
+impl Display for IPAFailure {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      IPAFailure::NumRounds => write!(f, "wrong number of rounds"),
+      IPAFailure::ZeroChallenge => write!(f, "a challenge is zero"),
+      IPAFailure::Commitments => write!(f, "the commitments do not open to the claim"),
+      IPAFailure::DeferredIO => write!(f, "the public IO of the deferred check differ"),
+      IPAFailure::FoldedGenerator => write!(f, "wrong folded generator"),
+    }
+  }
+}
+
+/// An error of a dependency, shared so that `NovaError` remains `Clone`.
+/// It dereferences to the typed error, and two errors are equal if their messages are.
+#[derive(Debug)]
+pub struct SharedError<E>(Arc<E>);
+
+impl<E> Clone for SharedError<E> {
+  fn clone(&self) -> Self {
+    SharedError(self.0.clone())
+  }
+}
+
+impl<E> Deref for SharedError<E> {
+  type Target = E;
+
+  fn deref(&self) -> &E {
+    &self.0
+  }
+}
+
+impl<E: Display> Display for SharedError<E> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    self.0.fmt(f)
+  }
+}
+
+impl<E: Display> PartialEq for SharedError<E> {
+  fn eq(&self, other: &Self) -> bool {
+    Arc::ptr_eq(&self.0, &other.0) || self.0.to_string() == other.0.to_string()
+  }
+}
+
+impl<E: Display> Eq for SharedError<E> {}
 
 /// Errors returned by Nova
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
 pub enum NovaError {
-  /// returned if the supplied row or col in (row,col,val) tuple is out of range
-  InvalidIndex,
+  /// returned if the supplied row or col in (row,col,val) tuple is out of range,
+  /// with the index of the matrix (0 for A, 1 for B and 2 for C in R1CS)
+  InvalidIndex {
+    /// the index of the matrix
+    matrix: usize,
+    /// the row of the entry
+    row: usize,
+    /// the column of the entry
+    col: usize,
+  },
+  /// returned if a wire of a Circom circuit is out of range
+  InvalidWire {
+    /// the index of the wire
+    wire: usize,
+    /// the number of wires of the circuit
+    num_wires: usize,
+  },
   /// returned if the supplied input is not even-sized
-  OddInputLength,
+  OddInputLength {
+    /// the number of public inputs and outputs
+    num_io: usize,
+  },
   /// returned if the supplied input is not of the right length
-  InvalidInputLength,
+  InvalidInputLength {
+    /// the expected length
+    expected: usize,
+    /// the supplied length
+    actual: usize,
+  },
   /// returned if the supplied witness is not of the right length
-  InvalidWitnessLength,
+  InvalidWitnessLength {
+    /// the expected length
+    expected: usize,
+    /// the supplied length
+    actual: usize,
+  },
+  /// returned if the dimensions or the multisets of a shape are invalid
+  InvalidShape {
+    /// what is wrong with the shape
+    reason: &'static str,
+  },
   /// returned if the supplied witness is not a satisfying witness to a given shape and instance
-  UnSat,
+  UnSat {
+    /// the first unsatisfied constraint, or `None` if the failed check is not about a single
+    /// constraint, e.g., the commitments in the instance do not open to the witness
+    constraint: Option<usize>,
+  },
   /// returned when the supplied compressed commitment cannot be decompressed
   DecompressionError,
   /// returned if proof verification fails
-  ProofVerifyError,
+  ProofVerifyError {
+    /// the reason why the proof is rejected
+    reason: VerificationFailure,
+  },
   /// returned if the provided number of steps is zero
   InvalidNumSteps,
   /// returned when an invalid inner product argument is provided
-  InvalidIPA,
+  InvalidIPA {
+    /// the check that fails
+    check: IPAFailure,
+  },
   /// returned when an invalid sum-check proof is provided
-  InvalidSumcheckProof,
+  InvalidSumcheckProof {
+    /// the check that fails
+    check: SumcheckFailure,
+  },
+  /// returned if a value is not in the lookup table, or if an instance does not carry
+  /// a valid lookup argument for its shape
+  InvalidLookup,
+  /// returned if a Circom `.r1cs` or `.wtns` file is malformed or defined over another field
+  InvalidCircomFile {
+    /// what is wrong with the file
+    reason: String,
+  },
+  /// returned if a security configuration is out of the supported bounds
+  InvalidSecurityConfig {
+    /// the bound that is not met
+    reason: &'static str,
+  },
+  /// returned if a circuit cannot be synthesized
+  Synthesis {
+    /// the error of the constraint system
+    source: SharedError<SynthesisError>,
+  },
+  /// returned if a key is used with public parameters other than those for which it is produced
+  KeyMismatch,
+  /// returned if bytes cannot be decoded into a proof or another object
+  Decoding {
+    /// the error of the decoder
+    source: SharedError<bincode::ErrorKind>,
+  },
+  /// returned if an error occurs in a step of an incremental computation
+  Step {
+    /// the circuit in which the error occurs
+    curve: Curve,
+    /// the index of the step, starting from 0
+    step: usize,
+    /// the error
+    source: Box<NovaError>,
+  },
+}
+
+impl NovaError {
+  /// Returns an `InvalidInputLength` error for the first of the `(expected, actual)` lengths
+  /// that differ, if any
+  pub(crate) fn check_lengths(lengths: &[(usize, usize)]) -> Result<(), NovaError> {
+    match lengths.iter().find(|(expected, actual)| expected != actual) {
+      Some(&(expected, actual)) => Err(NovaError::InvalidInputLength { expected, actual }),
+      None => Ok(()),
+    }
+  }
+
+  /// Returns a `ProofVerifyError` for `reason`
+  pub(crate) fn verify_failure(reason: VerificationFailure) -> Self {
+    NovaError::ProofVerifyError { reason }
+  }
+
+  /// Wraps the error with the circuit and the step of an incremental computation
+  /// in which it occurs
+  pub fn in_step(self, curve: Curve, step: usize) -> Self {
+    NovaError::Step {
+      curve,
+      step,
+      source: Box::new(self),
+    }
+  }
+}
+
+impl Display for NovaError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      NovaError::InvalidIndex { matrix, row, col } => write!(
+        f,
+        "entry ({}, {}) of matrix {} is out of range",
+        row, col, matrix
+      ),
+      NovaError::InvalidWire { wire, num_wires } => write!(
+        f,
+        "wire {} is out of range for a circuit with {} wires",
+        wire, num_wires
+      ),
+      NovaError::InvalidShape { reason } => write!(f, "invalid shape: {}", reason),
+      NovaError::OddInputLength { num_io } => {
+        write!(f, "the number of public IO {} is odd", num_io)
+      }
+      NovaError::InvalidInputLength { expected, actual } => write!(
+        f,
+        "invalid input length: expected {}, got {}",
+        expected, actual
+      ),
+      NovaError::InvalidWitnessLength { expected, actual } => write!(
+        f,
+        "invalid witness length: expected {}, got {}",
+        expected, actual
+      ),
+      NovaError::UnSat {
+        constraint: Some(constraint),
+      } => write!(f, "constraint {} is not satisfied", constraint),
+      NovaError::UnSat { constraint: None } => {
+        write!(f, "the witness does not satisfy the instance")
+      }
+      NovaError::DecompressionError => write!(f, "cannot decompress a commitment"),
+      NovaError::ProofVerifyError { reason } => {
+        write!(f, "proof verification failed: {}", reason)
+      }
+      NovaError::InvalidNumSteps => write!(f, "the number of steps is zero"),
+      NovaError::InvalidIPA { check } => write!(f, "invalid inner product argument: {}", check),
+      NovaError::InvalidSumcheckProof { check } => write!(f, "invalid sum-check proof: {}", check),
+      NovaError::InvalidLookup => write!(f, "invalid lookup argument"),
+      NovaError::InvalidCircomFile { reason } => write!(f, "invalid Circom file: {}", reason),
+      NovaError::InvalidSecurityConfig { reason } => {
+        write!(f, "invalid security configuration: {}", reason)
+      }
+      NovaError::Synthesis { source } => write!(f, "synthesis error: {}", source),
+      NovaError::KeyMismatch => write!(f, "the key is produced for other public parameters"),
+      NovaError::Decoding { source } => write!(f, "decoding error: {}", source),
+      NovaError::Step {
+        curve,
+        step,
+        source,
+      } => write!(f, "{} circuit, step {}: {}", curve, step, source),
+    }
+  }
+}
+
+impl std::error::Error for NovaError {
+  fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+    match self {
+      NovaError::Step { source, .. } => Some(source.as_ref()),
+      NovaError::ProofVerifyError { reason } => reason
+        .source()
+        .map(|source| source as &(dyn std::error::Error + 'static)),
+      NovaError::Synthesis { source } => Some(&**source),
+      NovaError::Decoding { source } => Some(&**source),
+      _ => None,
+    }
+  }
+}
+
+impl From<SynthesisError> for NovaError {
+  fn from(e: SynthesisError) -> Self {
+    NovaError::Synthesis {
+      source: SharedError(Arc::new(e)),
+    }
+  }
+}
+
+impl From<bincode::Error> for NovaError {
+  fn from(e: bincode::Error) -> Self {
+    NovaError::Decoding {
+      source: SharedError(Arc::from(e)),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::error::Error;
+
+  #[test]
+  fn test_error_context() {
+    let e = NovaError::from(SynthesisError::Unsatisfiable).in_step(Curve::Secondary, 3);
+    assert!(matches!(
+      &e,
+      NovaError::Step {
+        curve: Curve::Secondary,
+        step: 3,
+        source,
+      } if matches!(source.as_ref(), NovaError::Synthesis { .. })
+    ));
+    assert!(e
+      .to_string()
+      .starts_with("secondary circuit, step 3: synthesis error"));
+    assert!(e.source().is_some());
+
+    let e = NovaError::InvalidInputLength {
+      expected: 2,
+      actual: 3,
+    };
+    assert_eq!(e.to_string(), "invalid input length: expected 2, got 3");
+    assert_eq!(NovaError::check_lengths(&[(1, 1), (2, 3), (4, 5)]), Err(e));
+    assert!(NovaError::check_lengths(&[(1, 1)]).is_ok());
+
+    let sumcheck = NovaError::InvalidSumcheckProof {
+      check: SumcheckFailure::RoundSum { round: 4 },
+    };
+    let e = NovaError::verify_failure(VerificationFailure::SNARK {
+      curve: Curve::Primary,
+      source: Box::new(sumcheck.clone()),
+    });
+    assert!(e
+      .to_string()
+      .starts_with("proof verification failed: the SNARK of the primary circuit"));
+    assert!(e.to_string().ends_with("wrong sum in round 4"));
+    assert_eq!(e.source().unwrap().to_string(), sumcheck.to_string());
+  }
+
+  #[test]
+  fn test_typed_sources() {
+    // the error of the constraint system is kept, and survives a clone
+    let e = NovaError::from(SynthesisError::DivisionByZero);
+    match e.clone() {
+      NovaError::Synthesis { source } => {
+        assert!(matches!(*source, SynthesisError::DivisionByZero))
+      }
+      _ => panic!("expected a synthesis error"),
+    }
+    assert_eq!(e, NovaError::from(SynthesisError::DivisionByZero));
+    assert_ne!(e, NovaError::from(SynthesisError::Unsatisfiable));
+    assert!(e
+      .source()
+      .unwrap()
+      .downcast_ref::<SynthesisError>()
+      .is_some());
+
+    let e = NovaError::from(bincode::deserialize::<u64>(&[0u8; 4]).unwrap_err());
+    assert!(matches!(
+      &e,
+      NovaError::Decoding { source } if matches!(**source, bincode::ErrorKind::Io(_))
+    ));
+    assert!(e
+      .source()
+      .unwrap()
+      .downcast_ref::<bincode::ErrorKind>()
+      .is_some());
+  }
 }
@@ -0,0 +1,281 @@
+//! This module implements plain folding of many R1CS instances of one circuit, without
+//! recursion or a second curve. `FoldingAccumulator` folds the instances of a bellperson
+//! circuit with `NIFS` and proves the final running instance with a zkSNARK for relaxed R1CS,
+//! and `FoldingProof` checks that proof against the list of folded instances.
+//!
+//! Every fold is bound to the digest of `FoldingParams`, which covers the shape, the generators
+//! and the constants of the random oracle, and the verifier recomputes the running instance
+//! from the instances it is given, so a proof covers exactly that list in that order. Without
+//! recursion, the proof size and the verifier time are linear in the number of instances, and
+//! a witness that does not satisfy its instance is only detected when the proof is generated.
+//! Shapes with a lookup argument are not supported, the parameters take no domain-separation
+//! tag or security configuration, and `FoldingProof` cannot be serialized.
+use crate::{
+  bellperson::{
+    r1cs::{NovaShape, NovaWitness},
+    shape_cs::ShapeCS,
+    solver::SatisfyingAssignment,
+  },
+  errors::NovaError,
+  nifs::NIFS,
+  r1cs::{
+    digest_to_scalar, R1CSGens, R1CSInstance, R1CSShape, R1CSWitness, RelaxedR1CSInstance,
+    RelaxedR1CSWitness,
+  },
+  snark::RelaxedR1CSSNARKTrait,
+  traits::{Group, HashFuncConstantsTrait, HashFuncTrait},
+};
+use bellperson::{Circuit, ConstraintSystem};
+use ff::PrimeField;
+
+type ROConstants<G> =
+  <<G as Group>::HashFunc as HashFuncTrait<<G as Group>::Base, <G as Group>::Scalar>>::Constants;
+
+/// The public parameters to fold the instances of a circuit
+pub struct FoldingParams<G: Group> {
+  ro_consts: ROConstants<G>,
+  gens: R1CSGens<G>,
+  shape: R1CSShape<G>,
+  shape_padded: R1CSShape<G>,
+  digest: G::Scalar,
+}
+
+impl<G: Group> FoldingParams<G> {
+  /// Creates the parameters to fold the instances of `circuit`, whose values are not used
+  pub fn setup<C: Circuit<G::Scalar>>(circuit: C) -> Result<Self, NovaError> {
+    let mut cs: ShapeCS<G> = ShapeCS::new();
+    circuit.synthesize(&mut cs)?;
+    let shape = cs.r1cs_shape();
+    let shape_padded = shape.pad();
+    let ro_consts = ROConstants::<G>::new();
+    let gens = cs.r1cs_gens();
+
+    // bind the shape, the generators and the constants of the random oracle
+    let digest = digest_to_scalar(
+      &[
+        shape.get_digest().to_repr().as_ref(),
+        &gens.to_bytes(),
+        &ro_consts.to_bytes(),
+      ]
+      .concat(),
+    );
+
+    Ok(Self {
+      ro_consts,
+      gens,
+      shape,
+      shape_padded,
+      digest,
+    })
+  }
+
+  /// Returns the digest of the parameters, to which proofs are bound
+  pub fn digest(&self) -> G::Scalar {
+    self.digest
+  }
+
+  /// Returns the shape of the circuit
+  pub fn shape(&self) -> &R1CSShape<G> {
+    &self.shape
+  }
+
+  /// Returns the generators used to commit to witnesses
+  pub fn gens(&self) -> &R1CSGens<G> {
+    &self.gens
+  }
+
+  /// Synthesizes `circuit` and returns an instance and a witness of the shape of the circuit
+  pub fn instance_and_witness<C: Circuit<G::Scalar>>(
+    &self,
+    circuit: C,
+  ) -> Result<(R1CSInstance<G>, R1CSWitness<G>), NovaError> {
+    let mut cs: SatisfyingAssignment<G> = SatisfyingAssignment::new();
+    circuit.synthesize(&mut cs)?;
+    cs.r1cs_instance_and_witness_with_key(&self.shape, &self.gens, &self.ro_consts, &self.digest)
+  }
+}
+
+/// A running relaxed R1CS instance and witness that accumulates R1CS instances of one shape
+pub struct FoldingAccumulator<G: Group> {
+  U: RelaxedR1CSInstance<G>,
+  W: RelaxedR1CSWitness<G>,
+  nifs: Vec<NIFS<G>>,
+  instances: Vec<R1CSInstance<G>>,
+}
+
+impl<G: Group> FoldingAccumulator<G> {
+  /// Creates an accumulator that holds no instance
+  pub fn new(pp: &FoldingParams<G>) -> Self {
+    Self {
+      U: RelaxedR1CSInstance::default(&pp.gens, &pp.shape),
+      W: RelaxedR1CSWitness::default(&pp.shape),
+      nifs: Vec::new(),
+      instances: Vec::new(),
+    }
+  }
+
+  /// Folds an instance and its witness into the accumulator. A witness that does not satisfy
+  /// its instance is not detected here, but makes the proof of the accumulator fail.
+  pub fn fold(
+    &mut self,
+    pp: &FoldingParams<G>,
+    U: R1CSInstance<G>,
+    W: &R1CSWitness<G>,
+  ) -> Result<(), NovaError> {
+    let (nifs, (U_folded, W_folded)) = NIFS::prove(
+      &pp.gens,
+      &pp.ro_consts,
+      &pp.digest,
+      &pp.shape,
+      &self.U,
+      &self.W,
+      &U,
+      W,
+    )?;
+    self.U = U_folded;
+    self.W = W_folded;
+    self.nifs.push(nifs);
+    self.instances.push(U);
+    Ok(())
+  }
+
+  /// Returns the instances folded so far, in order
+  pub fn instances(&self) -> &[R1CSInstance<G>] {
+    &self.instances
+  }
+
+  /// Proves the knowledge of a witness that satisfies the running instance
+  pub fn prove<S: RelaxedR1CSSNARKTrait<G>>(
+    &self,
+    pp: &FoldingParams<G>,
+  ) -> Result<FoldingProof<G, S>, NovaError> {
+    if self.instances.is_empty() {
+      return Err(NovaError::InvalidNumSteps);
+    }
+
+    let pk = S::prover_key(&pp.gens, &pp.shape_padded, &pp.digest);
+    let snark = S::prove(&pk, &self.U, &self.W.pad(&pp.shape_padded))?;
+
+    Ok(FoldingProof {
+      nifs: self.nifs.clone(),
+      snark,
+    })
+  }
+}
+
+/// A proof that every instance in a list of R1CS instances of one shape is satisfiable
+pub struct FoldingProof<G: Group, S: RelaxedR1CSSNARKTrait<G>> {
+  nifs: Vec<NIFS<G>>,
+  snark: S,
+}
+
+impl<G: Group, S: RelaxedR1CSSNARKTrait<G>> FoldingProof<G, S> {
+  /// Verifies the proof against the instances that were folded, in the order of folding
+  pub fn verify(
+    &self,
+    pp: &FoldingParams<G>,
+    instances: &[R1CSInstance<G>],
+  ) -> Result<(), NovaError> {
+    if instances.is_empty() {
+      return Err(NovaError::InvalidNumSteps);
+    }
+
+    NovaError::check_lengths(&[(self.nifs.len(), instances.len())])?;
+    if let Some(U) = instances.iter().find(|U| U.X.len() != pp.shape.num_io) {
+      return Err(NovaError::InvalidInputLength {
+        expected: pp.shape.num_io,
+        actual: U.X.len(),
+      });
+    }
+
+    // fold the instances from the default running instance, as the prover did
+    let U = self.nifs.iter().zip(instances).try_fold(
+      RelaxedR1CSInstance::default(&pp.gens, &pp.shape),
+      |U, (nifs, u)| nifs.verify(&pp.ro_consts, &pp.digest, &pp.shape, &U, u),
+    )?;
+
+    let vk = S::verifier_key(&pp.gens, &pp.shape_padded, &pp.digest);
+    self.snark.verify(&vk, &U)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::spartan_with_ipa_pc::RelaxedR1CSSNARK;
+  use bellperson::{gadgets::num::AllocatedNum, SynthesisError};
+  use ff::PrimeField;
+
+  type G = pasta_curves::pallas::Point;
+  type S = RelaxedR1CSSNARK<G>;
+  type F = <G as Group>::Scalar;
+
+  // proves the knowledge of `x` such that `x^3 + x + 5 = y` for a public `y`
+  struct CubicCircuit<F: PrimeField> {
+    x: Option<F>,
+  }
+
+  impl<F: PrimeField> Circuit<F> for CubicCircuit<F> {
+    fn synthesize<CS: ConstraintSystem<F>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+      let x = AllocatedNum::alloc(cs.namespace(|| "x"), || {
+        self.x.ok_or(SynthesisError::AssignmentMissing)
+      })?;
+      let x_sq = x.square(cs.namespace(|| "x_sq"))?;
+      let x_cu = x_sq.mul(cs.namespace(|| "x_cu"), &x)?;
+      let y = AllocatedNum::alloc(cs.namespace(|| "y"), || {
+        Ok(x_cu.get_value().unwrap() + x.get_value().unwrap() + F::from(5u64))
+      })?;
+      cs.enforce(
+        || "y = x^3 + x + 5",
+        |lc| lc + x_cu.get_variable() + x.get_variable() + (F::from(5u64), CS::one()),
+        |lc| lc + CS::one(),
+        |lc| lc + y.get_variable(),
+      );
+      y.inputize(cs.namespace(|| "y is output"))?;
+      // the number of public IO must be even
+      x_cu.inputize(cs.namespace(|| "x^3 is output"))
+    }
+  }
+
+  #[test]
+  fn test_folding_accumulator() {
+    let pp = FoldingParams::<G>::setup(CubicCircuit { x: None }).unwrap();
+
+    let mut acc = FoldingAccumulator::new(&pp);
+    assert_eq!(acc.prove::<S>(&pp).err(), Some(NovaError::InvalidNumSteps));
+    for x in 1..5u64 {
+      let (U, W) = pp
+        .instance_and_witness(CubicCircuit {
+          x: Some(F::from(x)),
+        })
+        .unwrap();
+      acc.fold(&pp, U, &W).unwrap();
+    }
+
+    let proof = acc.prove::<S>(&pp).unwrap();
+    let instances = acc.instances().to_vec();
+    assert!(proof.verify(&pp, &instances).is_ok());
+
+    // the proof does not verify against other instances
+    assert!(proof.verify(&pp, &instances[1..]).is_err());
+    let mut reordered = instances.clone();
+    reordered.swap(0, 1);
+    assert!(proof.verify(&pp, &reordered).is_err());
+
+    // a witness that does not satisfy its instance makes the proof fail
+    let mut acc = FoldingAccumulator::new(&pp);
+    let (U, _) = pp
+      .instance_and_witness(CubicCircuit {
+        x: Some(F::from(2)),
+      })
+      .unwrap();
+    let (_, W) = pp
+      .instance_and_witness(CubicCircuit {
+        x: Some(F::from(3)),
+      })
+      .unwrap();
+    acc.fold(&pp, U, &W).unwrap();
+    let res = acc.prove::<S>(&pp);
+    assert!(res.is_err() || res.unwrap().verify(&pp, acc.instances()).is_err());
+  }
+}
@@ -1,19 +1,25 @@
 //! This module implements various elliptic curve gadgets
 #![allow(non_snake_case)]
+use crate::constants::{BN_LIMB_WIDTH, BN_N_LIMBS};
 use crate::gadgets::utils::{
-  alloc_num_equals, alloc_one, alloc_zero, conditionally_select, conditionally_select2,
-  select_num_or_one, select_num_or_zero, select_num_or_zero2, select_one_or_diff2,
-  select_one_or_num2, select_zero_or_num2,
+  alloc_bignat_constant, alloc_num_equals, alloc_one, alloc_zero, bits_to_bignat,
+  conditionally_select, conditionally_select2, conditionally_select_bignat, select_num_or_one,
+  select_num_or_zero, select_num_or_zero2, select_one_or_diff2, select_one_or_num2,
+  select_zero_or_num2,
 };
+use crate::traits::Group;
 use bellperson::{
   gadgets::{
     boolean::{AllocatedBit, Boolean},
     num::AllocatedNum,
     Assignment,
   },
-  ConstraintSystem, SynthesisError,
+  ConstraintSystem, LinearCombination, SynthesisError,
 };
+use bellperson_nonnative::{mp::bignat::BigNat, util::convert::f_to_nat};
 use ff::PrimeField;
+use num_bigint::{BigInt, Sign};
+use num_traits::{One, Zero};
 
 /// AllocatedPoint provides an elliptic curve abstraction inside a circuit.
 #[derive(Clone)]
@@ -43,7 +49,7 @@ where
       Ok(coords.map_or(Fp::zero(), |c| c.1))
     })?;
     let is_infinity = AllocatedNum::alloc(cs.namespace(|| "is_infinity"), || {
-      Ok(if coords.map_or(true, |c| c.2) {
+      Ok(if !matches!(coords, Some((_, _, false))) {
         Fp::one()
       } else {
         Fp::zero()
@@ -79,7 +85,7 @@ where
     (&self.x, &self.y, &self.is_infinity)
   }
 
-  // Allocate a random point. Only used for testing
+  /// Allocate a random point. Only used for testing
   #[cfg(test)]
   pub fn random_vartime<CS: ConstraintSystem<Fp>>(mut cs: CS) -> Result<Self, SynthesisError> {
     loop {
@@ -98,8 +104,7 @@ where
     }
   }
 
-  /// Make the point io
-  #[cfg(test)]
+  /// Makes the coordinates of the point public inputs of the circuit
   pub fn inputize<CS: ConstraintSystem<Fp>>(&self, mut cs: CS) -> Result<(), SynthesisError> {
     let _ = self.x.inputize(cs.namespace(|| "Input point.x"));
     let _ = self.y.inputize(cs.namespace(|| "Input point.y"));
@@ -109,6 +114,56 @@ where
     Ok(())
   }
 
+  /// Enforces that the point satisfies y^2 = x^3 + a * x + b, unless it is the point at infinity
+  pub fn check_on_curve<CS: ConstraintSystem<Fp>>(
+    &self,
+    mut cs: CS,
+    a: Fp,
+    b: Fp,
+  ) -> Result<(), SynthesisError> {
+    let x_sq = self.x.square(cs.namespace(|| "x_sq"))?;
+    let x_cu = x_sq.mul(cs.namespace(|| "x_cu"), &self.x)?;
+    let y_sq = self.y.square(cs.namespace(|| "y_sq"))?;
+    cs.enforce(
+      || "(y^2 - x^3 - a * x - b) * (1 - is_infinity) = 0",
+      |lc| {
+        lc + y_sq.get_variable() - x_cu.get_variable() - (a, self.x.get_variable()) - (b, CS::one())
+      },
+      |lc| lc + CS::one() - self.is_infinity.get_variable(),
+      |lc| lc,
+    );
+    Ok(())
+  }
+
+  /// Enforces that the point is not the point at infinity
+  pub fn check_not_infinity<CS: ConstraintSystem<Fp>>(&self, mut cs: CS) {
+    cs.enforce(
+      || "is_infinity = 0",
+      |lc| lc + self.is_infinity.get_variable(),
+      |lc| lc + CS::one(),
+      |lc| lc,
+    );
+  }
+
+  /// Enforces that the two points are equal
+  pub fn enforce_equal<CS: ConstraintSystem<Fp>>(&self, mut cs: CS, other: &Self) {
+    for (i, (a, b)) in [
+      (&self.x, &other.x),
+      (&self.y, &other.y),
+      (&self.is_infinity, &other.is_infinity),
+    ]
+    .into_iter()
+    .enumerate()
+    {
+      cs.enforce(
+        || format!("coordinate {}", i),
+        |lc| lc + a.get_variable() - b.get_variable(),
+        |lc| lc + CS::one(),
+        |lc| lc,
+      );
+    }
+  }
+
   /// Add two points (may be equal)
   pub fn add<CS: ConstraintSystem<Fp>>(
     &self,
@@ -492,6 +547,333 @@ where
     Ok(res)
   }
 
+  /// A gadget for scalar multiplication that processes `window` bits of the scalar at a time.
+  /// It precomputes the multiples `0 * self, ..., (2^window - 1) * self` and selects one of them
+  /// per window with a tree of conditional selections, so each window costs a single addition.
+  pub fn scalar_mul_windowed<CS: ConstraintSystem<Fp>>(
+    &self,
+    mut cs: CS,
+    scalar: Vec<AllocatedBit>,
+    window: usize,
+  ) -> Result<Self, SynthesisError> {
+    assert!(window > 0);
+
+    // table[j] = j * self
+    let mut table = vec![Self::default(cs.namespace(|| "table 0"))?, self.clone()];
+    for j in 2..(1 << window) {
+      let entry = table[j - 1].add(cs.namespace(|| format!("table {}", j)), self)?;
+      table.push(entry);
+    }
+
+    let mut res: Option<Self> = None;
+    for (i, chunk) in scalar.chunks(window).enumerate().rev() {
+      let selected = Self::select_from_table(
+        cs.namespace(|| format!("{}: select", i)),
+        &table[..1 << chunk.len()],
+        chunk,
+      )?;
+      res = Some(match res {
+        None => selected,
+        Some(mut acc) => {
+          for j in 0..chunk.len() {
+            acc = acc.double(cs.namespace(|| format!("{}: double {}", i, j)))?;
+          }
+          acc.add(cs.namespace(|| format!("{}: add", i)), &selected)?
+        }
+      });
+    }
+
+    match res {
+      Some(res) => Ok(res),
+      None => Self::default(cs.namespace(|| "res")),
+    }
+  }
+
+  /// A gadget for scalar multiplication by the scalar with the little-endian bits `scalar`,
+  /// given its decomposition `k1 + k2 * lambda` for the endomorphism of `G` (see `GLVScalar`).
+  /// The two halves are given as their signs and the little-endian bits of their absolute values,
+  /// and are processed together, so the cost is that of a scalar with the bits of the longer half
+  /// plus a non-native check that `k1 + k2 * lambda = scalar` modulo the order of `G`.
+  /// The halves of a scalar with at most half the bits of the order, such as a 128-bit NIFS
+  /// challenge, are no shorter than the scalar, so `scalar_mul_windowed` is cheaper for those.
+  #[allow(clippy::too_many_arguments)]
+  pub fn scalar_mul_glv<G, CS>(
+    &self,
+    mut cs: CS,
+    scalar: &[AllocatedBit],
+    k1_neg: &AllocatedBit,
+    k1: Vec<AllocatedBit>,
+    k2_neg: &AllocatedBit,
+    k2: Vec<AllocatedBit>,
+  ) -> Result<Self, SynthesisError>
+  where
+    G: Group<Base = Fp>,
+    CS: ConstraintSystem<Fp>,
+  {
+    check_glv_decomposition::<G, _>(
+      cs.namespace(|| "check decomposition"),
+      scalar,
+      k1_neg,
+      &k1,
+      k2_neg,
+      &k2,
+    )?;
+    self.scalar_mul_glv_unchecked(
+      cs.namespace(|| "scalar mul"),
+      G::get_endomorphism().0,
+      k1_neg,
+      k1,
+      k2_neg,
+      k2,
+    )
+  }
+
+  /// Multiplies by `k1 + k2 * lambda`, where `(x, y) -> (zeta * x, y)` is the endomorphism
+  /// that multiplies points by `lambda`, without relating the halves to any particular scalar
+  fn scalar_mul_glv_unchecked<CS: ConstraintSystem<Fp>>(
+    &self,
+    mut cs: CS,
+    zeta: Fp,
+    k1_neg: &AllocatedBit,
+    k1: Vec<AllocatedBit>,
+    k2_neg: &AllocatedBit,
+    k2: Vec<AllocatedBit>,
+  ) -> Result<Self, SynthesisError> {
+    let p1 = self.conditionally_negate(cs.namespace(|| "p1"), k1_neg)?;
+    let p2 = self
+      .endomorphism(cs.namespace(|| "endomorphism"), zeta)?
+      .conditionally_negate(cs.namespace(|| "p2"), k2_neg)?;
+
+    // table = [0, p1, p2, p1 + p2], indexed by the bits of k1 and k2
+    let table = vec![
+      Self::default(cs.namespace(|| "table 0"))?,
+      p1.clone(),
+      p2.clone(),
+      p1.add(cs.namespace(|| "p1 + p2"), &p2)?,
+    ];
+
+    // the shorter half is padded with zeros
+    let zero = AllocatedBit::alloc(cs.namespace(|| "zero"), Some(false))?;
+    cs.enforce(
+      || "zero is zero",
+      |lc| lc,
+      |lc| lc,
+      |lc| lc + zero.get_variable(),
+    );
+
+    let mut res = Self::default(cs.namespace(|| "res"))?;
+    for i in (0..std::cmp::max(k1.len(), k2.len())).rev() {
+      res = res.double(cs.namespace(|| format!("{}: double", i)))?;
+      let bit1 = k1.get(i).unwrap_or(&zero).clone();
+      let bit2 = k2.get(i).unwrap_or(&zero).clone();
+      let selected = Self::select_from_table(
+        cs.namespace(|| format!("{}: select", i)),
+        &table,
+        &[bit1, bit2],
+      )?;
+      res = res.add(cs.namespace(|| format!("{}: add", i)), &selected)?;
+    }
+    Ok(res)
+  }
+
+  /// A gadget for multiplying the fixed point `base` by a scalar given as little-endian bits.
+  /// The multiples of `base` needed for each window of the scalar are computed outside the circuit,
+  /// so a window only costs the selection of a constant point and a single addition.
+  pub fn scalar_mul_fixed_base<G, CS>(
+    mut cs: CS,
+    base: &G,
+    scalar: Vec<AllocatedBit>,
+  ) -> Result<Self, SynthesisError>
+  where
+    G: Group<Base = Fp>,
+    CS: ConstraintSystem<Fp>,
+  {
+    let mut res: Option<Self> = None;
+    // window_base = 2^(FIXED_BASE_WINDOW * i) * base
+    let mut window_base = *base;
+    for (i, chunk) in scalar.chunks(FIXED_BASE_WINDOW).enumerate() {
+      let table = (0..1u64 << chunk.len())
+        .map(|j| (window_base * G::Scalar::from(j)).to_coordinates())
+        .collect::<Vec<_>>();
+      let selected =
+        Self::select_from_constants(cs.namespace(|| format!("{}: select", i)), &table, chunk)?;
+      res = Some(match res {
+        None => selected,
+        Some(acc) => acc.add(cs.namespace(|| format!("{}: add", i)), &selected)?,
+      });
+      window_base *= G::Scalar::from(1 << FIXED_BASE_WINDOW);
+    }
+
+    match res {
+      Some(res) => Ok(res),
+      None => Self::default(cs.namespace(|| "res")),
+    }
+  }
+
+  /// Selects the constant point `table[j]`, where `j` is the number encoded by the little-endian `bits`.
+  /// Each coordinate is the multilinear extension of the table evaluated at the bits, which needs
+  /// one constraint per product of two or more bits and one constraint per coordinate.
+  fn select_from_constants<CS: ConstraintSystem<Fp>>(
+    mut cs: CS,
+    table: &[(Fp, Fp, bool)],
+    bits: &[AllocatedBit],
+  ) -> Result<Self, SynthesisError> {
+    assert_eq!(table.len(), 1 << bits.len());
+
+    // monomials[S] is the product of the bits in the set S
+    let mut monomials: Vec<(LinearCombination<Fp>, Option<Fp>)> =
+      vec![(LinearCombination::zero() + CS::one(), Some(Fp::one()))];
+    for (i, bit) in bits.iter().enumerate() {
+      let bit_value = bit
+        .get_value()
+        .map(|b| if b { Fp::one() } else { Fp::zero() });
+      for S in 0..(1 << i) {
+        if S == 0 {
+          monomials.push((LinearCombination::zero() + bit.get_variable(), bit_value));
+        } else {
+          let (lc, value) = monomials[S].clone();
+          let product = value.and_then(|v| bit_value.map(|b| v * b));
+          let num = AllocatedNum::alloc(
+            cs.namespace(|| format!("monomial {}", S + (1 << i))),
+            || product.ok_or(SynthesisError::AssignmentMissing),
+          )?;
+          cs.enforce(
+            || format!("check monomial {}", S + (1 << i)),
+            |_| lc,
+            |lc| lc + bit.get_variable(),
+            |lc| lc + num.get_variable(),
+          );
+          monomials.push((LinearCombination::zero() + num.get_variable(), product));
+        }
+      }
+    }
+
+    let mut coordinates = Vec::new();
+    for (c, name) in ["x", "y", "is_infinity"].iter().enumerate() {
+      let mut coeffs = table
+        .iter()
+        .map(|(x, y, is_infinity)| match c {
+          0 => *x,
+          1 => *y,
+          _ if *is_infinity => Fp::one(),
+          _ => Fp::zero(),
+        })
+        .collect::<Vec<Fp>>();
+      // turn the evaluations into the coefficients of the multilinear polynomial
+      for i in 0..bits.len() {
+        for S in 0..coeffs.len() {
+          if S & (1 << i) != 0 {
+            let lower = coeffs[S ^ (1 << i)];
+            coeffs[S] -= lower;
+          }
+        }
+      }
+
+      let value = coeffs
+        .iter()
+        .zip(monomials.iter())
+        .try_fold(Fp::zero(), |acc, (coeff, (_, value))| {
+          value.map(|v| acc + *coeff * v)
+        });
+      let num = AllocatedNum::alloc(cs.namespace(|| *name), || {
+        value.ok_or(SynthesisError::AssignmentMissing)
+      })?;
+      cs.enforce(
+        || format!("check {}", name),
+        |lc| {
+          coeffs
+            .iter()
+            .zip(monomials.iter())
+            .fold(lc, |acc, (coeff, (m, _))| acc + (*coeff, m))
+        },
+        |lc| lc + CS::one(),
+        |lc| lc + num.get_variable(),
+      );
+      coordinates.push(num);
+    }
+
+    Ok(Self {
+      is_infinity: coordinates.pop().unwrap(),
+      y: coordinates.pop().unwrap(),
+      x: coordinates.pop().unwrap(),
+    })
+  }
+
+  /// Selects `table[j]`, where `j` is the number encoded by the little-endian `bits`.
+  /// The table must hold `2^bits.len()` points.
+  fn select_from_table<CS: ConstraintSystem<Fp>>(
+    mut cs: CS,
+    table: &[Self],
+    bits: &[AllocatedBit],
+  ) -> Result<Self, SynthesisError> {
+    assert_eq!(table.len(), 1 << bits.len());
+    let mut level = table.to_vec();
+    for (i, bit) in bits.iter().enumerate() {
+      level = level
+        .chunks(2)
+        .enumerate()
+        .map(|(j, pair)| {
+          Self::conditionally_select(
+            cs.namespace(|| format!("level {}, pair {}", i, j)),
+            &pair[1],
+            &pair[0],
+            &Boolean::from(bit.clone()),
+          )
+        })
+        .collect::<Result<Vec<Self>, SynthesisError>>()?;
+    }
+    Ok(level[0].clone())
+  }
+
+  /// Returns `-self` if condition is set and `self` otherwise
+  fn conditionally_negate<CS: ConstraintSystem<Fp>>(
+    &self,
+    mut cs: CS,
+    condition: &AllocatedBit,
+  ) -> Result<Self, SynthesisError> {
+    let y = AllocatedNum::alloc(cs.namespace(|| "y"), || {
+      if *condition.get_value().get()? {
+        Ok(-*self.y.get_value().get()?)
+      } else {
+        Ok(*self.y.get_value().get()?)
+      }
+    })?;
+    // self.y - y = 2 * condition * self.y
+    cs.enforce(
+      || "check y",
+      |lc| lc + condition.get_variable() + condition.get_variable(),
+      |lc| lc + self.y.get_variable(),
+      |lc| lc + self.y.get_variable() - y.get_variable(),
+    );
+    Ok(Self {
+      x: self.x.clone(),
+      y,
+      is_infinity: self.is_infinity.clone(),
+    })
+  }
+
+  /// Applies the endomorphism `(x, y) -> (zeta * x, y)`
+  fn endomorphism<CS: ConstraintSystem<Fp>>(
+    &self,
+    mut cs: CS,
+    zeta: Fp,
+  ) -> Result<Self, SynthesisError> {
+    let x = AllocatedNum::alloc(cs.namespace(|| "x"), || {
+      Ok(*self.x.get_value().get()? * zeta)
+    })?;
+    cs.enforce(
+      || "check x",
+      |lc| lc + (zeta, self.x.get_variable()),
+      |lc| lc + CS::one(),
+      |lc| lc + x.get_variable(),
+    );
+    Ok(Self {
+      x,
+      y: self.y.clone(),
+      is_infinity: self.is_infinity.clone(),
+    })
+  }
+
   /// If condition outputs a otherwise outputs b
   pub fn conditionally_select<CS: ConstraintSystem<Fp>>(
     mut cs: CS,
@@ -533,6 +915,149 @@ where
   }
 }
 
+/// The number of bits of the scalar processed at a time by `AllocatedPoint::scalar_mul_fixed_base`
+const FIXED_BASE_WINDOW: usize = 4;
+
+/// A scalar `k` decomposed as `k = k1 + k2 * lambda` for the `lambda` of `Group::get_endomorphism`,
+/// where `k1` and `k2` have about half the bits of `k`. Each half is given as its sign and
+/// the little-endian bits of its absolute value, as expected by `AllocatedPoint::scalar_mul_glv`.
+#[derive(Clone, Debug)]
+pub struct GLVScalar {
+  /// Whether `k1` is negative
+  pub k1_neg: bool,
+  /// The bits of the absolute value of `k1`
+  pub k1: Vec<bool>,
+  /// Whether `k2` is negative
+  pub k2_neg: bool,
+  /// The bits of the absolute value of `k2`
+  pub k2: Vec<bool>,
+}
+
+impl GLVScalar {
+  /// The number of bits of each half for the group `G`
+  pub fn num_bits<G: Group>() -> usize {
+    (G::get_order().bits() as usize).div_ceil(2) + 1
+  }
+
+  /// Decomposes the scalar with Babai rounding against a short basis of the lattice
+  /// `{(a, b) : a + b * lambda = 0 mod n}`, found with the extended Euclidean algorithm
+  pub fn decompose<G: Group>(k: &G::Scalar) -> Self {
+    let n = G::get_order();
+    let lambda = f_to_nat(&G::get_endomorphism().1);
+
+    // run the extended Euclidean algorithm on (n, lambda), keeping r_i = s_i * n + t_i * lambda
+    let sqrt_n = n.sqrt();
+    let (mut r0, mut r1) = (n.clone(), lambda);
+    let (mut t0, mut t1) = (BigInt::zero(), BigInt::one());
+    while r1 >= sqrt_n {
+      let q = &r0 / &r1;
+      let r2 = &r0 - &q * &r1;
+      let t2 = &t0 - &q * &t1;
+      r0 = std::mem::replace(&mut r1, r2);
+      t0 = std::mem::replace(&mut t1, t2);
+    }
+    // r0 is the last remainder at least sqrt(n) and r1 the first one below it
+    let q = &r0 / &r1;
+    let (r2, t2) = (&r0 - &q * &r1, &t0 - &q * &t1);
+    let (a1, b1) = (r1, -t1);
+    let (a2, b2) = if &r0 * &r0 + &t0 * &t0 <= &r2 * &r2 + &t2 * &t2 {
+      (r0, -t0)
+    } else {
+      (r2, -t2)
+    };
+
+    // (c1, c2) = round((b2 * k, -b1 * k) / n)
+    let k = f_to_nat(k);
+    let c1 = div_round(&(&b2 * &k), &n);
+    let c2 = div_round(&(-&b1 * &k), &n);
+    let k1 = &k - &c1 * &a1 - &c2 * &a2;
+    let k2 = -&c1 * &b1 - &c2 * &b2;
+
+    let num_bits = Self::num_bits::<G>();
+    let to_bits = |v: &BigInt| -> Vec<bool> {
+      let v = v.magnitude();
+      assert!(v.bits() as usize <= num_bits);
+      (0..num_bits as u64).map(|i| v.bit(i)).collect()
+    };
+    Self {
+      k1_neg: k1.sign() == Sign::Minus,
+      k1: to_bits(&k1),
+      k2_neg: k2.sign() == Sign::Minus,
+      k2: to_bits(&k2),
+    }
+  }
+}
+
+/// Enforces `k1 + k2 * lambda = k` modulo the order of `G`, where `k` and the absolute values of
+/// `k1` and `k2` are given as little-endian bits. The negative terms are moved to the other side,
+/// so the check is that the sums of the terms on both sides are congruent modulo the order.
+fn check_glv_decomposition<G, CS>(
+  mut cs: CS,
+  k: &[AllocatedBit],
+  k1_neg: &AllocatedBit,
+  k1: &[AllocatedBit],
+  k2_neg: &AllocatedBit,
+  k2: &[AllocatedBit],
+) -> Result<(), SynthesisError>
+where
+  G: Group,
+  CS: ConstraintSystem<G::Base>,
+{
+  let alloc_constant = |cs: &mut CS, name: &str, v: &BigInt| {
+    alloc_bignat_constant(cs.namespace(|| name), v, BN_LIMB_WIDTH, BN_N_LIMBS)
+  };
+  let order = alloc_constant(&mut cs, "order", &G::get_order())?;
+  let lambda = alloc_constant(&mut cs, "lambda", &f_to_nat(&G::get_endomorphism().1))?;
+  let zero = alloc_constant(&mut cs, "zero", &BigInt::zero())?;
+
+  let k = bits_to_bignat(k, BN_LIMB_WIDTH, BN_N_LIMBS);
+  let k1 = bits_to_bignat(k1, BN_LIMB_WIDTH, BN_N_LIMBS);
+  let (_, k2_lambda) = bits_to_bignat(k2, BN_LIMB_WIDTH, BN_N_LIMBS).mult_mod(
+    cs.namespace(|| "k2 * lambda"),
+    &lambda,
+    &order,
+  )?;
+
+  // returns the term on the left side and the term on the right side, one of which is zero
+  let split = |cs: &mut CS, name: &str, term: &BigNat<G::Base>, neg: &AllocatedBit| {
+    let neg = Boolean::from(neg.clone());
+    Ok::<_, SynthesisError>((
+      conditionally_select_bignat(cs.namespace(|| format!("{} left", name)), &zero, term, &neg)?,
+      conditionally_select_bignat(
+        cs.namespace(|| format!("{} right", name)),
+        term,
+        &zero,
+        &neg,
+      )?,
+    ))
+  };
+  let (k1_left, k1_right) = split(&mut cs, "k1", &k1, k1_neg)?;
+  let (k2_left, k2_right) = split(&mut cs, "k2 * lambda", &k2_lambda, k2_neg)?;
+
+  // |k1| * [k1 >= 0] + |k2 * lambda| * [k2 >= 0] = k + |k1| * [k1 < 0] + |k2 * lambda| * [k2 < 0]
+  let left = k1_left
+    .add::<CS>(&k2_left)?
+    .red_mod(cs.namespace(|| "left mod order"), &order)?;
+  let right = k
+    .add::<CS>(&k1_right)?
+    .add::<CS>(&k2_right)?
+    .red_mod(cs.namespace(|| "right mod order"), &order)?;
+  left.equal(cs.namespace(|| "left = right"), &right)
+}
+
+/// Divides `a` by the positive `b` and rounds to the nearest integer
+fn div_round(a: &BigInt, b: &BigInt) -> BigInt {
+  // floor((2a + b) / 2b)
+  let num: BigInt = a * 2 + b;
+  let den: BigInt = b * 2;
+  let q = &num / &den;
+  if num.sign() == Sign::Minus && &q * &den != num {
+    q - 1
+  } else {
+    q
+  }
+}
+
 #[cfg(test)]
 use ff::PrimeFieldBits;
 #[cfg(test)]
@@ -540,6 +1065,7 @@ use rand::rngs::OsRng;
 #[cfg(test)]
 use std::marker::PhantomData;
 
+/// A native elliptic curve point used to test the gadgets in this module
 #[cfg(test)]
 #[derive(Debug, Clone)]
 pub struct Point<Fp, Fq>
@@ -559,6 +1085,7 @@ where
   Fp: PrimeField,
   Fq: PrimeField + PrimeFieldBits,
 {
+  /// Creates a new point from its coordinates
   pub fn new(x: Fp, y: Fp, is_infinity: bool) -> Self {
     Self {
       x,
@@ -568,6 +1095,7 @@ where
     }
   }
 
+  /// Samples a random point on the curve
   pub fn random_vartime() -> Self {
     loop {
       let x = Fp::random(&mut OsRng);
@@ -624,6 +1152,7 @@ where
     }
   }
 
+  /// Doubles the point
   pub fn double(&self) -> Self {
     if self.is_infinity {
       return Self {
@@ -648,6 +1177,7 @@ where
     }
   }
 
+  /// Multiplies the point by a scalar using double-and-add
   pub fn scalar_mul(&self, scalar: &Fq) -> Self {
     let mut res = Self {
       x: Fp::zero(),
@@ -724,7 +1254,19 @@ mod tests {
     assert_eq!(e_pasta, e_pasta_2);
   }
 
+  #[test]
+  fn test_default_curve_params() {
+    use crate::traits::curve_params_with_zero_a;
+    type G1 = pasta_curves::pallas::Point;
+    type G2 = pasta_curves::vesta::Point;
+
+    // the default of `Group::get_curve_params` agrees with the parameters of the pasta curves
+    assert_eq!(curve_params_with_zero_a::<G1>(), G1::get_curve_params());
+    assert_eq!(curve_params_with_zero_a::<G2>(), G2::get_curve_params());
+  }
+
   use crate::bellperson::{shape_cs::ShapeCS, solver::SatisfyingAssignment};
+  use crate::constants::NUM_CHALLENGE_BITS;
   use ff::{Field, PrimeFieldBits};
   use pasta_curves::{arithmetic::CurveAffine, group::Curve, EpAffine};
   use std::ops::Mul;
@@ -740,7 +1282,7 @@ mod tests {
     CS: ConstraintSystem<Fp>,
   {
     let a = AllocatedPoint::<Fp>::random_vartime(cs.namespace(|| "a")).unwrap();
-    let _ = a.inputize(cs.namespace(|| "inputize a")).unwrap();
+    a.inputize(cs.namespace(|| "inputize a")).unwrap();
     let s = Fq::random(&mut OsRng);
     // Allocate random bits and only keep 128 bits
     let bits: Vec<AllocatedBit> = s
@@ -751,7 +1293,7 @@ mod tests {
       .collect::<Result<Vec<AllocatedBit>, SynthesisError>>()
       .unwrap();
     let e = a.scalar_mul(cs.namespace(|| "Scalar Mul"), bits).unwrap();
-    let _ = e.inputize(cs.namespace(|| "inputize e")).unwrap();
+    e.inputize(cs.namespace(|| "inputize e")).unwrap();
     (a, e, s)
   }
 
@@ -785,6 +1327,236 @@ mod tests {
     assert!(shape.is_sat(&gens, &inst, &witness).is_ok());
   }
 
+  fn synthesize_smul_windowed<Fp, Fq, CS>(
+    mut cs: CS,
+    window: usize,
+  ) -> (AllocatedPoint<Fp>, AllocatedPoint<Fp>, Fq)
+  where
+    Fp: PrimeField,
+    Fq: PrimeField + PrimeFieldBits,
+    CS: ConstraintSystem<Fp>,
+  {
+    let a = AllocatedPoint::<Fp>::random_vartime(cs.namespace(|| "a")).unwrap();
+    a.inputize(cs.namespace(|| "inputize a")).unwrap();
+    let s = Fq::random(&mut OsRng);
+    let bits: Vec<AllocatedBit> = s
+      .to_le_bits()
+      .into_iter()
+      .enumerate()
+      .map(|(i, bit)| AllocatedBit::alloc(cs.namespace(|| format!("bit {}", i)), Some(bit)))
+      .collect::<Result<Vec<AllocatedBit>, SynthesisError>>()
+      .unwrap();
+    let e = a
+      .scalar_mul_windowed(cs.namespace(|| "Scalar Mul"), bits, window)
+      .unwrap();
+    e.inputize(cs.namespace(|| "inputize e")).unwrap();
+    (a, e, s)
+  }
+
+  fn synthesize_smul_glv<G, CS>(
+    mut cs: CS,
+    num_bits: usize,
+    tamper: bool,
+  ) -> (AllocatedPoint<G::Base>, AllocatedPoint<G::Base>, G::Scalar)
+  where
+    G: Group,
+    CS: ConstraintSystem<G::Base>,
+  {
+    let a = AllocatedPoint::<G::Base>::random_vartime(cs.namespace(|| "a")).unwrap();
+    a.inputize(cs.namespace(|| "inputize a")).unwrap();
+    // a random scalar with only num_bits bits
+    let bits = G::Scalar::random(&mut OsRng)
+      .to_le_bits()
+      .into_iter()
+      .take(num_bits)
+      .collect::<Vec<bool>>();
+    let s = bits.iter().rev().fold(G::Scalar::zero(), |acc, bit| {
+      acc.double()
+        + if *bit {
+          G::Scalar::one()
+        } else {
+          G::Scalar::zero()
+        }
+    });
+    let mut k = GLVScalar::decompose::<G>(&s);
+    if tamper {
+      k.k1[0] = !k.k1[0];
+    }
+    let alloc_bits = |cs: &mut CS, name: &str, bits: &[bool]| {
+      bits
+        .iter()
+        .enumerate()
+        .map(|(i, bit)| {
+          AllocatedBit::alloc(cs.namespace(|| format!("{} bit {}", name, i)), Some(*bit))
+        })
+        .collect::<Result<Vec<AllocatedBit>, SynthesisError>>()
+        .unwrap()
+    };
+    let s_bits = alloc_bits(&mut cs, "s", &bits);
+    let k1 = alloc_bits(&mut cs, "k1", &k.k1);
+    let k2 = alloc_bits(&mut cs, "k2", &k.k2);
+    let k1_neg = AllocatedBit::alloc(cs.namespace(|| "k1_neg"), Some(k.k1_neg)).unwrap();
+    let k2_neg = AllocatedBit::alloc(cs.namespace(|| "k2_neg"), Some(k.k2_neg)).unwrap();
+    let e = a
+      .scalar_mul_glv::<G, _>(
+        cs.namespace(|| "Scalar Mul"),
+        &s_bits,
+        &k1_neg,
+        k1,
+        &k2_neg,
+        k2,
+      )
+      .unwrap();
+    e.inputize(cs.namespace(|| "inputize e")).unwrap();
+    (a, e, s)
+  }
+
+  #[test]
+  fn test_ecc_circuit_ops_windowed() {
+    let mut cs: ShapeCS<G> = ShapeCS::new();
+    let _ = synthesize_smul::<Fp, Fq, _>(cs.namespace(|| "synthesize"));
+    let num_cons_baseline = cs.num_constraints();
+
+    for window in [2, 3, 4, 5] {
+      // First create the shape
+      let mut cs: ShapeCS<G> = ShapeCS::new();
+      let _ = synthesize_smul_windowed::<Fp, Fq, _>(cs.namespace(|| "synthesize"), window);
+      println!(
+        "Number of constraints with window {}: {} (baseline: {})",
+        window,
+        cs.num_constraints(),
+        num_cons_baseline
+      );
+      assert!(cs.num_constraints() < num_cons_baseline);
+      let shape = cs.r1cs_shape();
+      let gens = cs.r1cs_gens();
+
+      // Then the satisfying assignment
+      let mut cs: SatisfyingAssignment<G> = SatisfyingAssignment::new();
+      let (a, e, s) = synthesize_smul_windowed::<Fp, Fq, _>(cs.namespace(|| "synthesize"), window);
+      let (inst, witness) = cs.r1cs_instance_and_witness(&shape, &gens).unwrap();
+
+      let a_p: Point<Fp, Fq> = Point::new(
+        a.x.get_value().unwrap(),
+        a.y.get_value().unwrap(),
+        a.is_infinity.get_value().unwrap() == Fp::one(),
+      );
+      let e_p: Point<Fp, Fq> = Point::new(
+        e.x.get_value().unwrap(),
+        e.y.get_value().unwrap(),
+        e.is_infinity.get_value().unwrap() == Fp::one(),
+      );
+      let e_new = a_p.scalar_mul(&s);
+      assert!(e_p.x == e_new.x && e_p.y == e_new.y);
+      // Make sure that this is satisfiable
+      assert!(shape.is_sat(&gens, &inst, &witness).is_ok());
+    }
+  }
+
+  #[test]
+  fn test_ecc_circuit_ops_glv() {
+    // the points live on the curve whose base field is the scalar field of G
+    type G2 = pasta_curves::vesta::Point;
+
+    // the number of constraints of the double-and-add and of GLV over the same number of bits:
+    // GLV saves a fifth of the constraints for full scalars, but costs half again as much for
+    // 128-bit challenges such as those of the verifier circuit, whose halves are no shorter
+    for (num_bits, num_cons_baseline, num_cons_glv) in [
+      (Fq::NUM_BITS as usize, 12498, 9679),
+      (NUM_CHALLENGE_BITS, 6275, 9552),
+    ] {
+      let mut cs: ShapeCS<G> = ShapeCS::new();
+      let a = AllocatedPoint::<Fp>::random_vartime(cs.namespace(|| "a")).unwrap();
+      let bits = (0..num_bits)
+        .map(|i| AllocatedBit::alloc(cs.namespace(|| format!("bit {}", i)), Some(false)))
+        .collect::<Result<Vec<AllocatedBit>, SynthesisError>>()
+        .unwrap();
+      let _ = a.scalar_mul(cs.namespace(|| "Scalar Mul"), bits).unwrap();
+      assert_eq!(cs.num_constraints(), num_cons_baseline);
+
+      // First create the shape
+      let mut cs: ShapeCS<G> = ShapeCS::new();
+      let _ = synthesize_smul_glv::<G2, _>(cs.namespace(|| "synthesize"), num_bits, false);
+      assert_eq!(cs.num_constraints(), num_cons_glv);
+      let shape = cs.r1cs_shape();
+      let gens = cs.r1cs_gens();
+
+      // Then the satisfying assignment
+      let mut cs: SatisfyingAssignment<G> = SatisfyingAssignment::new();
+      let (a, e, s) = synthesize_smul_glv::<G2, _>(cs.namespace(|| "synthesize"), num_bits, false);
+      let (inst, witness) = cs.r1cs_instance_and_witness(&shape, &gens).unwrap();
+
+      let a_p: Point<Fp, Fq> = Point::new(
+        a.x.get_value().unwrap(),
+        a.y.get_value().unwrap(),
+        a.is_infinity.get_value().unwrap() == Fp::one(),
+      );
+      let e_p: Point<Fp, Fq> = Point::new(
+        e.x.get_value().unwrap(),
+        e.y.get_value().unwrap(),
+        e.is_infinity.get_value().unwrap() == Fp::one(),
+      );
+      let e_new = a_p.scalar_mul(&s);
+      assert!(e_p.x == e_new.x && e_p.y == e_new.y);
+      // Make sure that this is satisfiable
+      assert!(shape.is_sat(&gens, &inst, &witness).is_ok());
+
+      // a decomposition of another scalar is rejected
+      let mut cs: SatisfyingAssignment<G> = SatisfyingAssignment::new();
+      let _ = synthesize_smul_glv::<G2, _>(cs.namespace(|| "synthesize"), num_bits, true);
+      let (inst, witness) = cs.r1cs_instance_and_witness(&shape, &gens).unwrap();
+      assert!(shape.is_sat(&gens, &inst, &witness).is_err());
+    }
+  }
+
+  fn synthesize_smul_fixed_base<G2, CS>(
+    mut cs: CS,
+    base: &G2,
+    s: &G2::Scalar,
+  ) -> AllocatedPoint<G2::Base>
+  where
+    G2: Group,
+    CS: ConstraintSystem<G2::Base>,
+  {
+    let bits: Vec<AllocatedBit> = s
+      .to_le_bits()
+      .into_iter()
+      .take(G2::Scalar::NUM_BITS as usize)
+      .enumerate()
+      .map(|(i, bit)| AllocatedBit::alloc(cs.namespace(|| format!("bit {}", i)), Some(bit)))
+      .collect::<Result<Vec<AllocatedBit>, SynthesisError>>()
+      .unwrap();
+    AllocatedPoint::scalar_mul_fixed_base(cs.namespace(|| "Scalar Mul"), base, bits).unwrap()
+  }
+
+  #[test]
+  fn test_ecc_circuit_ops_fixed_base() {
+    // the points live on the curve whose base field is the scalar field of G
+    type G2 = pasta_curves::vesta::Point;
+    let base = <G2 as pasta_curves::group::Group>::generator();
+    let s = Fq::random(&mut OsRng);
+
+    // First create the shape
+    let mut cs: ShapeCS<G> = ShapeCS::new();
+    let _ = synthesize_smul_fixed_base(cs.namespace(|| "synthesize"), &base, &s);
+    println!("Number of constraints: {}", cs.num_constraints());
+    let shape = cs.r1cs_shape();
+    let gens = cs.r1cs_gens();
+
+    // Then the satisfying assignment
+    let mut cs: SatisfyingAssignment<G> = SatisfyingAssignment::new();
+    let e = synthesize_smul_fixed_base(cs.namespace(|| "synthesize"), &base, &s);
+    let (inst, witness) = cs.r1cs_instance_and_witness(&shape, &gens).unwrap();
+
+    let (x, y, is_infinity) = (base * s).to_coordinates();
+    assert_eq!(e.x.get_value().unwrap(), x);
+    assert_eq!(e.y.get_value().unwrap(), y);
+    assert!(!is_infinity && e.is_infinity.get_value().unwrap() == Fp::zero());
+    // Make sure that this is satisfiable
+    assert!(shape.is_sat(&gens, &inst, &witness).is_ok());
+  }
+
+  #[allow(clippy::extra_unused_type_parameters)]
   fn synthesize_add_equal<Fp, Fq, CS>(mut cs: CS) -> (AllocatedPoint<Fp>, AllocatedPoint<Fp>)
   where
     Fp: PrimeField,
@@ -792,9 +1564,9 @@ mod tests {
     CS: ConstraintSystem<Fp>,
   {
     let a = AllocatedPoint::<Fp>::random_vartime(cs.namespace(|| "a")).unwrap();
-    let _ = a.inputize(cs.namespace(|| "inputize a")).unwrap();
+    a.inputize(cs.namespace(|| "inputize a")).unwrap();
     let e = a.add(cs.namespace(|| "add a to a"), &a).unwrap();
-    let _ = e.inputize(cs.namespace(|| "inputize e")).unwrap();
+    e.inputize(cs.namespace(|| "inputize e")).unwrap();
     (a, e)
   }
 
@@ -827,6 +1599,7 @@ mod tests {
     assert!(shape.is_sat(&gens, &inst, &witness).is_ok());
   }
 
+  #[allow(clippy::extra_unused_type_parameters)]
   fn synthesize_add_negation<Fp, Fq, CS>(mut cs: CS) -> AllocatedPoint<Fp>
   where
     Fp: PrimeField,
@@ -834,11 +1607,11 @@ mod tests {
     CS: ConstraintSystem<Fp>,
   {
     let a = AllocatedPoint::<Fp>::random_vartime(cs.namespace(|| "a")).unwrap();
-    let _ = a.inputize(cs.namespace(|| "inputize a")).unwrap();
+    a.inputize(cs.namespace(|| "inputize a")).unwrap();
     let mut b = a.clone();
     b.y =
       AllocatedNum::alloc(cs.namespace(|| "allocate negation of a"), || Ok(Fp::zero())).unwrap();
-    let _ = b.inputize(cs.namespace(|| "inputize b")).unwrap();
+    b.inputize(cs.namespace(|| "inputize b")).unwrap();
     let e = a.add(cs.namespace(|| "add a to b"), &b).unwrap();
     e
   }
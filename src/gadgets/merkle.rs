@@ -0,0 +1,412 @@
+//! This module implements a sparse Merkle tree over field elements that uses Poseidon to hash
+//! two children into their parent, along with gadgets that prove membership of a leaf and
+//! updates of a leaf inside a circuit. The native tree and the gadgets share `MerkleConstants`.
+use crate::gadgets::utils::{conditionally_select, le_bits_to_num};
+use bellperson::{
+  gadgets::{
+    boolean::{AllocatedBit, Boolean},
+    num::AllocatedNum,
+  },
+  ConstraintSystem, SynthesisError,
+};
+use ff::{PrimeField, PrimeFieldBits};
+use generic_array::typenum::U2;
+use neptune::{
+  circuit::poseidon_hash,
+  poseidon::{Poseidon, PoseidonConstants},
+  Strength,
+};
+use std::collections::HashMap;
+
+/// The Poseidon constants used to hash two children into their parent
+#[derive(Clone)]
+pub struct MerkleConstants<F>
+where
+  F: PrimeField + PrimeFieldBits,
+{
+  constants: PoseidonConstants<F, U2>,
+}
+
+impl<F> MerkleConstants<F>
+where
+  F: PrimeField + PrimeFieldBits,
+{
+  /// Generates the Poseidon constants for arity two
+  #[allow(clippy::new_without_default)]
+  pub fn new() -> Self {
+    Self {
+      constants: PoseidonConstants::<F, U2>::new_with_strength(Strength::Strengthened),
+    }
+  }
+
+  /// Hashes two children into their parent outside the circuit
+  pub fn hash(&self, left: &F, right: &F) -> F {
+    Poseidon::<F, U2>::new_with_preimage(&[*left, *right], &self.constants).hash()
+  }
+
+  /// Hashes two children into their parent inside the circuit
+  pub fn hash_circuit<CS: ConstraintSystem<F>>(
+    &self,
+    cs: CS,
+    left: &AllocatedNum<F>,
+    right: &AllocatedNum<F>,
+  ) -> Result<AllocatedNum<F>, SynthesisError> {
+    poseidon_hash(cs, vec![left.clone(), right.clone()], &self.constants)
+  }
+}
+
+/// A sparse Merkle tree of depth `depth` whose leaves are all zero unless they have been set.
+/// Only the nodes above leaves that have been set are stored.
+#[derive(Clone)]
+pub struct MerkleTree<F>
+where
+  F: PrimeField + PrimeFieldBits,
+{
+  depth: usize,
+  constants: MerkleConstants<F>,
+  // the root of an empty subtree at each level, starting from the leaves
+  empty: Vec<F>,
+  // the nodes that differ from empty ones, keyed by their level and their index in the level
+  nodes: HashMap<(usize, u64), F>,
+}
+
+impl<F> MerkleTree<F>
+where
+  F: PrimeField + PrimeFieldBits,
+{
+  /// Creates a tree of the given depth where all the leaves are zero
+  pub fn new(constants: MerkleConstants<F>, depth: usize) -> Self {
+    assert!(depth > 0 && depth < 64);
+    let mut empty = vec![F::zero()];
+    for level in 0..depth {
+      empty.push(constants.hash(&empty[level], &empty[level]));
+    }
+    Self {
+      depth,
+      constants,
+      empty,
+      nodes: HashMap::new(),
+    }
+  }
+
+  /// Returns the depth of the tree
+  pub fn depth(&self) -> usize {
+    self.depth
+  }
+
+  /// Returns the root of the tree
+  pub fn root(&self) -> F {
+    self.node(self.depth, 0)
+  }
+
+  /// Returns the leaf at the given index
+  pub fn leaf(&self, index: u64) -> F {
+    assert!(index >> self.depth == 0);
+    self.node(0, index)
+  }
+
+  fn node(&self, level: usize, index: u64) -> F {
+    *self
+      .nodes
+      .get(&(level, index))
+      .unwrap_or(&self.empty[level])
+  }
+
+  /// Sets the leaf at the given index and recomputes the nodes above it
+  pub fn update(&mut self, index: u64, leaf: F) {
+    assert!(index >> self.depth == 0);
+    self.nodes.insert((0, index), leaf);
+    let mut index = index;
+    for level in 0..self.depth {
+      let (left, right) = (self.node(level, index & !1), self.node(level, index | 1));
+      index >>= 1;
+      let parent = self.constants.hash(&left, &right);
+      self.nodes.insert((level + 1, index), parent);
+    }
+  }
+
+  /// Returns a proof that the leaf at the given index is in the tree
+  pub fn proof(&self, index: u64) -> MerkleProof<F> {
+    assert!(index >> self.depth == 0);
+    let siblings = (0..self.depth)
+      .map(|level| self.node(level, (index >> level) ^ 1))
+      .collect();
+    MerkleProof { index, siblings }
+  }
+}
+
+/// A proof that a leaf is at a given index in a tree, made of the siblings of the nodes on the
+/// path from the leaf to the root
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MerkleProof<F: PrimeField> {
+  /// The index of the leaf
+  pub index: u64,
+  /// The siblings, starting from the sibling of the leaf
+  pub siblings: Vec<F>,
+}
+
+impl<F> MerkleProof<F>
+where
+  F: PrimeField + PrimeFieldBits,
+{
+  /// Computes the root of the tree that has the leaf at the index of the proof
+  pub fn compute_root(&self, constants: &MerkleConstants<F>, leaf: &F) -> F {
+    self
+      .siblings
+      .iter()
+      .enumerate()
+      .fold(*leaf, |node, (level, sibling)| {
+        if (self.index >> level) & 1 == 0 {
+          constants.hash(&node, sibling)
+        } else {
+          constants.hash(sibling, &node)
+        }
+      })
+  }
+
+  /// Checks that the leaf is at the index of the proof in the tree with the given root
+  pub fn verify(&self, constants: &MerkleConstants<F>, root: &F, leaf: &F) -> bool {
+    self.compute_root(constants, leaf) == *root
+  }
+}
+
+/// A Merkle proof allocated in a circuit, with the index of the leaf as little-endian bits
+#[derive(Clone)]
+pub struct AllocatedMerkleProof<F>
+where
+  F: PrimeField + PrimeFieldBits,
+{
+  index: Vec<AllocatedBit>,
+  siblings: Vec<AllocatedNum<F>>,
+}
+
+impl<F> AllocatedMerkleProof<F>
+where
+  F: PrimeField + PrimeFieldBits,
+{
+  /// Allocates a proof for a tree of the given depth.
+  /// If the proof is None, it allocates a proof without values, as needed to create the shape
+  pub fn alloc<CS: ConstraintSystem<F>>(
+    mut cs: CS,
+    proof: Option<&MerkleProof<F>>,
+    depth: usize,
+  ) -> Result<Self, SynthesisError> {
+    if let Some(proof) = proof {
+      assert_eq!(proof.siblings.len(), depth);
+    }
+    let index = (0..depth)
+      .map(|i| {
+        AllocatedBit::alloc(
+          cs.namespace(|| format!("index bit {}", i)),
+          proof.map(|proof| (proof.index >> i) & 1 == 1),
+        )
+      })
+      .collect::<Result<Vec<AllocatedBit>, SynthesisError>>()?;
+    let siblings = (0..depth)
+      .map(|i| {
+        AllocatedNum::alloc(cs.namespace(|| format!("sibling {}", i)), || {
+          proof
+            .map(|proof| proof.siblings[i])
+            .ok_or(SynthesisError::AssignmentMissing)
+        })
+      })
+      .collect::<Result<Vec<AllocatedNum<F>>, SynthesisError>>()?;
+    Ok(Self { index, siblings })
+  }
+
+  /// Returns the little-endian bits of the index of the leaf
+  pub fn index_bits(&self) -> &[AllocatedBit] {
+    &self.index
+  }
+
+  /// Returns the index of the leaf as a number
+  pub fn index<CS: ConstraintSystem<F>>(&self, cs: CS) -> Result<AllocatedNum<F>, SynthesisError> {
+    le_bits_to_num(cs, self.index.clone())
+  }
+
+  /// Computes the root of the tree that has the leaf at the index of the proof
+  pub fn compute_root<CS: ConstraintSystem<F>>(
+    &self,
+    mut cs: CS,
+    constants: &MerkleConstants<F>,
+    leaf: &AllocatedNum<F>,
+  ) -> Result<AllocatedNum<F>, SynthesisError> {
+    let mut node = leaf.clone();
+    for (level, (bit, sibling)) in self.index.iter().zip(&self.siblings).enumerate() {
+      // the node is the right child if the bit is set
+      let condition = Boolean::from(bit.clone());
+      let left = conditionally_select(
+        cs.namespace(|| format!("level {}: left", level)),
+        sibling,
+        &node,
+        &condition,
+      )?;
+      let right = conditionally_select(
+        cs.namespace(|| format!("level {}: right", level)),
+        &node,
+        sibling,
+        &condition,
+      )?;
+      node = constants.hash_circuit(
+        cs.namespace(|| format!("level {}: hash", level)),
+        &left,
+        &right,
+      )?;
+    }
+    Ok(node)
+  }
+
+  /// Enforces that the leaf is at the index of the proof in the tree with the given root
+  pub fn enforce_membership<CS: ConstraintSystem<F>>(
+    &self,
+    mut cs: CS,
+    constants: &MerkleConstants<F>,
+    root: &AllocatedNum<F>,
+    leaf: &AllocatedNum<F>,
+  ) -> Result<(), SynthesisError> {
+    let computed = self.compute_root(cs.namespace(|| "compute root"), constants, leaf)?;
+    cs.enforce(
+      || "computed root = root",
+      |lc| lc + computed.get_variable() - root.get_variable(),
+      |lc| lc + CS::one(),
+      |lc| lc,
+    );
+    Ok(())
+  }
+
+  /// Enforces that `old_leaf` is at the index of the proof in the tree with the given root,
+  /// and returns the root of the tree where it is replaced with `new_leaf`
+  pub fn update<CS: ConstraintSystem<F>>(
+    &self,
+    mut cs: CS,
+    constants: &MerkleConstants<F>,
+    root: &AllocatedNum<F>,
+    old_leaf: &AllocatedNum<F>,
+    new_leaf: &AllocatedNum<F>,
+  ) -> Result<AllocatedNum<F>, SynthesisError> {
+    self.enforce_membership(cs.namespace(|| "old leaf"), constants, root, old_leaf)?;
+    self.compute_root(cs.namespace(|| "new root"), constants, new_leaf)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::bellperson::{
+    r1cs::{NovaShape, NovaWitness},
+    shape_cs::ShapeCS,
+    solver::SatisfyingAssignment,
+  };
+  use ff::Field;
+  use rand::rngs::OsRng;
+
+  type G = pasta_curves::pallas::Point;
+  type Fp = pasta_curves::pallas::Scalar;
+
+  const DEPTH: usize = 16;
+
+  #[test]
+  fn test_merkle_tree() {
+    let constants = MerkleConstants::<Fp>::new();
+    let mut tree = MerkleTree::new(constants.clone(), DEPTH);
+    let empty_root = tree.root();
+
+    let leaves = [
+      (0, Fp::random(&mut OsRng)),
+      (7, Fp::random(&mut OsRng)),
+      (65535, Fp::one()),
+    ];
+    for (index, leaf) in leaves.iter() {
+      let proof = tree.proof(*index);
+      tree.update(*index, *leaf);
+      // the proof for the old leaf also opens the new leaf against the new root
+      assert_eq!(proof.compute_root(&constants, leaf), tree.root());
+    }
+    assert_ne!(tree.root(), empty_root);
+
+    for (index, leaf) in leaves.iter() {
+      assert_eq!(tree.leaf(*index), *leaf);
+      let proof = tree.proof(*index);
+      assert!(proof.verify(&constants, &tree.root(), leaf));
+      assert!(!proof.verify(&constants, &tree.root(), &(*leaf + Fp::one())));
+    }
+
+    // resetting the leaves gives back the empty tree
+    for (index, _) in leaves.iter() {
+      tree.update(*index, Fp::zero());
+    }
+    assert_eq!(tree.root(), empty_root);
+  }
+
+  /// Allocates the root, the leaves and the proof, and proves the update of the leaf
+  fn synthesize_update<CS: ConstraintSystem<Fp>>(
+    mut cs: CS,
+    constants: &MerkleConstants<Fp>,
+    values: Option<(Fp, Fp, Fp, &MerkleProof<Fp>)>,
+  ) -> AllocatedNum<Fp> {
+    let root = AllocatedNum::alloc(cs.namespace(|| "root"), || {
+      values.map(|v| v.0).ok_or(SynthesisError::AssignmentMissing)
+    })
+    .unwrap();
+    let old_leaf = AllocatedNum::alloc(cs.namespace(|| "old leaf"), || {
+      values.map(|v| v.1).ok_or(SynthesisError::AssignmentMissing)
+    })
+    .unwrap();
+    let new_leaf = AllocatedNum::alloc(cs.namespace(|| "new leaf"), || {
+      values.map(|v| v.2).ok_or(SynthesisError::AssignmentMissing)
+    })
+    .unwrap();
+    let proof =
+      AllocatedMerkleProof::alloc(cs.namespace(|| "proof"), values.map(|v| v.3), DEPTH).unwrap();
+    proof
+      .update(
+        cs.namespace(|| "update"),
+        constants,
+        &root,
+        &old_leaf,
+        &new_leaf,
+      )
+      .unwrap()
+  }
+
+  #[test]
+  fn test_merkle_circuit_update() {
+    let constants = MerkleConstants::<Fp>::new();
+    let mut tree = MerkleTree::new(constants.clone(), DEPTH);
+    tree.update(3, Fp::random(&mut OsRng));
+    tree.update(12345, Fp::random(&mut OsRng));
+
+    // First create the shape
+    let mut cs: ShapeCS<G> = ShapeCS::new();
+    let _ = synthesize_update(&mut cs, &constants, None);
+    println!("Number of constraints: {}", cs.num_constraints());
+    let shape = cs.r1cs_shape();
+    let gens = cs.r1cs_gens();
+
+    // Then the satisfying assignment
+    let index = 12345;
+    let (root, old_leaf, new_leaf) = (tree.root(), tree.leaf(index), Fp::random(&mut OsRng));
+    let proof = tree.proof(index);
+    let mut cs: SatisfyingAssignment<G> = SatisfyingAssignment::new();
+    let new_root = synthesize_update(
+      &mut cs,
+      &constants,
+      Some((root, old_leaf, new_leaf, &proof)),
+    );
+    let (inst, witness) = cs.r1cs_instance_and_witness(&shape, &gens).unwrap();
+    assert!(shape.is_sat(&gens, &inst, &witness).is_ok());
+
+    tree.update(index, new_leaf);
+    assert_eq!(new_root.get_value().unwrap(), tree.root());
+
+    // the circuit is not satisfied if the old leaf is not in the tree
+    let mut cs: SatisfyingAssignment<G> = SatisfyingAssignment::new();
+    let _ = synthesize_update(
+      &mut cs,
+      &constants,
+      Some((root, old_leaf + Fp::one(), new_leaf, &proof)),
+    );
+    let (inst, witness) = cs.r1cs_instance_and_witness(&shape, &gens).unwrap();
+    assert!(shape.is_sat(&gens, &inst, &witness).is_err());
+  }
+}
@@ -1,5 +1,7 @@
 //! This module implements various gadgets necessary for Nova
 //! and applications built with Nova.
 pub mod ecc;
+pub mod merkle;
 pub mod r1cs;
+pub mod schnorr;
 pub mod utils;
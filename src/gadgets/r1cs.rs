@@ -3,10 +3,11 @@ use crate::{
   gadgets::{
     ecc::AllocatedPoint,
     utils::{
-      alloc_bignat_constant, alloc_one, alloc_scalar_as_base, conditionally_select,
+      alloc_bignat_constant, alloc_one, alloc_scalar_as_base, alloc_zero, conditionally_select,
       conditionally_select_bignat, le_bits_to_num,
     },
   },
+  lookup::NUM_LOOKUP_IO,
   poseidon::{PoseidonROGadget, ROConstantsCircuit},
   r1cs::{R1CSInstance, RelaxedR1CSInstance},
   traits::Group,
@@ -28,18 +29,24 @@ where
   G: Group,
 {
   pub(crate) W: AllocatedPoint<G::Base>,
+  pub(crate) L: Option<AllocatedPoint<G::Base>>, // the commitment of a lookup argument
   pub(crate) X0: AllocatedNum<G::Base>,
   pub(crate) X1: AllocatedNum<G::Base>,
+  pub(crate) X_lookup: Vec<AllocatedNum<G::Base>>, // the public IO of a lookup argument
 }
 
 impl<G> AllocatedR1CSInstance<G>
 where
   G: Group,
 {
-  /// Takes the r1cs instance and creates a new allocated r1cs instance
+  /// Takes the r1cs instance and creates a new allocated r1cs instance.
+  /// If `lookup` holds the digest of the public parameters and the constants of the random
+  /// oracle that folds the instance, the instance carries a lookup argument, whose challenge
+  /// is computed in the circuit from the digest and the commitment W.
   pub fn alloc<CS: ConstraintSystem<<G as Group>::Base>>(
     mut cs: CS,
     u: Option<R1CSInstance<G>>,
+    lookup: Option<(&AllocatedNum<G::Base>, &ROConstantsCircuit<G::Base>)>,
   ) -> Result<Self, SynthesisError> {
     // Check that the incoming instance has exactly 2 io
     let W = AllocatedPoint::alloc(
@@ -57,7 +64,35 @@ where
       u.get().map_or(None, |u| Some(u.X[1])),
     )?;
 
-    Ok(AllocatedR1CSInstance { W, X0, X1 })
+    let (L, X_lookup) = if let Some((params, ro_consts)) = lookup {
+      let L = AllocatedPoint::alloc(
+        cs.namespace(|| "allocate L"),
+        u.get().map_or(None, |u| {
+          u.comm_L.map(|comm_L| comm_L.comm.to_coordinates())
+        }),
+      )?;
+
+      // the challenge is H(params, W) and is followed by a zero
+      let mut ro = PoseidonROGadget::new(ro_consts.clone());
+      ro.absorb(params.clone());
+      ro.absorb(W.x.clone());
+      ro.absorb(W.y.clone());
+      ro.absorb(W.is_infinity.clone());
+      let c_bits = ro.get_challenge(cs.namespace(|| "lookup challenge bits"))?;
+      let c = le_bits_to_num(cs.namespace(|| "lookup challenge"), c_bits)?;
+      let zero = alloc_zero(cs.namespace(|| "lookup zero"))?;
+      (Some(L), vec![c, zero])
+    } else {
+      (None, Vec::new())
+    };
+
+    Ok(AllocatedR1CSInstance {
+      W,
+      L,
+      X0,
+      X1,
+      X_lookup,
+    })
   }
 
   /// Absorb the provided instance in the RO
@@ -65,8 +100,16 @@ where
     ro.absorb(self.W.x.clone());
     ro.absorb(self.W.y.clone());
     ro.absorb(self.W.is_infinity.clone());
+    if let Some(L) = &self.L {
+      ro.absorb(L.x.clone());
+      ro.absorb(L.y.clone());
+      ro.absorb(L.is_infinity.clone());
+    }
     ro.absorb(self.X0.clone());
     ro.absorb(self.X1.clone());
+    for x in &self.X_lookup {
+      ro.absorb(x.clone());
+    }
   }
 }
 
@@ -76,20 +119,24 @@ where
   G: Group,
 {
   pub(crate) W: AllocatedPoint<G::Base>,
+  pub(crate) L: Option<AllocatedPoint<G::Base>>, // the commitment of a lookup argument
   pub(crate) E: AllocatedPoint<G::Base>,
   pub(crate) u: AllocatedNum<G::Base>,
   pub(crate) X0: BigNat<G::Base>,
   pub(crate) X1: BigNat<G::Base>,
+  pub(crate) X_lookup: Vec<BigNat<G::Base>>, // the public IO of a lookup argument
 }
 
 impl<G> AllocatedRelaxedR1CSInstance<G>
 where
   G: Group,
 {
-  /// Allocates the given RelaxedR1CSInstance as a witness of the circuit
+  /// Allocates the given RelaxedR1CSInstance as a witness of the circuit.
+  /// If `lookup` is true, the instance carries a lookup argument.
   pub fn alloc<CS: ConstraintSystem<<G as Group>::Base>>(
     mut cs: CS,
     inst: Option<RelaxedR1CSInstance<G>>,
+    lookup: bool,
     limb_width: usize,
     n_limbs: usize,
   ) -> Result<Self, SynthesisError> {
@@ -137,13 +184,48 @@ where
       n_limbs,
     )?;
 
-    Ok(AllocatedRelaxedR1CSInstance { W, E, u, X0, X1 })
+    let (L, X_lookup) = if lookup {
+      let L = AllocatedPoint::alloc(
+        cs.namespace(|| "allocate L"),
+        inst.get().map_or(None, |inst| {
+          inst.comm_L.map(|comm_L| comm_L.comm.to_coordinates())
+        }),
+      )?;
+      let X_lookup = (0..NUM_LOOKUP_IO)
+        .map(|i| {
+          BigNat::alloc_from_nat(
+            cs.namespace(|| format!("allocate X_lookup[{}]", i)),
+            || {
+              Ok(f_to_nat(&inst.clone().map_or(G::Scalar::zero(), |inst| {
+                inst.X[inst.X.len() - NUM_LOOKUP_IO + i]
+              })))
+            },
+            limb_width,
+            n_limbs,
+          )
+        })
+        .collect::<Result<Vec<BigNat<G::Base>>, _>>()?;
+      (Some(L), X_lookup)
+    } else {
+      (None, Vec::new())
+    };
+
+    Ok(AllocatedRelaxedR1CSInstance {
+      W,
+      L,
+      E,
+      u,
+      X0,
+      X1,
+      X_lookup,
+    })
   }
 
   /// Allocates the hardcoded default RelaxedR1CSInstance in the circuit.
-  /// W = E = 0, u = 1, X0 = X1 = 0
+  /// W = E = 0, u = 1, X0 = X1 = 0, and L = 0 with a zero lookup IO if `lookup` is true
   pub fn default<CS: ConstraintSystem<<G as Group>::Base>>(
     mut cs: CS,
+    lookup: bool,
     limb_width: usize,
     n_limbs: usize,
   ) -> Result<Self, SynthesisError> {
@@ -166,7 +248,31 @@ where
       n_limbs,
     )?;
 
-    Ok(AllocatedRelaxedR1CSInstance { W, E, u, X0, X1 })
+    let (L, X_lookup) = if lookup {
+      let X_lookup = (0..NUM_LOOKUP_IO)
+        .map(|i| {
+          BigNat::alloc_from_nat(
+            cs.namespace(|| format!("allocate x_default_lookup[{}]", i)),
+            || Ok(f_to_nat(&G::Scalar::zero())),
+            limb_width,
+            n_limbs,
+          )
+        })
+        .collect::<Result<Vec<BigNat<G::Base>>, _>>()?;
+      (Some(W.clone()), X_lookup)
+    } else {
+      (None, Vec::new())
+    };
+
+    Ok(AllocatedRelaxedR1CSInstance {
+      W,
+      L,
+      E,
+      u,
+      X0,
+      X1,
+      X_lookup,
+    })
   }
 
   /// Allocates the R1CS Instance as a RelaxedR1CSInstance in the circuit.
@@ -195,12 +301,28 @@ where
       n_limbs,
     )?;
 
+    let X_lookup = inst
+      .X_lookup
+      .iter()
+      .enumerate()
+      .map(|(i, x)| {
+        BigNat::from_num(
+          cs.namespace(|| format!("allocate X_lookup[{}] from relaxed r1cs", i)),
+          Num::from(x.clone()),
+          limb_width,
+          n_limbs,
+        )
+      })
+      .collect::<Result<Vec<BigNat<G::Base>>, _>>()?;
+
     Ok(AllocatedRelaxedR1CSInstance {
       W: inst.W,
+      L: inst.L,
       E,
       u,
       X0,
       X1,
+      X_lookup,
     })
   }
 
@@ -213,6 +335,11 @@ where
     ro.absorb(self.W.x.clone());
     ro.absorb(self.W.y.clone());
     ro.absorb(self.W.is_infinity.clone());
+    if let Some(L) = &self.L {
+      ro.absorb(L.x.clone());
+      ro.absorb(L.y.clone());
+      ro.absorb(L.is_infinity.clone());
+    }
     ro.absorb(self.E.x.clone());
     ro.absorb(self.E.y.clone());
     ro.absorb(self.E.is_infinity.clone());
@@ -252,6 +379,16 @@ where
       ro.absorb(limb);
     }
 
+    // absorb each of the limbs of the lookup IO
+    for (i, x) in self.X_lookup.iter().enumerate() {
+      for (j, limb) in x.as_limbs::<CS>().iter().enumerate() {
+        let limb = limb.as_sapling_allocated_num(
+          cs.namespace(|| format!("convert limb {} of X_r_lookup[{}] to num", j, i)),
+        )?;
+        ro.absorb(limb);
+      }
+    }
+
     Ok(())
   }
 
@@ -282,6 +419,16 @@ where
     let rW = u.W.scalar_mul(cs.namespace(|| "r * u.W"), r_bits.clone())?;
     let W_fold = self.W.add(cs.namespace(|| "self.W + r * u.W"), &rW)?;
 
+    // L_fold = self.L + r * u.L
+    let L_fold = match (&self.L, &u.L) {
+      (Some(L), Some(u_L)) => {
+        let rL = u_L.scalar_mul(cs.namespace(|| "r * u.L"), r_bits.clone())?;
+        Some(L.add(cs.namespace(|| "self.L + r * u.L"), &rL)?)
+      }
+      (None, None) => None,
+      _ => return Err(SynthesisError::Unsatisfiable),
+    };
+
     // E_fold = self.E + r * T
     let rT = T.scalar_mul(cs.namespace(|| "r * T"), r_bits)?;
     let E_fold = self.E.add(cs.namespace(|| "self.E + r * T"), &rT)?;
@@ -344,12 +491,36 @@ where
     // Now reduce
     let X1_fold = r_new_1.red_mod(cs.namespace(|| "reduce folded X[1]"), &m_bn)?;
 
+    // Fold the lookup IO in the same way
+    let X_lookup_fold = self
+      .X_lookup
+      .iter()
+      .zip(u.X_lookup.iter())
+      .enumerate()
+      .map(|(i, (X_r, x))| {
+        let x_bn = BigNat::from_num(
+          cs.namespace(|| format!("allocate X_lookup_bn[{}]", i)),
+          Num::from(x.clone()),
+          limb_width,
+          n_limbs,
+        )?;
+        let (_, r_x) =
+          x_bn.mult_mod(cs.namespace(|| format!("r*X_lookup[{}]", i)), &r_bn, &m_bn)?;
+        X_r.add::<CS>(&r_x)?.red_mod(
+          cs.namespace(|| format!("reduce folded X_lookup[{}]", i)),
+          &m_bn,
+        )
+      })
+      .collect::<Result<Vec<BigNat<G::Base>>, _>>()?;
+
     Ok(Self {
       W: W_fold,
+      L: L_fold,
       E: E_fold,
       u: u_fold,
       X0: X0_fold,
       X1: X1_fold,
+      X_lookup: X_lookup_fold,
     })
   }
 
@@ -395,6 +566,40 @@ where
       condition,
     )?;
 
-    Ok(AllocatedRelaxedR1CSInstance { W, E, u, X0, X1 })
+    let L = match (&self.L, &other.L) {
+      (Some(L), Some(other_L)) => Some(AllocatedPoint::conditionally_select(
+        cs.namespace(|| "L = cond ? self.L : other.L"),
+        L,
+        other_L,
+        condition,
+      )?),
+      (None, None) => None,
+      _ => return Err(SynthesisError::Unsatisfiable),
+    };
+
+    let X_lookup = self
+      .X_lookup
+      .iter()
+      .zip(other.X_lookup.iter())
+      .enumerate()
+      .map(|(i, (x, other_x))| {
+        conditionally_select_bignat(
+          cs.namespace(|| format!("X_lookup[{}] = cond ? self : other", i)),
+          x,
+          other_x,
+          condition,
+        )
+      })
+      .collect::<Result<Vec<BigNat<G::Base>>, _>>()?;
+
+    Ok(AllocatedRelaxedR1CSInstance {
+      W,
+      L,
+      E,
+      u,
+      X0,
+      X1,
+      X_lookup,
+    })
   }
 }
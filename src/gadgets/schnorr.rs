@@ -0,0 +1,387 @@
+//! This module implements Schnorr signatures over a curve of the cycle, along with a gadget
+//! that verifies them in a circuit over the base field of that curve, where the group operations
+//! are native. This lets a step circuit over `G1::Scalar` check signatures on `G2` and vice versa.
+//!
+//! EdDSA is not provided: it is defined over twisted Edwards curves, whose order is divisible
+//! by four, while the curves of the cycle have prime order, so no EdDSA variant has native group
+//! operations here. Verifying Ed25519 would need non-native arithmetic over its base field,
+//! which costs far more than the Schnorr gadget. Signers that want deterministic nonces as in
+//! EdDSA can derive `rng` from the secret key and the message; verification is unchanged.
+use crate::{
+  gadgets::{
+    ecc::AllocatedPoint,
+    utils::{alloc_constant, enforce_bits_le_constant},
+  },
+  poseidon::{PoseidonRO, PoseidonROGadget, ROConstantsCircuit},
+  security::SecurityConfig,
+  traits::{Group, HashFuncConstantsTrait, HashFuncTrait},
+};
+use bellperson::{
+  gadgets::{boolean::AllocatedBit, num::AllocatedNum},
+  ConstraintSystem, SynthesisError,
+};
+use ff::{Field, PrimeField, PrimeFieldBits};
+use rand::RngCore;
+
+/// The number of bits of the challenge processed at a time by the verifier circuit
+const CHALLENGE_WINDOW: usize = 3;
+
+/// The domain-separation tag absorbed first in the challenge, the bytes of "schnorr" read as
+/// a little-endian number, so that challenges are not hashes computed for other purposes
+const DOMAIN_TAG: u64 = 0x0072_726f_6e68_6373;
+
+/// The Poseidon constants used to hash the challenge of a signature, which the signer, the
+/// native verifier and the gadget must share
+#[derive(Clone)]
+pub struct SchnorrConstants<F>
+where
+  F: PrimeField + PrimeFieldBits,
+{
+  constants: ROConstantsCircuit<F>,
+}
+
+impl<F> SchnorrConstants<F>
+where
+  F: PrimeField + PrimeFieldBits,
+{
+  /// Generates the Poseidon constants for the default security configuration
+  #[allow(clippy::new_without_default)]
+  pub fn new() -> Self {
+    Self::new_with_config(&SecurityConfig::default())
+  }
+
+  /// Generates the Poseidon constants for the security configuration `config`
+  pub fn new_with_config(config: &SecurityConfig) -> Self {
+    Self {
+      constants: ROConstantsCircuit::new_with_config(config),
+    }
+  }
+}
+
+/// A Schnorr signature `(R, s)` on a message of base field elements for the public key
+/// `pk = sk * generator`. It is valid if `s * generator = R + e * pk`, where the challenge `e`
+/// is the Poseidon hash of `(tag, |msg|, pk, R, msg)` truncated to 128 bits.
+/// The response `s` must be reduced modulo the order of the group: it is a scalar outside
+/// the circuit, `from_bytes` rejects a non-canonical encoding, and the gadget checks its bits.
+#[derive(Clone, Debug)]
+pub struct SchnorrSignature<G: Group> {
+  /// The commitment to the nonce
+  pub R: G,
+  /// The response
+  pub s: G::Scalar,
+}
+
+impl<G: Group> SchnorrSignature<G> {
+  /// Signs the message with the secret key `sk`
+  pub fn sign(
+    constants: &SchnorrConstants<G::Base>,
+    generator: &G,
+    sk: &G::Scalar,
+    msg: &[G::Base],
+    rng: impl RngCore,
+  ) -> Self {
+    let k = G::Scalar::random(rng);
+    let R = *generator * k;
+    let e = challenge(constants, &(*generator * sk), &R, msg);
+    Self { R, s: k + e * sk }
+  }
+
+  /// Creates a signature from `R` and the encoding of `s`, or returns `None` if the encoding
+  /// is not canonical, i.e., `s` is not reduced modulo the order of the group
+  pub fn from_bytes(R: G, s: &<<G as Group>::Scalar as PrimeField>::Repr) -> Option<Self> {
+    Option::from(G::Scalar::from_repr(*s)).map(|s| Self { R, s })
+  }
+
+  /// Checks that the signature is valid for the message and the public key
+  pub fn verify(
+    &self,
+    constants: &SchnorrConstants<G::Base>,
+    generator: &G,
+    pk: &G,
+    msg: &[G::Base],
+  ) -> bool {
+    if pk.to_coordinates().2 || self.R.to_coordinates().2 {
+      return false;
+    }
+    let e = challenge(constants, pk, &self.R, msg);
+    *generator * self.s == self.R + *pk * e
+  }
+}
+
+/// Computes the challenge of a signature outside the circuit
+fn challenge<G: Group>(
+  constants: &SchnorrConstants<G::Base>,
+  pk: &G,
+  R: &G,
+  msg: &[G::Base],
+) -> G::Scalar {
+  let mut ro = PoseidonRO::<G::Base, G::Scalar>::new(constants.constants.clone());
+  let (pk_x, pk_y, _) = pk.to_coordinates();
+  let (R_x, R_y, _) = R.to_coordinates();
+  let (tag, len) = (G::Base::from(DOMAIN_TAG), G::Base::from(msg.len() as u64));
+  for e in [tag, len, pk_x, pk_y, R_x, R_y].iter().chain(msg) {
+    ro.absorb(*e);
+  }
+  ro.get_challenge()
+}
+
+/// A Schnorr signature allocated in a circuit over `G::Base`
+#[derive(Clone)]
+pub struct AllocatedSchnorrSignature<G: Group> {
+  R: AllocatedPoint<G::Base>,
+  s: Vec<AllocatedBit>,
+}
+
+impl<G: Group> AllocatedSchnorrSignature<G> {
+  /// Allocates the signature, and checks that `R` is on the curve and that `s` is reduced
+  /// modulo the order of the group, so that signatures are not malleable
+  pub fn alloc<CS: ConstraintSystem<G::Base>>(
+    cs: CS,
+    sig: Option<&SchnorrSignature<G>>,
+  ) -> Result<Self, SynthesisError> {
+    let s_bits = sig.map(|sig| {
+      let bits = sig.s.to_le_bits();
+      (0..G::Scalar::NUM_BITS as usize)
+        .map(|i| bits[i])
+        .collect::<Vec<bool>>()
+    });
+    Self::alloc_with_bits(cs, sig.map(|sig| sig.R), s_bits)
+  }
+
+  /// Allocates a signature given `R` and the little-endian bits of `s`, which may encode
+  /// a number that is not reduced
+  fn alloc_with_bits<CS: ConstraintSystem<G::Base>>(
+    mut cs: CS,
+    R: Option<G>,
+    s_bits: Option<Vec<bool>>,
+  ) -> Result<Self, SynthesisError> {
+    let R = AllocatedPoint::alloc(cs.namespace(|| "allocate R"), R.map(|R| R.to_coordinates()))?;
+    let (a, b) = G::get_curve_params();
+    R.check_on_curve(cs.namespace(|| "R is on the curve"), a, b)?;
+
+    let s = (0..G::Scalar::NUM_BITS as usize)
+      .map(|i| {
+        AllocatedBit::alloc(
+          cs.namespace(|| format!("s bit {}", i)),
+          s_bits.as_ref().map(|bits| bits[i]),
+        )
+      })
+      .collect::<Result<Vec<AllocatedBit>, SynthesisError>>()?;
+    let order_minus_one = (-G::Scalar::one()).to_le_bits();
+    let bound = (0..s.len())
+      .map(|i| order_minus_one[i])
+      .collect::<Vec<bool>>();
+    enforce_bits_le_constant(cs.namespace(|| "s is reduced"), &s, &bound)?;
+
+    Ok(Self { R, s })
+  }
+
+  /// Enforces that the signature is valid for the message and the public key.
+  /// The public key is checked to be on the curve and not the point at infinity.
+  pub fn verify<CS: ConstraintSystem<G::Base>>(
+    &self,
+    mut cs: CS,
+    constants: &SchnorrConstants<G::Base>,
+    generator: &G,
+    pk: &AllocatedPoint<G::Base>,
+    msg: &[AllocatedNum<G::Base>],
+  ) -> Result<(), SynthesisError> {
+    let (a, b) = G::get_curve_params();
+    pk.check_on_curve(cs.namespace(|| "pk is on the curve"), a, b)?;
+    pk.check_not_infinity(cs.namespace(|| "pk is not infinity"));
+    self
+      .R
+      .check_not_infinity(cs.namespace(|| "R is not infinity"));
+
+    // e = H(tag, |msg|, pk, R, msg)
+    let mut ro = PoseidonROGadget::new(constants.constants.clone());
+    let tag = alloc_constant(cs.namespace(|| "tag"), G::Base::from(DOMAIN_TAG))?;
+    let len = alloc_constant(cs.namespace(|| "len"), G::Base::from(msg.len() as u64))?;
+    let (pk_x, pk_y, _) = pk.get_coordinates();
+    let (R_x, R_y, _) = self.R.get_coordinates();
+    for e in [&tag, &len, pk_x, pk_y, R_x, R_y].into_iter().chain(msg) {
+      ro.absorb(e.clone());
+    }
+    let e_bits = ro.get_challenge(cs.namespace(|| "e"))?;
+
+    // check s * generator = R + e * pk
+    let sG = AllocatedPoint::scalar_mul_fixed_base(
+      cs.namespace(|| "s * generator"),
+      generator,
+      self.s.clone(),
+    )?;
+    let epk = pk.scalar_mul_windowed(cs.namespace(|| "e * pk"), e_bits, CHALLENGE_WINDOW)?;
+    let rhs = self.R.add(cs.namespace(|| "R + e * pk"), &epk)?;
+    sG.enforce_equal(cs.namespace(|| "check signature"), &rhs);
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::bellperson::{
+    r1cs::{NovaShape, NovaWitness},
+    shape_cs::ShapeCS,
+    solver::SatisfyingAssignment,
+  };
+  use pasta_curves::group::Group as OtherGroup;
+  use rand::rngs::OsRng;
+
+  /// Allocates the public key, the message and the signature given as `R` and the bits of `s`,
+  /// and verifies the signature
+  fn synthesize_verify<G, CS>(
+    mut cs: CS,
+    generator: &G,
+    pk: Option<&G>,
+    msg: &[G::Base],
+    sig: Option<(G, Vec<bool>)>,
+  ) where
+    G: Group,
+    CS: ConstraintSystem<G::Base>,
+  {
+    let pk =
+      AllocatedPoint::alloc(cs.namespace(|| "pk"), pk.map(|pk| pk.to_coordinates())).unwrap();
+    let msg = msg
+      .iter()
+      .enumerate()
+      .map(|(i, m)| AllocatedNum::alloc(cs.namespace(|| format!("msg {}", i)), || Ok(*m)).unwrap())
+      .collect::<Vec<_>>();
+    let (R, s_bits) = match sig {
+      Some((R, s_bits)) => (Some(R), Some(s_bits)),
+      None => (None, None),
+    };
+    let sig =
+      AllocatedSchnorrSignature::alloc_with_bits(cs.namespace(|| "sig"), R, s_bits).unwrap();
+    sig
+      .verify(
+        cs.namespace(|| "verify"),
+        &SchnorrConstants::new(),
+        generator,
+        &pk,
+        &msg,
+      )
+      .unwrap();
+  }
+
+  /// Returns the little-endian bits of `s + k * order`, with one more bit for each addition
+  fn shifted_bits<F: PrimeFieldBits>(s: &F, k: usize) -> Vec<bool> {
+    let order = F::char_le_bits().into_iter().collect::<Vec<bool>>();
+    let mut bits = s.to_le_bits().into_iter().collect::<Vec<bool>>();
+    for _ in 0..k {
+      let mut carry = false;
+      for (i, b) in bits.iter_mut().enumerate() {
+        let sum = *b as u8 + order.get(i).copied().unwrap_or(false) as u8 + carry as u8;
+        *b = sum & 1 == 1;
+        carry = sum > 1;
+      }
+      bits.push(carry);
+    }
+    bits
+  }
+
+  /// Returns the `NUM_BITS` little-endian bits of `s + k * order`, which must fit in them
+  fn scalar_bits<F: PrimeFieldBits>(s: &F, k: usize) -> Vec<bool> {
+    let mut bits = shifted_bits(s, k);
+    assert!(bits[F::NUM_BITS as usize..].iter().all(|b| !b));
+    bits.truncate(F::NUM_BITS as usize);
+    bits
+  }
+
+  fn test_schnorr_with<G, G2>()
+  where
+    G: Group<Base = <G2 as Group>::Scalar> + OtherGroup,
+    G2: Group,
+  {
+    let constants = SchnorrConstants::new();
+    let generator = <G as OtherGroup>::generator();
+    let sk = <G as Group>::Scalar::random(&mut OsRng);
+    let pk = generator * sk;
+    let msg = (0..4)
+      .map(|_| G::Base::random(&mut OsRng))
+      .collect::<Vec<_>>();
+    let sig = SchnorrSignature::sign(&constants, &generator, &sk, &msg, OsRng);
+    assert!(sig.verify(&constants, &generator, &pk, &msg));
+    let bits = |sig: &SchnorrSignature<G>, k: usize| Some((sig.R, scalar_bits(&sig.s, k)));
+
+    // a signature does not verify for a different message
+    let mut other_msg = msg.clone();
+    other_msg[0] += G::Base::one();
+    assert!(!sig.verify(&constants, &generator, &pk, &other_msg));
+
+    // nor with the constants of another security configuration
+    let other_constants = SchnorrConstants::new_with_config(&SecurityConfig::conservative());
+    assert!(!sig.verify(&other_constants, &generator, &pk, &msg));
+
+    // a non-canonical encoding of s is rejected
+    let mut repr = <<G as Group>::Scalar as PrimeField>::Repr::default();
+    repr.as_mut().fill(0xff);
+    assert!(SchnorrSignature::<G>::from_bytes(sig.R, &repr).is_none());
+    let sig_decoded = SchnorrSignature::<G>::from_bytes(sig.R, &sig.s.to_repr()).unwrap();
+    assert!(sig_decoded.verify(&constants, &generator, &pk, &msg));
+
+    // First create the shape
+    let mut cs: ShapeCS<G2> = ShapeCS::new();
+    synthesize_verify::<G, _>(&mut cs, &generator, None, &msg, None);
+    println!("Number of constraints: {}", cs.num_constraints());
+    let shape = cs.r1cs_shape();
+    let gens = cs.r1cs_gens();
+
+    // Then the satisfying assignment
+    let mut cs: SatisfyingAssignment<G2> = SatisfyingAssignment::new();
+    synthesize_verify(&mut cs, &generator, Some(&pk), &msg, bits(&sig, 0));
+    let (inst, witness) = cs.r1cs_instance_and_witness(&shape, &gens).unwrap();
+    assert!(shape.is_sat(&gens, &inst, &witness).is_ok());
+
+    // the circuit is not satisfied for a different message
+    let mut cs: SatisfyingAssignment<G2> = SatisfyingAssignment::new();
+    synthesize_verify(&mut cs, &generator, Some(&pk), &other_msg, bits(&sig, 0));
+    let (inst, witness) = cs.r1cs_instance_and_witness(&shape, &gens).unwrap();
+    assert!(shape.is_sat(&gens, &inst, &witness).is_err());
+
+    // nor for s + order, which satisfies the verification equation, so signatures are not
+    // malleable; s + order fits in the bits of s for about half of the signatures
+    let sig = (0..)
+      .map(|_| SchnorrSignature::sign(&constants, &generator, &sk, &msg, OsRng))
+      .find(|sig| {
+        shifted_bits(&sig.s, 1)[<G as Group>::Scalar::NUM_BITS as usize..]
+          .iter()
+          .all(|b| !b)
+      })
+      .unwrap();
+    let mut cs: SatisfyingAssignment<G2> = SatisfyingAssignment::new();
+    synthesize_verify(&mut cs, &generator, Some(&pk), &msg, bits(&sig, 0));
+    let (inst, witness) = cs.r1cs_instance_and_witness(&shape, &gens).unwrap();
+    assert!(shape.is_sat(&gens, &inst, &witness).is_ok());
+    let mut cs: SatisfyingAssignment<G2> = SatisfyingAssignment::new();
+    synthesize_verify(&mut cs, &generator, Some(&pk), &msg, bits(&sig, 1));
+    let (inst, witness) = cs.r1cs_instance_and_witness(&shape, &gens).unwrap();
+    assert!(shape.is_sat(&gens, &inst, &witness).is_err());
+
+    // a signature on one element does not verify for the message that it would be padded
+    // to without the length in the challenge, with zeros up to the arity and the length
+    let msg = [G::Base::random(&mut OsRng)];
+    let sig = SchnorrSignature::sign(&constants, &generator, &sk, &msg, OsRng);
+    assert!(sig.verify(&constants, &generator, &pk, &msg));
+    let padded_msg = [&msg[..], &[G::Base::zero(); 21], &[G::Base::from(5u64)]].concat();
+    assert!(!sig.verify(&constants, &generator, &pk, &padded_msg));
+
+    let mut cs: ShapeCS<G2> = ShapeCS::new();
+    synthesize_verify::<G, _>(&mut cs, &generator, None, &padded_msg, None);
+    let (shape, gens) = (cs.r1cs_shape(), cs.r1cs_gens());
+    let mut cs: SatisfyingAssignment<G2> = SatisfyingAssignment::new();
+    synthesize_verify(&mut cs, &generator, Some(&pk), &padded_msg, bits(&sig, 0));
+    let (inst, witness) = cs.r1cs_instance_and_witness(&shape, &gens).unwrap();
+    assert!(shape.is_sat(&gens, &inst, &witness).is_err());
+  }
+
+  #[test]
+  fn test_schnorr_pallas_in_vesta() {
+    test_schnorr_with::<pasta_curves::pallas::Point, pasta_curves::vesta::Point>();
+  }
+
+  #[test]
+  fn test_schnorr_vesta_in_pallas() {
+    test_schnorr_with::<pasta_curves::vesta::Point, pasta_curves::pallas::Point>();
+  }
+}
@@ -8,7 +8,10 @@ use bellperson::{
   },
   ConstraintSystem, LinearCombination, SynthesisError,
 };
-use bellperson_nonnative::mp::bignat::{nat_to_limbs, BigNat};
+use bellperson_nonnative::{
+  mp::bignat::{nat_to_limbs, BigNat, BigNatParams},
+  util::convert::{f_to_nat, nat_to_f},
+};
 use ff::{Field, PrimeField, PrimeFieldBits};
 use num_bigint::BigInt;
 
@@ -45,6 +48,90 @@ where
   Ok(num)
 }
 
+/// Enforces that the number with the little-endian bits `bits` is at most the constant with
+/// the little-endian bits `bound`, which has as many bits. From the most significant bit down,
+/// a bit must be zero where `bound` has a zero if the bits above are equal to those of `bound`,
+/// which holds if they are one wherever `bound` has a one.
+pub fn enforce_bits_le_constant<F: PrimeField, CS: ConstraintSystem<F>>(
+  mut cs: CS,
+  bits: &[AllocatedBit],
+  bound: &[bool],
+) -> Result<(), SynthesisError> {
+  assert_eq!(bits.len(), bound.len());
+
+  // whether the bits above are equal to those of the bound, where it has a one; `None` until
+  // the bound has a one, in which case the bits above are all zero like those of the bound
+  let mut last_run: Option<AllocatedBit> = None;
+  let mut current_run: Vec<AllocatedBit> = Vec::new();
+  for (i, (bit, b)) in bits.iter().zip(bound.iter()).enumerate().rev() {
+    if *b {
+      current_run.push(bit.clone());
+      continue;
+    }
+
+    if !current_run.is_empty() {
+      let mut acc = match last_run.take() {
+        Some(last_run) => last_run,
+        None => current_run.remove(0),
+      };
+      for (j, x) in current_run.drain(..).enumerate() {
+        acc = AllocatedBit::and(
+          cs.namespace(|| format!("run at bit {}, and {}", i, j)),
+          &acc,
+          &x,
+        )?;
+      }
+      last_run = Some(acc);
+    }
+
+    // bit * last_run = 0, or bit = 0 if the bound has no one above
+    cs.enforce(
+      || format!("bit {} is bounded", i),
+      |lc| lc + bit.get_variable(),
+      |lc| match &last_run {
+        Some(last_run) => lc + last_run.get_variable(),
+        None => lc + CS::one(),
+      },
+      |lc| lc,
+    );
+  }
+  Ok(())
+}
+
+/// Builds a `BigNat` with `n_limbs` limbs of width `limb_width` from little-endian bits.
+/// This adds no constraints since the limbs are linear combinations of the bits.
+pub fn bits_to_bignat<F: PrimeField>(
+  bits: &[AllocatedBit],
+  limb_width: usize,
+  n_limbs: usize,
+) -> BigNat<F> {
+  assert!(bits.len() <= limb_width * n_limbs);
+  let mut limbs = vec![LinearCombination::zero(); n_limbs];
+  for (i, bit) in bits.iter().enumerate() {
+    let coeff = (0..i % limb_width).fold(F::one(), |acc, _| acc.double());
+    limbs[i / limb_width] = limbs[i / limb_width].clone() + (coeff, bit.get_variable());
+  }
+  let value = bits
+    .iter()
+    .rev()
+    .map(|bit| bit.get_value())
+    .collect::<Option<Vec<bool>>>()
+    .map(|bits| {
+      bits
+        .into_iter()
+        .fold(BigInt::from(0), |acc, bit| (acc << 1) + u8::from(bit))
+    });
+  let limb_values = value
+    .as_ref()
+    .map(|v| nat_to_limbs(v, limb_width, n_limbs).unwrap());
+  BigNat {
+    limbs,
+    limb_values,
+    value,
+    params: BigNatParams::new(limb_width, n_limbs),
+  }
+}
+
 /// Allocate a variable that is set to zero
 pub fn alloc_zero<F: PrimeField, CS: ConstraintSystem<F>>(
   mut cs: CS,
@@ -74,6 +161,75 @@ pub fn alloc_one<F: PrimeField, CS: ConstraintSystem<F>>(
   Ok(one)
 }
 
+/// Allocates a number that is constrained to be the given constant
+pub fn alloc_constant<F: PrimeField, CS: ConstraintSystem<F>>(
+  mut cs: CS,
+  c: F,
+) -> Result<AllocatedNum<F>, SynthesisError> {
+  let num = AllocatedNum::alloc(cs.namespace(|| "alloc"), || Ok(c))?;
+  cs.enforce(
+    || "check constant",
+    |lc| lc,
+    |lc| lc,
+    |lc| lc + num.get_variable() - (c, CS::one()),
+  );
+  Ok(num)
+}
+
+/// Allocates `a + b * c`
+pub fn mul_add<F: PrimeField, CS: ConstraintSystem<F>>(
+  mut cs: CS,
+  a: &AllocatedNum<F>,
+  b: &AllocatedNum<F>,
+  c: &AllocatedNum<F>,
+) -> Result<AllocatedNum<F>, SynthesisError> {
+  let res = AllocatedNum::alloc(cs.namespace(|| "alloc"), || {
+    let mut bc = b.get_value().ok_or(SynthesisError::AssignmentMissing)?;
+    bc.mul_assign(&c.get_value().ok_or(SynthesisError::AssignmentMissing)?);
+    Ok(a.get_value().ok_or(SynthesisError::AssignmentMissing)? + bc)
+  })?;
+  cs.enforce(
+    || "check a + b * c",
+    |lc| lc + b.get_variable(),
+    |lc| lc + c.get_variable(),
+    |lc| lc + res.get_variable() - a.get_variable(),
+  );
+  Ok(res)
+}
+
+/// Returns `prod_i (a_i * b_i + (1 - a_i) * (1 - b_i))`
+pub fn eval_eq<F: PrimeField, CS: ConstraintSystem<F>>(
+  mut cs: CS,
+  a: &[AllocatedNum<F>],
+  b: &[AllocatedNum<F>],
+  one: &AllocatedNum<F>,
+) -> Result<AllocatedNum<F>, SynthesisError> {
+  let mut acc = one.clone();
+  for (i, (a_i, b_i)) in a.iter().zip(b.iter()).enumerate() {
+    // a_i * b_i + (1 - a_i) * (1 - b_i) = 1 - a_i - b_i + 2 * a_i * b_i
+    let ab = a_i.mul(cs.namespace(|| format!("a_{} * b_{}", i, i)), b_i)?;
+    let term = AllocatedNum::alloc(cs.namespace(|| format!("term {}", i)), || {
+      let (a, b, ab) = (
+        a_i.get_value().ok_or(SynthesisError::AssignmentMissing)?,
+        b_i.get_value().ok_or(SynthesisError::AssignmentMissing)?,
+        ab.get_value().ok_or(SynthesisError::AssignmentMissing)?,
+      );
+      Ok(F::one() - a - b + ab.double())
+    })?;
+    cs.enforce(
+      || format!("check term {}", i),
+      |lc| {
+        lc + CS::one() - a_i.get_variable() - b_i.get_variable()
+          + (F::from(2u64), ab.get_variable())
+      },
+      |lc| lc + CS::one(),
+      |lc| lc + term.get_variable(),
+    );
+    acc = acc.mul(cs.namespace(|| format!("product {}", i)), &term)?;
+  }
+  Ok(acc)
+}
+
 /// Allocate a scalar as a base. Only to be used is the scalar fits in base!
 pub fn alloc_scalar_as_base<G, CS>(
   mut cs: CS,
@@ -416,3 +572,358 @@ pub fn select_num_or_one<F: PrimeField, CS: ConstraintSystem<F>>(
 
   Ok(c)
 }
+
+/// Decomposes a number into `n` little-endian bits. This also checks that the number fits in `n` bits.
+/// It requires `n <= F::CAPACITY`, so that the decomposition is unique
+pub fn num_to_le_bits<F: PrimeField + PrimeFieldBits, CS: ConstraintSystem<F>>(
+  mut cs: CS,
+  a: &AllocatedNum<F>,
+  n: usize,
+) -> Result<Vec<AllocatedBit>, SynthesisError> {
+  assert!(n <= F::CAPACITY as usize);
+  let values = a.get_value().map(|v| v.to_le_bits());
+  let bits = (0..n)
+    .map(|i| {
+      AllocatedBit::alloc(
+        cs.namespace(|| format!("bit {}", i)),
+        values.as_ref().map(|v| v[i]),
+      )
+    })
+    .collect::<Result<Vec<AllocatedBit>, SynthesisError>>()?;
+
+  let mut lc = LinearCombination::zero();
+  let mut coeff = F::one();
+  for bit in bits.iter() {
+    lc = lc + (coeff, bit.get_variable());
+    coeff = coeff.double();
+  }
+  cs.enforce(
+    || "check bits",
+    |_| lc,
+    |lc| lc + CS::one(),
+    |lc| lc + a.get_variable(),
+  );
+  Ok(bits)
+}
+
+/// Packs little-endian bits into numbers that hold `F::CAPACITY` bits each, except for the last one
+pub fn pack_bits<F: PrimeField + PrimeFieldBits, CS: ConstraintSystem<F>>(
+  mut cs: CS,
+  bits: &[AllocatedBit],
+) -> Result<Vec<AllocatedNum<F>>, SynthesisError> {
+  bits
+    .chunks(F::CAPACITY as usize)
+    .enumerate()
+    .map(|(i, chunk)| le_bits_to_num(cs.namespace(|| format!("chunk {}", i)), chunk.to_vec()))
+    .collect()
+}
+
+/// Checks that the number fits in `n` bits
+pub fn enforce_range<F: PrimeField + PrimeFieldBits, CS: ConstraintSystem<F>>(
+  cs: CS,
+  a: &AllocatedNum<F>,
+  n: usize,
+) -> Result<(), SynthesisError> {
+  num_to_le_bits(cs, a, n).map(|_| ())
+}
+
+/// Returns a bit that is set if `a + offset >= b`, for `a` and `b` that fit in `n` bits.
+/// It decomposes `a + offset - b + 2^n` into `n + 1` bits and returns the top one.
+fn alloc_num_ge_with_offset<F: PrimeField + PrimeFieldBits, CS: ConstraintSystem<F>>(
+  mut cs: CS,
+  a: &AllocatedNum<F>,
+  b: &AllocatedNum<F>,
+  offset: F,
+  n: usize,
+) -> Result<AllocatedBit, SynthesisError> {
+  assert!(n < F::CAPACITY as usize);
+  let shift = (0..n).fold(F::one(), |acc, _| acc.double());
+  let diff = AllocatedNum::alloc(cs.namespace(|| "diff"), || {
+    Ok(*a.get_value().get()? + offset - *b.get_value().get()? + shift)
+  })?;
+  cs.enforce(
+    || "check diff",
+    |lc| lc + a.get_variable() - b.get_variable() + (offset + shift, CS::one()),
+    |lc| lc + CS::one(),
+    |lc| lc + diff.get_variable(),
+  );
+  let bits = num_to_le_bits(cs.namespace(|| "diff bits"), &diff, n + 1)?;
+  Ok(bits[n].clone())
+}
+
+/// Returns a bit that is set if `a < b`, for `a` and `b` that fit in `n` bits.
+/// The caller must ensure that `a` and `b` fit in `n` bits, for example with `enforce_range`
+pub fn alloc_num_lt<F: PrimeField + PrimeFieldBits, CS: ConstraintSystem<F>>(
+  cs: CS,
+  a: &AllocatedNum<F>,
+  b: &AllocatedNum<F>,
+  n: usize,
+) -> Result<AllocatedBit, SynthesisError> {
+  // a < b iff b - 1 >= a
+  alloc_num_ge_with_offset(cs, b, a, -F::one(), n)
+}
+
+/// Returns a bit that is set if `a <= b`, for `a` and `b` that fit in `n` bits.
+/// The caller must ensure that `a` and `b` fit in `n` bits, for example with `enforce_range`
+pub fn alloc_num_le<F: PrimeField + PrimeFieldBits, CS: ConstraintSystem<F>>(
+  cs: CS,
+  a: &AllocatedNum<F>,
+  b: &AllocatedNum<F>,
+  n: usize,
+) -> Result<AllocatedBit, SynthesisError> {
+  alloc_num_ge_with_offset(cs, b, a, F::zero(), n)
+}
+
+/// Computes the quotient and the remainder of the integer division of `a` by `b`, for `a` and `b`
+/// that fit in `n` bits. This checks that `b` is not zero. The caller must ensure that `a` and `b`
+/// fit in `n` bits, for example with `enforce_range`
+pub fn alloc_div_rem<F: PrimeField + PrimeFieldBits, CS: ConstraintSystem<F>>(
+  mut cs: CS,
+  a: &AllocatedNum<F>,
+  b: &AllocatedNum<F>,
+  n: usize,
+) -> Result<(AllocatedNum<F>, AllocatedNum<F>), SynthesisError> {
+  // q * b + r must not wrap around the modulus
+  assert!(2 * n < F::CAPACITY as usize);
+  let values = || -> Result<(BigInt, BigInt), SynthesisError> {
+    let a = f_to_nat(a.get_value().get()?);
+    let b = f_to_nat(b.get_value().get()?);
+    if b == BigInt::from(0) {
+      return Err(SynthesisError::DivisionByZero);
+    }
+    Ok((&a / &b, &a % &b))
+  };
+  let q = AllocatedNum::alloc(cs.namespace(|| "quotient"), || {
+    nat_to_f(&values()?.0).ok_or(SynthesisError::Unsatisfiable)
+  })?;
+  let r = AllocatedNum::alloc(cs.namespace(|| "remainder"), || {
+    nat_to_f(&values()?.1).ok_or(SynthesisError::Unsatisfiable)
+  })?;
+  enforce_range(cs.namespace(|| "quotient range"), &q, n)?;
+  enforce_range(cs.namespace(|| "remainder range"), &r, n)?;
+
+  // a = q * b + r
+  cs.enforce(
+    || "a = q * b + r",
+    |lc| lc + q.get_variable(),
+    |lc| lc + b.get_variable(),
+    |lc| lc + a.get_variable() - r.get_variable(),
+  );
+
+  // r < b, which also implies that b is not zero
+  let lt = alloc_num_lt(cs.namespace(|| "r < b"), &r, b, n)?;
+  cs.enforce(
+    || "check r < b",
+    |lc| lc + lt.get_variable(),
+    |lc| lc + CS::one(),
+    |lc| lc + CS::one(),
+  );
+  Ok((q, r))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::bellperson::{
+    r1cs::{NovaShape, NovaWitness},
+    shape_cs::ShapeCS,
+    solver::SatisfyingAssignment,
+  };
+  use bellperson::Circuit;
+  use rand::{rngs::OsRng, RngCore};
+
+  type G = pasta_curves::pallas::Point;
+  type Fp = pasta_curves::pallas::Scalar;
+
+  const N: usize = 16;
+
+  /// The gadgets under test. Each one is checked against a native reference on integers
+  #[derive(Clone, Copy, Debug)]
+  enum Op {
+    Range,
+    Bits,
+    Lt,
+    Le,
+    LeConst,
+    DivRem,
+  }
+
+  #[derive(Clone, Debug)]
+  struct TestCircuit {
+    op: Op,
+    a: u64,
+    b: u64,
+    n: usize,
+  }
+
+  /// Enforces that the number equals the constant
+  fn enforce_const<CS: ConstraintSystem<Fp>>(mut cs: CS, a: &AllocatedNum<Fp>, c: u64) {
+    cs.enforce(
+      || "check constant",
+      |lc| lc + a.get_variable(),
+      |lc| lc + CS::one(),
+      |lc| lc + (Fp::from(c), CS::one()),
+    );
+  }
+
+  impl Circuit<Fp> for TestCircuit {
+    fn synthesize<CS: ConstraintSystem<Fp>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+      let a = AllocatedNum::alloc(cs.namespace(|| "a"), || Ok(Fp::from(self.a)))?;
+      let b = AllocatedNum::alloc(cs.namespace(|| "b"), || Ok(Fp::from(self.b)))?;
+      match self.op {
+        Op::Range => enforce_range(cs.namespace(|| "range"), &a, self.n)?,
+        Op::Bits => {
+          let bits = num_to_le_bits(cs.namespace(|| "bits"), &a, self.n)?;
+          for (i, bit) in bits.iter().enumerate() {
+            cs.enforce(
+              || format!("check bit {}", i),
+              |lc| lc + bit.get_variable(),
+              |lc| lc + CS::one(),
+              |lc| lc + (Fp::from((self.a >> i) & 1), CS::one()),
+            );
+          }
+          let packed = pack_bits(cs.namespace(|| "pack"), &bits)?;
+          assert_eq!(packed.len(), 1);
+          enforce_const(cs.namespace(|| "check packed"), &packed[0], self.a);
+        }
+        Op::Lt | Op::Le => {
+          let (bit, expected) = match self.op {
+            Op::Lt => (
+              alloc_num_lt(cs.namespace(|| "lt"), &a, &b, self.n)?,
+              self.a < self.b,
+            ),
+            _ => (
+              alloc_num_le(cs.namespace(|| "le"), &a, &b, self.n)?,
+              self.a <= self.b,
+            ),
+          };
+          cs.enforce(
+            || "check result",
+            |lc| lc + bit.get_variable(),
+            |lc| lc + CS::one(),
+            |lc| lc + (Fp::from(expected as u64), CS::one()),
+          );
+        }
+        Op::LeConst => {
+          let bits = (0..self.n)
+            .map(|i| {
+              AllocatedBit::alloc(
+                cs.namespace(|| format!("bit {}", i)),
+                Some((self.a >> i) & 1 == 1),
+              )
+            })
+            .collect::<Result<Vec<AllocatedBit>, SynthesisError>>()?;
+          let bound = (0..self.n)
+            .map(|i| (self.b >> i) & 1 == 1)
+            .collect::<Vec<bool>>();
+          enforce_bits_le_constant(cs.namespace(|| "le const"), &bits, &bound)?;
+        }
+        Op::DivRem => {
+          let (q, r) = alloc_div_rem(cs.namespace(|| "div rem"), &a, &b, self.n)?;
+          if let (Some(q_native), Some(r_native)) =
+            (self.a.checked_div(self.b), self.a.checked_rem(self.b))
+          {
+            enforce_const(cs.namespace(|| "check q"), &q, q_native);
+            enforce_const(cs.namespace(|| "check r"), &r, r_native);
+          }
+        }
+      }
+      Ok(())
+    }
+  }
+
+  fn is_sat(circuit: TestCircuit) -> bool {
+    let mut cs: ShapeCS<G> = ShapeCS::new();
+    let _ = circuit.clone().synthesize(&mut cs);
+    let shape = cs.r1cs_shape();
+    let gens = cs.r1cs_gens();
+
+    let mut cs: SatisfyingAssignment<G> = SatisfyingAssignment::new();
+    if circuit.synthesize(&mut cs).is_err() {
+      return false;
+    }
+    let (inst, witness) = cs.r1cs_instance_and_witness(&shape, &gens).unwrap();
+    shape.is_sat(&gens, &inst, &witness).is_ok()
+  }
+
+  fn random_pairs() -> Vec<(u64, u64)> {
+    let max = (1 << N) - 1;
+    let mut pairs = vec![
+      (0, 0),
+      (0, 1),
+      (1, 0),
+      (max, max),
+      (max - 1, max),
+      (max, 0),
+      (0, max),
+    ];
+    for _ in 0..4 {
+      let a = OsRng.next_u64() & max;
+      pairs.push((a, a));
+      pairs.push((a, OsRng.next_u64() & max));
+    }
+    pairs
+  }
+
+  #[test]
+  fn test_range_and_bits() {
+    for op in [Op::Range, Op::Bits] {
+      for a in [0, 1, (1 << N) - 1, OsRng.next_u64() & ((1 << N) - 1)] {
+        assert!(is_sat(TestCircuit { op, a, b: 0, n: N }));
+      }
+      for a in [1 << N, u64::MAX] {
+        assert!(!is_sat(TestCircuit { op, a, b: 0, n: N }));
+      }
+    }
+    // bits that take up a whole u64
+    let a = OsRng.next_u64();
+    assert!(is_sat(TestCircuit {
+      op: Op::Bits,
+      a,
+      b: 0,
+      n: 64
+    }));
+  }
+
+  #[test]
+  fn test_comparisons() {
+    for (a, b) in random_pairs() {
+      assert!(is_sat(TestCircuit {
+        op: Op::Lt,
+        a,
+        b,
+        n: N
+      }));
+      assert!(is_sat(TestCircuit {
+        op: Op::Le,
+        a,
+        b,
+        n: N
+      }));
+      assert_eq!(
+        is_sat(TestCircuit {
+          op: Op::LeConst,
+          a,
+          b,
+          n: N
+        }),
+        a <= b
+      );
+    }
+  }
+
+  #[test]
+  fn test_div_rem() {
+    for (a, b) in random_pairs() {
+      assert_eq!(
+        is_sat(TestCircuit {
+          op: Op::DivRem,
+          a,
+          b,
+          n: N
+        }),
+        b != 0
+      );
+    }
+  }
+}
@@ -4,39 +4,59 @@
 #![deny(missing_docs)]
 
 // private modules
-mod bellperson;
 mod circuit;
-mod commitments;
 mod constants;
-mod nifs;
+mod lookup;
+mod parallel;
+#[cfg(not(feature = "bench-internals"))]
+mod polynomial;
 mod poseidon;
-mod r1cs;
+mod serde_utils;
 
 // public modules
+pub mod bellperson;
+pub mod ccs;
+pub mod circom;
+pub mod commitments;
+pub mod cyclefold;
 pub mod errors;
+pub mod folding;
 pub mod gadgets;
+pub mod nifs;
+pub mod nimfs;
 pub mod pasta;
+#[cfg(feature = "bench-internals")]
+pub mod polynomial;
+pub mod profile;
+pub mod r1cs;
+pub mod security;
 pub mod snark;
 pub mod spartan_with_ipa_pc;
 pub mod traits;
+pub mod transcript;
 
 use crate::bellperson::{
   r1cs::{NovaShape, NovaWitness},
   shape_cs::ShapeCS,
   solver::SatisfyingAssignment,
 };
-use ::bellperson::{Circuit, ConstraintSystem};
+use ::bellperson::{gadgets::num::AllocatedNum, Circuit, ConstraintSystem};
+use circom::CircomR1CS;
 use circuit::{NIFSVerifierCircuit, NIFSVerifierCircuitInputs, NIFSVerifierCircuitParams};
-use constants::{BN_LIMB_WIDTH, BN_N_LIMBS};
 use core::marker::PhantomData;
-use errors::NovaError;
-use ff::Field;
+use errors::{Curve, NovaError, VerificationFailure};
+use ff::{Field, PrimeField};
 use gadgets::utils::scalar_as_base;
 use nifs::NIFS;
 use poseidon::ROConstantsCircuit; // TODO: make this a trait so we can use it without the concrete implementation
+use profile::CircuitProfile;
 use r1cs::{
-  R1CSGens, R1CSInstance, R1CSShape, R1CSWitness, RelaxedR1CSInstance, RelaxedR1CSWitness,
+  digest_to_scalar, R1CSGens, R1CSInstance, R1CSShape, R1CSWitness, RelaxedR1CSInstance,
+  RelaxedR1CSWitness,
 };
+use security::SecurityConfig;
+use serde::{Deserialize, Serialize};
+use serde_utils::scalar;
 use snark::RelaxedR1CSSNARKTrait;
 use traits::{AbsorbInROTrait, Group, HashFuncConstantsTrait, HashFuncTrait, StepCircuit};
 
@@ -65,6 +85,8 @@ where
   c_secondary: C2,
   params_primary: NIFSVerifierCircuitParams,
   params_secondary: NIFSVerifierCircuitParams,
+  config: SecurityConfig,
+  digest: G1::Scalar,
 }
 
 impl<G1, G2, C1, C2> PublicParams<G1, G2, C1, C2>
@@ -75,17 +97,66 @@ where
   C2: StepCircuit<G2::Scalar> + Clone,
 {
   /// Create a new `PublicParams`
+  ///
+  /// # Panics
+  ///
+  /// Panics if a step circuit fails to synthesize; use `setup_with_config` to get the error.
   pub fn setup(c_primary: C1, c_secondary: C2) -> Self {
-    let params_primary = NIFSVerifierCircuitParams::new(BN_LIMB_WIDTH, BN_N_LIMBS, true);
-    let params_secondary = NIFSVerifierCircuitParams::new(BN_LIMB_WIDTH, BN_N_LIMBS, false);
+    Self::setup_with_tag(c_primary, c_secondary, b"")
+  }
+
+  /// Create a new `PublicParams` for an application with the domain-separation tag `tag`.
+  /// The tag extends the labels from which the generators of the circuits and of the IPA are
+  /// derived, and the label of the transcript of the compressed SNARK. It is also bound in the
+  /// digest of the parameters, which every random oracle and transcript absorbs first, so
+  /// the parameters and the proofs of applications with different tags are independent.
+  /// The empty tag gives the parameters of `setup`. The folding accumulator of `folding` and
+  /// the multi-folding scheme of `nimfs` take no tag.
+  ///
+  /// # Panics
+  ///
+  /// Panics if a step circuit fails to synthesize; use `setup_with_config` to get the error.
+  pub fn setup_with_tag(c_primary: C1, c_secondary: C2, tag: &[u8]) -> Self {
+    Self::setup_inner(c_primary, c_secondary, tag, &SecurityConfig::default())
+      .expect("the step circuits must synthesize without witnesses")
+  }
+
+  /// Create a new `PublicParams` for the domain-separation tag `tag` with the security
+  /// configuration `config`, which is bound in the digest of the parameters, so proofs
+  /// produced under one configuration do not verify under another.
+  /// Returns an error if `config` is out of the supported bounds, or `NovaError::Synthesis`
+  /// if a step circuit fails to synthesize.
+  pub fn setup_with_config(
+    c_primary: C1,
+    c_secondary: C2,
+    tag: &[u8],
+    config: &SecurityConfig,
+  ) -> Result<Self, NovaError> {
+    config.validate()?;
+    Self::setup_inner(c_primary, c_secondary, tag, config)
+  }
+
+  fn setup_inner(
+    c_primary: C1,
+    c_secondary: C2,
+    tag: &[u8],
+    config: &SecurityConfig,
+  ) -> Result<Self, NovaError> {
+    // each circuit folds the instances of the other, which carry a lookup argument if its step circuit has a table
+    let (table_primary, table_secondary) = (c_primary.lookup_table(), c_secondary.lookup_table());
+    let (limb_width, n_limbs) = (config.limb_width, config.n_limbs);
+    let params_primary =
+      NIFSVerifierCircuitParams::new(limb_width, n_limbs, true, !table_secondary.is_empty());
+    let params_secondary =
+      NIFSVerifierCircuitParams::new(limb_width, n_limbs, false, !table_primary.is_empty());
 
-    let ro_consts_primary: ROConstants<G1> = ROConstants::<G1>::new();
-    let ro_consts_secondary: ROConstants<G2> = ROConstants::<G2>::new();
+    let ro_consts_primary: ROConstants<G1> = ROConstants::<G1>::new_with_config(config);
+    let ro_consts_secondary: ROConstants<G2> = ROConstants::<G2>::new_with_config(config);
 
     let ro_consts_circuit_primary: ROConstantsCircuit<<G2 as Group>::Base> =
-      ROConstantsCircuit::new();
+      ROConstantsCircuit::new_with_config(config);
     let ro_consts_circuit_secondary: ROConstantsCircuit<<G1 as Group>::Base> =
-      ROConstantsCircuit::new();
+      ROConstantsCircuit::new_with_config(config);
 
     // Initialize gens for the primary
     let circuit_primary: NIFSVerifierCircuit<G2, C1> = NIFSVerifierCircuit::new(
@@ -95,8 +166,9 @@ where
       ro_consts_circuit_primary.clone(),
     );
     let mut cs: ShapeCS<G1> = ShapeCS::new();
-    let _ = circuit_primary.synthesize(&mut cs);
-    let (r1cs_shape_primary, r1cs_gens_primary) = (cs.r1cs_shape(), cs.r1cs_gens());
+    let lookups = circuit_primary.synthesize_with_lookups(&mut cs)?;
+    let (r1cs_shape_primary, r1cs_gens_primary) =
+      shape_and_gens(&cs, &table_primary, &lookups, tag);
     let r1cs_shape_padded_primary = r1cs_shape_primary.pad();
 
     // Initialize gens for the secondary
@@ -107,11 +179,33 @@ where
       ro_consts_circuit_secondary.clone(),
     );
     let mut cs: ShapeCS<G2> = ShapeCS::new();
-    let _ = circuit_secondary.synthesize(&mut cs);
-    let (r1cs_shape_secondary, r1cs_gens_secondary) = (cs.r1cs_shape(), cs.r1cs_gens());
+    let lookups = circuit_secondary.synthesize_with_lookups(&mut cs)?;
+    let (r1cs_shape_secondary, r1cs_gens_secondary) =
+      shape_and_gens(&cs, &table_secondary, &lookups, tag);
     let r1cs_shape_padded_secondary = r1cs_shape_secondary.pad();
 
-    Self {
+    let pp_serialized = PublicParamsSerialized {
+      tag: tag.to_vec(),
+      config: *config,
+      shapes: [
+        [&r1cs_shape_primary, &r1cs_shape_padded_primary]
+          .map(|S| S.get_digest().to_repr().as_ref().to_vec()),
+        [&r1cs_shape_secondary, &r1cs_shape_padded_secondary]
+          .map(|S| S.get_digest().to_repr().as_ref().to_vec()),
+      ]
+      .concat(),
+      gens: vec![r1cs_gens_primary.to_bytes(), r1cs_gens_secondary.to_bytes()],
+      ro_consts: vec![
+        ro_consts_primary.to_bytes(),
+        ro_consts_circuit_primary.to_bytes(),
+        ro_consts_secondary.to_bytes(),
+        ro_consts_circuit_secondary.to_bytes(),
+      ],
+      params: vec![params_primary.clone(), params_secondary.clone()],
+    };
+    let digest = digest_to_scalar(&bincode::serialize(&pp_serialized).unwrap());
+
+    Ok(Self {
       ro_consts_primary,
       ro_consts_circuit_primary,
       r1cs_gens_primary,
@@ -126,11 +220,164 @@ where
       c_secondary,
       params_primary,
       params_secondary,
-    }
+      config: *config,
+      digest,
+    })
+  }
+
+  /// Returns the digest of the public parameters, which binds the tag, the security
+  /// configuration, the shapes and the generators of both circuits, the constants of the random
+  /// oracles and the parameters of the verifier circuits. Proofs are bound to it, so a verifier
+  /// can pin the parameters that it trusts by comparing it to a known value.
+  ///
+  /// The digest is a SHA3-256 hash of a bincode serialization, truncated to 250 bits, so it
+  /// depends on that layout and is not stable across releases. It binds the shape of the step
+  /// circuits, not their `compute` function.
+  pub fn digest(&self) -> G1::Scalar {
+    self.digest
+  }
+
+  /// Returns the security configuration of the public parameters
+  pub fn security_config(&self) -> &SecurityConfig {
+    &self.config
+  }
+
+  /// Returns the number of constraints in the primary and the secondary circuits
+  pub fn num_constraints(&self) -> (usize, usize) {
+    (
+      self.r1cs_shape_primary.num_cons,
+      self.r1cs_shape_secondary.num_cons,
+    )
+  }
+
+  /// Returns the number of variables in the primary and the secondary circuits
+  pub fn num_variables(&self) -> (usize, usize) {
+    (
+      self.r1cs_shape_primary.num_vars,
+      self.r1cs_shape_secondary.num_vars,
+    )
+  }
+
+  /// Returns the constraints of the primary and the secondary circuits, each made of
+  /// the verifier circuit and the step circuit, with the wires laid out as in Circom
+  pub fn circom_r1cs(&self) -> (CircomR1CS<G1::Scalar>, CircomR1CS<G2::Scalar>) {
+    (
+      CircomR1CS::from_shape(&self.r1cs_shape_primary),
+      CircomR1CS::from_shape(&self.r1cs_shape_secondary),
+    )
+  }
+
+  /// Returns the constraints and the auxiliary variables of each namespace of the primary and
+  /// the secondary circuits. The step circuit is synthesized in the namespace `F`.
+  /// The constraints of a lookup argument are not in any namespace, so they are not counted.
+  pub fn profile(&self) -> (CircuitProfile, CircuitProfile) {
+    let circuit_primary: NIFSVerifierCircuit<G2, C1> = NIFSVerifierCircuit::new(
+      self.params_primary.clone(),
+      None,
+      self.c_primary.clone(),
+      self.ro_consts_circuit_primary.clone(),
+    );
+    let mut cs: ShapeCS<G1> = ShapeCS::new();
+    let _ = circuit_primary.synthesize_with_lookups(&mut cs);
+    let profile_primary = cs.profile();
+
+    let circuit_secondary: NIFSVerifierCircuit<G1, C2> = NIFSVerifierCircuit::new(
+      self.params_secondary.clone(),
+      None,
+      self.c_secondary.clone(),
+      self.ro_consts_circuit_secondary.clone(),
+    );
+    let mut cs: ShapeCS<G2> = ShapeCS::new();
+    let _ = circuit_secondary.synthesize_with_lookups(&mut cs);
+    let profile_secondary = cs.profile();
+
+    (profile_primary, profile_secondary)
+  }
+}
+
+/// The parts of `PublicParams` that its digest binds, with the parameters `P` of the circuits.
+/// The CycleFold recursion computes the digest of its parameters the same way.
+#[derive(Serialize)]
+pub(crate) struct PublicParamsSerialized<P: Serialize> {
+  pub(crate) tag: Vec<u8>,
+  pub(crate) config: SecurityConfig,
+  pub(crate) shapes: Vec<Vec<u8>>,
+  pub(crate) gens: Vec<Vec<u8>>,
+  pub(crate) ro_consts: Vec<Vec<u8>>,
+  pub(crate) params: Vec<P>,
+}
+
+/// Returns the shape and the generators for the domain-separation tag `tag` of a circuit
+/// synthesized in `cs`, along with a lookup argument for the variables in `lookups`
+/// if the step circuit has a table
+fn shape_and_gens<G: Group>(
+  cs: &ShapeCS<G>,
+  table: &[G::Scalar],
+  lookups: &[AllocatedNum<G::Scalar>],
+  tag: &[u8],
+) -> (R1CSShape<G>, R1CSGens<G>) {
+  let shape = if table.is_empty() {
+    assert!(
+      lookups.is_empty(),
+      "the step circuit looks up values but has no lookup table"
+    );
+    cs.r1cs_shape()
+  } else {
+    cs.r1cs_shape_with_lookup(table, lookups)
+  };
+  let gens = R1CSGens::new_with_tag(shape.num_cons, shape.num_vars, tag);
+  (shape, gens)
+}
+
+/// Checks that the number of steps and the initial inputs that a proof claims are the supplied
+/// ones. The claims only tell a proof for another statement apart from an invalid proof: the
+/// verifier still checks the hashes against the supplied values.
+fn check_statement<F1: PartialEq, F2: PartialEq>(
+  claimed: (usize, F1, F2),
+  supplied: (usize, F1, F2),
+) -> Result<(), NovaError> {
+  if claimed.0 != supplied.0 {
+    return Err(NovaError::verify_failure(
+      VerificationFailure::NumStepsMismatch {
+        claimed: claimed.0,
+        supplied: supplied.0,
+      },
+    ));
+  }
+  if claimed.1 != supplied.1 {
+    return Err(NovaError::verify_failure(
+      VerificationFailure::InitialInputMismatch {
+        curve: Curve::Primary,
+      },
+    ));
+  }
+  if claimed.2 != supplied.2 {
+    return Err(NovaError::verify_failure(
+      VerificationFailure::InitialInputMismatch {
+        curve: Curve::Secondary,
+      },
+    ));
+  }
+  Ok(())
+}
+
+/// Checks that the instances of a circuit have the number of public IO of its shape
+fn check_io_length(curve: Curve, num_io: usize, lengths: &[usize]) -> Result<(), NovaError> {
+  match lengths.iter().find(|&&len| len != num_io) {
+    Some(&actual) => Err(NovaError::verify_failure(
+      VerificationFailure::IOLengthMismatch {
+        curve,
+        expected: num_io,
+        actual,
+      },
+    )),
+    None => Ok(()),
   }
 }
 
 /// A SNARK that proves the correct execution of an incremental computation
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
 pub struct RecursiveSNARK<G1, G2, C1, C2>
 where
   G1: Group<Base = <G2 as Group>::Scalar>,
@@ -146,7 +393,14 @@ where
   r_U_secondary: RelaxedR1CSInstance<G2>,
   l_w_secondary: R1CSWitness<G2>,
   l_u_secondary: R1CSInstance<G2>,
+  num_steps: usize,
+  #[serde(with = "scalar")]
+  z0_primary: G1::Scalar,
+  #[serde(with = "scalar")]
+  z0_secondary: G2::Scalar,
+  #[serde(with = "scalar")]
   zn_primary: G1::Scalar,
+  #[serde(with = "scalar")]
   zn_secondary: G2::Scalar,
   _p_c1: PhantomData<C1>,
   _p_c2: PhantomData<C2>,
@@ -173,7 +427,7 @@ where
     // Execute the base case for the primary
     let mut cs_primary: SatisfyingAssignment<G1> = SatisfyingAssignment::new();
     let inputs_primary: NIFSVerifierCircuitInputs<G2> = NIFSVerifierCircuitInputs::new(
-      pp.r1cs_shape_secondary.get_digest(),
+      scalar_as_base::<G1>(pp.digest),
       G1::Scalar::zero(),
       z0_primary,
       None,
@@ -187,15 +441,23 @@ where
       pp.c_primary.clone(),
       pp.ro_consts_circuit_primary.clone(),
     );
-    let _ = circuit_primary.synthesize(&mut cs_primary);
-    let (u_primary, w_primary) = cs_primary
-      .r1cs_instance_and_witness(&pp.r1cs_shape_primary, &pp.r1cs_gens_primary)
-      .map_err(|_e| NovaError::UnSat)?;
+    let (u_primary, w_primary) = circuit_primary
+      .synthesize(&mut cs_primary)
+      .map_err(NovaError::from)
+      .and_then(|_| {
+        cs_primary.r1cs_instance_and_witness_with_key(
+          &pp.r1cs_shape_primary,
+          &pp.r1cs_gens_primary,
+          &pp.ro_consts_primary,
+          &pp.digest,
+        )
+      })
+      .map_err(|e| e.in_step(Curve::Primary, 0))?;
 
     // Execute the base case for the secondary
     let mut cs_secondary: SatisfyingAssignment<G2> = SatisfyingAssignment::new();
     let inputs_secondary: NIFSVerifierCircuitInputs<G1> = NIFSVerifierCircuitInputs::new(
-      pp.r1cs_shape_primary.get_digest(),
+      pp.digest,
       G2::Scalar::zero(),
       z0_secondary,
       None,
@@ -209,10 +471,18 @@ where
       pp.c_secondary.clone(),
       pp.ro_consts_circuit_secondary.clone(),
     );
-    let _ = circuit_secondary.synthesize(&mut cs_secondary);
-    let (u_secondary, w_secondary) = cs_secondary
-      .r1cs_instance_and_witness(&pp.r1cs_shape_secondary, &pp.r1cs_gens_secondary)
-      .map_err(|_e| NovaError::UnSat)?;
+    let (u_secondary, w_secondary) = circuit_secondary
+      .synthesize(&mut cs_secondary)
+      .map_err(NovaError::from)
+      .and_then(|_| {
+        cs_secondary.r1cs_instance_and_witness_with_key(
+          &pp.r1cs_shape_secondary,
+          &pp.r1cs_gens_secondary,
+          &pp.ro_consts_secondary,
+          &scalar_as_base::<G1>(pp.digest),
+        )
+      })
+      .map_err(|e| e.in_step(Curve::Secondary, 0))?;
 
     // execute the remaining steps, alternating between G1 and G2
     let mut l_w_primary = w_primary;
@@ -233,24 +503,32 @@ where
 
     let mut z_next_primary = z0_primary;
     let mut z_next_secondary = z0_secondary;
-    z_next_primary = pp.c_primary.compute(&z_next_primary);
-    z_next_secondary = pp.c_secondary.compute(&z_next_secondary);
+    z_next_primary = pp
+      .c_primary
+      .try_compute(&z_next_primary)
+      .map_err(|e| e.in_step(Curve::Primary, 0))?;
+    z_next_secondary = pp
+      .c_secondary
+      .try_compute(&z_next_secondary)
+      .map_err(|e| e.in_step(Curve::Secondary, 0))?;
 
     for i in 1..num_steps {
       // fold the secondary circuit's instance
       let (nifs_secondary, (r_U_next_secondary, r_W_next_secondary)) = NIFS::prove(
         &pp.r1cs_gens_secondary,
         &pp.ro_consts_secondary,
+        &scalar_as_base::<G1>(pp.digest),
         &pp.r1cs_shape_secondary,
         &r_U_secondary,
         &r_W_secondary,
         &l_u_secondary,
         &l_w_secondary,
-      )?;
+      )
+      .map_err(|e| e.in_step(Curve::Secondary, i))?;
 
       let mut cs_primary: SatisfyingAssignment<G1> = SatisfyingAssignment::new();
       let inputs_primary: NIFSVerifierCircuitInputs<G2> = NIFSVerifierCircuitInputs::new(
-        pp.r1cs_shape_secondary.get_digest(),
+        scalar_as_base::<G1>(pp.digest),
         G1::Scalar::from(i as u64),
         z0_primary,
         Some(z_next_primary),
@@ -265,26 +543,35 @@ where
         pp.c_primary.clone(),
         pp.ro_consts_circuit_primary.clone(),
       );
-      let _ = circuit_primary.synthesize(&mut cs_primary);
-
-      (l_u_primary, l_w_primary) = cs_primary
-        .r1cs_instance_and_witness(&pp.r1cs_shape_primary, &pp.r1cs_gens_primary)
-        .map_err(|_e| NovaError::UnSat)?;
+      (l_u_primary, l_w_primary) = circuit_primary
+        .synthesize(&mut cs_primary)
+        .map_err(NovaError::from)
+        .and_then(|_| {
+          cs_primary.r1cs_instance_and_witness_with_key(
+            &pp.r1cs_shape_primary,
+            &pp.r1cs_gens_primary,
+            &pp.ro_consts_primary,
+            &pp.digest,
+          )
+        })
+        .map_err(|e| e.in_step(Curve::Primary, i))?;
 
       // fold the primary circuit's instance
       let (nifs_primary, (r_U_next_primary, r_W_next_primary)) = NIFS::prove(
         &pp.r1cs_gens_primary,
         &pp.ro_consts_primary,
+        &pp.digest,
         &pp.r1cs_shape_primary,
         &r_U_primary.clone(),
         &r_W_primary.clone(),
         &l_u_primary.clone(),
         &l_w_primary.clone(),
-      )?;
+      )
+      .map_err(|e| e.in_step(Curve::Primary, i))?;
 
       let mut cs_secondary: SatisfyingAssignment<G2> = SatisfyingAssignment::new();
       let inputs_secondary: NIFSVerifierCircuitInputs<G1> = NIFSVerifierCircuitInputs::new(
-        pp.r1cs_shape_primary.get_digest(),
+        pp.digest,
         G2::Scalar::from(i as u64),
         z0_secondary,
         Some(z_next_secondary),
@@ -299,19 +586,32 @@ where
         pp.c_secondary.clone(),
         pp.ro_consts_circuit_secondary.clone(),
       );
-      let _ = circuit_secondary.synthesize(&mut cs_secondary);
-
-      (l_u_secondary, l_w_secondary) = cs_secondary
-        .r1cs_instance_and_witness(&pp.r1cs_shape_secondary, &pp.r1cs_gens_secondary)
-        .map_err(|_e| NovaError::UnSat)?;
+      (l_u_secondary, l_w_secondary) = circuit_secondary
+        .synthesize(&mut cs_secondary)
+        .map_err(NovaError::from)
+        .and_then(|_| {
+          cs_secondary.r1cs_instance_and_witness_with_key(
+            &pp.r1cs_shape_secondary,
+            &pp.r1cs_gens_secondary,
+            &pp.ro_consts_secondary,
+            &scalar_as_base::<G1>(pp.digest),
+          )
+        })
+        .map_err(|e| e.in_step(Curve::Secondary, i))?;
 
       // update the running instances and witnesses
       r_U_secondary = r_U_next_secondary;
       r_W_secondary = r_W_next_secondary;
       r_U_primary = r_U_next_primary;
       r_W_primary = r_W_next_primary;
-      z_next_primary = pp.c_primary.compute(&z_next_primary);
-      z_next_secondary = pp.c_secondary.compute(&z_next_secondary);
+      z_next_primary = pp
+        .c_primary
+        .try_compute(&z_next_primary)
+        .map_err(|e| e.in_step(Curve::Primary, i))?;
+      z_next_secondary = pp
+        .c_secondary
+        .try_compute(&z_next_secondary)
+        .map_err(|e| e.in_step(Curve::Secondary, i))?;
     }
 
     Ok(Self {
@@ -323,6 +623,9 @@ where
       r_U_secondary,
       l_w_secondary,
       l_u_secondary,
+      num_steps,
+      z0_primary,
+      z0_secondary,
       zn_primary: z_next_primary,
       zn_secondary: z_next_secondary,
       _p_c1: Default::default(),
@@ -330,6 +633,16 @@ where
     })
   }
 
+  /// Encodes the `RecursiveSNARK` with bincode
+  pub fn to_bytes(&self) -> Vec<u8> {
+    bincode::serialize(self).expect("a RecursiveSNARK is always serializable")
+  }
+
+  /// Decodes a `RecursiveSNARK` encoded by `to_bytes`
+  pub fn from_bytes(bytes: &[u8]) -> Result<Self, NovaError> {
+    Ok(bincode::deserialize(bytes)?)
+  }
+
   /// Verify the correctness of the `RecursiveSNARK`
   pub fn verify(
     &self,
@@ -340,29 +653,38 @@ where
   ) -> Result<(G1::Scalar, G2::Scalar), NovaError> {
     // number of steps cannot be zero
     if num_steps == 0 {
-      return Err(NovaError::ProofVerifyError);
+      return Err(NovaError::InvalidNumSteps);
     }
 
-    // check if the (relaxed) R1CS instances have two public outputs
-    if self.l_u_primary.X.len() != 2
-      || self.l_u_secondary.X.len() != 2
-      || self.r_U_primary.X.len() != 2
-      || self.r_U_secondary.X.len() != 2
-    {
-      return Err(NovaError::ProofVerifyError);
-    }
+    // check if the proof is for the supplied statement, before checking the proof itself
+    check_statement(
+      (self.num_steps, self.z0_primary, self.z0_secondary),
+      (num_steps, z0_primary, z0_secondary),
+    )?;
+
+    // check if the (relaxed) R1CS instances have two public outputs, and the IO of a lookup argument if any
+    check_io_length(
+      Curve::Primary,
+      pp.r1cs_shape_primary.num_io,
+      &[self.l_u_primary.X.len(), self.r_U_primary.X.len()],
+    )?;
+    check_io_length(
+      Curve::Secondary,
+      pp.r1cs_shape_secondary.num_io,
+      &[self.l_u_secondary.X.len(), self.r_U_secondary.X.len()],
+    )?;
 
     // check if the output hashes in R1CS instances point to the right running instances
     let (hash_primary, hash_secondary) = {
       let mut hasher = <G2 as Group>::HashFunc::new(pp.ro_consts_secondary.clone());
-      hasher.absorb(scalar_as_base::<G2>(pp.r1cs_shape_secondary.get_digest()));
+      hasher.absorb(pp.digest);
       hasher.absorb(G1::Scalar::from(num_steps as u64));
       hasher.absorb(z0_primary);
       hasher.absorb(self.zn_primary);
       self.r_U_secondary.absorb_in_ro(&mut hasher);
 
       let mut hasher2 = <G1 as Group>::HashFunc::new(pp.ro_consts_primary.clone());
-      hasher2.absorb(scalar_as_base::<G1>(pp.r1cs_shape_primary.get_digest()));
+      hasher2.absorb(scalar_as_base::<G1>(pp.digest));
       hasher2.absorb(G2::Scalar::from(num_steps as u64));
       hasher2.absorb(z0_secondary);
       hasher2.absorb(self.zn_secondary);
@@ -371,16 +693,25 @@ where
       (hasher.get_hash(), hasher2.get_hash())
     };
 
-    if hash_primary != scalar_as_base::<G1>(self.l_u_primary.X[1])
-      || hash_secondary != scalar_as_base::<G2>(self.l_u_secondary.X[1])
-    {
-      return Err(NovaError::ProofVerifyError);
+    if hash_primary != scalar_as_base::<G1>(self.l_u_primary.X[1]) {
+      return Err(NovaError::verify_failure(
+        VerificationFailure::HashMismatch {
+          curve: Curve::Primary,
+        },
+      ));
+    }
+    if hash_secondary != scalar_as_base::<G2>(self.l_u_secondary.X[1]) {
+      return Err(NovaError::verify_failure(
+        VerificationFailure::HashMismatch {
+          curve: Curve::Secondary,
+        },
+      ));
     }
 
     // check the satisfiability of the provided instances
-    let ((res_r_primary, res_l_primary), (res_r_secondary, res_l_secondary)) = rayon::join(
+    let ((res_r_primary, res_l_primary), (res_r_secondary, res_l_secondary)) = parallel::join(
       || {
-        rayon::join(
+        parallel::join(
           || {
             pp.r1cs_shape_primary.is_sat_relaxed(
               &pp.r1cs_gens_primary,
@@ -398,7 +729,7 @@ where
         )
       },
       || {
-        rayon::join(
+        parallel::join(
           || {
             pp.r1cs_shape_secondary.is_sat_relaxed(
               &pp.r1cs_gens_secondary,
@@ -418,16 +749,210 @@ where
     );
 
     // check the returned res objects
-    res_r_primary?;
-    res_l_primary?;
-    res_r_secondary?;
-    res_l_secondary?;
+    let relaxed_unsat = |curve| {
+      move |e| {
+        NovaError::verify_failure(VerificationFailure::RelaxedInstanceUnSat {
+          curve,
+          source: Box::new(e),
+        })
+      }
+    };
+    let fresh_unsat = |curve| {
+      move |e| {
+        NovaError::verify_failure(VerificationFailure::FreshInstanceUnSat {
+          curve,
+          source: Box::new(e),
+        })
+      }
+    };
+    res_r_primary.map_err(relaxed_unsat(Curve::Primary))?;
+    res_l_primary.map_err(fresh_unsat(Curve::Primary))?;
+    res_r_secondary.map_err(relaxed_unsat(Curve::Secondary))?;
+    res_l_secondary.map_err(fresh_unsat(Curve::Secondary))?;
 
     Ok((self.zn_primary, self.zn_secondary))
   }
 }
 
+/// The key with which `CompressedSNARK::prove_with_key` proves, made of the prover keys of the
+/// SNARKs of both circuits
+#[derive(Serialize, Deserialize)]
+#[serde(bound(
+  serialize = "S1::ProverKey: Serialize, S2::ProverKey: Serialize",
+  deserialize = "S1::ProverKey: Deserialize<'de>, S2::ProverKey: Deserialize<'de>"
+))]
+pub struct ProverKey<G1, G2, C1, C2, S1, S2>
+where
+  G1: Group<Base = <G2 as Group>::Scalar>,
+  G2: Group<Base = <G1 as Group>::Scalar>,
+  C1: StepCircuit<G1::Scalar>,
+  C2: StepCircuit<G2::Scalar>,
+  S1: RelaxedR1CSSNARKTrait<G1>,
+  S2: RelaxedR1CSSNARKTrait<G2>,
+{
+  pk_primary: S1::ProverKey,
+  pk_secondary: S2::ProverKey,
+  #[serde(with = "scalar")]
+  digest: G1::Scalar,
+  _p_c1: PhantomData<C1>,
+  _p_c2: PhantomData<C2>,
+}
+
+impl<G1, G2, C1, C2, S1, S2> ProverKey<G1, G2, C1, C2, S1, S2>
+where
+  G1: Group<Base = <G2 as Group>::Scalar>,
+  G2: Group<Base = <G1 as Group>::Scalar>,
+  C1: StepCircuit<G1::Scalar>,
+  C2: StepCircuit<G2::Scalar>,
+  S1: RelaxedR1CSSNARKTrait<G1>,
+  S2: RelaxedR1CSSNARKTrait<G2>,
+{
+  fn new(pp: &PublicParams<G1, G2, C1, C2>) -> Self {
+    let (pk_primary, pk_secondary) = parallel::join(
+      || {
+        S1::prover_key(
+          &pp.r1cs_gens_primary,
+          &pp.r1cs_shape_padded_primary,
+          &pp.digest,
+        )
+      },
+      || {
+        S2::prover_key(
+          &pp.r1cs_gens_secondary,
+          &pp.r1cs_shape_padded_secondary,
+          &scalar_as_base::<G1>(pp.digest),
+        )
+      },
+    );
+    Self {
+      pk_primary,
+      pk_secondary,
+      digest: pp.digest,
+      _p_c1: Default::default(),
+      _p_c2: Default::default(),
+    }
+  }
+
+  /// Returns the digest of the public parameters for which the key is produced
+  pub fn digest(&self) -> G1::Scalar {
+    self.digest
+  }
+
+  /// Encodes the key with bincode
+  pub fn to_bytes(&self) -> Vec<u8>
+  where
+    S1::ProverKey: Serialize,
+    S2::ProverKey: Serialize,
+  {
+    bincode::serialize(self).expect("a ProverKey is always serializable")
+  }
+
+  /// Decodes a key encoded by `to_bytes`
+  pub fn from_bytes(bytes: &[u8]) -> Result<Self, NovaError>
+  where
+    S1::ProverKey: for<'de> Deserialize<'de>,
+    S2::ProverKey: for<'de> Deserialize<'de>,
+  {
+    Ok(bincode::deserialize(bytes)?)
+  }
+}
+
+/// The key with which `CompressedSNARK::verify_with_key` verifies, made of the verifier keys of
+/// the SNARKs of both circuits along with the shapes, the security configuration and the digest
+/// of the public parameters that the folding verifier needs
+#[derive(Serialize, Deserialize)]
+#[serde(bound(
+  serialize = "S1::VerifierKey: Serialize, S2::VerifierKey: Serialize",
+  deserialize = "S1::VerifierKey: Deserialize<'de>, S2::VerifierKey: Deserialize<'de>"
+))]
+pub struct VerifierKey<G1, G2, C1, C2, S1, S2>
+where
+  G1: Group<Base = <G2 as Group>::Scalar>,
+  G2: Group<Base = <G1 as Group>::Scalar>,
+  C1: StepCircuit<G1::Scalar>,
+  C2: StepCircuit<G2::Scalar>,
+  S1: RelaxedR1CSSNARKTrait<G1>,
+  S2: RelaxedR1CSSNARKTrait<G2>,
+{
+  r1cs_shape_primary: R1CSShape<G1>,
+  r1cs_shape_secondary: R1CSShape<G2>,
+  vk_primary: S1::VerifierKey,
+  vk_secondary: S2::VerifierKey,
+  config: SecurityConfig,
+  #[serde(with = "scalar")]
+  digest: G1::Scalar,
+  _p_c1: PhantomData<C1>,
+  _p_c2: PhantomData<C2>,
+}
+
+impl<G1, G2, C1, C2, S1, S2> VerifierKey<G1, G2, C1, C2, S1, S2>
+where
+  G1: Group<Base = <G2 as Group>::Scalar>,
+  G2: Group<Base = <G1 as Group>::Scalar>,
+  C1: StepCircuit<G1::Scalar>,
+  C2: StepCircuit<G2::Scalar>,
+  S1: RelaxedR1CSSNARKTrait<G1>,
+  S2: RelaxedR1CSSNARKTrait<G2>,
+{
+  fn new(pp: &PublicParams<G1, G2, C1, C2>) -> Self {
+    let (vk_primary, vk_secondary) = parallel::join(
+      || {
+        S1::verifier_key(
+          &pp.r1cs_gens_primary,
+          &pp.r1cs_shape_padded_primary,
+          &pp.digest,
+        )
+      },
+      || {
+        S2::verifier_key(
+          &pp.r1cs_gens_secondary,
+          &pp.r1cs_shape_padded_secondary,
+          &scalar_as_base::<G1>(pp.digest),
+        )
+      },
+    );
+    Self {
+      r1cs_shape_primary: pp.r1cs_shape_primary.clone(),
+      r1cs_shape_secondary: pp.r1cs_shape_secondary.clone(),
+      vk_primary,
+      vk_secondary,
+      config: pp.config,
+      digest: pp.digest,
+      _p_c1: Default::default(),
+      _p_c2: Default::default(),
+    }
+  }
+
+  /// Returns the digest of the public parameters for which the key is produced
+  pub fn digest(&self) -> G1::Scalar {
+    self.digest
+  }
+
+  /// Encodes the key with bincode
+  pub fn to_bytes(&self) -> Vec<u8>
+  where
+    S1::VerifierKey: Serialize,
+    S2::VerifierKey: Serialize,
+  {
+    bincode::serialize(self).expect("a VerifierKey is always serializable")
+  }
+
+  /// Decodes a key encoded by `to_bytes`
+  pub fn from_bytes(bytes: &[u8]) -> Result<Self, NovaError>
+  where
+    S1::VerifierKey: for<'de> Deserialize<'de>,
+    S2::VerifierKey: for<'de> Deserialize<'de>,
+  {
+    Ok(bincode::deserialize(bytes)?)
+  }
+}
+
 /// A SNARK that proves the knowledge of a valid `RecursiveSNARK`
+#[derive(Serialize, Deserialize)]
+#[serde(bound(
+  serialize = "S1: Serialize, S2: Serialize",
+  deserialize = "S1: Deserialize<'de>, S2: Deserialize<'de>"
+))]
 pub struct CompressedSNARK<G1, G2, C1, C2, S1, S2>
 where
   G1: Group<Base = <G2 as Group>::Scalar>,
@@ -447,7 +972,14 @@ where
   nifs_secondary: NIFS<G2>,
   f_W_snark_secondary: S2,
 
+  num_steps: usize,
+  #[serde(with = "scalar")]
+  z0_primary: G1::Scalar,
+  #[serde(with = "scalar")]
+  z0_secondary: G2::Scalar,
+  #[serde(with = "scalar")]
   zn_primary: G1::Scalar,
+  #[serde(with = "scalar")]
   zn_secondary: G2::Scalar,
 
   _p_c1: PhantomData<C1>,
@@ -463,17 +995,43 @@ where
   S1: RelaxedR1CSSNARKTrait<G1>,
   S2: RelaxedR1CSSNARKTrait<G2>,
 {
+  /// Creates the keys with which `CompressedSNARK`s are produced and verified for the public
+  /// parameters `pp`. The verifier key holds all that the verifier needs, so a verifier that
+  /// loads it does not need the public parameters or the step circuits.
+  pub fn setup(
+    pp: &PublicParams<G1, G2, C1, C2>,
+  ) -> (
+    ProverKey<G1, G2, C1, C2, S1, S2>,
+    VerifierKey<G1, G2, C1, C2, S1, S2>,
+  ) {
+    (ProverKey::new(pp), VerifierKey::new(pp))
+  }
+
   /// Create a new `CompressedSNARK`
   pub fn prove(
     pp: &PublicParams<G1, G2, C1, C2>,
     recursive_snark: &RecursiveSNARK<G1, G2, C1, C2>,
   ) -> Result<Self, NovaError> {
-    let (res_primary, res_secondary) = rayon::join(
+    Self::prove_with_key(pp, &ProverKey::new(pp), recursive_snark)
+  }
+
+  /// Create a new `CompressedSNARK` with a prover key that `setup` produced for `pp`
+  pub fn prove_with_key(
+    pp: &PublicParams<G1, G2, C1, C2>,
+    pk: &ProverKey<G1, G2, C1, C2, S1, S2>,
+    recursive_snark: &RecursiveSNARK<G1, G2, C1, C2>,
+  ) -> Result<Self, NovaError> {
+    if pk.digest != pp.digest {
+      return Err(NovaError::KeyMismatch);
+    }
+
+    let (res_primary, res_secondary) = parallel::join(
       // fold the primary circuit's instance
       || {
         NIFS::prove(
           &pp.r1cs_gens_primary,
           &pp.ro_consts_primary,
+          &pp.digest,
           &pp.r1cs_shape_primary,
           &recursive_snark.r_U_primary,
           &recursive_snark.r_W_primary,
@@ -486,6 +1044,7 @@ where
         NIFS::prove(
           &pp.r1cs_gens_secondary,
           &pp.ro_consts_secondary,
+          &scalar_as_base::<G1>(pp.digest),
           &pp.r1cs_shape_secondary,
           &recursive_snark.r_U_secondary,
           &recursive_snark.r_W_secondary,
@@ -498,24 +1057,18 @@ where
     let (nifs_primary, (f_U_primary, f_W_primary)) = res_primary?;
     let (nifs_secondary, (f_U_secondary, f_W_secondary)) = res_secondary?;
 
-    // produce a prover key for the SNARK
-    let (pk_primary, pk_secondary) = rayon::join(
-      || S1::prover_key(&pp.r1cs_gens_primary, &pp.r1cs_shape_padded_primary),
-      || S2::prover_key(&pp.r1cs_gens_secondary, &pp.r1cs_shape_padded_secondary),
-    );
-
     // create SNARKs proving the knowledge of f_W_primary and f_W_secondary
-    let (f_W_snark_primary, f_W_snark_secondary) = rayon::join(
+    let (f_W_snark_primary, f_W_snark_secondary) = parallel::join(
       || {
         S1::prove(
-          &pk_primary,
+          &pk.pk_primary,
           &f_U_primary,
           &f_W_primary.pad(&pp.r1cs_shape_padded_primary), // pad the witness since shape was padded
         )
       },
       || {
         S2::prove(
-          &pk_secondary,
+          &pk.pk_secondary,
           &f_U_secondary,
           &f_W_secondary.pad(&pp.r1cs_shape_padded_secondary), // pad the witness since the shape was padded
         )
@@ -533,6 +1086,9 @@ where
       nifs_secondary,
       f_W_snark_secondary: f_W_snark_secondary?,
 
+      num_steps: recursive_snark.num_steps,
+      z0_primary: recursive_snark.z0_primary,
+      z0_secondary: recursive_snark.z0_secondary,
       zn_primary: recursive_snark.zn_primary,
       zn_secondary: recursive_snark.zn_secondary,
 
@@ -541,6 +1097,24 @@ where
     })
   }
 
+  /// Encodes the `CompressedSNARK` with bincode
+  pub fn to_bytes(&self) -> Vec<u8>
+  where
+    S1: Serialize,
+    S2: Serialize,
+  {
+    bincode::serialize(self).expect("a CompressedSNARK is always serializable")
+  }
+
+  /// Decodes a `CompressedSNARK` encoded by `to_bytes`
+  pub fn from_bytes(bytes: &[u8]) -> Result<Self, NovaError>
+  where
+    S1: for<'de> Deserialize<'de>,
+    S2: for<'de> Deserialize<'de>,
+  {
+    Ok(bincode::deserialize(bytes)?)
+  }
+
   /// Verify the correctness of the `CompressedSNARK`
   pub fn verify(
     &self,
@@ -549,31 +1123,56 @@ where
     z0_primary: G1::Scalar,
     z0_secondary: G2::Scalar,
   ) -> Result<(G1::Scalar, G2::Scalar), NovaError> {
+    self.verify_with_key(&VerifierKey::new(pp), num_steps, z0_primary, z0_secondary)
+  }
+
+  /// Verify the correctness of the `CompressedSNARK` with a verifier key produced by `setup`
+  pub fn verify_with_key(
+    &self,
+    vk: &VerifierKey<G1, G2, C1, C2, S1, S2>,
+    num_steps: usize,
+    z0_primary: G1::Scalar,
+    z0_secondary: G2::Scalar,
+  ) -> Result<(G1::Scalar, G2::Scalar), NovaError> {
+    // the constants of the random oracles are not part of the key, as they follow from its configuration
+    vk.config.validate()?;
+    let ro_consts_primary = ROConstants::<G1>::new_with_config(&vk.config);
+    let ro_consts_secondary = ROConstants::<G2>::new_with_config(&vk.config);
+
     // number of steps cannot be zero
     if num_steps == 0 {
-      return Err(NovaError::ProofVerifyError);
+      return Err(NovaError::InvalidNumSteps);
     }
 
-    // check if the (relaxed) R1CS instances have two public outputs
-    if self.l_u_primary.X.len() != 2
-      || self.l_u_secondary.X.len() != 2
-      || self.r_U_primary.X.len() != 2
-      || self.r_U_secondary.X.len() != 2
-    {
-      return Err(NovaError::ProofVerifyError);
-    }
+    // check if the proof is for the supplied statement, before checking the proof itself
+    check_statement(
+      (self.num_steps, self.z0_primary, self.z0_secondary),
+      (num_steps, z0_primary, z0_secondary),
+    )?;
+
+    // check if the (relaxed) R1CS instances have two public outputs, and the IO of a lookup argument if any
+    check_io_length(
+      Curve::Primary,
+      vk.r1cs_shape_primary.num_io,
+      &[self.l_u_primary.X.len(), self.r_U_primary.X.len()],
+    )?;
+    check_io_length(
+      Curve::Secondary,
+      vk.r1cs_shape_secondary.num_io,
+      &[self.l_u_secondary.X.len(), self.r_U_secondary.X.len()],
+    )?;
 
     // check if the output hashes in R1CS instances point to the right running instances
     let (hash_primary, hash_secondary) = {
-      let mut hasher = <G2 as Group>::HashFunc::new(pp.ro_consts_secondary.clone());
-      hasher.absorb(scalar_as_base::<G2>(pp.r1cs_shape_secondary.get_digest()));
+      let mut hasher = <G2 as Group>::HashFunc::new(ro_consts_secondary.clone());
+      hasher.absorb(vk.digest);
       hasher.absorb(G1::Scalar::from(num_steps as u64));
       hasher.absorb(z0_primary);
       hasher.absorb(self.zn_primary);
       self.r_U_secondary.absorb_in_ro(&mut hasher);
 
-      let mut hasher2 = <G1 as Group>::HashFunc::new(pp.ro_consts_primary.clone());
-      hasher2.absorb(scalar_as_base::<G1>(pp.r1cs_shape_primary.get_digest()));
+      let mut hasher2 = <G1 as Group>::HashFunc::new(ro_consts_primary.clone());
+      hasher2.absorb(scalar_as_base::<G1>(vk.digest));
       hasher2.absorb(G2::Scalar::from(num_steps as u64));
       hasher2.absorb(z0_secondary);
       hasher2.absorb(self.zn_secondary);
@@ -582,44 +1181,73 @@ where
       (hasher.get_hash(), hasher2.get_hash())
     };
 
-    if hash_primary != scalar_as_base::<G1>(self.l_u_primary.X[1])
-      || hash_secondary != scalar_as_base::<G2>(self.l_u_secondary.X[1])
-    {
-      return Err(NovaError::ProofVerifyError);
+    if hash_primary != scalar_as_base::<G1>(self.l_u_primary.X[1]) {
+      return Err(NovaError::verify_failure(
+        VerificationFailure::HashMismatch {
+          curve: Curve::Primary,
+        },
+      ));
+    }
+    if hash_secondary != scalar_as_base::<G2>(self.l_u_secondary.X[1]) {
+      return Err(NovaError::verify_failure(
+        VerificationFailure::HashMismatch {
+          curve: Curve::Secondary,
+        },
+      ));
     }
 
     // fold the running instance and last instance to get a folded instance
-    let f_U_primary = self.nifs_primary.verify(
-      &pp.ro_consts_primary,
-      &pp.r1cs_shape_primary,
-      &self.r_U_primary,
-      &self.l_u_primary,
-    )?;
-    let f_U_secondary = self.nifs_secondary.verify(
-      &pp.ro_consts_secondary,
-      &pp.r1cs_shape_secondary,
-      &self.r_U_secondary,
-      &self.l_u_secondary,
-    )?;
-
-    // produce a verifier key for the SNARK
-    let (vk_primary, vk_secondary) = rayon::join(
-      || S1::verifier_key(&pp.r1cs_gens_primary, &pp.r1cs_shape_padded_primary),
-      || S2::verifier_key(&pp.r1cs_gens_secondary, &pp.r1cs_shape_padded_secondary),
-    );
+    let f_U_primary = self
+      .nifs_primary
+      .verify(
+        &ro_consts_primary,
+        &vk.digest,
+        &vk.r1cs_shape_primary,
+        &self.r_U_primary,
+        &self.l_u_primary,
+      )
+      .map_err(|e| {
+        NovaError::verify_failure(VerificationFailure::Folding {
+          curve: Curve::Primary,
+          source: Box::new(e),
+        })
+      })?;
+    let f_U_secondary = self
+      .nifs_secondary
+      .verify(
+        &ro_consts_secondary,
+        &scalar_as_base::<G1>(vk.digest),
+        &vk.r1cs_shape_secondary,
+        &self.r_U_secondary,
+        &self.l_u_secondary,
+      )
+      .map_err(|e| {
+        NovaError::verify_failure(VerificationFailure::Folding {
+          curve: Curve::Secondary,
+          source: Box::new(e),
+        })
+      })?;
 
     // check the satisfiability of the folded instances using SNARKs proving the knowledge of their satisfying witnesses
-    let (res_primary, res_secondary) = rayon::join(
-      || self.f_W_snark_primary.verify(&vk_primary, &f_U_primary),
+    let (res_primary, res_secondary) = parallel::join(
+      || self.f_W_snark_primary.verify(&vk.vk_primary, &f_U_primary),
       || {
         self
           .f_W_snark_secondary
-          .verify(&vk_secondary, &f_U_secondary)
+          .verify(&vk.vk_secondary, &f_U_secondary)
       },
     );
 
-    res_primary?;
-    res_secondary?;
+    let snark_failure = |curve| {
+      move |e| {
+        NovaError::verify_failure(VerificationFailure::SNARK {
+          curve,
+          source: Box::new(e),
+        })
+      }
+    };
+    res_primary.map_err(snark_failure(Curve::Primary))?;
+    res_secondary.map_err(snark_failure(Curve::Secondary))?;
 
     Ok((self.zn_primary, self.zn_secondary))
   }
@@ -632,7 +1260,10 @@ mod tests {
   type G2 = pasta_curves::vesta::Point;
   type S1 = spartan_with_ipa_pc::RelaxedR1CSSNARK<G1>;
   type S2 = spartan_with_ipa_pc::RelaxedR1CSSNARK<G2>;
-  use ::bellperson::{gadgets::num::AllocatedNum, ConstraintSystem, SynthesisError};
+  use ::bellperson::{
+    gadgets::{num::AllocatedNum, Assignment},
+    ConstraintSystem, SynthesisError,
+  };
   use ff::PrimeField;
   use std::marker::PhantomData;
 
@@ -703,6 +1334,52 @@ mod tests {
     }
   }
 
+  #[derive(Clone, Debug)]
+  struct LookupCircuit<F: PrimeField> {
+    _p: PhantomData<F>,
+  }
+
+  impl<F> StepCircuit<F> for LookupCircuit<F>
+  where
+    F: PrimeField,
+  {
+    fn synthesize<CS: ConstraintSystem<F>>(
+      &self,
+      cs: &mut CS,
+      z: AllocatedNum<F>,
+    ) -> Result<AllocatedNum<F>, SynthesisError> {
+      self.synthesize_with_lookups(cs, z, &mut Vec::new())
+    }
+
+    fn compute(&self, z: &F) -> F {
+      *z + F::one()
+    }
+
+    fn lookup_table(&self) -> Vec<F> {
+      (0..256u64).map(F::from).collect()
+    }
+
+    fn synthesize_with_lookups<CS: ConstraintSystem<F>>(
+      &self,
+      cs: &mut CS,
+      z: AllocatedNum<F>,
+      lookups: &mut Vec<AllocatedNum<F>>,
+    ) -> Result<AllocatedNum<F>, SynthesisError> {
+      // `y = x + 1`, where `x` is in the table
+      let y = AllocatedNum::alloc(cs.namespace(|| "y"), || {
+        Ok(*z.get_value().get()? + F::one())
+      })?;
+      cs.enforce(
+        || "y = x + 1",
+        |lc| lc + z.get_variable() + CS::one(),
+        |lc| lc + CS::one(),
+        |lc| lc + y.get_variable(),
+      );
+      lookups.push(z);
+      Ok(y)
+    }
+  }
+
   #[test]
   fn test_ivc_trivial() {
     // produce public parameters
@@ -757,6 +1434,11 @@ mod tests {
       },
     );
 
+    // the step circuit is profiled in its own namespace
+    let (_, profile_secondary) = pp.profile();
+    let step = profile_secondary.get("F").unwrap();
+    assert_eq!((step.num_constraints, step.num_aux), (3, 3));
+
     let num_steps = 3;
 
     // produce a recursive SNARK
@@ -860,6 +1542,540 @@ mod tests {
     assert!(res.is_ok());
   }
 
+  #[test]
+  fn test_ivc_compression_with_poseidon_transcript() {
+    type C1 = TrivialTestCircuit<<G1 as Group>::Scalar>;
+    type C2 = CubicCircuit<<G2 as Group>::Scalar>;
+    type P1 = spartan_with_ipa_pc::RelaxedR1CSSNARK<G1, transcript::PoseidonTranscript<G1>>;
+    type P2 = spartan_with_ipa_pc::RelaxedR1CSSNARK<G2, transcript::PoseidonTranscript<G2>>;
+
+    let pp = PublicParams::<G1, G2, C1, C2>::setup(
+      TrivialTestCircuit {
+        _p: Default::default(),
+      },
+      CubicCircuit {
+        _p: Default::default(),
+      },
+    );
+
+    let num_steps = 2;
+    let z0_primary = <G1 as Group>::Scalar::one();
+    let z0_secondary = <G2 as Group>::Scalar::zero();
+    let recursive_snark = RecursiveSNARK::prove(&pp, num_steps, z0_primary, z0_secondary).unwrap();
+
+    // the compressed SNARK derives its challenges with Poseidon instead of Merlin
+    let compressed_snark =
+      CompressedSNARK::<_, _, _, _, P1, P2>::prove(&pp, &recursive_snark).unwrap();
+    assert!(compressed_snark
+      .verify(&pp, num_steps, z0_primary, z0_secondary)
+      .is_ok());
+
+    // a proof is bound to the transcript with which it was produced
+    let bytes = compressed_snark.to_bytes();
+    let compressed_snark = CompressedSNARK::<G1, G2, C1, C2, S1, S2>::from_bytes(&bytes).unwrap();
+    assert!(matches!(
+      compressed_snark.verify(&pp, num_steps, z0_primary, z0_secondary),
+      Err(NovaError::ProofVerifyError {
+        reason: VerificationFailure::SNARK { .. }
+      })
+    ));
+  }
+
+  #[test]
+  fn test_ivc_compression_verifier_circuits() {
+    use crate::bellperson::checking_cs::CheckingCS;
+    use ::bellperson::Circuit;
+    use spartan_with_ipa_pc::{
+      DeferredIPACheck, IPAGroupCircuit, IPAGroupInputs, SNARKVerifierCircuit,
+    };
+
+    type C1 = TrivialTestCircuit<<G1 as Group>::Scalar>;
+    type C2 = CubicCircuit<<G2 as Group>::Scalar>;
+    type P1 = spartan_with_ipa_pc::RelaxedR1CSSNARK<G1, transcript::PoseidonTranscript<G1>>;
+    type P2 = spartan_with_ipa_pc::RelaxedR1CSSNARK<G2, transcript::PoseidonTranscript<G2>>;
+
+    let pp = PublicParams::<G1, G2, C1, C2>::setup(
+      TrivialTestCircuit {
+        _p: Default::default(),
+      },
+      CubicCircuit {
+        _p: Default::default(),
+      },
+    );
+
+    let num_steps = 2;
+    let z0_primary = <G1 as Group>::Scalar::one();
+    let z0_secondary = <G2 as Group>::Scalar::zero();
+    let recursive_snark = RecursiveSNARK::prove(&pp, num_steps, z0_primary, z0_secondary).unwrap();
+    let compressed_snark =
+      CompressedSNARK::<_, _, _, _, P1, P2>::prove(&pp, &recursive_snark).unwrap();
+
+    // the SNARK for the folded primary instance is checked by the pair of verifier circuits
+    let f_U_primary = compressed_snark
+      .nifs_primary
+      .verify(
+        &pp.ro_consts_primary,
+        &pp.digest,
+        &pp.r1cs_shape_primary,
+        &compressed_snark.r_U_primary,
+        &compressed_snark.l_u_primary,
+      )
+      .unwrap();
+    let vk = P1::verifier_key(
+      &pp.r1cs_gens_primary,
+      &pp.r1cs_shape_padded_primary,
+      &pp.digest,
+    );
+    let snark = &compressed_snark.f_W_snark_primary;
+
+    let mut cs = CheckingCS::new();
+    SNARKVerifierCircuit::new(&vk, Some((&f_U_primary, snark)))
+      .synthesize(&mut cs)
+      .unwrap();
+    assert!(cs.is_sat().is_ok());
+    let io_verifier = cs.inputs().to_vec();
+
+    let mut cs = CheckingCS::new();
+    let inputs = IPAGroupInputs::new(&vk, &f_U_primary, snark).unwrap();
+    IPAGroupCircuit::new(&vk, Some(inputs))
+      .synthesize(&mut cs)
+      .unwrap();
+    assert!(cs.is_sat().is_ok());
+    let io_group = cs.inputs().to_vec();
+
+    let deferred = DeferredIPACheck::from_io(&vk, &io_verifier, &io_group).unwrap();
+    assert!(deferred.verify(&vk).is_ok());
+
+    // the verifier circuit rejects the proof for another instance
+    let mut cs = CheckingCS::new();
+    SNARKVerifierCircuit::new(&vk, Some((&compressed_snark.r_U_primary, snark)))
+      .synthesize(&mut cs)
+      .unwrap();
+    assert!(cs.is_sat().is_err());
+  }
+
+  #[test]
+  fn test_ivc_serialization() {
+    type C1 = TrivialTestCircuit<<G1 as Group>::Scalar>;
+    type C2 = CubicCircuit<<G2 as Group>::Scalar>;
+
+    let pp = PublicParams::<G1, G2, C1, C2>::setup(
+      TrivialTestCircuit {
+        _p: Default::default(),
+      },
+      CubicCircuit {
+        _p: Default::default(),
+      },
+    );
+
+    let num_steps = 2;
+    let z0_primary = <G1 as Group>::Scalar::one();
+    let z0_secondary = <G2 as Group>::Scalar::zero();
+    let recursive_snark = RecursiveSNARK::prove(&pp, num_steps, z0_primary, z0_secondary).unwrap();
+    let compressed_snark =
+      CompressedSNARK::<_, _, _, _, S1, S2>::prove(&pp, &recursive_snark).unwrap();
+
+    // the proofs survive a round trip through bincode and still verify
+    let bytes = recursive_snark.to_bytes();
+    let recursive_snark = RecursiveSNARK::<G1, G2, C1, C2>::from_bytes(&bytes).unwrap();
+    assert_eq!(recursive_snark.to_bytes(), bytes);
+    assert!(recursive_snark
+      .verify(&pp, num_steps, z0_primary, z0_secondary)
+      .is_ok());
+
+    let bytes = compressed_snark.to_bytes();
+    let compressed_snark = CompressedSNARK::<G1, G2, C1, C2, S1, S2>::from_bytes(&bytes).unwrap();
+    assert_eq!(compressed_snark.to_bytes(), bytes);
+    assert!(compressed_snark
+      .verify(&pp, num_steps, z0_primary, z0_secondary)
+      .is_ok());
+
+    // a truncated proof is rejected with a decoding error
+    assert!(matches!(
+      CompressedSNARK::<G1, G2, C1, C2, S1, S2>::from_bytes(&bytes[..bytes.len() - 1]),
+      Err(NovaError::Decoding { .. })
+    ));
+
+    // so do the keys, and a proof produced with the prover key verifies with the verifier key alone
+    let (pk, vk) = CompressedSNARK::<G1, G2, C1, C2, S1, S2>::setup(&pp);
+    let bytes = pk.to_bytes();
+    let pk = ProverKey::<G1, G2, C1, C2, S1, S2>::from_bytes(&bytes).unwrap();
+    assert_eq!(pk.to_bytes(), bytes);
+    let bytes = vk.to_bytes();
+    let vk = VerifierKey::<G1, G2, C1, C2, S1, S2>::from_bytes(&bytes).unwrap();
+    assert_eq!(vk.to_bytes(), bytes);
+    assert_eq!(vk.digest(), pp.digest());
+    let compressed_snark =
+      CompressedSNARK::<_, _, _, _, S1, S2>::prove_with_key(&pp, &pk, &recursive_snark).unwrap();
+    assert!(compressed_snark
+      .verify_with_key(&vk, num_steps, z0_primary, z0_secondary)
+      .is_ok());
+  }
+
+  #[test]
+  fn test_ivc_with_lookups() {
+    // produce public parameters
+    let pp = PublicParams::<
+      G1,
+      G2,
+      LookupCircuit<<G1 as Group>::Scalar>,
+      LookupCircuit<<G2 as Group>::Scalar>,
+    >::setup(
+      LookupCircuit {
+        _p: Default::default(),
+      },
+      LookupCircuit {
+        _p: Default::default(),
+      },
+    );
+
+    let num_steps = 3;
+
+    // produce a recursive SNARK
+    let res = RecursiveSNARK::prove(
+      &pp,
+      num_steps,
+      <G1 as Group>::Scalar::zero(),
+      <G2 as Group>::Scalar::from(100u64),
+    );
+    assert!(res.is_ok());
+    let recursive_snark = res.unwrap();
+
+    // verify the recursive SNARK
+    let res = recursive_snark.verify(
+      &pp,
+      num_steps,
+      <G1 as Group>::Scalar::zero(),
+      <G2 as Group>::Scalar::from(100u64),
+    );
+    assert!(res.is_ok());
+    assert_eq!(
+      res.unwrap(),
+      (
+        <G1 as Group>::Scalar::from(3u64),
+        <G2 as Group>::Scalar::from(103u64)
+      )
+    );
+
+    // produce a compressed SNARK
+    let res = CompressedSNARK::<_, _, _, _, S1, S2>::prove(&pp, &recursive_snark);
+    assert!(res.is_ok());
+    let compressed_snark = res.unwrap();
+
+    // verify the compressed SNARK
+    let res = compressed_snark.verify(
+      &pp,
+      num_steps,
+      <G1 as Group>::Scalar::zero(),
+      <G2 as Group>::Scalar::from(100u64),
+    );
+    assert!(res.is_ok());
+
+    // the shapes of the lookup arguments survive a round trip of the verifier key
+    let (_, vk) = CompressedSNARK::<_, _, _, _, S1, S2>::setup(&pp);
+    let vk = VerifierKey::from_bytes(&vk.to_bytes()).unwrap();
+    let res = compressed_snark.verify_with_key(
+      &vk,
+      num_steps,
+      <G1 as Group>::Scalar::zero(),
+      <G2 as Group>::Scalar::from(100u64),
+    );
+    assert!(res.is_ok());
+
+    // a step whose input is not in the table cannot be proven
+    let res = RecursiveSNARK::prove(
+      &pp,
+      num_steps,
+      <G1 as Group>::Scalar::from(254u64),
+      <G2 as Group>::Scalar::zero(),
+    );
+    assert!(matches!(
+      res.err(),
+      Some(NovaError::Step {
+        curve: Curve::Primary,
+        ..
+      })
+    ));
+  }
+
+  #[test]
+  fn test_pp_digest() {
+    type C1 = TrivialTestCircuit<<G1 as Group>::Scalar>;
+    type C2 = CubicCircuit<<G2 as Group>::Scalar>;
+    let setup = |tag: &[u8]| {
+      PublicParams::<G1, G2, C1, C2>::setup_with_tag(
+        TrivialTestCircuit {
+          _p: Default::default(),
+        },
+        CubicCircuit {
+          _p: Default::default(),
+        },
+        tag,
+      )
+    };
+
+    // the digest is deterministic and fits in NUM_HASH_BITS bits
+    let pp = setup(b"");
+    assert_eq!(pp.digest(), setup(b"").digest());
+    assert!(pp.digest().to_repr().as_ref()[31] < 4);
+
+    // the digest depends on the domain-separation tag
+    assert_ne!(pp.digest(), setup(b"app").digest());
+
+    // the digest depends on the circuits
+    let pp_other = PublicParams::<G1, G2, C1, TrivialTestCircuit<<G2 as Group>::Scalar>>::setup(
+      TrivialTestCircuit {
+        _p: Default::default(),
+      },
+      TrivialTestCircuit {
+        _p: Default::default(),
+      },
+    );
+    assert_ne!(pp.digest(), pp_other.digest());
+  }
+
+  #[test]
+  fn test_ivc_verification_failures() {
+    type C1 = TrivialTestCircuit<<G1 as Group>::Scalar>;
+    type C2 = CubicCircuit<<G2 as Group>::Scalar>;
+
+    let pp = PublicParams::<G1, G2, C1, C2>::setup(
+      TrivialTestCircuit {
+        _p: Default::default(),
+      },
+      CubicCircuit {
+        _p: Default::default(),
+      },
+    );
+
+    let num_steps = 2;
+    let z0_primary = <G1 as Group>::Scalar::one();
+    let z0_secondary = <G2 as Group>::Scalar::zero();
+    let prove =
+      |num_steps| RecursiveSNARK::prove(&pp, num_steps, z0_primary, z0_secondary).unwrap();
+    let reason = |res: Result<_, NovaError>| match res {
+      Err(NovaError::ProofVerifyError { reason }) => reason,
+      _ => panic!("expected a verification failure"),
+    };
+
+    // a valid proof for another statement is told apart from an invalid proof
+    let recursive_snark = prove(num_steps);
+    let failure = reason(recursive_snark.verify(&pp, num_steps + 1, z0_primary, z0_secondary));
+    assert!(failure.is_statement_mismatch());
+    assert_eq!(
+      failure,
+      VerificationFailure::NumStepsMismatch {
+        claimed: num_steps,
+        supplied: num_steps + 1,
+      }
+    );
+    let failure =
+      reason(recursive_snark.verify(&pp, num_steps, z0_primary, <G2 as Group>::Scalar::one()));
+    assert!(failure.is_statement_mismatch());
+    assert_eq!(
+      failure,
+      VerificationFailure::InitialInputMismatch {
+        curve: Curve::Secondary,
+      }
+    );
+
+    // the claims of a proof are not trusted
+    let mut tampered = prove(num_steps);
+    tampered.num_steps = num_steps + 1;
+    let failure = reason(tampered.verify(&pp, num_steps + 1, z0_primary, z0_secondary));
+    assert!(!failure.is_statement_mismatch());
+    assert_eq!(
+      failure,
+      VerificationFailure::HashMismatch {
+        curve: Curve::Primary,
+      }
+    );
+
+    // an unsatisfied instance is reported with its curve
+    let mut tampered = prove(num_steps);
+    tampered.l_w_secondary.W[0] += <G2 as Group>::Scalar::one();
+    assert!(matches!(
+      reason(tampered.verify(&pp, num_steps, z0_primary, z0_secondary)),
+      VerificationFailure::FreshInstanceUnSat {
+        curve: Curve::Secondary,
+        ..
+      }
+    ));
+    let mut tampered = prove(num_steps);
+    tampered.r_W_primary.W[0] += <G1 as Group>::Scalar::one();
+    assert!(matches!(
+      reason(tampered.verify(&pp, num_steps, z0_primary, z0_secondary)),
+      VerificationFailure::RelaxedInstanceUnSat {
+        curve: Curve::Primary,
+        ..
+      }
+    ));
+
+    // a compressed SNARK whose SNARK proves another folded instance is rejected by the SNARK
+    let mut compressed_snark =
+      CompressedSNARK::<_, _, _, _, S1, S2>::prove(&pp, &recursive_snark).unwrap();
+    assert!(reason(compressed_snark.verify(
+      &pp,
+      num_steps,
+      <G1 as Group>::Scalar::zero(),
+      z0_secondary
+    ))
+    .is_statement_mismatch());
+    let other = CompressedSNARK::<_, _, _, _, S1, S2>::prove(&pp, &prove(num_steps + 1)).unwrap();
+    compressed_snark.f_W_snark_secondary = other.f_W_snark_secondary;
+    assert!(matches!(
+      reason(compressed_snark.verify(&pp, num_steps, z0_primary, z0_secondary)),
+      VerificationFailure::SNARK {
+        curve: Curve::Secondary,
+        ..
+      }
+    ));
+  }
+
+  #[test]
+  fn test_ivc_with_tag() {
+    type C1 = TrivialTestCircuit<<G1 as Group>::Scalar>;
+    type C2 = CubicCircuit<<G2 as Group>::Scalar>;
+    let setup = |tag: &[u8]| {
+      PublicParams::<G1, G2, C1, C2>::setup_with_tag(
+        TrivialTestCircuit {
+          _p: Default::default(),
+        },
+        CubicCircuit {
+          _p: Default::default(),
+        },
+        tag,
+      )
+    };
+    let pp_a = setup(b"app-a");
+    let pp_b = setup(b"app-b");
+
+    let num_steps = 2;
+    let z0_primary = <G1 as Group>::Scalar::one();
+    let z0_secondary = <G2 as Group>::Scalar::zero();
+    let recursive_snark =
+      RecursiveSNARK::prove(&pp_a, num_steps, z0_primary, z0_secondary).unwrap();
+
+    // a proof only verifies with the parameters of its application
+    assert!(recursive_snark
+      .verify(&pp_a, num_steps, z0_primary, z0_secondary)
+      .is_ok());
+    assert!(recursive_snark
+      .verify(&pp_b, num_steps, z0_primary, z0_secondary)
+      .is_err());
+
+    // and is only compressed with a prover key for them
+    let (pk_b, _) = CompressedSNARK::<G1, G2, C1, C2, S1, S2>::setup(&pp_b);
+    assert!(matches!(
+      CompressedSNARK::prove_with_key(&pp_a, &pk_b, &recursive_snark),
+      Err(NovaError::KeyMismatch)
+    ));
+  }
+
+  #[test]
+  fn test_ivc_with_security_config() {
+    type C1 = TrivialTestCircuit<<G1 as Group>::Scalar>;
+    type C2 = CubicCircuit<<G2 as Group>::Scalar>;
+    let setup = |config: &SecurityConfig| {
+      PublicParams::<G1, G2, C1, C2>::setup_with_config(
+        TrivialTestCircuit {
+          _p: Default::default(),
+        },
+        CubicCircuit {
+          _p: Default::default(),
+        },
+        b"",
+        config,
+      )
+    };
+
+    // a configuration out of bounds is rejected
+    let invalid = SecurityConfig {
+      num_challenge_bits: 16,
+      ..SecurityConfig::default()
+    };
+    assert!(matches!(
+      setup(&invalid).err(),
+      Some(NovaError::InvalidSecurityConfig { .. })
+    ));
+
+    // the default configuration gives the parameters of `setup`
+    let pp_default = setup(&SecurityConfig::default()).unwrap();
+    assert_eq!(
+      pp_default.digest(),
+      PublicParams::<G1, G2, C1, C2>::setup(
+        TrivialTestCircuit {
+          _p: Default::default(),
+        },
+        CubicCircuit {
+          _p: Default::default(),
+        },
+      )
+      .digest()
+    );
+
+    let num_steps = 3;
+    let z0_primary = <G1 as Group>::Scalar::one();
+    let z0_secondary = <G2 as Group>::Scalar::zero();
+    for config in [SecurityConfig::conservative(), SecurityConfig::fast_test()] {
+      let pp = setup(&config).unwrap();
+      assert_eq!(pp.security_config(), &config);
+      assert_ne!(pp.digest(), pp_default.digest());
+
+      let recursive_snark =
+        RecursiveSNARK::prove(&pp, num_steps, z0_primary, z0_secondary).unwrap();
+      assert!(recursive_snark
+        .verify(&pp, num_steps, z0_primary, z0_secondary)
+        .is_ok());
+
+      // a proof does not verify with the parameters of another configuration
+      assert!(recursive_snark
+        .verify(&pp_default, num_steps, z0_primary, z0_secondary)
+        .is_err());
+    }
+  }
+
+  #[derive(Clone, Debug)]
+  struct FailingCircuit<F: PrimeField> {
+    _p: PhantomData<F>,
+  }
+
+  impl<F> StepCircuit<F> for FailingCircuit<F>
+  where
+    F: PrimeField,
+  {
+    fn synthesize<CS: ConstraintSystem<F>>(
+      &self,
+      _cs: &mut CS,
+      _z: AllocatedNum<F>,
+    ) -> Result<AllocatedNum<F>, SynthesisError> {
+      Err(SynthesisError::Unsatisfiable)
+    }
+
+    fn compute(&self, z: &F) -> F {
+      *z
+    }
+  }
+
+  #[test]
+  fn test_setup_synthesis_error() {
+    type C1 = TrivialTestCircuit<<G1 as Group>::Scalar>;
+    type C2 = FailingCircuit<<G2 as Group>::Scalar>;
+
+    // a step circuit that fails to synthesize fails the setup rather than giving a partial shape
+    let res = PublicParams::<G1, G2, C1, C2>::setup_with_config(
+      TrivialTestCircuit {
+        _p: Default::default(),
+      },
+      FailingCircuit {
+        _p: Default::default(),
+      },
+      b"",
+      &SecurityConfig::default(),
+    );
+    assert!(matches!(res.err(), Some(NovaError::Synthesis { .. })));
+  }
+
   #[test]
   fn test_ivc_base() {
     // produce public parameters
@@ -0,0 +1,385 @@
+//! This module implements a LogUp-style lookup argument that is folded along with relaxed R1CS.
+//! A step circuit looks up some of its variables `q_1, ..., q_k` in a fixed table `t_1, ..., t_N`.
+//! The prover commits to the witness of the circuit along with the multiplicities `m_j` of each
+//! table entry among the queries, derives a challenge `c` from that commitment, and then commits
+//! separately to `h_i = 1 / (c - q_i)` and `g_j = m_j / (c - t_j)`. If `sum_i h_i = sum_j g_j`
+//! for a random `c`, then every query is in the table.
+//!
+//! The checks are expressed as additional R1CS constraints over the extended witness
+//! `W' = (W, m, 0, ..., 0 | h, g, 0, ..., 0)` with `c` as an additional public IO:
+//!
+//!   h_i * (c - q_i) = u * u
+//!   g_j * (c - u * t_j) = u * m_j
+//!   (sum_i h_i - sum_j g_j) * u = 0
+//!
+//! Both sides are homogeneous of degree two, so these constraints are folded and proven by the
+//! existing machinery for relaxed R1CS. The two halves of `W'` are committed with the same
+//! generators and are folded separately (as `comm_W` and `comm_L`), so that the challenge is bound
+//! to a commitment that does not depend on it.
+//!
+//! The challenge is `c = H(pp_digest, comm_W)` under the constants of the random oracle that
+//! folds the instance; NIFS and the verifier circuit recompute it. It does not bind the public
+//! IO, so the queries must be auxiliary variables. The sum identity is then checked at a random
+//! point, with an error of about `(k + N) / 2^128`, and the multiplicities, which are field
+//! elements, assume that `k` is smaller than the characteristic of the field.
+//!
+//! Each step circuit has one table, fixed at setup and bound in the digest of the shape. The
+//! extended witness has twice the power of two of `max(|W|, k) + N` variables, so a large
+//! table grows every step. The CycleFold recursion and the folding accumulator of `folding`
+//! do not support lookups.
+use crate::{
+  commitments::{CommitTrait, Commitment},
+  errors::NovaError,
+  gadgets::utils::scalar_as_base,
+  r1cs::{R1CSGens, R1CSInstance, R1CSShape, R1CSWitness},
+  serde_utils::scalars,
+  traits::{AbsorbInROTrait, Group, HashFuncTrait},
+};
+use core::cmp::max;
+use ff::{Field, PrimeField};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// The number of public IO that the lookup argument appends to an R1CS instance:
+/// the challenge, and a zero that keeps the number of IO even
+pub(crate) const NUM_LOOKUP_IO: usize = 2;
+
+pub(crate) type ROConstants<G> =
+  <<G as Group>::HashFunc as HashFuncTrait<<G as Group>::Base, <G as Group>::Scalar>>::Constants;
+
+/// A fixed table along with the columns of `z = (W', u, X)` that are looked up in it
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct LookupShape<G: Group> {
+  #[serde(with = "scalars")]
+  table: Vec<G::Scalar>,
+  queries: Vec<usize>,
+  num_aux: usize,
+}
+
+impl<G: Group> LookupShape<G> {
+  /// Returns the number of variables of the extended witness of a circuit with `num_aux` variables
+  pub fn num_vars(num_aux: usize, table_len: usize, num_queries: usize) -> usize {
+    2 * (max(num_aux, num_queries) + table_len).next_power_of_two()
+  }
+
+  /// Creates a new lookup shape for a circuit with `num_aux` variables,
+  /// where `queries` are columns of `z = (W', u, X)`
+  pub fn new(table: &[G::Scalar], queries: &[usize], num_aux: usize) -> Self {
+    Self {
+      table: table.to_vec(),
+      queries: queries.to_vec(),
+      num_aux,
+    }
+  }
+
+  /// Appends the constraints of the lookup argument to the provided matrices, which have `num_cons`
+  /// constraints over `num_vars` extended variables and `num_io` public IO including those of the
+  /// lookup argument. Returns the number of constraints appended.
+  pub fn append_constraints(
+    &self,
+    num_cons: usize,
+    num_vars: usize,
+    num_io: usize,
+    A: &mut Vec<(usize, usize, G::Scalar)>,
+    B: &mut Vec<(usize, usize, G::Scalar)>,
+    C: &mut Vec<(usize, usize, G::Scalar)>,
+  ) -> usize {
+    let (k, half) = (self.queries.len(), num_vars / 2);
+    let u = num_vars;
+    let c = num_vars + num_io - 1;
+    let one = G::Scalar::one();
+
+    // h_i * (c - q_i) = u * u
+    for (i, q) in self.queries.iter().enumerate() {
+      let row = num_cons + i;
+      A.push((row, half + i, one));
+      B.push((row, c, one));
+      B.push((row, *q, -one));
+      C.push((row, u, one));
+    }
+
+    // g_j * (c - u * t_j) = u * m_j
+    for (j, t) in self.table.iter().enumerate() {
+      let row = num_cons + k + j;
+      A.push((row, half + k + j, one));
+      B.push((row, c, one));
+      B.push((row, u, -*t));
+      C.push((row, self.num_aux + j, one));
+    }
+
+    // (sum_i h_i - sum_j g_j) * u = 0
+    let row = num_cons + k + self.table.len();
+    A.extend((0..k).map(|i| (row, half + i, one)));
+    A.extend((0..self.table.len()).map(|j| (row, half + k + j, -one)));
+    B.push((row, u, one));
+
+    k + self.table.len() + 1
+  }
+
+  /// Extends the assignment `(aux, io)` of a circuit with the witness and the public IO of the
+  /// lookup argument, whose challenge is derived with `ro_consts` from `pp_digest`,
+  /// and returns the resulting instance and witness
+  pub fn r1cs_instance_and_witness(
+    &self,
+    S: &R1CSShape<G>,
+    gens: &R1CSGens<G>,
+    ro_consts: &ROConstants<G>,
+    pp_digest: &G::Scalar,
+    aux: &[G::Scalar],
+    io: &[G::Scalar],
+  ) -> Result<(R1CSInstance<G>, R1CSWitness<G>), NovaError> {
+    if aux.len() != self.num_aux {
+      return Err(NovaError::InvalidWitnessLength {
+        expected: self.num_aux,
+        actual: aux.len(),
+      });
+    }
+    if io.len() + NUM_LOOKUP_IO != S.num_io {
+      return Err(NovaError::InvalidInputLength {
+        expected: S.num_io - NUM_LOOKUP_IO,
+        actual: io.len(),
+      });
+    }
+
+    let (k, half) = (self.queries.len(), S.num_vars / 2);
+    let value = |col: usize| -> G::Scalar {
+      if col < self.num_aux {
+        aux[col]
+      } else if col == S.num_vars {
+        G::Scalar::one()
+      } else {
+        io[col - S.num_vars - 1]
+      }
+    };
+    let queries = self
+      .queries
+      .iter()
+      .map(|col| value(*col))
+      .collect::<Vec<G::Scalar>>();
+
+    // the first half holds the witness of the circuit and the multiplicities
+    let mut W = vec![G::Scalar::zero(); S.num_vars];
+    W[..self.num_aux].copy_from_slice(aux);
+    let index = self
+      .table
+      .iter()
+      .enumerate()
+      .rev()
+      .map(|(j, t)| (t.to_repr().as_ref().to_vec(), j))
+      .collect::<HashMap<Vec<u8>, usize>>();
+    for q in &queries {
+      let j = index
+        .get(q.to_repr().as_ref())
+        .ok_or(NovaError::InvalidLookup)?;
+      W[self.num_aux + j] += G::Scalar::one();
+    }
+    let comm_W = W[..half].commit(&gens.gens);
+
+    // the second half holds the inverses, which depend on the challenge
+    let c = challenge(ro_consts, pp_digest, &comm_W);
+    let invert = |x: G::Scalar| -> Result<G::Scalar, NovaError> {
+      Option::from(x.invert()).ok_or(NovaError::InvalidLookup)
+    };
+    for (i, q) in queries.iter().enumerate() {
+      W[half + i] = invert(c - q)?;
+    }
+    for (j, t) in self.table.iter().enumerate() {
+      W[half + k + j] = W[self.num_aux + j] * invert(c - t)?;
+    }
+    let comm_L = W[half..].commit(&gens.gens);
+
+    let X = io
+      .iter()
+      .copied()
+      .chain([c, G::Scalar::zero()])
+      .collect::<Vec<G::Scalar>>();
+    let U = R1CSInstance {
+      comm_W,
+      comm_L: Some(comm_L),
+      X,
+    };
+    Ok((U, R1CSWitness::new(S, &W)?))
+  }
+}
+
+/// Computes the challenge of the lookup argument from the digest of the public parameters
+/// and the commitment to the first half of the witness
+pub fn challenge<G: Group>(
+  ro_consts: &ROConstants<G>,
+  pp_digest: &G::Scalar,
+  comm_W: &Commitment<G>,
+) -> G::Scalar {
+  let mut ro = G::HashFunc::new(ro_consts.clone());
+  ro.absorb(scalar_as_base::<G>(*pp_digest));
+  comm_W.absorb_in_ro(&mut ro);
+  ro.get_challenge()
+}
+
+/// Checks that an R1CS instance carries the lookup argument, and that its challenge is derived
+/// with `ro_consts` from `pp_digest` and its commitment to the first half of the witness
+pub fn check_challenge<G: Group>(
+  ro_consts: &ROConstants<G>,
+  pp_digest: &G::Scalar,
+  U: &R1CSInstance<G>,
+) -> Result<(), NovaError> {
+  match (&U.comm_L, U.X.len().checked_sub(NUM_LOOKUP_IO)) {
+    (Some(_), Some(n))
+      if U.X[n] == challenge(ro_consts, pp_digest, &U.comm_W)
+        && U.X[n + 1] == G::Scalar::zero() =>
+    {
+      Ok(())
+    }
+    _ => Err(NovaError::InvalidLookup),
+  }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+  use super::*;
+  use crate::{
+    bellperson::{
+      r1cs::{NovaShape, NovaWitness},
+      shape_cs::ShapeCS,
+      solver::SatisfyingAssignment,
+    },
+    nifs::NIFS,
+    r1cs::{RelaxedR1CSInstance, RelaxedR1CSWitness},
+    snark::{ProverKeyTrait, RelaxedR1CSSNARKTrait, VerifierKeyTrait},
+    spartan_with_ipa_pc::{LowMemoryRelaxedR1CSSNARK, ProverKey, RelaxedR1CSSNARK, VerifierKey},
+    traits::HashFuncConstantsTrait,
+  };
+  use ::bellperson::{gadgets::num::AllocatedNum, ConstraintSystem, SynthesisError};
+
+  type G = pasta_curves::pallas::Point;
+  type S = pasta_curves::pallas::Scalar;
+
+  /// Looks up `x` and `x + y` in the table and outputs `x + y`
+  pub(crate) fn synthesize_add<CS: ConstraintSystem<S>>(
+    cs: &mut CS,
+    x: Option<u64>,
+    y: Option<u64>,
+  ) -> Result<Vec<AllocatedNum<S>>, SynthesisError> {
+    let x = AllocatedNum::alloc(cs.namespace(|| "x"), || Ok(S::from(x.unwrap())))?;
+    let y = AllocatedNum::alloc(cs.namespace(|| "y"), || Ok(S::from(y.unwrap())))?;
+    let sum = AllocatedNum::alloc(cs.namespace(|| "x + y"), || {
+      Ok(x.get_value().unwrap() + y.get_value().unwrap())
+    })?;
+    cs.enforce(
+      || "check x + y",
+      |lc| lc + x.get_variable() + y.get_variable(),
+      |lc| lc + CS::one(),
+      |lc| lc + sum.get_variable(),
+    );
+    sum.inputize(cs.namespace(|| "output"))?;
+    sum.inputize(cs.namespace(|| "output again"))?;
+    Ok(vec![x, sum])
+  }
+
+  pub(crate) fn table() -> Vec<S> {
+    (0..16u64).map(S::from).collect()
+  }
+
+  #[test]
+  fn test_lookup_folding() {
+    let mut cs: ShapeCS<G> = ShapeCS::new();
+    let queries = synthesize_add(&mut cs, None, None).unwrap();
+    let shape = cs.r1cs_shape_with_lookup(&table(), &queries);
+    let gens = R1CSGens::new(shape.num_cons, shape.num_vars);
+
+    // fold a few instances whose queries are in the table
+    let ro_consts = ROConstants::<G>::new();
+    let mut r_U = RelaxedR1CSInstance::default(&gens, &shape);
+    let mut r_W = RelaxedR1CSWitness::default(&shape);
+    for (x, y) in [(1, 2), (3, 3), (0, 15)] {
+      let mut cs: SatisfyingAssignment<G> = SatisfyingAssignment::new();
+      let _ = synthesize_add(&mut cs, Some(x), Some(y));
+      let (U, W) = cs.r1cs_instance_and_witness(&shape, &gens).unwrap();
+      assert!(shape.is_sat(&gens, &U, &W).is_ok());
+      let (nifs, (U_fold, W_fold)) = NIFS::prove(
+        &gens,
+        &ro_consts,
+        &shape.get_digest(),
+        &shape,
+        &r_U,
+        &r_W,
+        &U,
+        &W,
+      )
+      .unwrap();
+      assert_eq!(
+        nifs
+          .verify(&ro_consts, &shape.get_digest(), &shape, &r_U, &U)
+          .unwrap(),
+        U_fold
+      );
+      r_U = U_fold;
+      r_W = W_fold;
+      assert!(shape.is_sat_relaxed(&gens, &r_U, &r_W).is_ok());
+    }
+
+    // the accumulated lookup claim is proven along with the rest of the instance
+    let shape_padded = shape.pad();
+    let r_W = r_W.pad(&shape_padded);
+    let pk = ProverKey::new(&gens, &shape_padded, &shape.get_digest());
+    let vk = VerifierKey::new(&gens, &shape_padded, &shape.get_digest());
+    let snark = RelaxedR1CSSNARK::<G>::prove(&pk, &r_U, &r_W).unwrap();
+    let snark_low_memory = LowMemoryRelaxedR1CSSNARK::<G>::prove(&pk, &r_U, &r_W).unwrap();
+    assert!(snark.verify(&vk, &r_U).is_ok());
+    assert!(snark_low_memory.verify(&vk, &r_U).is_ok());
+
+    // the lookup commitment cannot be dropped from the instance
+    let mut r_U_bad = r_U.clone();
+    r_U_bad.comm_L = None;
+    assert!(snark.verify(&vk, &r_U_bad).is_err());
+
+    // queries outside of the table cannot be proven
+    let mut cs: SatisfyingAssignment<G> = SatisfyingAssignment::new();
+    let _ = synthesize_add(&mut cs, Some(10), Some(6));
+    assert_eq!(
+      cs.r1cs_instance_and_witness(&shape, &gens),
+      Err(NovaError::InvalidLookup)
+    );
+
+    // the challenge must be derived from the commitment to the first half of the witness
+    let mut cs: SatisfyingAssignment<G> = SatisfyingAssignment::new();
+    let _ = synthesize_add(&mut cs, Some(1), Some(1));
+    let (mut U, W) = cs.r1cs_instance_and_witness(&shape, &gens).unwrap();
+    U.X[2] += S::one();
+    assert!(shape.is_sat(&gens, &U, &W).is_err());
+    assert!(NIFS::prove(
+      &gens,
+      &ro_consts,
+      &shape.get_digest(),
+      &shape,
+      &r_U,
+      &r_W,
+      &U,
+      &W
+    )
+    .is_err());
+
+    // the challenge is bound to the digest of the parameters that the instance is folded under
+    let mut cs: SatisfyingAssignment<G> = SatisfyingAssignment::new();
+    let _ = synthesize_add(&mut cs, Some(1), Some(1));
+    let (U, W) = cs
+      .r1cs_instance_and_witness_with_key(&shape, &gens, &ro_consts, &S::one())
+      .unwrap();
+    assert!(shape.is_sat(&gens, &U, &W).is_ok());
+    assert!(NIFS::prove(&gens, &ro_consts, &S::one(), &shape, &r_U, &r_W, &U, &W).is_ok());
+    assert_eq!(
+      NIFS::prove(
+        &gens,
+        &ro_consts,
+        &shape.get_digest(),
+        &shape,
+        &r_U,
+        &r_W,
+        &U,
+        &W
+      )
+      .err(),
+      Some(NovaError::InvalidLookup)
+    );
+  }
+}
@@ -1,17 +1,24 @@
-//! This module implements a non-interactive folding scheme
+//! This module implements a non-interactive folding scheme. It is part of the low-level API
+//! described in the `r1cs` module, and follows the same stability policy.
 #![allow(non_snake_case)]
 #![allow(clippy::type_complexity)]
+#![allow(clippy::too_many_arguments)]
 
 use super::commitments::CompressedCommitment;
 use super::errors::NovaError;
+use super::gadgets::utils::scalar_as_base;
+use super::lookup::check_challenge;
 use super::r1cs::{
   R1CSGens, R1CSInstance, R1CSShape, R1CSWitness, RelaxedR1CSInstance, RelaxedR1CSWitness,
 };
 use super::traits::{AbsorbInROTrait, Group, HashFuncTrait};
+use serde::{Deserialize, Serialize};
 use std::marker::PhantomData;
 
 /// A SNARK that holds the proof of a step of an incremental computation
 #[allow(clippy::upper_case_acronyms)]
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(bound = "")]
 pub struct NIFS<G: Group> {
   pub(crate) comm_T: CompressedCommitment<G::CompressedGroupElement>,
   _p: PhantomData<G>,
@@ -27,20 +34,28 @@ impl<G: Group> NIFS<G> {
   /// a folded Relaxed R1CS instance-witness tuple `(U, W)` of the same shape `shape`,
   /// with the guarantee that the folded witness `W` satisfies the folded instance `U`
   /// if and only if `W1` satisfies `U1` and `W2` satisfies `U2`.
+  /// The challenge is derived from `pp_digest`, the digest of the public parameters
+  /// that `shape` belongs to, such as `PublicParams::digest` or the digest of the shape.
   pub fn prove(
     gens: &R1CSGens<G>,
     ro_consts: &ROConstants<G>,
+    pp_digest: &G::Scalar,
     S: &R1CSShape<G>,
     U1: &RelaxedR1CSInstance<G>,
     W1: &RelaxedR1CSWitness<G>,
     U2: &R1CSInstance<G>,
     W2: &R1CSWitness<G>,
   ) -> Result<(NIFS<G>, (RelaxedR1CSInstance<G>, RelaxedR1CSWitness<G>)), NovaError> {
+    // check that the challenge of the lookup argument is derived from U2's commitment
+    if S.lookup.is_some() {
+      check_challenge(ro_consts, pp_digest, U2)?;
+    }
+
     // initialize a new RO
     let mut ro = G::HashFunc::new(ro_consts.clone());
 
-    // append S to the transcript
-    S.absorb_in_ro(&mut ro);
+    // append the digest of the public parameters to the transcript
+    ro.absorb(scalar_as_base::<G>(*pp_digest));
 
     // append U1 and U2 to transcript
     U1.absorb_in_ro(&mut ro);
@@ -79,15 +94,21 @@ impl<G: Group> NIFS<G> {
   pub fn verify(
     &self,
     ro_consts: &ROConstants<G>,
+    pp_digest: &G::Scalar,
     S: &R1CSShape<G>,
     U1: &RelaxedR1CSInstance<G>,
     U2: &R1CSInstance<G>,
   ) -> Result<RelaxedR1CSInstance<G>, NovaError> {
+    // check that the challenge of the lookup argument is derived from U2's commitment
+    if S.lookup.is_some() {
+      check_challenge(ro_consts, pp_digest, U2)?;
+    }
+
     // initialize a new RO
     let mut ro = G::HashFunc::new(ro_consts.clone());
 
-    // append S to the transcript
-    S.absorb_in_ro(&mut ro);
+    // append the digest of the public parameters to the transcript
+    ro.absorb(scalar_as_base::<G>(*pp_digest));
 
     // append U1 and U2 to transcript
     U1.absorb_in_ro(&mut ro);
@@ -207,12 +228,21 @@ mod tests {
     let mut r_U = RelaxedR1CSInstance::default(gens, shape);
 
     // produce a step SNARK with (W1, U1) as the first incoming witness-instance pair
-    let res = NIFS::prove(gens, ro_consts, shape, &r_U, &r_W, U1, W1);
+    let res = NIFS::prove(
+      gens,
+      ro_consts,
+      &shape.get_digest(),
+      shape,
+      &r_U,
+      &r_W,
+      U1,
+      W1,
+    );
     assert!(res.is_ok());
     let (nifs, (_U, W)) = res.unwrap();
 
     // verify the step SNARK with U1 as the first incoming instance
-    let res = nifs.verify(ro_consts, shape, &r_U, U1);
+    let res = nifs.verify(ro_consts, &shape.get_digest(), shape, &r_U, U1);
     assert!(res.is_ok());
     let U = res.unwrap();
 
@@ -223,12 +253,21 @@ mod tests {
     r_U = U;
 
     // produce a step SNARK with (W2, U2) as the second incoming witness-instance pair
-    let res = NIFS::prove(gens, ro_consts, shape, &r_U, &r_W, U2, W2);
+    let res = NIFS::prove(
+      gens,
+      ro_consts,
+      &shape.get_digest(),
+      shape,
+      &r_U,
+      &r_W,
+      U2,
+      W2,
+    );
     assert!(res.is_ok());
     let (nifs, (_U, W)) = res.unwrap();
 
     // verify the step SNARK with U1 as the first incoming instance
-    let res = nifs.verify(ro_consts, shape, &r_U, U2);
+    let res = nifs.verify(ro_consts, &shape.get_digest(), shape, &r_U, U2);
     assert!(res.is_ok());
     let U = res.unwrap();
 
@@ -300,6 +339,8 @@ mod tests {
       assert!(res.is_ok());
       res.unwrap()
     };
+    assert_eq!((S.num_cons(), S.num_vars(), S.num_io()), (4, 4, 2));
+    assert_eq!(S.matrices(), (&A[..], &B[..], &C[..]));
 
     // generate generators and ro constants
     let gens = R1CSGens::new(num_cons, num_vars);
@@ -336,6 +377,7 @@ mod tests {
           assert!(res.is_ok());
           res.unwrap()
         };
+        assert_eq!((U.X(), W.W()), (&X[..], &vars[..]));
 
         // check that generated instance is satisfiable
         assert!(S.is_sat(gens, &U, &W).is_ok());
@@ -0,0 +1,519 @@
+//! This module implements the verifier circuit of the multi-folding scheme, over `G::Scalar`.
+//! The transcript, the sum-check and the folding of the scalars are verified natively.
+//! As in the CycleFold recursion, the folded commitment `comm_W_1 + rho * comm_W_2` is only
+//! provided as a witness, and must be checked by a CycleFold circuit over the other curve,
+//! using the bits of `rho` returned by the gadget.
+use super::NIMFSConstants;
+use crate::{
+  ccs::{CCCSInstance, CCSShape, LCCCSInstance},
+  cyclefold::gadgets::AllocatedCompressedPoint,
+  gadgets::utils::{alloc_constant, eval_eq, le_bits_to_num, mul_add},
+  poseidon::PoseidonROGadget,
+  traits::Group,
+};
+use bellperson::{
+  gadgets::{boolean::AllocatedBit, num::AllocatedNum},
+  ConstraintSystem, LinearCombination, SynthesisError,
+};
+use core::cmp::max;
+use ff::{Field, PrimeField};
+
+/// A transcript gadget that mirrors `NIMFSTranscript`
+struct NIMFSTranscriptGadget<G: Group> {
+  constants: NIMFSConstants<G>,
+  ro: PoseidonROGadget<G::Scalar>,
+}
+
+impl<G: Group> NIMFSTranscriptGadget<G> {
+  fn new(constants: &NIMFSConstants<G>) -> Self {
+    Self {
+      constants: constants.clone(),
+      ro: PoseidonROGadget::new(constants.clone()),
+    }
+  }
+
+  fn absorb(&mut self, e: &AllocatedNum<G::Scalar>) {
+    self.ro.absorb(e.clone());
+  }
+
+  fn squeeze<CS: ConstraintSystem<G::Scalar>>(
+    &mut self,
+    mut cs: CS,
+  ) -> Result<(AllocatedNum<G::Scalar>, Vec<AllocatedBit>), SynthesisError> {
+    let bits = self.ro.get_challenge(cs.namespace(|| "get challenge"))?;
+    let c = le_bits_to_num(cs.namespace(|| "challenge"), bits.clone())?;
+    self.ro = PoseidonROGadget::new(self.constants.clone());
+    self.ro.absorb(c.clone());
+    Ok((c, bits))
+  }
+}
+
+/// A linearized CCS instance allocated in a circuit over `G::Scalar`
+pub struct AllocatedLCCCSInstance<G: Group> {
+  pub(crate) W: AllocatedCompressedPoint<G>,
+  pub(crate) X: Vec<AllocatedNum<G::Scalar>>,
+  pub(crate) u: AllocatedNum<G::Scalar>,
+  pub(crate) r_x: Vec<AllocatedNum<G::Scalar>>,
+  pub(crate) v: Vec<AllocatedNum<G::Scalar>>,
+}
+
+impl<G: Group> AllocatedLCCCSInstance<G> {
+  /// Allocates the given linearized instance of the CCS `S` as a witness of the circuit
+  pub fn alloc<CS: ConstraintSystem<G::Scalar>>(
+    mut cs: CS,
+    S: &CCSShape<G>,
+    inst: Option<&LCCCSInstance<G>>,
+    limb_width: usize,
+    n_limbs: usize,
+  ) -> Result<Self, SynthesisError> {
+    let W = AllocatedCompressedPoint::alloc(
+      cs.namespace(|| "allocate W"),
+      inst.map(|inst| &inst.comm_W),
+      limb_width,
+      n_limbs,
+    )?;
+    let u = AllocatedNum::alloc(cs.namespace(|| "allocate u"), || {
+      Ok(inst.map_or(G::Scalar::zero(), |inst| inst.u))
+    })?;
+    let mut alloc_vec = |name: &str, len: usize, f: &dyn Fn(&LCCCSInstance<G>) -> &[G::Scalar]| {
+      (0..len)
+        .map(|i| {
+          AllocatedNum::alloc(cs.namespace(|| format!("allocate {}[{}]", name, i)), || {
+            Ok(inst.map_or(G::Scalar::zero(), |inst| f(inst)[i]))
+          })
+        })
+        .collect::<Result<Vec<_>, _>>()
+    };
+    let X = alloc_vec("X", S.num_io, &|inst| &inst.X)?;
+    let r_x = alloc_vec("r_x", S.num_vars_x(), &|inst| &inst.r_x)?;
+    let v = alloc_vec("v", S.M.len(), &|inst| &inst.v)?;
+    Ok(Self { W, X, u, r_x, v })
+  }
+}
+
+/// A committed CCS instance allocated in a circuit over `G::Scalar`
+pub struct AllocatedCCCSInstance<G: Group> {
+  pub(crate) W: AllocatedCompressedPoint<G>,
+  pub(crate) X: Vec<AllocatedNum<G::Scalar>>,
+}
+
+impl<G: Group> AllocatedCCCSInstance<G> {
+  /// Allocates the given instance of the CCS `S` as a witness of the circuit
+  pub fn alloc<CS: ConstraintSystem<G::Scalar>>(
+    mut cs: CS,
+    S: &CCSShape<G>,
+    inst: Option<&CCCSInstance<G>>,
+    limb_width: usize,
+    n_limbs: usize,
+  ) -> Result<Self, SynthesisError> {
+    let W = AllocatedCompressedPoint::alloc(
+      cs.namespace(|| "allocate W"),
+      inst.map(|inst| &inst.comm_W),
+      limb_width,
+      n_limbs,
+    )?;
+    let X = (0..S.num_io)
+      .map(|i| {
+        AllocatedNum::alloc(cs.namespace(|| format!("allocate X[{}]", i)), || {
+          Ok(inst.map_or(G::Scalar::zero(), |inst| inst.X[i]))
+        })
+      })
+      .collect::<Result<Vec<_>, _>>()?;
+    Ok(Self { W, X })
+  }
+}
+
+/// A proof of the multi-folding scheme allocated in a circuit over `G::Scalar`
+pub struct AllocatedNIMFS<G: Group> {
+  polys: Vec<Vec<AllocatedNum<G::Scalar>>>,
+  sigmas: Vec<AllocatedNum<G::Scalar>>,
+  thetas: Vec<AllocatedNum<G::Scalar>>,
+}
+
+impl<G: Group> AllocatedNIMFS<G> {
+  /// Allocates the given proof for the CCS `S` as a witness of the circuit
+  pub fn alloc<CS: ConstraintSystem<G::Scalar>>(
+    mut cs: CS,
+    S: &CCSShape<G>,
+    proof: Option<&super::NIMFS<G>>,
+  ) -> Result<Self, SynthesisError> {
+    let degree = max(S.degree() + 1, 2);
+    let polys = (0..S.num_vars_x())
+      .map(|i| {
+        (0..=degree)
+          .map(|k| {
+            AllocatedNum::alloc(
+              cs.namespace(|| format!("allocate coefficient {} of poly {}", k, i)),
+              || {
+                Ok(proof.map_or(G::Scalar::zero(), |proof| {
+                  proof.polys[i]
+                    .get(k)
+                    .copied()
+                    .unwrap_or_else(G::Scalar::zero)
+                }))
+              },
+            )
+          })
+          .collect::<Result<Vec<_>, _>>()
+      })
+      .collect::<Result<Vec<_>, _>>()?;
+    let mut alloc_vec = |name: &str, f: &dyn Fn(&super::NIMFS<G>) -> &[G::Scalar]| {
+      (0..S.M.len())
+        .map(|j| {
+          AllocatedNum::alloc(cs.namespace(|| format!("allocate {}[{}]", name, j)), || {
+            Ok(proof.map_or(G::Scalar::zero(), |proof| {
+              f(proof).get(j).copied().unwrap_or_else(G::Scalar::zero)
+            }))
+          })
+        })
+        .collect::<Result<Vec<_>, _>>()
+    };
+    let sigmas = alloc_vec("sigma", &|proof| &proof.sigmas)?;
+    let thetas = alloc_vec("theta", &|proof| &proof.thetas)?;
+    Ok(Self {
+      polys,
+      sigmas,
+      thetas,
+    })
+  }
+
+  /// Verifies the folding of `U1` and `U2` in the circuit. The folded commitment `W_fold` is
+  /// provided by the prover and must be checked by a CycleFold circuit.
+  /// Returns the folded instance and the bits of the challenge `rho`.
+  #[allow(clippy::too_many_arguments)]
+  pub fn verify<CS: ConstraintSystem<G::Scalar>>(
+    &self,
+    mut cs: CS,
+    ro_consts: &NIMFSConstants<G>,
+    S: &CCSShape<G>,
+    U1: &AllocatedLCCCSInstance<G>,
+    U2: &AllocatedCCCSInstance<G>,
+    W_fold: AllocatedCompressedPoint<G>,
+  ) -> Result<(AllocatedLCCCSInstance<G>, Vec<AllocatedBit>), SynthesisError> {
+    let one = alloc_constant(cs.namespace(|| "one"), G::Scalar::one())?;
+
+    // absorb the shape and the instances, and squeeze gamma and beta
+    let mut transcript = NIMFSTranscriptGadget::<G>::new(ro_consts);
+    let digest = alloc_constant(cs.namespace(|| "digest"), S.get_digest())?;
+    transcript.absorb(&digest);
+    U1.W
+      .absorb_in_ro(cs.namespace(|| "absorb U1.W"), &mut transcript.ro)?;
+    for e in U1.X.iter().chain([&U1.u]).chain(&U1.r_x).chain(&U1.v) {
+      transcript.absorb(e);
+    }
+    U2.W
+      .absorb_in_ro(cs.namespace(|| "absorb U2.W"), &mut transcript.ro)?;
+    for x in &U2.X {
+      transcript.absorb(x);
+    }
+    let (gamma, _) = transcript.squeeze(cs.namespace(|| "gamma"))?;
+    let beta = (0..S.num_vars_x())
+      .map(|i| {
+        transcript
+          .squeeze(cs.namespace(|| format!("beta[{}]", i)))
+          .map(|(b, _)| b)
+      })
+      .collect::<Result<Vec<_>, _>>()?;
+
+    // the initial claim is sum_j gamma^j * v_j
+    let (mut claim, gamma_t) = batch(cs.namespace(|| "batch v"), &U1.v, &gamma, &one)?;
+
+    // verify the sum-check
+    let mut r_x = Vec::new();
+    for (i, poly) in self.polys.iter().enumerate() {
+      let mut cs = cs.namespace(|| format!("round {}", i));
+
+      // poly(0) + poly(1) = 2 * c_0 + c_1 + ... + c_d
+      let lc = poly
+        .iter()
+        .fold(LinearCombination::zero(), |lc, c| lc + c.get_variable())
+        + poly[0].get_variable();
+      cs.enforce(
+        || "check poly(0) + poly(1) = claim",
+        |_| lc,
+        |lc| lc + CS::one(),
+        |lc| lc + claim.get_variable(),
+      );
+
+      for c in poly {
+        transcript.absorb(c);
+      }
+      let (r_i, _) = transcript.squeeze(cs.namespace(|| "r_i"))?;
+
+      // evaluate the poly at r_i with Horner's method
+      let mut eval = poly[poly.len() - 1].clone();
+      for (k, c) in poly.iter().rev().skip(1).enumerate() {
+        eval = mul_add(cs.namespace(|| format!("horner {}", k)), c, &eval, &r_i)?;
+      }
+      claim = eval;
+      r_x.push(r_i);
+    }
+
+    // check the final claim of the sum-check against sigma and theta
+    let (sum_sigmas, _) = batch(cs.namespace(|| "batch sigmas"), &self.sigmas, &gamma, &one)?;
+    let mut sum_thetas_lc = LinearCombination::zero();
+    let mut sum_thetas_val = Some(G::Scalar::zero());
+    for (i, (S_i, c_i)) in S.S.iter().zip(S.c.iter()).enumerate() {
+      let mut prod = self.thetas[S_i[0]].clone();
+      for (k, j) in S_i.iter().enumerate().skip(1) {
+        prod = prod.mul(
+          cs.namespace(|| format!("product {} of theta {}", k, i)),
+          &self.thetas[*j],
+        )?;
+      }
+      sum_thetas_lc = sum_thetas_lc + (*c_i, prod.get_variable());
+      sum_thetas_val = sum_thetas_val.and_then(|v| Some(v + *c_i * prod.get_value()?));
+    }
+    let sum_thetas = AllocatedNum::alloc(cs.namespace(|| "sum_thetas"), || {
+      sum_thetas_val.ok_or(SynthesisError::AssignmentMissing)
+    })?;
+    cs.enforce(
+      || "check sum_thetas",
+      |lc| lc + CS::one(),
+      |_| sum_thetas_lc,
+      |lc| lc + sum_thetas.get_variable(),
+    );
+
+    let eq_rx = eval_eq(cs.namespace(|| "eq(r_x, r_x')"), &U1.r_x, &r_x, &one)?;
+    let eq_beta = eval_eq(cs.namespace(|| "eq(beta, r_x')"), &beta, &r_x, &one)?;
+    let sigma_term = eq_rx.mul(cs.namespace(|| "eq_rx * sum_sigmas"), &sum_sigmas)?;
+    let theta_term = gamma_t
+      .mul(cs.namespace(|| "gamma^t * eq_beta"), &eq_beta)?
+      .mul(
+        cs.namespace(|| "gamma^t * eq_beta * sum_thetas"),
+        &sum_thetas,
+      )?;
+    cs.enforce(
+      || "check final claim",
+      |lc| lc + sigma_term.get_variable() + theta_term.get_variable(),
+      |lc| lc + CS::one(),
+      |lc| lc + claim.get_variable(),
+    );
+
+    // squeeze rho and fold the scalars
+    for e in self.sigmas.iter().chain(self.thetas.iter()) {
+      transcript.absorb(e);
+    }
+    let (rho, rho_bits) = transcript.squeeze(cs.namespace(|| "rho"))?;
+
+    let X = U1
+      .X
+      .iter()
+      .zip(U2.X.iter())
+      .enumerate()
+      .map(|(i, (x1, x2))| mul_add(cs.namespace(|| format!("fold X[{}]", i)), x1, &rho, x2))
+      .collect::<Result<Vec<_>, _>>()?;
+    let u = mul_add(cs.namespace(|| "fold u"), &U1.u, &rho, &one)?;
+    let v = self
+      .sigmas
+      .iter()
+      .zip(self.thetas.iter())
+      .enumerate()
+      .map(|(j, (sigma, theta))| {
+        mul_add(
+          cs.namespace(|| format!("fold v[{}]", j)),
+          sigma,
+          &rho,
+          theta,
+        )
+      })
+      .collect::<Result<Vec<_>, _>>()?;
+
+    Ok((
+      AllocatedLCCCSInstance {
+        W: W_fold,
+        X,
+        u,
+        r_x,
+        v,
+      },
+      rho_bits,
+    ))
+  }
+}
+
+/// Returns `sum_j gamma^j * v_j` and `gamma^t`
+fn batch<F: PrimeField, CS: ConstraintSystem<F>>(
+  mut cs: CS,
+  v: &[AllocatedNum<F>],
+  gamma: &AllocatedNum<F>,
+  one: &AllocatedNum<F>,
+) -> Result<(AllocatedNum<F>, AllocatedNum<F>), SynthesisError> {
+  let mut acc = alloc_constant(cs.namespace(|| "zero"), F::zero())?;
+  let mut pow = one.clone();
+  for (j, v_j) in v.iter().enumerate() {
+    acc = mul_add(cs.namespace(|| format!("add term {}", j)), &acc, &pow, v_j)?;
+    pow = pow.mul(cs.namespace(|| format!("gamma^{}", j + 1)), gamma)?;
+  }
+  Ok((acc, pow))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    bellperson::{
+      r1cs::{NovaShape, NovaWitness},
+      shape_cs::ShapeCS,
+      solver::SatisfyingAssignment,
+    },
+    ccs::CCSWitness,
+    constants::{BN_LIMB_WIDTH, BN_N_LIMBS},
+    errors::NovaError,
+    nimfs::NIMFS,
+    traits::HashFuncConstantsTrait,
+  };
+  use bellperson::Circuit;
+  use itertools::concat;
+
+  type G = pasta_curves::pallas::Point;
+  type S = pasta_curves::pallas::Scalar;
+
+  /// A circuit that verifies one step of the multi-folding scheme and outputs the folded scalars
+  struct NIMFSVerifierCircuit<'a> {
+    ro_consts: &'a NIMFSConstants<G>,
+    shape: &'a CCSShape<G>,
+    inputs: Option<(
+      LCCCSInstance<G>,
+      CCCSInstance<G>,
+      NIMFS<G>,
+      LCCCSInstance<G>,
+    )>,
+  }
+
+  impl<'a> Circuit<S> for NIMFSVerifierCircuit<'a> {
+    fn synthesize<CS: ConstraintSystem<S>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+      let inputs = self.inputs.as_ref();
+      let U1 = AllocatedLCCCSInstance::alloc(
+        cs.namespace(|| "U1"),
+        self.shape,
+        inputs.map(|i| &i.0),
+        BN_LIMB_WIDTH,
+        BN_N_LIMBS,
+      )?;
+      let U2 = AllocatedCCCSInstance::alloc(
+        cs.namespace(|| "U2"),
+        self.shape,
+        inputs.map(|i| &i.1),
+        BN_LIMB_WIDTH,
+        BN_N_LIMBS,
+      )?;
+      let proof =
+        AllocatedNIMFS::alloc(cs.namespace(|| "proof"), self.shape, inputs.map(|i| &i.2))?;
+      let W_fold = AllocatedCompressedPoint::alloc(
+        cs.namespace(|| "W_fold"),
+        inputs.map(|i| &i.3.comm_W),
+        BN_LIMB_WIDTH,
+        BN_N_LIMBS,
+      )?;
+      let (U, _) = proof.verify(
+        cs.namespace(|| "verify"),
+        self.ro_consts,
+        self.shape,
+        &U1,
+        &U2,
+        W_fold,
+      )?;
+      for (i, e) in U
+        .X
+        .iter()
+        .chain([&U.u])
+        .chain(&U.r_x)
+        .chain(&U.v)
+        .enumerate()
+      {
+        e.inputize(cs.namespace(|| format!("output {}", i)))?;
+      }
+      Ok(())
+    }
+  }
+
+  #[test]
+  fn test_nimfs_verifier_circuit() {
+    // a CCS with the degree-3 gates `x_i^3 = y_i`, over `z = (x, y, 1, X)`
+    let one = S::one();
+    let num_cons = 2;
+    let M0 = (0..num_cons).map(|i| (i, i, one)).collect::<Vec<_>>();
+    let M1 = (0..num_cons)
+      .map(|i| (i, num_cons + i, one))
+      .collect::<Vec<_>>();
+    let M2 = vec![(0, 2 * num_cons + 1, one)];
+    let shape = CCSShape::<G>::new(
+      num_cons,
+      2 * num_cons,
+      1,
+      &[M0, M1, M2],
+      &[vec![0, 0, 0], vec![1], vec![2]],
+      &[one, -one, -one],
+    )
+    .unwrap();
+    let gens = shape.gens();
+    let ro_consts = NIMFSConstants::<G>::new();
+
+    // x_0^3 = y_0 + X and x_1^3 = y_1
+    let instance = |x0: u64, x1: u64, X: u64| {
+      let xs = vec![S::from(x0), S::from(x1)];
+      let ys = vec![xs[0].cube() - S::from(X), xs[1].cube()];
+      let W = CCSWitness::new(&shape, &concat(vec![xs, ys])).unwrap();
+      let U = CCCSInstance::new(&shape, &W.commit(&gens), &[S::from(X)]).unwrap();
+      assert!(shape.is_sat(&gens, &U, &W).is_ok());
+      (U, W)
+    };
+    let (u1, w1) = instance(2, 3, 5);
+    let (u2, w2) = instance(7, 11, 13);
+    let (_, (U1, W1)) = NIMFS::prove(
+      &gens,
+      &ro_consts,
+      &shape,
+      &LCCCSInstance::default(&gens, &shape),
+      &CCSWitness::default(&shape),
+      &u1,
+      &w1,
+    )
+    .unwrap();
+    let (nimfs, (U, _)) = NIMFS::prove(&gens, &ro_consts, &shape, &U1, &W1, &u2, &w2).unwrap();
+
+    let mut cs: ShapeCS<G> = ShapeCS::new();
+    let circuit = NIMFSVerifierCircuit {
+      ro_consts: &ro_consts,
+      shape: &shape,
+      inputs: None,
+    };
+    let _ = circuit.synthesize(&mut cs);
+    let (r1cs_shape, r1cs_gens) = (cs.r1cs_shape(), cs.r1cs_gens());
+
+    let mut cs: SatisfyingAssignment<G> = SatisfyingAssignment::new();
+    let circuit = NIMFSVerifierCircuit {
+      ro_consts: &ro_consts,
+      shape: &shape,
+      inputs: Some((U1.clone(), u2.clone(), nimfs.clone(), U.clone())),
+    };
+    let _ = circuit.synthesize(&mut cs);
+    let (inst, witness) = cs
+      .r1cs_instance_and_witness(&r1cs_shape, &r1cs_gens)
+      .unwrap();
+    assert!(r1cs_shape.is_sat(&r1cs_gens, &inst, &witness).is_ok());
+    let outputs = concat(vec![U.X.clone(), vec![U.u], U.r_x.clone(), U.v.clone()]);
+    assert_eq!(inst.X, outputs);
+
+    // a tampered proof does not satisfy the circuit
+    let mut bad = nimfs;
+    bad.sigmas[0] += S::one();
+    let mut cs: SatisfyingAssignment<G> = SatisfyingAssignment::new();
+    let circuit = NIMFSVerifierCircuit {
+      ro_consts: &ro_consts,
+      shape: &shape,
+      inputs: Some((U1, u2, bad, U)),
+    };
+    let _ = circuit.synthesize(&mut cs);
+    let (inst, witness) = cs
+      .r1cs_instance_and_witness(&r1cs_shape, &r1cs_gens)
+      .unwrap();
+    assert!(matches!(
+      r1cs_shape.is_sat(&r1cs_gens, &inst, &witness),
+      Err(NovaError::UnSat {
+        constraint: Some(_)
+      })
+    ));
+  }
+}
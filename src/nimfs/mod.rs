@@ -0,0 +1,519 @@
+//! This module implements a HyperNova-style multi-folding scheme for CCS. It folds a linearized
+//! committed CCS instance `(comm_W, X, u, r_x, v)` and a committed CCS instance `(comm_W, X)`
+//! into a new linearized instance. The prover runs a sum-check over `x` in `{0, 1}^s` for
+//!
+//!   g(x) = eq(r_x, x) * sum_j gamma^j * L_j(x) + gamma^t * eq(beta, x) * sum_i c_i * prod_{j in S_i} Q_j(x)
+//!
+//! whose sum is `sum_j gamma^j * v_j`, where `L_j` and `Q_j` are the multilinear extensions of
+//! `M_j * z_1` and `M_j * z_2`. It then sends `sigma_j = L_j(r_x')` and `theta_j = Q_j(r_x')`,
+//! which are folded with a random `rho` into the claims of the new instance at `r_x'`.
+//!
+//! The challenges are derived with a chain of Poseidon hashes over `G::Scalar`, so that a
+//! circuit over `G::Scalar` can verify the folding with native field operations.
+//!
+//! The scheme is a building block and is not yet wired into an IVC: neither `RecursiveSNARK`
+//! nor the CycleFold recursion folds CCS instances, and no SNARK compresses an `LCCCSInstance`,
+//! so a chain of multi-folds can only be checked by `CCSShape::is_sat_linearized` on its witness.
+//! It folds one linearized instance with one committed instance; folding several of each is not
+//! implemented. The prover evaluates every `M_j * z` and its multilinear extension, so its memory
+//! is linear in `t` times the number of constraints.
+#![allow(non_snake_case)]
+#![allow(clippy::type_complexity)]
+mod circuit;
+
+pub use circuit::{AllocatedCCCSInstance, AllocatedLCCCSInstance, AllocatedNIMFS};
+
+use super::{
+  ccs::{CCCSInstance, CCSShape, CCSWitness, LCCCSInstance},
+  commitments::{compressed_point_elements, Commitment},
+  errors::{NovaError, SumcheckFailure},
+  parallel::prelude::*,
+  polynomial::{EqPolynomial, MultilinearPolynomial},
+  poseidon::{PoseidonRO, ROConstantsCircuit},
+  r1cs::R1CSGens,
+  traits::{Group, HashFuncTrait},
+};
+use core::cmp::max;
+use ff::{Field, PrimeField};
+use itertools::concat;
+
+/// The constants of the Poseidon hash used by the multi-folding scheme
+pub type NIMFSConstants<G> = ROConstantsCircuit<<G as Group>::Scalar>;
+
+/// A transcript made of a chain of Poseidon hashes over `G::Scalar`:
+/// each challenge is the hash of the previous challenge and of the elements absorbed since
+pub(crate) struct NIMFSTranscript<G: Group> {
+  constants: NIMFSConstants<G>,
+  ro: PoseidonRO<G::Scalar, G::Scalar>,
+}
+
+impl<G: Group> NIMFSTranscript<G> {
+  pub fn new(constants: &NIMFSConstants<G>) -> Self {
+    Self {
+      constants: constants.clone(),
+      ro: PoseidonRO::new(constants.clone()),
+    }
+  }
+
+  pub fn absorb(&mut self, e: G::Scalar) {
+    self.ro.absorb(e);
+  }
+
+  pub fn absorb_commitment(&mut self, comm: &Commitment<G>) {
+    for e in compressed_point_elements(comm) {
+      self.ro.absorb(e);
+    }
+  }
+
+  pub fn squeeze(&mut self) -> G::Scalar {
+    let c = self.ro.get_challenge();
+    self.ro = PoseidonRO::new(self.constants.clone());
+    self.ro.absorb(c);
+    c
+  }
+
+  /// Absorbs the shape and the two instances to fold, and returns `gamma` and `beta`
+  fn start(
+    &mut self,
+    S: &CCSShape<G>,
+    U1: &LCCCSInstance<G>,
+    U2: &CCCSInstance<G>,
+  ) -> (G::Scalar, Vec<G::Scalar>) {
+    self.absorb(S.get_digest());
+    self.absorb_commitment(&U1.comm_W);
+    for e in U1.X.iter().chain([U1.u].iter()).chain(&U1.r_x).chain(&U1.v) {
+      self.absorb(*e);
+    }
+    self.absorb_commitment(&U2.comm_W);
+    for x in &U2.X {
+      self.absorb(*x);
+    }
+
+    let gamma = self.squeeze();
+    let beta = (0..S.num_vars_x()).map(|_| self.squeeze()).collect();
+    (gamma, beta)
+  }
+}
+
+/// A proof that a linearized instance and an instance of a CCS were folded correctly
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NIMFS<G: Group> {
+  pub(crate) polys: Vec<Vec<G::Scalar>>, // coefficients of the round polynomials of the sum-check
+  pub(crate) sigmas: Vec<G::Scalar>,
+  pub(crate) thetas: Vec<G::Scalar>,
+}
+
+/// Returns the coefficients of the polynomial of degree `evals.len() - 1`
+/// that takes the value `evals[k]` at `k`
+fn interpolate<F: PrimeField>(evals: &[F]) -> Vec<F> {
+  let n = evals.len();
+  let mut coeffs = vec![F::zero(); n];
+  for (i, eval) in evals.iter().enumerate() {
+    // the Lagrange basis polynomial prod_{m != i} (x - m) / (i - m)
+    let mut basis = vec![F::one()];
+    let mut denom = F::one();
+    for m in (0..n).filter(|m| *m != i) {
+      let mut next = vec![F::zero(); basis.len() + 1];
+      for (k, b) in basis.iter().enumerate() {
+        next[k + 1] += b;
+        next[k] -= *b * F::from(m as u64);
+      }
+      basis = next;
+      denom *= F::from(i as u64) - F::from(m as u64);
+    }
+    let scale = *eval * denom.invert().unwrap();
+    for (c, b) in coeffs.iter_mut().zip(basis.iter()) {
+      *c += scale * b;
+    }
+  }
+  coeffs
+}
+
+/// Evaluates the polynomial with the given coefficients at `r`
+pub(crate) fn evaluate_poly<F: PrimeField>(coeffs: &[F], r: &F) -> F {
+  coeffs.iter().rev().fold(F::zero(), |acc, c| acc * r + c)
+}
+
+/// Returns `sum_j gamma^j * v_j` and `gamma^t`
+fn batch<F: PrimeField>(v: &[F], gamma: &F) -> (F, F) {
+  v.iter().fold((F::zero(), F::one()), |(acc, pow), v_j| {
+    (acc + pow * v_j, pow * gamma)
+  })
+}
+
+impl<G: Group> NIMFS<G> {
+  /// Takes as input a linearized instance-witness tuple `(U1, W1)` and an instance-witness tuple
+  /// `(U2, W2)` of the same CCS `S`, and outputs a folded linearized instance-witness tuple
+  /// `(U, W)`, with the guarantee that `W` satisfies `U` if and only if `W1` satisfies `U1`
+  /// and `W2` satisfies `U2`.
+  pub fn prove(
+    gens: &R1CSGens<G>,
+    ro_consts: &NIMFSConstants<G>,
+    S: &CCSShape<G>,
+    U1: &LCCCSInstance<G>,
+    W1: &CCSWitness<G>,
+    U2: &CCCSInstance<G>,
+    W2: &CCSWitness<G>,
+  ) -> Result<(NIMFS<G>, (LCCCSInstance<G>, CCSWitness<G>)), NovaError> {
+    NovaError::check_lengths(&[(S.num_vars_x(), U1.r_x.len()), (S.M.len(), U1.v.len())])?;
+
+    let mut transcript = NIMFSTranscript::new(ro_consts);
+    let (gamma, beta) = transcript.start(S, U1, U2);
+
+    let z1 = concat(vec![W1.W.clone(), vec![U1.u], U1.X.clone()]);
+    let z2 = concat(vec![W2.W.clone(), vec![G::Scalar::one()], U2.X.clone()]);
+    let to_polys = |Mz: Vec<Vec<G::Scalar>>| {
+      Mz.into_iter()
+        .map(MultilinearPolynomial::new)
+        .collect::<Vec<_>>()
+    };
+    let mut L = to_polys(S.multiply_vec(&z1)?);
+    let mut Q = to_polys(S.multiply_vec(&z2)?);
+    let mut eq_rx = MultilinearPolynomial::new(EqPolynomial::new(U1.r_x.clone()).evals());
+    let mut eq_beta = MultilinearPolynomial::new(EqPolynomial::new(beta).evals());
+
+    let (_, gamma_t) = batch(&U1.v, &gamma);
+    let degree = max(S.degree() + 1, 2);
+
+    // the sum-check, binding the variables from the top
+    let mut polys = Vec::new();
+    let mut r_x = Vec::new();
+    for _ in 0..S.num_vars_x() {
+      let half = eq_rx.len() / 2;
+      let evals = (0..half)
+        .into_par_iter()
+        .map(|b| {
+          (0..=degree)
+            .map(|k| {
+              let k = G::Scalar::from(k as u64);
+              let at = |p: &MultilinearPolynomial<G::Scalar>| p[b] + k * (p[b + half] - p[b]);
+              let (sum_L, _) = batch(&L.iter().map(at).collect::<Vec<_>>(), &gamma);
+              let sum_Q = S
+                .S
+                .iter()
+                .zip(S.c.iter())
+                .map(|(S_i, c_i)| S_i.iter().fold(*c_i, |acc, j| acc * at(&Q[*j])))
+                .fold(G::Scalar::zero(), |acc, x| acc + x);
+              at(&eq_rx) * sum_L + gamma_t * at(&eq_beta) * sum_Q
+            })
+            .collect::<Vec<G::Scalar>>()
+        })
+        .reduce(
+          || vec![G::Scalar::zero(); degree + 1],
+          |a, b| a.iter().zip(b.iter()).map(|(a, b)| *a + b).collect(),
+        );
+
+      let poly = interpolate(&evals);
+      for c in &poly {
+        transcript.absorb(*c);
+      }
+      let r_i = transcript.squeeze();
+
+      for p in L
+        .iter_mut()
+        .chain(Q.iter_mut())
+        .chain([&mut eq_rx, &mut eq_beta])
+      {
+        p.bound_poly_var_top(&r_i);
+      }
+      polys.push(poly);
+      r_x.push(r_i);
+    }
+
+    let sigmas = L.iter().map(|L_j| L_j[0]).collect::<Vec<G::Scalar>>();
+    let thetas = Q.iter().map(|Q_j| Q_j[0]).collect::<Vec<G::Scalar>>();
+    for e in sigmas.iter().chain(thetas.iter()) {
+      transcript.absorb(*e);
+    }
+    let rho = transcript.squeeze();
+
+    let nimfs = NIMFS {
+      polys,
+      sigmas,
+      thetas,
+    };
+    let U = nimfs.fold(U1, U2, r_x, &rho);
+    let W = W1.fold(W2, &rho)?;
+    debug_assert!(S.is_sat_linearized(gens, &U, &W).is_ok());
+
+    Ok((nimfs, (U, W)))
+  }
+
+  /// Takes as input a linearized instance `U1` and an instance `U2` of the same CCS `S`,
+  /// and outputs a folded linearized instance if the proof is valid
+  pub fn verify(
+    &self,
+    ro_consts: &NIMFSConstants<G>,
+    S: &CCSShape<G>,
+    U1: &LCCCSInstance<G>,
+    U2: &CCCSInstance<G>,
+  ) -> Result<LCCCSInstance<G>, NovaError> {
+    NovaError::check_lengths(&[
+      (S.num_vars_x(), U1.r_x.len()),
+      (S.M.len(), U1.v.len()),
+      (S.num_io, U1.X.len()),
+      (S.num_io, U2.X.len()),
+      (S.num_vars_x(), self.polys.len()),
+      (S.M.len(), self.sigmas.len()),
+      (S.M.len(), self.thetas.len()),
+    ])?;
+
+    let mut transcript = NIMFSTranscript::new(ro_consts);
+    let (gamma, beta) = transcript.start(S, U1, U2);
+    let (mut claim, gamma_t) = batch(&U1.v, &gamma);
+    let degree = max(S.degree() + 1, 2);
+
+    // verify the sum-check
+    let mut r_x = Vec::new();
+    for (round, poly) in self.polys.iter().enumerate() {
+      if poly.len() != degree + 1 {
+        return Err(NovaError::InvalidSumcheckProof {
+          check: SumcheckFailure::Degree { round },
+        });
+      }
+      if evaluate_poly(poly, &G::Scalar::zero()) + evaluate_poly(poly, &G::Scalar::one()) != claim {
+        return Err(NovaError::InvalidSumcheckProof {
+          check: SumcheckFailure::RoundSum { round },
+        });
+      }
+      for c in poly {
+        transcript.absorb(*c);
+      }
+      let r_i = transcript.squeeze();
+      claim = evaluate_poly(poly, &r_i);
+      r_x.push(r_i);
+    }
+
+    // check the final claim of the sum-check against sigma and theta
+    let (sum_sigmas, _) = batch(&self.sigmas, &gamma);
+    let sum_thetas = S
+      .S
+      .iter()
+      .zip(S.c.iter())
+      .map(|(S_i, c_i)| S_i.iter().fold(*c_i, |acc, j| acc * self.thetas[*j]))
+      .fold(G::Scalar::zero(), |acc, x| acc + x);
+    let expected = EqPolynomial::new(U1.r_x.clone()).evaluate(&r_x) * sum_sigmas
+      + gamma_t * EqPolynomial::new(beta).evaluate(&r_x) * sum_thetas;
+    if expected != claim {
+      return Err(NovaError::InvalidSumcheckProof {
+        check: SumcheckFailure::FinalClaim,
+      });
+    }
+
+    for e in self.sigmas.iter().chain(self.thetas.iter()) {
+      transcript.absorb(*e);
+    }
+    let rho = transcript.squeeze();
+
+    Ok(self.fold(U1, U2, r_x, &rho))
+  }
+
+  /// Folds the instances with `rho` into a linearized instance at `r_x`
+  fn fold(
+    &self,
+    U1: &LCCCSInstance<G>,
+    U2: &CCCSInstance<G>,
+    r_x: Vec<G::Scalar>,
+    rho: &G::Scalar,
+  ) -> LCCCSInstance<G> {
+    LCCCSInstance {
+      comm_W: U1.comm_W + U2.comm_W * *rho,
+      X: U1
+        .X
+        .iter()
+        .zip(U2.X.iter())
+        .map(|(x1, x2)| *x1 + *rho * x2)
+        .collect(),
+      u: U1.u + rho,
+      r_x,
+      v: self
+        .sigmas
+        .iter()
+        .zip(self.thetas.iter())
+        .map(|(sigma, theta)| *sigma + *rho * theta)
+        .collect(),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    bellperson::{
+      r1cs::{NovaShape, NovaWitness},
+      shape_cs::ShapeCS,
+      solver::SatisfyingAssignment,
+    },
+    traits::HashFuncConstantsTrait,
+  };
+  use ::bellperson::{gadgets::num::AllocatedNum, ConstraintSystem, SynthesisError};
+
+  type G = pasta_curves::pallas::Point;
+  type S = pasta_curves::pallas::Scalar;
+
+  fn synthesize_cubic<CS: ConstraintSystem<S>>(
+    cs: &mut CS,
+    x_val: Option<S>,
+  ) -> Result<(), SynthesisError> {
+    // `x^3 + x + 5 = y`, where `x` and `y` are respectively the input and output
+    let x = AllocatedNum::alloc(cs.namespace(|| "x"), || Ok(x_val.unwrap()))?;
+    x.inputize(cs.namespace(|| "x is input"))?;
+    let x_sq = x.square(cs.namespace(|| "x_sq"))?;
+    let x_cu = x_sq.mul(cs.namespace(|| "x_cu"), &x)?;
+    let y = AllocatedNum::alloc(cs.namespace(|| "y"), || {
+      Ok(x_cu.get_value().unwrap() + x.get_value().unwrap() + S::from(5u64))
+    })?;
+    y.inputize(cs.namespace(|| "y is output"))?;
+    cs.enforce(
+      || "y = x^3 + x + 5",
+      |lc| lc + x_cu.get_variable() + x.get_variable() + (S::from(5u64), CS::one()),
+      |lc| lc + CS::one(),
+      |lc| lc + y.get_variable(),
+    );
+    Ok(())
+  }
+
+  /// Folds the instances into the default linearized instance, checking each step
+  fn fold_all(
+    gens: &R1CSGens<G>,
+    shape: &CCSShape<G>,
+    instances: Vec<(CCCSInstance<G>, CCSWitness<G>)>,
+  ) -> (LCCCSInstance<G>, CCSWitness<G>) {
+    let ro_consts = NIMFSConstants::<G>::new();
+    let mut U = LCCCSInstance::default(gens, shape);
+    let mut W = CCSWitness::default(shape);
+    for (u, w) in instances {
+      assert!(shape.is_sat(gens, &u, &w).is_ok());
+      let (nimfs, (U_next, W_next)) =
+        NIMFS::prove(gens, &ro_consts, shape, &U, &W, &u, &w).unwrap();
+      assert_eq!(nimfs.verify(&ro_consts, shape, &U, &u), Ok(U_next.clone()));
+      assert!(shape.is_sat_linearized(gens, &U_next, &W_next).is_ok());
+      U = U_next;
+      W = W_next;
+    }
+    (U, W)
+  }
+
+  #[test]
+  fn test_interpolate() {
+    let coeffs = [S::from(3), S::from(5), S::from(7), S::from(11)];
+    let evals = (0..4u64)
+      .map(|k| evaluate_poly(&coeffs, &S::from(k)))
+      .collect::<Vec<S>>();
+    assert_eq!(interpolate(&evals), coeffs.to_vec());
+  }
+
+  #[test]
+  fn test_nimfs_with_r1cs() {
+    let mut cs: ShapeCS<G> = ShapeCS::new();
+    let _ = synthesize_cubic(&mut cs, None);
+    let r1cs_shape = cs.r1cs_shape();
+    let gens = cs.r1cs_gens();
+    let shape = CCSShape::from_r1cs(&r1cs_shape);
+
+    let instances = [3u64, 5, 11]
+      .iter()
+      .map(|x| {
+        let mut cs: SatisfyingAssignment<G> = SatisfyingAssignment::new();
+        let _ = synthesize_cubic(&mut cs, Some(S::from(*x)));
+        let (U, W) = cs.r1cs_instance_and_witness(&r1cs_shape, &gens).unwrap();
+        (
+          CCCSInstance::new(&shape, &U.comm_W, &U.X).unwrap(),
+          CCSWitness::new(&shape, &W.W).unwrap(),
+        )
+      })
+      .collect();
+    fold_all(&gens, &shape, instances);
+  }
+
+  /// A CCS with `num_cons` degree-5 gates `x_i^5 + x_i = y_i`, over `z = (x, y, 1)`
+  fn quintic_shape(num_cons: usize) -> CCSShape<G> {
+    let one = S::one();
+    let M0 = (0..num_cons).map(|i| (i, i, one)).collect::<Vec<_>>();
+    let M1 = (0..num_cons)
+      .map(|i| (i, num_cons + i, one))
+      .collect::<Vec<_>>();
+    CCSShape::new(
+      num_cons,
+      2 * num_cons,
+      0,
+      &[M0, M1],
+      &[vec![0, 0, 0, 0, 0], vec![0], vec![1]],
+      &[one, one, -one],
+    )
+    .unwrap()
+  }
+
+  fn quintic_witness(shape: &CCSShape<G>, xs: &[u64]) -> CCSWitness<G> {
+    let xs = xs.iter().map(|x| S::from(*x)).collect::<Vec<S>>();
+    let ys = xs
+      .iter()
+      .map(|x| x.square().square() * x + x)
+      .collect::<Vec<S>>();
+    CCSWitness::new(shape, &concat(vec![xs, ys])).unwrap()
+  }
+
+  #[test]
+  fn test_nimfs_with_custom_gates() {
+    let shape = quintic_shape(3);
+    let gens = shape.gens();
+    assert_eq!(shape.degree(), 5);
+
+    let instances = [[1, 2, 3], [4, 5, 6], [0, 0, 7]]
+      .iter()
+      .map(|xs| {
+        let W = quintic_witness(&shape, xs);
+        let U = CCCSInstance::new(&shape, &W.commit(&gens), &[]).unwrap();
+        (U, W)
+      })
+      .collect();
+    let (U, W) = fold_all(&gens, &shape, instances);
+
+    // an instance that does not satisfy the CCS cannot be folded
+    let mut W_bad = quintic_witness(&shape, &[1, 1, 1]);
+    W_bad.W[3] += S::one();
+    let U_bad = CCCSInstance::new(&shape, &W_bad.commit(&gens), &[]).unwrap();
+    assert!(matches!(
+      shape.is_sat(&gens, &U_bad, &W_bad),
+      Err(NovaError::UnSat {
+        constraint: Some(_)
+      })
+    ));
+    let ro_consts = NIMFSConstants::<G>::new();
+    let (nimfs, _) = NIMFS::prove(&gens, &ro_consts, &shape, &U, &W, &U_bad, &W_bad).unwrap();
+    assert_eq!(
+      nimfs.verify(&ro_consts, &shape, &U, &U_bad),
+      Err(NovaError::InvalidSumcheckProof {
+        check: SumcheckFailure::RoundSum { round: 0 }
+      })
+    );
+
+    // a tampered proof is rejected
+    let u = CCCSInstance::new(
+      &shape,
+      &quintic_witness(&shape, &[2, 2, 2]).commit(&gens),
+      &[],
+    )
+    .unwrap();
+    let (mut nimfs, _) = NIMFS::prove(
+      &gens,
+      &ro_consts,
+      &shape,
+      &U,
+      &W,
+      &u,
+      &quintic_witness(&shape, &[2, 2, 2]),
+    )
+    .unwrap();
+    assert!(nimfs.verify(&ro_consts, &shape, &U, &u).is_ok());
+    nimfs.thetas[0] += S::one();
+    assert_eq!(
+      nimfs.verify(&ro_consts, &shape, &U, &u),
+      Err(NovaError::InvalidSumcheckProof {
+        check: SumcheckFailure::FinalClaim
+      })
+    );
+  }
+}
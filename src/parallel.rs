@@ -0,0 +1,146 @@
+//! This module gates the use of rayon behind the `parallel` feature. With the feature, it
+//! re-exports `rayon::join` and the parallel iterators of rayon. Without it, `join` runs its
+//! closures one after the other, and `par_iter`, `par_iter_mut` and `into_par_iter` return
+//! sequential iterators with the methods of parallel iterators that differ from `Iterator`.
+#[cfg(feature = "parallel")]
+pub(crate) use rayon::join;
+
+#[cfg(not(feature = "parallel"))]
+pub(crate) use sequential::join;
+
+/// The traits that provide the parallel iterators, or their sequential fallbacks
+pub(crate) mod prelude {
+  #[cfg(feature = "parallel")]
+  pub(crate) use rayon::prelude::*;
+
+  #[cfg(not(feature = "parallel"))]
+  pub(crate) use super::sequential::{
+    IntoParallelIterator, IntoParallelRefIterator, IntoParallelRefMutIterator,
+  };
+}
+
+#[cfg(not(feature = "parallel"))]
+mod sequential {
+  use core::iter::{Enumerate, Map, Zip};
+
+  /// Runs the two closures one after the other and returns their results
+  pub(crate) fn join<A, B, RA, RB>(a: A, b: B) -> (RA, RB)
+  where
+    A: FnOnce() -> RA,
+    B: FnOnce() -> RB,
+  {
+    (a(), b())
+  }
+
+  /// A sequential iterator whose adapters and reductions have the signatures of rayon's
+  pub(crate) struct SeqIter<I>(I);
+
+  impl<I: Iterator> Iterator for SeqIter<I> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+      self.0.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+      self.0.size_hint()
+    }
+  }
+
+  impl<I: Iterator> SeqIter<I> {
+    pub(crate) fn map<B, F: FnMut(I::Item) -> B>(self, f: F) -> SeqIter<Map<I, F>> {
+      SeqIter(self.0.map(f))
+    }
+
+    pub(crate) fn zip<J: IntoIterator>(self, other: J) -> SeqIter<Zip<I, J::IntoIter>> {
+      SeqIter(self.0.zip(other))
+    }
+
+    pub(crate) fn enumerate(self) -> SeqIter<Enumerate<I>> {
+      SeqIter(self.0.enumerate())
+    }
+
+    /// Reduces the items with `op`, starting from `identity()`
+    pub(crate) fn reduce<ID, OP>(self, identity: ID, op: OP) -> I::Item
+    where
+      ID: Fn() -> I::Item,
+      OP: Fn(I::Item, I::Item) -> I::Item,
+    {
+      self.0.fold(identity(), op)
+    }
+
+    /// Returns the first item that satisfies `predicate`
+    pub(crate) fn find_first<P: FnMut(&I::Item) -> bool>(
+      mut self,
+      predicate: P,
+    ) -> Option<I::Item> {
+      self.0.find(predicate)
+    }
+  }
+
+  pub(crate) trait IntoParallelIterator: IntoIterator + Sized {
+    fn into_par_iter(self) -> SeqIter<Self::IntoIter> {
+      SeqIter(self.into_iter())
+    }
+  }
+
+  impl<T: IntoIterator> IntoParallelIterator for T {}
+
+  pub(crate) trait IntoParallelRefIterator<'a> {
+    type Iter: Iterator;
+
+    fn par_iter(&'a self) -> SeqIter<Self::Iter>;
+  }
+
+  impl<'a, T: 'a + ?Sized> IntoParallelRefIterator<'a> for T
+  where
+    &'a T: IntoIterator,
+  {
+    type Iter = <&'a T as IntoIterator>::IntoIter;
+
+    fn par_iter(&'a self) -> SeqIter<Self::Iter> {
+      SeqIter(self.into_iter())
+    }
+  }
+
+  pub(crate) trait IntoParallelRefMutIterator<'a> {
+    type Iter: Iterator;
+
+    fn par_iter_mut(&'a mut self) -> SeqIter<Self::Iter>;
+  }
+
+  impl<'a, T: 'a + ?Sized> IntoParallelRefMutIterator<'a> for T
+  where
+    &'a mut T: IntoIterator,
+  {
+    type Iter = <&'a mut T as IntoIterator>::IntoIter;
+
+    fn par_iter_mut(&'a mut self) -> SeqIter<Self::Iter> {
+      SeqIter(self.into_iter())
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{join, prelude::*};
+
+  #[test]
+  fn test_parallel_iterators() {
+    let mut v = (0..100usize).collect::<Vec<_>>();
+    v.par_iter_mut().for_each(|x| *x *= 2);
+    let sum = v
+      .par_iter()
+      .zip((0..100usize).into_par_iter())
+      .map(|(x, y)| x + y)
+      .reduce(|| 0, |x, y| x + y);
+    assert_eq!(sum, 3 * 4950);
+    assert_eq!(
+      v.par_iter()
+        .enumerate()
+        .find_first(|(i, x)| *i > 10 && **x % 3 == 0),
+      Some((12, &24))
+    );
+    assert_eq!(join(|| sum + 1, || v.len()), (3 * 4950 + 1, 100));
+  }
+}
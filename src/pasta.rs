@@ -1,5 +1,6 @@
 //! This module implements the Nova traits for pallas::Point, pallas::Scalar, vesta::Point, vesta::Scalar.
 use crate::{
+  parallel::prelude::*,
   poseidon::PoseidonRO,
   traits::{ChallengeTrait, CompressedGroup, Group},
 };
@@ -10,13 +11,12 @@ use num_bigint::BigInt;
 use num_traits::Num;
 use pasta_curves::{
   self,
-  arithmetic::{CurveAffine, CurveExt, Group as OtherGroup},
+  arithmetic::{CurveAffine, CurveExt, FieldExt, Group as OtherGroup},
   group::{Curve, GroupEncoding},
   pallas, vesta, Ep, Eq,
 };
 use rand::SeedableRng;
 use rand_chacha::ChaCha20Rng;
-use rayon::prelude::*;
 use sha3::Shake256;
 use std::{io::Read, ops::Mul};
 
@@ -61,11 +61,15 @@ impl Group for pallas::Point {
     self.to_affine()
   }
 
+  fn compress_preprocessed(p: &Self::PreprocessedGroupElement) -> Self::CompressedGroupElement {
+    PallasCompressedElementWrapper::new(p.to_bytes())
+  }
+
   fn compress(&self) -> Self::CompressedGroupElement {
     PallasCompressedElementWrapper::new(self.to_bytes())
   }
 
-  fn from_label(label: &'static [u8], n: usize) -> Vec<Self::PreprocessedGroupElement> {
+  fn from_label(label: &[u8], n: usize) -> Vec<Self::PreprocessedGroupElement> {
     let mut shake = Shake256::default();
     shake.input(label);
     let mut reader = shake.xof_result();
@@ -95,6 +99,14 @@ impl Group for pallas::Point {
     )
     .unwrap()
   }
+
+  fn get_curve_params() -> (Self::Base, Self::Base) {
+    (pallas::Affine::a(), pallas::Affine::b())
+  }
+
+  fn get_endomorphism() -> (Self::Base, Self::Scalar) {
+    (pallas::Base::ZETA, pallas::Scalar::ZETA)
+  }
 }
 
 impl ChallengeTrait for pallas::Scalar {
@@ -110,11 +122,14 @@ impl CompressedGroup for PallasCompressedElementWrapper {
   type GroupElement = pallas::Point;
 
   fn decompress(&self) -> Option<pallas::Point> {
-    Some(Ep::from_bytes(&self.repr).unwrap())
+    Option::from(Ep::from_bytes(&self.repr))
   }
   fn as_bytes(&self) -> &[u8] {
     &self.repr
   }
+  fn from_bytes(bytes: &[u8]) -> Option<Self> {
+    Some(Self::new(bytes.try_into().ok()?))
+  }
 }
 
 //////////////////////////////////////Vesta////////////////////////////////////////////////
@@ -154,6 +169,10 @@ impl Group for vesta::Point {
     }
   }
 
+  fn compress_preprocessed(p: &Self::PreprocessedGroupElement) -> Self::CompressedGroupElement {
+    VestaCompressedElementWrapper::new(p.to_bytes())
+  }
+
   fn compress(&self) -> Self::CompressedGroupElement {
     VestaCompressedElementWrapper::new(self.to_bytes())
   }
@@ -162,7 +181,7 @@ impl Group for vesta::Point {
     self.to_affine()
   }
 
-  fn from_label(label: &'static [u8], n: usize) -> Vec<Self::PreprocessedGroupElement> {
+  fn from_label(label: &[u8], n: usize) -> Vec<Self::PreprocessedGroupElement> {
     let mut shake = Shake256::default();
     shake.input(label);
     let mut reader = shake.xof_result();
@@ -192,6 +211,14 @@ impl Group for vesta::Point {
     )
     .unwrap()
   }
+
+  fn get_curve_params() -> (Self::Base, Self::Base) {
+    (vesta::Affine::a(), vesta::Affine::b())
+  }
+
+  fn get_endomorphism() -> (Self::Base, Self::Scalar) {
+    (vesta::Base::ZETA, vesta::Scalar::ZETA)
+  }
 }
 
 impl ChallengeTrait for vesta::Scalar {
@@ -207,9 +234,12 @@ impl CompressedGroup for VestaCompressedElementWrapper {
   type GroupElement = vesta::Point;
 
   fn decompress(&self) -> Option<vesta::Point> {
-    Some(Eq::from_bytes(&self.repr).unwrap())
+    Option::from(Eq::from_bytes(&self.repr))
   }
   fn as_bytes(&self) -> &[u8] {
     &self.repr
   }
+  fn from_bytes(bytes: &[u8]) -> Option<Self> {
+    Some(Self::new(bytes.try_into().ok()?))
+  }
 }
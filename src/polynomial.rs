@@ -0,0 +1,319 @@
+//! This module implements the multilinear polynomials used by the Spartan SNARK and the
+//! multi-folding scheme: the multilinear extension eq(r, .) of the equality function, and dense
+//! and sparse multilinear polynomials.
+use crate::parallel::prelude::*;
+use core::ops::Index;
+use ff::PrimeField;
+
+/// The multilinear extension of the equality function, eq(r, x) = \prod_i (r_i x_i + (1 - r_i)(1 - x_i))
+pub struct EqPolynomial<Scalar: PrimeField> {
+  r: Vec<Scalar>,
+}
+
+impl<Scalar: PrimeField> EqPolynomial<Scalar> {
+  /// Creates eq(r, .) for the point `r`
+  pub fn new(r: Vec<Scalar>) -> Self {
+    EqPolynomial { r }
+  }
+
+  /// Evaluates eq(r, rx)
+  pub fn evaluate(&self, rx: &[Scalar]) -> Scalar {
+    assert_eq!(self.r.len(), rx.len());
+    (0..rx.len())
+      .map(|i| rx[i] * self.r[i] + (Scalar::one() - rx[i]) * (Scalar::one() - self.r[i]))
+      .fold(Scalar::one(), |acc, item| acc * item)
+  }
+
+  /// Returns eq(r, x) for every x in the Boolean hypercube
+  pub fn evals(&self) -> Vec<Scalar> {
+    let mut evals = Vec::new();
+    self.evals_into(&mut evals);
+    evals
+  }
+
+  // same as evals, but writes into a caller-provided buffer so that its allocation can be reused
+  pub(crate) fn evals_into(&self, evals: &mut Vec<Scalar>) {
+    let ell = self.r.len();
+    evals.clear();
+    evals.resize((2_usize).pow(ell as u32), Scalar::zero());
+    let mut size = 1;
+    evals[0] = Scalar::one();
+
+    for r in self.r.iter().rev() {
+      let (evals_left, evals_right) = evals.split_at_mut(size);
+      let (evals_right, _) = evals_right.split_at_mut(size);
+
+      evals_left
+        .par_iter_mut()
+        .zip(evals_right.par_iter_mut())
+        .for_each(|(x, y)| {
+          *y = *x * r;
+          *x -= &*y;
+        });
+
+      size *= 2;
+    }
+  }
+
+  /// Splits the variables into two halves and returns evaluation tables for each half,
+  /// so that eq(r, i) = evals_hi[i >> ell_lo] * evals_lo[i & ((1 << ell_lo) - 1)]
+  pub fn split_evals(&self) -> SplitEqPolynomial<Scalar> {
+    let ell = self.r.len();
+    let (r_hi, r_lo) = self.r.split_at(ell - ell / 2);
+
+    SplitEqPolynomial {
+      evals_hi: EqPolynomial::new(r_hi.to_vec()).evals(),
+      evals_lo: EqPolynomial::new(r_lo.to_vec()).evals(),
+    }
+  }
+}
+
+/// Evaluations of eq(r, .) over the Boolean hypercube stored as two tables of size O(sqrt(2^ell)),
+/// where ell is the number of variables. An entry of the full table is recovered with one multiplication.
+pub struct SplitEqPolynomial<Scalar: PrimeField> {
+  evals_hi: Vec<Scalar>,
+  evals_lo: Vec<Scalar>,
+}
+
+impl<Scalar: PrimeField> SplitEqPolynomial<Scalar> {
+  pub(crate) fn len(&self) -> usize {
+    self.evals_hi.len() * self.evals_lo.len()
+  }
+
+  /// Returns \sum_i eq(r, i) * Z[i] in O(n) time and O(sqrt(n)) additional space
+  pub fn inner_product(&self, Z: &[Scalar]) -> Scalar {
+    assert_eq!(Z.len(), self.len());
+    self.weighted_sum(|i| [Z[i]])[0]
+  }
+
+  // returns \sum_i eq(r, i) * f(i) for a vector-valued f, without materializing eq(r, .)
+  pub(crate) fn weighted_sum<const K: usize, F>(&self, f: F) -> [Scalar; K]
+  where
+    F: Fn(usize) -> [Scalar; K] + Sync,
+  {
+    let len_lo = self.evals_lo.len();
+    self
+      .evals_hi
+      .par_iter()
+      .enumerate()
+      .map(|(i_hi, eval_hi)| {
+        let mut acc = [Scalar::zero(); K];
+        for (i_lo, eval_lo) in self.evals_lo.iter().enumerate() {
+          let f_i = f(i_hi * len_lo + i_lo);
+          for k in 0..K {
+            acc[k] += f_i[k] * eval_lo;
+          }
+        }
+        acc.map(|a| a * eval_hi)
+      })
+      .reduce(
+        || [Scalar::zero(); K],
+        |mut x, y| {
+          for k in 0..K {
+            x[k] += y[k];
+          }
+          x
+        },
+      )
+  }
+}
+
+/// A multilinear polynomial represented by its evaluations over the Boolean hypercube
+#[derive(Debug)]
+pub struct MultilinearPolynomial<Scalar: PrimeField> {
+  num_vars: usize, // the number of variables in the multilinear polynomial
+  Z: Vec<Scalar>,  // evaluations of the polynomial in all the 2^num_vars Boolean inputs
+}
+
+impl<Scalar: PrimeField> MultilinearPolynomial<Scalar> {
+  /// Creates the multilinear polynomial whose evaluations are `Z`, whose length is a power of two
+  pub fn new(Z: Vec<Scalar>) -> Self {
+    assert_eq!(Z.len(), (2_usize).pow((Z.len() as f64).log2() as u32));
+    MultilinearPolynomial {
+      num_vars: (Z.len() as f64).log2() as usize,
+      Z,
+    }
+  }
+
+  /// Returns the number of variables of the polynomial
+  pub fn get_num_vars(&self) -> usize {
+    self.num_vars
+  }
+
+  pub(crate) fn len(&self) -> usize {
+    self.Z.len()
+  }
+
+  // releases the underlying buffer so that its allocation can be reused
+  pub(crate) fn into_vec(self) -> Vec<Scalar> {
+    self.Z
+  }
+
+  pub(crate) fn bound_poly_var_top(&mut self, r: &Scalar) {
+    let n = self.len() / 2;
+
+    let (left, right) = self.Z.split_at_mut(n);
+    let (right, _) = right.split_at(n);
+
+    left
+      .par_iter_mut()
+      .zip(right.par_iter())
+      .for_each(|(a, b)| {
+        *a += *r * (*b - *a);
+      });
+
+    self.Z.resize(n, Scalar::zero());
+    self.num_vars -= 1;
+  }
+
+  /// Returns Z(r) in O(n) time and O(sqrt(n)) additional space
+  pub fn evaluate(&self, r: &[Scalar]) -> Scalar {
+    // r must have a value for each variable
+    assert_eq!(r.len(), self.get_num_vars());
+    EqPolynomial::new(r.to_vec())
+      .split_evals()
+      .inner_product(&self.Z)
+  }
+}
+
+impl<Scalar: PrimeField> Index<usize> for MultilinearPolynomial<Scalar> {
+  type Output = Scalar;
+
+  #[inline(always)]
+  fn index(&self, _index: usize) -> &Scalar {
+    &(self.Z[_index])
+  }
+}
+
+/// A multilinear polynomial represented by its non-zero evaluations over the Boolean hypercube
+pub struct SparsePolynomial<Scalar: PrimeField> {
+  #[cfg_attr(not(feature = "bench-internals"), allow(dead_code))]
+  num_vars: usize,
+  Z: Vec<(usize, Scalar)>,
+}
+
+impl<Scalar: PrimeField> SparsePolynomial<Scalar> {
+  /// Creates a polynomial in `num_vars` variables from its non-zero evaluations `(index, value)`
+  pub fn new(num_vars: usize, Z: Vec<(usize, Scalar)>) -> Self {
+    SparsePolynomial { num_vars, Z }
+  }
+
+  /// Returns Z(r) in O(min(m, |Z| * num_vars)) time, where m is the largest index in Z.
+  /// The verifier evaluates the public IO with `evaluate_with_table` instead, so outside of the
+  /// tests only the benchmarks call this method.
+  #[cfg_attr(not(feature = "bench-internals"), allow(dead_code))]
+  pub fn evaluate(&self, r: &[Scalar]) -> Scalar {
+    assert_eq!(self.num_vars, r.len());
+
+    // the number of low-order variables needed to index entries in Z;
+    // the remaining high-order variables are zero for every entry
+    let max_index = self.Z.iter().map(|(i, _)| *i).max().unwrap_or(0);
+    let num_vars_Z = (usize::BITS - max_index.leading_zeros()) as usize;
+    assert!(num_vars_Z <= self.num_vars);
+
+    // when the entries are dense in a prefix of the Boolean hypercube (e.g., the public IO of an
+    // R1CS instance), a table of eq(r, .) over that prefix is the cheapest
+    if (1 << num_vars_Z) <= self.Z.len() * self.num_vars {
+      let (r_hi, r_lo) = r.split_at(self.num_vars - num_vars_Z);
+      let common = r_hi
+        .iter()
+        .fold(Scalar::one(), |acc, r_i| acc * (Scalar::one() - r_i));
+      let chis = EqPolynomial::new(r_lo.to_vec()).evals();
+      return common * self.evaluate_with_table(&chis);
+    }
+
+    // otherwise, each entry contributes z * eq(r, i), where eq(r, i) is a product over the bits of i
+    let one_minus_r = r
+      .iter()
+      .map(|r_j| Scalar::one() - r_j)
+      .collect::<Vec<Scalar>>();
+    self
+      .Z
+      .par_iter()
+      .map(|(i, z)| {
+        // r[0] corresponds to the most significant bit of the index
+        let chi = (0..self.num_vars).fold(Scalar::one(), |chi, j| {
+          if (i >> (self.num_vars - 1 - j)) & 1 == 1 {
+            chi * r[j]
+          } else {
+            chi * one_minus_r[j]
+          }
+        });
+        chi * z
+      })
+      .reduce(Scalar::zero, |x, y| x + y)
+  }
+
+  // Takes O(|Z|) given chis = EqPolynomial::new(r).evals() for a point r
+  pub(crate) fn evaluate_with_table(&self, chis: &[Scalar]) -> Scalar {
+    self
+      .Z
+      .iter()
+      .fold(Scalar::zero(), |acc, (i, z)| acc + chis[*i] * z)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use ff::Field;
+  use rand::rngs::OsRng;
+
+  type S = pasta_curves::pallas::Scalar;
+
+  fn random_point(num_vars: usize) -> Vec<S> {
+    (0..num_vars).map(|_| S::random(&mut OsRng)).collect()
+  }
+
+  #[test]
+  fn test_split_eq_polynomial() {
+    for num_vars in 0..8 {
+      let r = random_point(num_vars);
+      let evals = EqPolynomial::new(r.clone()).evals();
+      let split = EqPolynomial::new(r).split_evals();
+      assert_eq!(split.len(), evals.len());
+
+      // taking an inner product with each unit vector recovers the full table
+      for (i, eval) in evals.iter().enumerate() {
+        let mut e_i = vec![S::zero(); evals.len()];
+        e_i[i] = S::one();
+        assert_eq!(split.inner_product(&e_i), *eval);
+      }
+    }
+  }
+
+  #[test]
+  fn test_multilinear_evaluate() {
+    for num_vars in 0..8 {
+      let Z = random_point(1 << num_vars);
+      let r = random_point(num_vars);
+      let chis = EqPolynomial::new(r.clone()).evals();
+      let expected = (0..Z.len()).fold(S::zero(), |acc, i| acc + chis[i] * Z[i]);
+      assert_eq!(MultilinearPolynomial::new(Z).evaluate(&r), expected);
+    }
+  }
+
+  #[test]
+  fn test_sparse_evaluate() {
+    let num_vars = 6;
+    let r = random_point(num_vars);
+    let chis = EqPolynomial::new(r.clone()).evals();
+    for num_entries in [1, 2, 3, 5, 8] {
+      let Z = (0..num_entries)
+        .map(|i| (i, S::random(&mut OsRng)))
+        .collect::<Vec<(usize, S)>>();
+      let expected = Z.iter().fold(S::zero(), |acc, (i, z)| acc + chis[*i] * z);
+      let poly = SparsePolynomial::new(num_vars, Z);
+      assert_eq!(poly.evaluate(&r), expected);
+      assert_eq!(poly.evaluate_with_table(&chis), expected);
+    }
+
+    // entries scattered over the whole hypercube
+    let Z = [0, 7, 33, 63]
+      .iter()
+      .map(|i| (*i, S::random(&mut OsRng)))
+      .collect::<Vec<(usize, S)>>();
+    let expected = Z.iter().fold(S::zero(), |acc, (i, z)| acc + chis[*i] * z);
+    assert_eq!(SparsePolynomial::new(num_vars, Z).evaluate(&r), expected);
+  }
+}
@@ -1,6 +1,7 @@
 //! Poseidon Constants and Poseidon-based RO used in Nova
 use super::{
-  constants::{NUM_CHALLENGE_BITS, NUM_HASH_BITS},
+  gadgets::utils::alloc_zero,
+  security::SecurityConfig,
   traits::{HashFuncConstantsTrait, HashFuncTrait},
 };
 use bellperson::{
@@ -10,13 +11,13 @@ use bellperson::{
   },
   ConstraintSystem, SynthesisError,
 };
-use core::marker::PhantomData;
+use core::{iter, marker::PhantomData};
 use ff::{PrimeField, PrimeFieldBits};
 use generic_array::typenum::{U27, U32};
 use neptune::{
   circuit::poseidon_hash,
   poseidon::{Poseidon, PoseidonConstants},
-  Strength,
+  Arity, Strength,
 };
 
 /// All Poseidon Constants that are used in Nova
@@ -27,22 +28,53 @@ where
 {
   constants27: PoseidonConstants<Scalar, U27>,
   constants32: PoseidonConstants<Scalar, U32>,
+  config: SecurityConfig,
 }
 
 impl<Scalar> HashFuncConstantsTrait<Scalar> for ROConstantsCircuit<Scalar>
 where
   Scalar: PrimeField + PrimeFieldBits,
 {
-  /// Generate Poseidon constants for the arities that Nova uses
-  #[allow(clippy::new_without_default)]
-  fn new() -> Self {
-    let constants27 = PoseidonConstants::<Scalar, U27>::new_with_strength(Strength::Strengthened);
-    let constants32 = PoseidonConstants::<Scalar, U32>::new_with_strength(Strength::Strengthened);
+  /// Generate Poseidon constants for the arities that Nova uses with the strength of `config`
+  fn new_with_config(config: &SecurityConfig) -> Self {
+    let strength: Strength = config.poseidon_strength.into();
+    let constants27 = PoseidonConstants::<Scalar, U27>::new_with_strength(strength);
+    let constants32 = PoseidonConstants::<Scalar, U32>::new_with_strength(strength);
     Self {
       constants27,
       constants32,
+      config: *config,
     }
   }
+
+  fn config(&self) -> &SecurityConfig {
+    &self.config
+  }
+
+  fn to_bytes(&self) -> Vec<u8> {
+    [
+      poseidon_constants_to_bytes(&self.constants27),
+      poseidon_constants_to_bytes(&self.constants32),
+    ]
+    .concat()
+  }
+}
+
+/// Serializes the numbers of rounds, the domain tag, the round constants and the MDS matrix,
+/// which determine the permutation of Poseidon
+fn poseidon_constants_to_bytes<Scalar: PrimeField, A: Arity<Scalar>>(
+  constants: &PoseidonConstants<Scalar, A>,
+) -> Vec<u8> {
+  let mut bytes = Vec::new();
+  bytes.extend((constants.full_rounds as u64).to_le_bytes());
+  bytes.extend((constants.partial_rounds as u64).to_le_bytes());
+  let elements = iter::once(&constants.domain_tag)
+    .chain(&constants.round_constants)
+    .chain(constants.mds_matrices.m.iter().flatten());
+  for e in elements {
+    bytes.extend(e.to_repr().as_ref());
+  }
+  bytes
 }
 
 /// A Poseidon-based RO to use outside circuits
@@ -63,24 +95,50 @@ where
   Base: PrimeField + PrimeFieldBits,
   Scalar: PrimeField + PrimeFieldBits,
 {
+  /// Hashes the state prefixed with its length, so that states of different lengths
+  /// never share a preimage of the permutation, whatever padding or chaining they go through
   fn hash_inner(&self) -> Base {
-    match self.state.len() {
-      27 => {
-        Poseidon::<Base, U27>::new_with_preimage(&self.state, &self.constants.constants27).hash()
-      }
-      32 => {
-        Poseidon::<Base, U32>::new_with_preimage(&self.state, &self.constants.constants32).hash()
-      }
-      _ => {
-        panic!(
-          "Number of elements in the RO state does not match any of the arities used in Nova: {:?}",
-          self.state.len()
-        );
+    let elements = [
+      vec![Base::from(self.state.len() as u64)],
+      self.state.clone(),
+    ]
+    .concat();
+    Self::hash_elements(&self.constants, &elements)
+  }
+
+  /// Hashes the elements with the arity that matches their number. Any other number of
+  /// elements is handled by `pad_state`, which reduces it to one of the supported arities.
+  /// The layout of the preimages only depends on the number of elements, which the caller
+  /// must bind, as `hash_inner` does.
+  fn hash_elements(constants: &ROConstantsCircuit<Base>, elements: &[Base]) -> Base {
+    match elements.len() {
+      27 => Poseidon::<Base, U27>::new_with_preimage(elements, &constants.constants27).hash(),
+      32 => Poseidon::<Base, U32>::new_with_preimage(elements, &constants.constants32).hash(),
+      n if n > 32 => {
+        // compress the first chunk and continue with its digest in place of the chunk,
+        // one chunk at a time so that long states such as transcripts hash in linear time
+        let mut digest = Self::hash_elements(constants, &elements[..32]);
+        let mut rest = &elements[32..];
+        while rest.len() >= 32 {
+          let chunk = [&[digest], &rest[..31]].concat();
+          digest = Self::hash_elements(constants, &chunk);
+          rest = &rest[31..];
+        }
+        Self::hash_elements(constants, &[&[digest], rest].concat())
       }
+      _ => Self::hash_elements(constants, &pad_state(elements.to_vec(), Base::zero())),
     }
   }
 }
 
+/// Pads a state with fewer than 32 elements with zeros to the next arity used in Nova.
+/// Padded states are only distinct from unpadded ones because the state starts with its length.
+fn pad_state<T: Clone>(mut state: Vec<T>, zero: T) -> Vec<T> {
+  let arity = if state.len() < 27 { 27 } else { 32 };
+  state.resize(arity, zero);
+  state
+}
+
 impl<Base, Scalar> HashFuncTrait<Base, Scalar> for PoseidonRO<Base, Scalar>
 where
   Base: PrimeField + PrimeFieldBits,
@@ -92,7 +150,7 @@ where
     Self {
       state: Vec::new(),
       constants,
-      _p: PhantomData::default(),
+      _p: PhantomData,
     }
   }
 
@@ -104,11 +162,11 @@ where
   /// Compute a challenge by hashing the current state
   fn get_challenge(&self) -> Scalar {
     let hash = self.hash_inner();
-    // Only keep NUM_CHALLENGE_BITS bits
+    // Only keep the number of bits of a challenge in the configuration
     let bits = hash.to_le_bits();
     let mut res = Scalar::zero();
     let mut coeff = Scalar::one();
-    for bit in bits[0..NUM_CHALLENGE_BITS].into_iter() {
+    for bit in bits[0..self.constants.config.num_challenge_bits].into_iter() {
       if *bit {
         res += coeff;
       }
@@ -119,11 +177,11 @@ where
 
   fn get_hash(&self) -> Scalar {
     let hash = self.hash_inner();
-    // Only keep NUM_HASH_BITS bits
+    // Only keep the number of bits of a hash in the configuration
     let bits = hash.to_le_bits();
     let mut res = Scalar::zero();
     let mut coeff = Scalar::one();
-    for bit in bits[0..NUM_HASH_BITS].into_iter() {
+    for bit in bits[0..self.constants.config.num_hash_bits].into_iter() {
       if *bit {
         res += coeff;
       }
@@ -131,6 +189,10 @@ where
     }
     res
   }
+
+  fn config(&self) -> &SecurityConfig {
+    &self.constants.config
+  }
 }
 
 /// A Poseidon-based RO gadget to use inside the verifier circuit.
@@ -160,28 +222,54 @@ where
     self.state.push(e);
   }
 
-  fn hash_inner<CS>(&mut self, mut cs: CS) -> Result<Vec<AllocatedBit>, SynthesisError>
+  /// Mirrors `PoseidonRO::hash_elements` inside the circuit
+  fn hash_elements<CS>(
+    &self,
+    mut cs: CS,
+    elements: Vec<AllocatedNum<Scalar>>,
+  ) -> Result<AllocatedNum<Scalar>, SynthesisError>
   where
     CS: ConstraintSystem<Scalar>,
   {
-    let out = match self.state.len() {
+    match elements.len() {
       27 => poseidon_hash(
         cs.namespace(|| "Poseidon hash"),
-        self.state.clone(),
+        elements,
         &self.constants.constants27,
-      )?,
+      ),
       32 => poseidon_hash(
         cs.namespace(|| "Posideon hash"),
-        self.state.clone(),
+        elements,
         &self.constants.constants32,
-      )?,
+      ),
+      n if n > 32 => {
+        let digest = self.hash_elements(cs.namespace(|| "hash chunk"), elements[..32].to_vec())?;
+        let rest = [vec![digest], elements[32..].to_vec()].concat();
+        self.hash_elements(cs.namespace(|| "hash rest"), rest)
+      }
       _ => {
-        panic!(
-          "Number of elements in the RO state does not match any of the arities used in Nova: {}",
-          self.state.len()
-        )
+        let zero = alloc_zero(cs.namespace(|| "padding"))?;
+        let state = pad_state(elements, zero);
+        self.hash_elements(cs.namespace(|| "hash padded"), state)
       }
-    };
+    }
+  }
+
+  fn hash_inner<CS>(&mut self, mut cs: CS) -> Result<Vec<AllocatedBit>, SynthesisError>
+  where
+    CS: ConstraintSystem<Scalar>,
+  {
+    // prefix the state with its length, as in `PoseidonRO::hash_inner`
+    let n = self.state.len() as u64;
+    let len = AllocatedNum::alloc(cs.namespace(|| "length"), || Ok(Scalar::from(n)))?;
+    cs.enforce(
+      || "check length",
+      |lc| lc,
+      |lc| lc,
+      |lc| lc + len.get_variable() - (Scalar::from(n), CS::one()),
+    );
+    let elements = [vec![len], self.state.clone()].concat();
+    let out = self.hash_elements(&mut cs, elements)?;
 
     // return the hash as a vector of bits
     Ok(
@@ -202,7 +290,7 @@ where
     CS: ConstraintSystem<Scalar>,
   {
     let bits = self.hash_inner(cs.namespace(|| "hash"))?;
-    Ok(bits[..NUM_CHALLENGE_BITS].into())
+    Ok(bits[..self.constants.config.num_challenge_bits].into())
   }
 
   pub fn get_hash<CS>(&mut self, mut cs: CS) -> Result<Vec<AllocatedBit>, SynthesisError>
@@ -210,7 +298,7 @@ where
     CS: ConstraintSystem<Scalar>,
   {
     let bits = self.hash_inner(cs.namespace(|| "hash"))?;
-    Ok(bits[..NUM_HASH_BITS].into())
+    Ok(bits[..self.constants.config.num_hash_bits].into())
   }
 }
 
@@ -237,7 +325,7 @@ mod tests {
       ro.absorb(num);
       let num_gadget =
         AllocatedNum::alloc(cs.namespace(|| format!("data {}", i)), || Ok(num)).unwrap();
-      let _ = num_gadget
+      num_gadget
         .inputize(&mut cs.namespace(|| format!("input {}", i)))
         .unwrap();
       ro_gadget.absorb(num_gadget);
@@ -247,4 +335,60 @@ mod tests {
     let num2 = le_bits_to_num(&mut cs, num2_bits).unwrap();
     assert_eq!(num.to_repr(), num2.get_value().unwrap().to_repr());
   }
+
+  #[test]
+  fn test_poseidon_ro_padded() {
+    // Check that states which do not match an arity hash to the same value in and out of the circuit
+    let mut csprng: OsRng = OsRng;
+    let constants = ROConstantsCircuit::new();
+    for len in [1, 2, 26, 28, 33, 60, 64, 100] {
+      let mut ro: PoseidonRO<S, B> = PoseidonRO::new(constants.clone());
+      let mut ro_gadget: PoseidonROGadget<S> = PoseidonROGadget::new(constants.clone());
+      let mut cs: SatisfyingAssignment<G> = SatisfyingAssignment::new();
+      for i in 0..len {
+        let num = S::random(&mut csprng);
+        ro.absorb(num);
+        let num_gadget =
+          AllocatedNum::alloc(cs.namespace(|| format!("data {}", i)), || Ok(num)).unwrap();
+        ro_gadget.absorb(num_gadget);
+      }
+      let num = ro.get_hash();
+      let num2_bits = ro_gadget.get_hash(&mut cs).unwrap();
+      let num2 = le_bits_to_num(&mut cs, num2_bits).unwrap();
+      assert_eq!(num.to_repr(), num2.get_value().unwrap().to_repr());
+    }
+  }
+
+  #[test]
+  fn test_poseidon_ro_no_padding_collisions() {
+    // Check that a state does not hash like its padded or chained form
+    let mut csprng: OsRng = OsRng;
+    let constants = ROConstantsCircuit::<S>::new();
+    let hash = |elements: &[S]| {
+      let mut ro: PoseidonRO<S, B> = PoseidonRO::new(constants.clone());
+      for e in elements {
+        ro.absorb(*e);
+      }
+      ro.get_hash()
+    };
+
+    // a short state against the same state followed by zeros and its length
+    for n in [1, 5, 26, 28] {
+      let short = (0..n).map(|_| S::random(&mut csprng)).collect::<Vec<S>>();
+      let arity = if n < 27 { 27 } else { 32 };
+      let mut long = short.clone();
+      long.resize(arity - 1, S::zero());
+      long.push(S::from(n as u64));
+      assert_ne!(hash(&short), hash(&long));
+      assert_ne!(
+        hash(&short),
+        hash(&[short.clone(), vec![S::zero()]].concat())
+      );
+    }
+
+    // a long state against the digest of its first chunk followed by the rest
+    let long = (0..33).map(|_| S::random(&mut csprng)).collect::<Vec<S>>();
+    let digest = PoseidonRO::<S, B>::hash_elements(&constants, &long[..32]);
+    assert_ne!(hash(&long), hash(&[digest, long[32]]));
+  }
 }
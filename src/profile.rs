@@ -0,0 +1,142 @@
+//! This module profiles circuits. It attributes the constraints and the auxiliary variables
+//! of a circuit to the namespaces in which they are created, so that the costs of the parts
+//! of a circuit can be compared between versions.
+//!
+//! Public inputs are not attributed to namespaces, and the report is keyed by the names of
+//! namespaces, so renaming one shows up as a removal and an addition. A profile is built by
+//! synthesizing the whole shape with `ShapeCS`, which keeps every constraint in memory.
+use core::fmt;
+
+/// The numbers of constraints and auxiliary variables created in a namespace,
+/// including those of its children
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CircuitProfile {
+  /// the name of the namespace, which is empty for the root of a circuit
+  pub name: String,
+  /// the number of constraints
+  pub num_constraints: usize,
+  /// the number of auxiliary variables
+  pub num_aux: usize,
+  /// the child namespaces, in the order in which they are first used
+  pub children: Vec<CircuitProfile>,
+}
+
+impl CircuitProfile {
+  /// Creates the profile of a circuit from the paths of its constraints and its auxiliary
+  /// variables, in which the names of nested namespaces are separated by `/`
+  pub fn from_paths<'a, C, A>(constraints: C, aux: A) -> Self
+  where
+    C: IntoIterator<Item = &'a str>,
+    A: IntoIterator<Item = &'a str>,
+  {
+    let mut root = CircuitProfile::default();
+    for path in constraints {
+      root.add(path, true);
+    }
+    for path in aux {
+      root.add(path, false);
+    }
+    root
+  }
+
+  /// Counts an object in the namespaces of its path, which are all components but the last
+  fn add(&mut self, path: &str, is_constraint: bool) {
+    let mut namespaces = path.split('/').collect::<Vec<_>>();
+    namespaces.pop();
+
+    let mut node = self;
+    node.count(is_constraint);
+    for name in namespaces {
+      let pos = match node.children.iter().position(|c| c.name == name) {
+        Some(pos) => pos,
+        None => {
+          node.children.push(CircuitProfile {
+            name: name.to_string(),
+            ..Default::default()
+          });
+          node.children.len() - 1
+        }
+      };
+      node = &mut node.children[pos];
+      node.count(is_constraint);
+    }
+  }
+
+  fn count(&mut self, is_constraint: bool) {
+    if is_constraint {
+      self.num_constraints += 1;
+    } else {
+      self.num_aux += 1;
+    }
+  }
+
+  /// Returns the profile of the namespace at `path`, relative to this namespace
+  pub fn get(&self, path: &str) -> Option<&CircuitProfile> {
+    path.split('/').try_fold(self, |node, name| {
+      node.children.iter().find(|c| c.name == name)
+    })
+  }
+
+  /// Returns the path, the number of constraints and the number of auxiliary variables of
+  /// each namespace at most `depth` levels below this one, in the order of synthesis
+  pub fn flatten(&self, depth: usize) -> Vec<(String, usize, usize)> {
+    let mut res = Vec::new();
+    self.flatten_into("", depth, &mut res);
+    res
+  }
+
+  fn flatten_into(&self, prefix: &str, depth: usize, res: &mut Vec<(String, usize, usize)>) {
+    if depth == 0 {
+      return;
+    }
+    for child in &self.children {
+      let path = if prefix.is_empty() {
+        child.name.clone()
+      } else {
+        format!("{}/{}", prefix, child.name)
+      };
+      res.push((path.clone(), child.num_constraints, child.num_aux));
+      child.flatten_into(&path, depth - 1, res);
+    }
+  }
+}
+
+/// Prints the totals and then one line per namespace with its path, its number of constraints
+/// and its number of auxiliary variables, separated by tabs, so that reports can be diffed
+impl fmt::Display for CircuitProfile {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    writeln!(f, "*\t{}\t{}", self.num_constraints, self.num_aux)?;
+    for (path, num_constraints, num_aux) in self.flatten(usize::MAX) {
+      writeln!(f, "{}\t{}\t{}", path, num_constraints, num_aux)?;
+    }
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_circuit_profile() {
+    let constraints = ["a/b/c0", "a/c1", "c2", "d/e/c3"];
+    let aux = ["a/b/x0", "a/b/x1", "x2", "d/x3"];
+    let profile = CircuitProfile::from_paths(constraints, aux);
+
+    assert_eq!((profile.num_constraints, profile.num_aux), (4, 4));
+    let a = profile.get("a").unwrap();
+    assert_eq!((a.num_constraints, a.num_aux), (2, 2));
+    let b = profile.get("a/b").unwrap();
+    assert_eq!((b.num_constraints, b.num_aux), (1, 2));
+    assert!(profile.get("a/c1").is_none());
+
+    assert_eq!(
+      profile.flatten(1),
+      vec![("a".to_string(), 2, 2), ("d".to_string(), 1, 1)]
+    );
+    assert_eq!(
+      profile.to_string(),
+      "*\t4\t4\na\t2\t2\na/b\t1\t2\nd\t1\t1\nd/e\t1\t0\n"
+    );
+  }
+}
@@ -1,26 +1,47 @@
 //! This module defines R1CS related types and a folding scheme for Relaxed R1CS
+//!
+//! # Stability
+//!
+//! This module, `nifs` and `commitments` form the low-level API on which `RecursiveSNARK`
+//! and the implementations of `RelaxedR1CSSNARKTrait` are built, and they are supported for use
+//! outside of this crate, along with `bellperson`, which builds shapes and witnesses from
+//! bellperson circuits with `ShapeCS` and `SatisfyingAssignment`. Their public types, methods
+//! and traits follow semantic versioning: while the crate is at `0.x`, a breaking change to
+//! them is only made in a release that bumps the minor version. The fields of these types are
+//! not part of the API, and are read through methods so that their representation can change
+//! in any release. The digest of a shape may also change in any release, so it must not be
+//! persisted across versions.
+//!
+//! A caller that folds instances outside of `PublicParams` must bind the folds to a digest
+//! that covers at least the shape, such as `R1CSShape::get_digest`. `is_sat` and
+//! `is_sat_relaxed` check a witness directly and are not proof verifiers. The lookup argument
+//! is not part of this API, so `R1CSShape::new_with_lookup` only accepts `None` from outside
+//! of this crate.
 #![allow(clippy::type_complexity)]
 use super::{
   commitments::{CommitGens, CommitTrait, Commitment},
-  constants::{BN_LIMB_WIDTH, BN_N_LIMBS, NUM_HASH_BITS},
+  constants::NUM_HASH_BITS,
   errors::NovaError,
   gadgets::utils::scalar_as_base,
-  traits::{AbsorbInROTrait, AppendToTranscriptTrait, Group, HashFuncTrait},
+  lookup::LookupShape,
+  parallel::{self, prelude::*},
+  serde_utils::{scalar, scalars, sparse_matrix},
+  traits::{AbsorbInROTrait, AppendToTranscriptTrait, Group, HashFuncTrait, TranscriptEngineTrait},
 };
 use bellperson_nonnative::{mp::bignat::nat_to_limbs, util::convert::f_to_nat};
 use core::cmp::max;
 use ff::{Field, PrimeField};
 use flate2::{write::ZlibEncoder, Compression};
 use itertools::concat;
-use merlin::Transcript;
-use rayon::prelude::*;
-use serde::{Deserialize, Serialize};
+use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
 use sha3::{Digest, Sha3_256};
 
 /// Public parameters for a given R1CS
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(bound = "")]
 pub struct R1CSGens<G: Group> {
   pub(crate) gens: CommitGens<G>,
+  pub(crate) tag: Vec<u8>,
 }
 
 /// A type that holds the shape of the R1CS matrices
@@ -32,45 +53,76 @@ pub struct R1CSShape<G: Group> {
   pub(crate) A: Vec<(usize, usize, G::Scalar)>,
   pub(crate) B: Vec<(usize, usize, G::Scalar)>,
   pub(crate) C: Vec<(usize, usize, G::Scalar)>,
+  pub(crate) lookup: Option<LookupShape<G>>,
   digest: G::Scalar, // digest of the rest of R1CSShape
 }
 
 /// A type that holds a witness for a given R1CS instance
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound = "")]
 pub struct R1CSWitness<G: Group> {
-  W: Vec<G::Scalar>,
+  #[serde(with = "scalars")]
+  pub(crate) W: Vec<G::Scalar>,
 }
 
 /// A type that holds an R1CS instance
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound = "")]
 pub struct R1CSInstance<G: Group> {
   pub(crate) comm_W: Commitment<G>,
+  pub(crate) comm_L: Option<Commitment<G>>, // commitment to the second half of W for lookups
+  #[serde(with = "scalars")]
   pub(crate) X: Vec<G::Scalar>,
 }
 
 /// A type that holds a witness for a given Relaxed R1CS instance
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound = "")]
 pub struct RelaxedR1CSWitness<G: Group> {
+  #[serde(with = "scalars")]
   pub(crate) W: Vec<G::Scalar>,
+  #[serde(with = "scalars")]
   pub(crate) E: Vec<G::Scalar>,
 }
 
 /// A type that holds a Relaxed R1CS instance
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound = "")]
 pub struct RelaxedR1CSInstance<G: Group> {
   pub(crate) comm_W: Commitment<G>,
+  pub(crate) comm_L: Option<Commitment<G>>,
   pub(crate) comm_E: Commitment<G>,
+  #[serde(with = "scalars")]
   pub(crate) X: Vec<G::Scalar>,
+  #[serde(with = "scalar")]
   pub(crate) u: G::Scalar,
 }
 
 impl<G: Group> R1CSGens<G> {
   /// Samples public parameters for the specified number of constraints and variables in an R1CS
   pub fn new(num_cons: usize, num_vars: usize) -> R1CSGens<G> {
+    Self::new_with_tag(num_cons, num_vars, b"")
+  }
+
+  /// Samples public parameters like `new`, from a label that is extended with the
+  /// domain-separation tag `tag` of an application, so that applications with different
+  /// tags use independent generators. The empty tag gives the generators of `new`.
+  pub fn new_with_tag(num_cons: usize, num_vars: usize, tag: &[u8]) -> R1CSGens<G> {
     R1CSGens {
-      gens: CommitGens::new(b"gens", max(num_vars, num_cons)),
+      gens: CommitGens::new(&[b"gens", tag].concat(), max(num_vars, num_cons)),
+      tag: tag.to_vec(),
     }
   }
+
+  /// Returns the generators with which witnesses are committed
+  pub fn commit_gens(&self) -> &CommitGens<G> {
+    &self.gens
+  }
+
+  /// Returns the generators as bytes, which the digest of public parameters binds
+  pub(crate) fn to_bytes(&self) -> Vec<u8> {
+    self.gens.to_bytes()
+  }
 }
 
 impl<G: Group> R1CSShape<G> {
@@ -83,43 +135,53 @@ impl<G: Group> R1CSShape<G> {
     B: &[(usize, usize, G::Scalar)],
     C: &[(usize, usize, G::Scalar)],
   ) -> Result<R1CSShape<G>, NovaError> {
-    let is_valid = |num_cons: usize,
-                    num_vars: usize,
-                    num_io: usize,
-                    M: &[(usize, usize, G::Scalar)]|
-     -> Result<(), NovaError> {
-      let res = (0..M.len())
-        .map(|i| {
-          let (row, col, _val) = M[i];
-          if row >= num_cons || col > num_io + num_vars {
-            Err(NovaError::InvalidIndex)
-          } else {
-            Ok(())
-          }
-        })
-        .collect::<Result<Vec<()>, NovaError>>();
+    Self::new_with_lookup(num_cons, num_vars, num_io, A, B, C, None)
+  }
 
-      if res.is_err() {
-        Err(NovaError::InvalidIndex)
-      } else {
-        Ok(())
+  /// Create an object of type `R1CSShape` from R1CS matrices that include the constraints of
+  /// the provided lookup argument, if any. The witness is then split into two halves that are
+  /// committed separately, and the last two public IO are reserved for the lookup argument.
+  pub fn new_with_lookup(
+    num_cons: usize,
+    num_vars: usize,
+    num_io: usize,
+    A: &[(usize, usize, G::Scalar)],
+    B: &[(usize, usize, G::Scalar)],
+    C: &[(usize, usize, G::Scalar)],
+    lookup: Option<LookupShape<G>>,
+  ) -> Result<R1CSShape<G>, NovaError> {
+    let is_valid = |matrix: usize, M: &[(usize, usize, G::Scalar)]| -> Result<(), NovaError> {
+      match M
+        .iter()
+        .find(|(row, col, _val)| *row >= num_cons || *col > num_io + num_vars)
+      {
+        Some((row, col, _val)) => Err(NovaError::InvalidIndex {
+          matrix,
+          row: *row,
+          col: *col,
+        }),
+        None => Ok(()),
       }
     };
 
-    let res_A = is_valid(num_cons, num_vars, num_io, A);
-    let res_B = is_valid(num_cons, num_vars, num_io, B);
-    let res_C = is_valid(num_cons, num_vars, num_io, C);
+    is_valid(0, A)?;
+    is_valid(1, B)?;
+    is_valid(2, C)?;
 
-    if res_A.is_err() || res_B.is_err() || res_C.is_err() {
-      return Err(NovaError::InvalidIndex);
+    // We require the number of public inputs/outputs to be even
+    if num_io % 2 == 1 {
+      return Err(NovaError::OddInputLength { num_io });
     }
 
-    // We require the number of public inputs/outputs to be even
-    if num_io % 2 != 0 {
-      return Err(NovaError::OddInputLength);
+    // the two halves of the witness of a lookup argument must be of the same size
+    if lookup.is_some() && num_vars.next_power_of_two() != num_vars {
+      return Err(NovaError::InvalidWitnessLength {
+        expected: num_vars.next_power_of_two(),
+        actual: num_vars,
+      });
     }
 
-    let digest = Self::compute_digest(num_cons, num_vars, num_io, A, B, C);
+    let digest = Self::compute_digest(num_cons, num_vars, num_io, A, B, C, lookup.is_some());
 
     let shape = R1CSShape {
       num_cons,
@@ -128,18 +190,50 @@ impl<G: Group> R1CSShape<G> {
       A: A.to_owned(),
       B: B.to_owned(),
       C: C.to_owned(),
+      lookup,
       digest,
     };
 
     Ok(shape)
   }
 
+  /// Returns the number of constraints
+  pub fn num_cons(&self) -> usize {
+    self.num_cons
+  }
+
+  /// Returns the number of variables in the witness
+  pub fn num_vars(&self) -> usize {
+    self.num_vars
+  }
+
+  /// Returns the number of public inputs and outputs
+  pub fn num_io(&self) -> usize {
+    self.num_io
+  }
+
+  /// Returns the sparse matrices `A`, `B` and `C` as `(row, col, val)` entries, where the columns
+  /// index `z = (W, u, X)`
+  pub fn matrices(
+    &self,
+  ) -> (
+    &[(usize, usize, G::Scalar)],
+    &[(usize, usize, G::Scalar)],
+    &[(usize, usize, G::Scalar)],
+  ) {
+    (&self.A, &self.B, &self.C)
+  }
+
+  /// Computes the products of the matrices `A`, `B` and `C` with `z = (W, u, X)`
   pub fn multiply_vec(
     &self,
     z: &[G::Scalar],
   ) -> Result<(Vec<G::Scalar>, Vec<G::Scalar>, Vec<G::Scalar>), NovaError> {
     if z.len() != self.num_io + self.num_vars + 1 {
-      return Err(NovaError::InvalidWitnessLength);
+      return Err(NovaError::InvalidWitnessLength {
+        expected: self.num_io + self.num_vars + 1,
+        actual: z.len(),
+      });
     }
 
     // computes a product between a sparse matrix `M` and a vector `z`
@@ -158,10 +252,10 @@ impl<G: Group> R1CSShape<G> {
           })
       };
 
-    let (Az, (Bz, Cz)) = rayon::join(
+    let (Az, (Bz, Cz)) = parallel::join(
       || sparse_matrix_vec_product(&self.A, self.num_cons, z),
       || {
-        rayon::join(
+        parallel::join(
           || sparse_matrix_vec_product(&self.B, self.num_cons, z),
           || sparse_matrix_vec_product(&self.C, self.num_cons, z),
         )
@@ -183,36 +277,29 @@ impl<G: Group> R1CSShape<G> {
     assert_eq!(U.X.len(), self.num_io);
 
     // verify if Az * Bz = u*Cz + E
-    let res_eq: bool = {
+    let unsat_constraint: Option<usize> = {
       let z = concat(vec![W.W.clone(), vec![U.u], U.X.clone()]);
       let (Az, Bz, Cz) = self.multiply_vec(&z)?;
       assert_eq!(Az.len(), self.num_cons);
       assert_eq!(Bz.len(), self.num_cons);
       assert_eq!(Cz.len(), self.num_cons);
 
-      let res: usize = (0..self.num_cons)
-        .map(|i| {
-          if Az[i] * Bz[i] == U.u * Cz[i] + W.E[i] {
-            0
-          } else {
-            1
-          }
-        })
-        .sum();
-
-      res == 0
+      (0..self.num_cons).find(|&i| Az[i] * Bz[i] != U.u * Cz[i] + W.E[i])
     };
 
-    // verify if comm_E and comm_W are commitments to E and W
+    // verify if comm_E, comm_W, and comm_L are commitments to E and W
     let res_comm: bool = {
-      let (comm_W, comm_E) = rayon::join(|| W.W.commit(&gens.gens), || W.E.commit(&gens.gens));
-      U.comm_W == comm_W && U.comm_E == comm_E
+      let ((comm_W, comm_L), comm_E) =
+        parallel::join(|| self.commit_W(gens, &W.W), || W.E.commit(&gens.gens));
+      U.comm_W == comm_W && U.comm_L == comm_L && U.comm_E == comm_E
     };
 
-    if res_eq && res_comm {
-      Ok(())
+    if unsat_constraint.is_some() || !res_comm {
+      Err(NovaError::UnSat {
+        constraint: unsat_constraint,
+      })
     } else {
-      Err(NovaError::UnSat)
+      Ok(())
     }
   }
 
@@ -227,27 +314,43 @@ impl<G: Group> R1CSShape<G> {
     assert_eq!(U.X.len(), self.num_io);
 
     // verify if Az * Bz = u*Cz
-    let res_eq: bool = {
+    let unsat_constraint: Option<usize> = {
       let z = concat(vec![W.W.clone(), vec![G::Scalar::one()], U.X.clone()]);
       let (Az, Bz, Cz) = self.multiply_vec(&z)?;
       assert_eq!(Az.len(), self.num_cons);
       assert_eq!(Bz.len(), self.num_cons);
       assert_eq!(Cz.len(), self.num_cons);
 
-      let res: usize = (0..self.num_cons)
-        .map(|i| if Az[i] * Bz[i] == Cz[i] { 0 } else { 1 })
-        .sum();
-
-      res == 0
+      (0..self.num_cons).find(|&i| Az[i] * Bz[i] != Cz[i])
     };
 
-    // verify if comm_W is a commitment to W
-    let res_comm: bool = U.comm_W == W.W.commit(&gens.gens);
+    // verify if comm_W and comm_L are commitments to W
+    let res_comm: bool = (U.comm_W, U.comm_L) == self.commit_W(gens, &W.W);
 
-    if res_eq && res_comm {
+    // the derivation of the challenge of a lookup argument depends on the parameters that the
+    // instance is folded under, so it is checked by `NIFS` and the verifier circuit instead
+    if unsat_constraint.is_some() || !res_comm {
+      Err(NovaError::UnSat {
+        constraint: unsat_constraint,
+      })
+    } else {
       Ok(())
+    }
+  }
+
+  /// Commits to a witness `W`. If the shape has a lookup argument, the two halves of `W`
+  /// are committed separately with the same generators.
+  pub fn commit_W(
+    &self,
+    gens: &R1CSGens<G>,
+    W: &[G::Scalar],
+  ) -> (Commitment<G>, Option<Commitment<G>>) {
+    if self.lookup.is_some() {
+      let (W1, W2) = W.split_at(self.num_vars / 2);
+      let (comm_W, comm_L) = parallel::join(|| W1.commit(&gens.gens), || W2.commit(&gens.gens));
+      (comm_W, Some(comm_L))
     } else {
-      Err(NovaError::UnSat)
+      (W.commit(&gens.gens), None)
     }
   }
 
@@ -306,6 +409,7 @@ impl<G: Group> R1CSShape<G> {
     self.digest
   }
 
+  #[allow(clippy::too_many_arguments)]
   fn compute_digest(
     num_cons: usize,
     num_vars: usize,
@@ -313,11 +417,13 @@ impl<G: Group> R1CSShape<G> {
     A: &[(usize, usize, G::Scalar)],
     B: &[(usize, usize, G::Scalar)],
     C: &[(usize, usize, G::Scalar)],
+    has_lookup: bool,
   ) -> G::Scalar {
     let shape_serialized = R1CSShapeSerialized {
       num_cons,
       num_vars,
       num_io,
+      has_lookup,
       A: A
         .par_iter()
         .map(|(i, j, v)| (*i, *j, v.to_repr().as_ref().to_vec()))
@@ -337,66 +443,53 @@ impl<G: Group> R1CSShape<G> {
     bincode::serialize_into(&mut encoder, &shape_serialized).unwrap();
     let shape_bytes = encoder.finish().unwrap();
 
-    // convert shape_bytes into a short digest
-    let mut hasher = Sha3_256::new();
-    hasher.input(&shape_bytes);
-    let digest = hasher.result();
-
-    // truncate the digest to 250 bits
-    let bv = (0..NUM_HASH_BITS).map(|i| {
-      let (byte_pos, bit_pos) = (i / 8, i % 8);
-      let bit = (digest[byte_pos] >> bit_pos) & 1;
-      bit == 1
-    });
-
-    // turn the bit vector into a scalar
-    let mut res = G::Scalar::zero();
-    let mut coeff = G::Scalar::one();
-    for bit in bv {
-      if bit {
-        res += coeff;
-      }
-      coeff += coeff;
-    }
-    res
+    digest_to_scalar(&shape_bytes)
   }
 
-  /// Pads the R1CSShape so that the number of variables is a power of two
+  /// Pads the R1CSShape so that the numbers of variables and constraints are the same power of two
   /// Renumbers variables to accomodate padded variables
   pub fn pad(&self) -> Self {
+    // the SNARK proves the witness and the error vector with one inner product argument,
+    // so they are padded to the same length, unless the variables of a lookup argument,
+    // which cannot be renumbered, are already a power of two
+    let (num_vars_padded, num_cons_padded) = if self.lookup.is_some() {
+      (self.num_vars, self.num_cons.next_power_of_two())
+    } else {
+      let n = max(self.num_vars, self.num_cons).next_power_of_two();
+      (n, n)
+    };
+
     // check if the provided R1CSShape is already as required
-    if self.num_vars.next_power_of_two() == self.num_vars
-      && self.num_cons.next_power_of_two() == self.num_cons
-    {
+    if num_vars_padded == self.num_vars && num_cons_padded == self.num_cons {
       return self.clone();
     }
 
     // check if the number of variables are as expected, then
-    // we simply set the number of constraints to the next power of two
-    if self.num_vars.next_power_of_two() == self.num_vars {
+    // we simply set the number of constraints
+    if num_vars_padded == self.num_vars {
       let digest = Self::compute_digest(
-        self.num_cons.next_power_of_two(),
+        num_cons_padded,
         self.num_vars,
         self.num_io,
         &self.A,
         &self.B,
         &self.C,
+        self.lookup.is_some(),
       );
 
       return R1CSShape {
-        num_cons: self.num_cons.next_power_of_two(),
+        num_cons: num_cons_padded,
         num_vars: self.num_vars,
         num_io: self.num_io,
         A: self.A.clone(),
         B: self.B.clone(),
         C: self.C.clone(),
+        lookup: self.lookup.clone(),
         digest,
       };
     }
 
     // otherwise, we need to pad the number of variables and renumber variable accesses
-    let num_vars_padded = self.num_vars.next_power_of_two();
-    let num_cons_padded = self.num_cons.next_power_of_two();
     let apply_pad = |M: &[(usize, usize, G::Scalar)]| -> Vec<(usize, usize, G::Scalar)> {
       M.par_iter()
         .map(|(r, c, v)| {
@@ -420,8 +513,10 @@ impl<G: Group> R1CSShape<G> {
       &A_padded,
       &B_padded,
       &C_padded,
+      false,
     );
 
+    // shapes with a lookup argument always have a power-of-two number of variables
     R1CSShape {
       num_cons: num_cons_padded,
       num_vars: num_vars_padded,
@@ -429,26 +524,70 @@ impl<G: Group> R1CSShape<G> {
       A: A_padded,
       B: B_padded,
       C: C_padded,
+      lookup: None,
       digest,
     }
   }
 }
 
+// shapes are serialized without their digest, which is recomputed when they are deserialized
+impl<G: Group> Serialize for R1CSShape<G> {
+  fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+    R1CSShapeEncoded {
+      num_cons: self.num_cons,
+      num_vars: self.num_vars,
+      num_io: self.num_io,
+      A: self.A.clone(),
+      B: self.B.clone(),
+      C: self.C.clone(),
+      lookup: self.lookup.clone(),
+    }
+    .serialize(s)
+  }
+}
+
+impl<'de, G: Group> Deserialize<'de> for R1CSShape<G> {
+  fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+    let S = R1CSShapeEncoded::<G>::deserialize(d)?;
+    R1CSShape::new_with_lookup(S.num_cons, S.num_vars, S.num_io, &S.A, &S.B, &S.C, S.lookup)
+      .map_err(|e| D::Error::custom(format!("invalid shape: {}", e)))
+  }
+}
+
+/// The contents of a serialized `R1CSShape`
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+struct R1CSShapeEncoded<G: Group> {
+  num_cons: usize,
+  num_vars: usize,
+  num_io: usize,
+  #[serde(with = "sparse_matrix")]
+  A: Vec<(usize, usize, G::Scalar)>,
+  #[serde(with = "sparse_matrix")]
+  B: Vec<(usize, usize, G::Scalar)>,
+  #[serde(with = "sparse_matrix")]
+  C: Vec<(usize, usize, G::Scalar)>,
+  lookup: Option<LookupShape<G>>,
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 struct R1CSShapeSerialized {
   num_cons: usize,
   num_vars: usize,
   num_io: usize,
+  has_lookup: bool,
   A: Vec<(usize, usize, Vec<u8>)>,
   B: Vec<(usize, usize, Vec<u8>)>,
   C: Vec<(usize, usize, Vec<u8>)>,
 }
 
-impl<G: Group> AppendToTranscriptTrait for R1CSShape<G> {
-  fn append_to_transcript(&self, _label: &'static [u8], transcript: &mut Transcript) {
-    self
-      .get_digest()
-      .append_to_transcript(b"R1CSShape", transcript);
+impl<G: Group> AppendToTranscriptTrait<G> for R1CSShape<G> {
+  fn append_to_transcript<T: TranscriptEngineTrait<G>>(
+    &self,
+    _label: &'static [u8],
+    transcript: &mut T,
+  ) {
+    transcript.absorb_scalar(b"R1CSShape", &self.get_digest());
   }
 }
 
@@ -458,11 +597,40 @@ impl<G: Group> AbsorbInROTrait<G> for R1CSShape<G> {
   }
 }
 
+/// Hashes `bytes` into a short digest, truncated to `NUM_HASH_BITS` bits so that it is
+/// an element of the scalar fields of both curves of a cycle
+pub(crate) fn digest_to_scalar<F: PrimeField>(bytes: &[u8]) -> F {
+  let mut hasher = Sha3_256::new();
+  hasher.input(bytes);
+  let digest = hasher.result();
+
+  // truncate the digest to 250 bits
+  let bv = (0..NUM_HASH_BITS).map(|i| {
+    let (byte_pos, bit_pos) = (i / 8, i % 8);
+    let bit = (digest[byte_pos] >> bit_pos) & 1;
+    bit == 1
+  });
+
+  // turn the bit vector into a scalar
+  let mut res = F::zero();
+  let mut coeff = F::one();
+  for bit in bv {
+    if bit {
+      res += coeff;
+    }
+    coeff += coeff;
+  }
+  res
+}
+
 impl<G: Group> R1CSWitness<G> {
   /// A method to create a witness object using a vector of scalars
   pub fn new(S: &R1CSShape<G>, W: &[G::Scalar]) -> Result<R1CSWitness<G>, NovaError> {
     if S.num_vars != W.len() {
-      Err(NovaError::InvalidWitnessLength)
+      Err(NovaError::InvalidWitnessLength {
+        expected: S.num_vars,
+        actual: W.len(),
+      })
     } else {
       Ok(R1CSWitness { W: W.to_owned() })
     }
@@ -472,6 +640,11 @@ impl<G: Group> R1CSWitness<G> {
   pub fn commit(&self, gens: &R1CSGens<G>) -> Commitment<G> {
     self.W.commit(&gens.gens)
   }
+
+  /// Returns the witness
+  pub fn W(&self) -> &[G::Scalar] {
+    &self.W
+  }
 }
 
 impl<G: Group> R1CSInstance<G> {
@@ -482,26 +655,52 @@ impl<G: Group> R1CSInstance<G> {
     X: &[G::Scalar],
   ) -> Result<R1CSInstance<G>, NovaError> {
     if S.num_io != X.len() {
-      Err(NovaError::InvalidInputLength)
+      Err(NovaError::InvalidInputLength {
+        expected: S.num_io,
+        actual: X.len(),
+      })
+    } else if S.lookup.is_some() {
+      Err(NovaError::InvalidLookup)
     } else {
       Ok(R1CSInstance {
         comm_W: *comm_W,
+        comm_L: None,
         X: X.to_owned(),
       })
     }
   }
+
+  /// Returns the commitment to the witness
+  pub fn comm_W(&self) -> &Commitment<G> {
+    &self.comm_W
+  }
+
+  /// Returns the public inputs and outputs
+  pub fn X(&self) -> &[G::Scalar] {
+    &self.X
+  }
 }
 
-impl<G: Group> AppendToTranscriptTrait for R1CSInstance<G> {
-  fn append_to_transcript(&self, _label: &'static [u8], transcript: &mut Transcript) {
+impl<G: Group> AppendToTranscriptTrait<G> for R1CSInstance<G> {
+  fn append_to_transcript<T: TranscriptEngineTrait<G>>(
+    &self,
+    _label: &'static [u8],
+    transcript: &mut T,
+  ) {
     self.comm_W.append_to_transcript(b"comm_W", transcript);
-    self.X.append_to_transcript(b"X", transcript);
+    if let Some(comm_L) = &self.comm_L {
+      comm_L.append_to_transcript(b"comm_L", transcript);
+    }
+    transcript.absorb_scalars(b"X", &self.X);
   }
 }
 
 impl<G: Group> AbsorbInROTrait<G> for R1CSInstance<G> {
   fn absorb_in_ro(&self, ro: &mut G::HashFunc) {
     self.comm_W.absorb_in_ro(ro);
+    if let Some(comm_L) = &self.comm_L {
+      comm_L.absorb_in_ro(ro);
+    }
     for x in &self.X {
       ro.absorb(scalar_as_base::<G>(*x));
     }
@@ -509,6 +708,16 @@ impl<G: Group> AbsorbInROTrait<G> for R1CSInstance<G> {
 }
 
 impl<G: Group> RelaxedR1CSWitness<G> {
+  /// Returns the witness
+  pub fn W(&self) -> &[G::Scalar] {
+    &self.W
+  }
+
+  /// Returns the error vector
+  pub fn E(&self) -> &[G::Scalar] {
+    &self.E
+  }
+
   /// Produces a default RelaxedR1CSWitness given an R1CSShape
   pub fn default(S: &R1CSShape<G>) -> RelaxedR1CSWitness<G> {
     RelaxedR1CSWitness {
@@ -525,11 +734,6 @@ impl<G: Group> RelaxedR1CSWitness<G> {
     }
   }
 
-  /// Commits to the witness using the supplied generators
-  pub fn commit(&self, gens: &R1CSGens<G>) -> (Commitment<G>, Commitment<G>) {
-    (self.W.commit(&gens.gens), self.E.commit(&gens.gens))
-  }
-
   /// Folds an incoming R1CSWitness into the current one
   pub fn fold(
     &self,
@@ -541,7 +745,10 @@ impl<G: Group> RelaxedR1CSWitness<G> {
     let W2 = &W2.W;
 
     if W1.len() != W2.len() {
-      return Err(NovaError::InvalidWitnessLength);
+      return Err(NovaError::InvalidWitnessLength {
+        expected: W1.len(),
+        actual: W2.len(),
+      });
     }
 
     let W = W1
@@ -576,12 +783,34 @@ impl<G: Group> RelaxedR1CSWitness<G> {
 }
 
 impl<G: Group> RelaxedR1CSInstance<G> {
+  /// Returns the commitment to the witness
+  pub fn comm_W(&self) -> &Commitment<G> {
+    &self.comm_W
+  }
+
+  /// Returns the commitment to the error vector
+  pub fn comm_E(&self) -> &Commitment<G> {
+    &self.comm_E
+  }
+
+  /// Returns the public inputs and outputs
+  pub fn X(&self) -> &[G::Scalar] {
+    &self.X
+  }
+
+  /// Returns the scalar that relaxes the constraints
+  pub fn u(&self) -> G::Scalar {
+    self.u
+  }
+
   /// Produces a default RelaxedR1CSInstance given R1CSGens and R1CSShape
   pub fn default(gens: &R1CSGens<G>, S: &R1CSShape<G>) -> RelaxedR1CSInstance<G> {
-    let (comm_W, comm_E) = RelaxedR1CSWitness::default(S).commit(gens);
+    let W = RelaxedR1CSWitness::default(S);
+    let (comm_W, comm_L) = S.commit_W(gens, &W.W);
     RelaxedR1CSInstance {
       comm_W,
-      comm_E,
+      comm_L,
+      comm_E: W.E.commit(&gens.gens),
       u: G::Scalar::zero(),
       X: vec![G::Scalar::zero(); S.num_io],
     }
@@ -595,6 +824,7 @@ impl<G: Group> RelaxedR1CSInstance<G> {
   ) -> RelaxedR1CSInstance<G> {
     let mut r_instance = RelaxedR1CSInstance::default(gens, S);
     r_instance.comm_W = instance.comm_W;
+    r_instance.comm_L = instance.comm_L;
     r_instance.u = G::Scalar::one();
     r_instance.X = instance.X.clone();
     r_instance
@@ -618,11 +848,17 @@ impl<G: Group> RelaxedR1CSInstance<G> {
       .map(|(a, b)| *a + *r * *b)
       .collect::<Vec<G::Scalar>>();
     let comm_W = comm_W_1 + comm_W_2 * r;
+    let comm_L = match (&self.comm_L, &U2.comm_L) {
+      (Some(comm_L_1), Some(comm_L_2)) => Some(comm_L_1 + comm_L_2 * r),
+      (None, None) => None,
+      _ => return Err(NovaError::InvalidLookup),
+    };
     let comm_E = *comm_E_1 + *comm_T * *r;
     let u = *u1 + *r;
 
     Ok(RelaxedR1CSInstance {
       comm_W,
+      comm_L,
       comm_E,
       X,
       u,
@@ -630,24 +866,35 @@ impl<G: Group> RelaxedR1CSInstance<G> {
   }
 }
 
-impl<G: Group> AppendToTranscriptTrait for RelaxedR1CSInstance<G> {
-  fn append_to_transcript(&self, _label: &'static [u8], transcript: &mut Transcript) {
+impl<G: Group> AppendToTranscriptTrait<G> for RelaxedR1CSInstance<G> {
+  fn append_to_transcript<T: TranscriptEngineTrait<G>>(
+    &self,
+    _label: &'static [u8],
+    transcript: &mut T,
+  ) {
     self.comm_W.append_to_transcript(b"comm_W", transcript);
+    if let Some(comm_L) = &self.comm_L {
+      comm_L.append_to_transcript(b"comm_L", transcript);
+    }
     self.comm_E.append_to_transcript(b"comm_E", transcript);
-    self.u.append_to_transcript(b"u", transcript);
-    self.X.append_to_transcript(b"X", transcript);
+    transcript.absorb_scalar(b"u", &self.u);
+    transcript.absorb_scalars(b"X", &self.X);
   }
 }
 
 impl<G: Group> AbsorbInROTrait<G> for RelaxedR1CSInstance<G> {
   fn absorb_in_ro(&self, ro: &mut G::HashFunc) {
     self.comm_W.absorb_in_ro(ro);
+    if let Some(comm_L) = &self.comm_L {
+      comm_L.absorb_in_ro(ro);
+    }
     self.comm_E.absorb_in_ro(ro);
     ro.absorb(scalar_as_base::<G>(self.u));
 
     // absorb each element of self.X in bignum format
     for x in &self.X {
-      let limbs: Vec<G::Scalar> = nat_to_limbs(&f_to_nat(x), BN_LIMB_WIDTH, BN_N_LIMBS).unwrap();
+      let (limb_width, n_limbs) = (ro.config().limb_width, ro.config().n_limbs);
+      let limbs: Vec<G::Scalar> = nat_to_limbs(&f_to_nat(x), limb_width, n_limbs).unwrap();
       for limb in limbs {
         ro.absorb(scalar_as_base::<G>(limb));
       }
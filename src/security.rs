@@ -0,0 +1,166 @@
+//! This module defines the security configuration of the public parameters, which sets the
+//! width of the challenges, the truncation of hashes, the strength of Poseidon and the limbs
+//! in which the verifier circuit represents non-native field elements.
+//!
+//! `PublicParams::setup_with_config` and the CycleFold setup validate a configuration, thread
+//! it into the constants of the random oracles and the parameters of the verifier circuits,
+//! and bind it in the digest of the parameters, so proofs do not verify under another
+//! configuration. The soundness error of a fold is about `2^-num_challenge_bits`, which is why
+//! `fast_test` must not be used in production. The configuration does not reach the Spartan
+//! verifier circuit, the Poseidon transcript or the multi-folding scheme of `nimfs`, which use
+//! 128-bit challenges and the default constants, nor the digests of shapes and parameters,
+//! which are always truncated to 250 bits. The challenges of the Merlin transcript are
+//! full-width field elements.
+use crate::{
+  constants::{BN_LIMB_WIDTH, BN_N_LIMBS, NUM_CHALLENGE_BITS, NUM_HASH_BITS},
+  errors::NovaError,
+};
+use neptune::Strength;
+use serde::{Deserialize, Serialize};
+
+/// The maximum number of bits of a hash, so that it fits in the scalar field of either curve
+const MAX_HASH_BITS: usize = 250;
+
+/// The minimum number of bits of a challenge
+const MIN_CHALLENGE_BITS: usize = 64;
+
+/// The number of bits that the limbs of a non-native field element must hold
+const MIN_BIGNAT_BITS: usize = 256;
+
+/// The strength of the round numbers of Poseidon
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PoseidonStrength {
+  /// The numbers of rounds of the Poseidon paper
+  Standard,
+  /// The numbers of rounds of the paper with 25% more partial rounds
+  Strengthened,
+}
+
+impl From<PoseidonStrength> for Strength {
+  fn from(strength: PoseidonStrength) -> Self {
+    match strength {
+      PoseidonStrength::Standard => Strength::Standard,
+      PoseidonStrength::Strengthened => Strength::Strengthened,
+    }
+  }
+}
+
+/// The security configuration of the public parameters. The default configuration is the one
+/// that `PublicParams::setup` uses.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SecurityConfig {
+  /// The number of bits of the challenges that the random oracle derives
+  pub num_challenge_bits: usize,
+  /// The number of bits to which the hashes of the random oracle are truncated
+  pub num_hash_bits: usize,
+  /// The strength of the Poseidon permutation
+  pub poseidon_strength: PoseidonStrength,
+  /// The width of the limbs of a non-native field element in the verifier circuit
+  pub limb_width: usize,
+  /// The number of limbs of a non-native field element in the verifier circuit
+  pub n_limbs: usize,
+}
+
+impl Default for SecurityConfig {
+  fn default() -> Self {
+    Self {
+      num_challenge_bits: NUM_CHALLENGE_BITS,
+      num_hash_bits: NUM_HASH_BITS,
+      poseidon_strength: PoseidonStrength::Strengthened,
+      limb_width: BN_LIMB_WIDTH,
+      n_limbs: BN_N_LIMBS,
+    }
+  }
+}
+
+impl SecurityConfig {
+  /// A configuration with 192-bit challenges for deployments that require a larger margin
+  /// than the default
+  pub fn conservative() -> Self {
+    Self {
+      num_challenge_bits: 192,
+      ..Self::default()
+    }
+  }
+
+  /// A configuration with 64-bit challenges, the standard strength of Poseidon and fewer
+  /// limbs, which gives smaller circuits for tests. It must not be used in production.
+  pub fn fast_test() -> Self {
+    Self {
+      num_challenge_bits: MIN_CHALLENGE_BITS,
+      num_hash_bits: MAX_HASH_BITS,
+      poseidon_strength: PoseidonStrength::Standard,
+      limb_width: 64,
+      n_limbs: 4,
+    }
+  }
+
+  /// Checks that the challenges are at least 64 bits and at most as wide as the hashes,
+  /// that the hashes fit in the scalar fields, and that the limbs hold a field element
+  /// while the products of two limbs fit in a native field element
+  pub fn validate(&self) -> Result<(), NovaError> {
+    let invalid = |reason| Err(NovaError::InvalidSecurityConfig { reason });
+    if self.num_challenge_bits < MIN_CHALLENGE_BITS {
+      return invalid("challenges must have at least 64 bits");
+    }
+    if self.num_challenge_bits > self.num_hash_bits {
+      return invalid("challenges must not be wider than hashes");
+    }
+    if self.num_hash_bits > MAX_HASH_BITS {
+      return invalid("hashes must have at most 250 bits");
+    }
+    if !(16..=64).contains(&self.limb_width) {
+      return invalid("limbs must have between 16 and 64 bits");
+    }
+    if !(MIN_BIGNAT_BITS..=2 * MIN_BIGNAT_BITS)
+      .contains(&self.limb_width.saturating_mul(self.n_limbs))
+    {
+      return invalid("limbs must hold between 256 and 512 bits in total");
+    }
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_security_config_validate() {
+    assert!(SecurityConfig::default().validate().is_ok());
+    assert!(SecurityConfig::conservative().validate().is_ok());
+    assert!(SecurityConfig::fast_test().validate().is_ok());
+
+    let invalid = [
+      SecurityConfig {
+        num_challenge_bits: 32,
+        ..SecurityConfig::default()
+      },
+      SecurityConfig {
+        num_challenge_bits: 200,
+        num_hash_bits: 128,
+        ..SecurityConfig::default()
+      },
+      SecurityConfig {
+        num_hash_bits: 255,
+        ..SecurityConfig::default()
+      },
+      SecurityConfig {
+        limb_width: 32,
+        n_limbs: 4,
+        ..SecurityConfig::default()
+      },
+      SecurityConfig {
+        limb_width: 128,
+        n_limbs: 2,
+        ..SecurityConfig::default()
+      },
+    ];
+    for config in invalid {
+      assert!(matches!(
+        config.validate(),
+        Err(NovaError::InvalidSecurityConfig { .. })
+      ));
+    }
+  }
+}
@@ -0,0 +1,118 @@
+//! This module serializes field elements with serde as the bytes of their canonical
+//! representation. Its submodules are meant for the `#[serde(with = "...")]` attribute
+//! on the fields of proofs, as field elements do not implement the serde traits.
+use ff::PrimeField;
+use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
+fn to_bytes<F: PrimeField>(f: &F) -> Vec<u8> {
+  f.to_repr().as_ref().to_vec()
+}
+
+fn from_bytes<'de, F: PrimeField, D: Deserializer<'de>>(bytes: &[u8]) -> Result<F, D::Error> {
+  let mut repr = F::Repr::default();
+  if repr.as_ref().len() != bytes.len() {
+    return Err(D::Error::custom("invalid length of a field element"));
+  }
+  repr.as_mut().copy_from_slice(bytes);
+  Option::from(F::from_repr(repr)).ok_or_else(|| D::Error::custom("invalid field element"))
+}
+
+/// Serializes a field element
+pub mod scalar {
+  use super::*;
+
+  /// Serializes a field element
+  pub fn serialize<F: PrimeField, S: Serializer>(f: &F, s: S) -> Result<S::Ok, S::Error> {
+    to_bytes(f).serialize(s)
+  }
+
+  /// Deserializes a field element
+  pub fn deserialize<'de, F: PrimeField, D: Deserializer<'de>>(d: D) -> Result<F, D::Error> {
+    from_bytes::<F, D>(&Vec::<u8>::deserialize(d)?)
+  }
+}
+
+/// Serializes a vector of field elements
+pub mod scalars {
+  use super::*;
+
+  /// Serializes a vector of field elements
+  pub fn serialize<F: PrimeField, S: Serializer>(v: &[F], s: S) -> Result<S::Ok, S::Error> {
+    v.iter().map(to_bytes).collect::<Vec<_>>().serialize(s)
+  }
+
+  /// Deserializes a vector of field elements
+  pub fn deserialize<'de, F: PrimeField, D: Deserializer<'de>>(d: D) -> Result<Vec<F>, D::Error> {
+    Vec::<Vec<u8>>::deserialize(d)?
+      .iter()
+      .map(|bytes| from_bytes::<F, D>(bytes))
+      .collect()
+  }
+}
+
+/// Serializes an optional field element
+pub mod option_scalar {
+  use super::*;
+
+  /// Serializes an optional field element
+  pub fn serialize<F: PrimeField, S: Serializer>(f: &Option<F>, s: S) -> Result<S::Ok, S::Error> {
+    f.as_ref().map(to_bytes).serialize(s)
+  }
+
+  /// Deserializes an optional field element
+  pub fn deserialize<'de, F: PrimeField, D: Deserializer<'de>>(
+    d: D,
+  ) -> Result<Option<F>, D::Error> {
+    Option::<Vec<u8>>::deserialize(d)?
+      .map(|bytes| from_bytes::<F, D>(&bytes))
+      .transpose()
+  }
+}
+
+/// Serializes a triple of field elements
+pub mod scalar_triple {
+  use super::*;
+
+  /// Serializes a triple of field elements
+  pub fn serialize<F: PrimeField, S: Serializer>(t: &(F, F, F), s: S) -> Result<S::Ok, S::Error> {
+    (to_bytes(&t.0), to_bytes(&t.1), to_bytes(&t.2)).serialize(s)
+  }
+
+  /// Deserializes a triple of field elements
+  pub fn deserialize<'de, F: PrimeField, D: Deserializer<'de>>(
+    d: D,
+  ) -> Result<(F, F, F), D::Error> {
+    let (a, b, c) = <(Vec<u8>, Vec<u8>, Vec<u8>)>::deserialize(d)?;
+    Ok((
+      from_bytes::<F, D>(&a)?,
+      from_bytes::<F, D>(&b)?,
+      from_bytes::<F, D>(&c)?,
+    ))
+  }
+}
+
+/// Serializes the entries `(row, column, value)` of a sparse matrix
+pub mod sparse_matrix {
+  use super::*;
+
+  /// Serializes the entries of a sparse matrix
+  pub fn serialize<F: PrimeField, S: Serializer>(
+    m: &[(usize, usize, F)],
+    s: S,
+  ) -> Result<S::Ok, S::Error> {
+    m.iter()
+      .map(|(row, col, val)| (*row, *col, to_bytes(val)))
+      .collect::<Vec<_>>()
+      .serialize(s)
+  }
+
+  /// Deserializes the entries of a sparse matrix
+  pub fn deserialize<'de, F: PrimeField, D: Deserializer<'de>>(
+    d: D,
+  ) -> Result<Vec<(usize, usize, F)>, D::Error> {
+    Vec::<(usize, usize, Vec<u8>)>::deserialize(d)?
+      .into_iter()
+      .map(|(row, col, bytes)| Ok((row, col, from_bytes::<F, D>(&bytes)?)))
+      .collect()
+  }
+}
@@ -7,14 +7,16 @@ use super::{
 
 /// A trait that defines the behavior of a zkSNARK's prover key
 pub trait ProverKeyTrait<G: Group>: Send + Sync {
-  /// Produces a new prover's key
-  fn new(gens: &R1CSGens<G>, S: &R1CSShape<G>) -> Self;
+  /// Produces a new prover's key for the shape `S` of public parameters with digest `digest`,
+  /// which is appended to the transcript of the proof
+  fn new(gens: &R1CSGens<G>, S: &R1CSShape<G>, digest: &G::Scalar) -> Self;
 }
 
 /// A trait that defines the behavior of a zkSNARK's verifier key
 pub trait VerifierKeyTrait<G: Group>: Send + Sync {
-  /// Produces a new verifier's key
-  fn new(gens: &R1CSGens<G>, S: &R1CSShape<G>) -> Self;
+  /// Produces a new verifier's key for the shape `S` of public parameters with digest `digest`,
+  /// which is appended to the transcript of the proof
+  fn new(gens: &R1CSGens<G>, S: &R1CSShape<G>, digest: &G::Scalar) -> Self;
 }
 
 /// A trait that defines the behavior of a zkSNARK
@@ -26,13 +28,13 @@ pub trait RelaxedR1CSSNARKTrait<G: Group>: Sized + Send + Sync {
   type VerifierKey: VerifierKeyTrait<G>;
 
   /// Produces a prover key
-  fn prover_key(gens: &R1CSGens<G>, S: &R1CSShape<G>) -> Self::ProverKey {
-    Self::ProverKey::new(gens, S)
+  fn prover_key(gens: &R1CSGens<G>, S: &R1CSShape<G>, digest: &G::Scalar) -> Self::ProverKey {
+    Self::ProverKey::new(gens, S, digest)
   }
 
   /// Produces a verifier key
-  fn verifier_key(gens: &R1CSGens<G>, S: &R1CSShape<G>) -> Self::VerifierKey {
-    Self::VerifierKey::new(gens, S)
+  fn verifier_key(gens: &R1CSGens<G>, S: &R1CSShape<G>, digest: &G::Scalar) -> Self::VerifierKey {
+    Self::VerifierKey::new(gens, S, digest)
   }
 
   /// Produces a new SNARK for a relaxed R1CS
@@ -0,0 +1,1515 @@
+//! This module implements the verifier of `RelaxedR1CSSNARK` with a `PoseidonTranscript` as a
+//! pair of circuits, in the style of CycleFold. `SNARKVerifierCircuit`, over `G::Scalar`, checks
+//! the transcript, the two sum-checks, the evaluations of the public IO and of the matrices,
+//! the folding of the inner product instances and the scalars of the IPA, and takes the
+//! commitments that result from group operations as witnesses. `IPAGroupCircuit`, over
+//! `G::Base`, checks these group operations natively: with a lookup argument
+//! `comm_W + rho * comm_L`, then the folded commitment `comm_E + r_fold * comm_W` and the final
+//! equation of the IPA. The two circuits output the commitments and scalars they share as limbs
+//! of `BN_LIMB_WIDTH` bits, which `DeferredIPACheck::from_io` compares. As in Halo, the only
+//! check left outside the circuits is that of the folded generator `G_hat = <s, G>`, which only
+//! depends on the round challenges of the IPA.
+//!
+//! The transcript must be a `PoseidonTranscript`, since Merlin's Keccak is not implemented as
+//! a gadget. Without a lookup argument, the shape must have as many variables as constraints
+//! (as padded shapes do), so that the inner product instances need no padding.
+//!
+//! The pair is a verifier only when the caller proves both circuits, checks with
+//! `DeferredIPACheck::from_io` that their public IO agree, and runs `DeferredIPACheck::verify`,
+//! which is linear in the number of generators and is meant to be batched or deferred by an
+//! outer accumulator. The verifier key is fixed as constants of the circuits, so they verify
+//! proofs for one key only. The challenges have `NUM_CHALLENGE_BITS` bits regardless of the
+//! security configuration, and the evaluations of the matrices at `(r_x, r_y)` are computed
+//! from the constant matrices, so the circuit grows linearly with their number of non-zero
+//! entries. Nothing folds these circuits into a `RecursiveSNARK`: wrapping a `CompressedSNARK`
+//! needs an IVC over both circuits that the caller builds.
+use super::{
+  ipa::{inner_product as ipa_inner_product, IPAChallenges},
+  new_transcript,
+  sumcheck::SumcheckProof,
+  RelaxedR1CSSNARK, VerifierKey,
+};
+use crate::{
+  commitments::{compress_point, CommitTrait, Commitment, CompressedCommitment},
+  constants::{BN_LIMB_WIDTH, BN_N_LIMBS, NUM_CHALLENGE_BITS},
+  cyclefold::{circuit::CycleFoldCircuit, gadgets::AllocatedCompressedPoint},
+  errors::{IPAFailure, NovaError},
+  gadgets::{
+    ecc::AllocatedPoint,
+    utils::{alloc_constant, eval_eq, le_bits_to_num, mul_add},
+  },
+  poseidon::{PoseidonROGadget, ROConstantsCircuit},
+  r1cs::RelaxedR1CSInstance,
+  traits::{Group, HashFuncConstantsTrait},
+  transcript::{bytes_to_elements, PoseidonTranscript},
+};
+use bellperson::{
+  gadgets::{
+    boolean::{AllocatedBit, Boolean},
+    num::AllocatedNum,
+  },
+  Circuit, ConstraintSystem, LinearCombination, SynthesisError,
+};
+use bellperson_nonnative::util::convert::f_to_nat;
+use core::{cmp::max, iter};
+use ff::{Field, PrimeField, PrimeFieldBits};
+use num_bigint::BigInt;
+use std::collections::HashMap;
+
+/// The number of bits of a scalar processed at a time by the scalar multiplications of
+/// `IPAGroupCircuit`
+const WINDOW: usize = 4;
+
+/// A transcript gadget that mirrors `PoseidonTranscript`
+struct PoseidonTranscriptGadget<G: Group> {
+  constants: ROConstantsCircuit<G::Scalar>,
+  ro: PoseidonROGadget<G::Scalar>,
+}
+
+impl<G: Group> PoseidonTranscriptGadget<G> {
+  fn new<CS: ConstraintSystem<G::Scalar>>(
+    mut cs: CS,
+    label: &[u8],
+  ) -> Result<Self, SynthesisError> {
+    let constants = ROConstantsCircuit::<G::Scalar>::new();
+    let mut transcript = Self {
+      ro: PoseidonROGadget::new(constants.clone()),
+      constants,
+    };
+    transcript.absorb_bytes(cs.namespace(|| "label"), label)?;
+    Ok(transcript)
+  }
+
+  fn absorb(&mut self, e: &AllocatedNum<G::Scalar>) {
+    self.ro.absorb(e.clone());
+  }
+
+  /// Absorbs bytes that are fixed by the protocol, as constants
+  fn absorb_bytes<CS: ConstraintSystem<G::Scalar>>(
+    &mut self,
+    mut cs: CS,
+    bytes: &[u8],
+  ) -> Result<(), SynthesisError> {
+    for (i, e) in bytes_to_elements::<G::Scalar>(bytes)
+      .into_iter()
+      .enumerate()
+    {
+      let e = alloc_constant(cs.namespace(|| format!("element {}", i)), e)?;
+      self.absorb(&e);
+    }
+    Ok(())
+  }
+
+  fn absorb_commitment<CS: ConstraintSystem<G::Scalar>>(
+    &mut self,
+    cs: CS,
+    comm: &AllocatedCompressedPoint<G>,
+  ) -> Result<(), SynthesisError> {
+    comm.absorb_in_ro(cs, &mut self.ro)
+  }
+
+  fn squeeze<CS: ConstraintSystem<G::Scalar>>(
+    &mut self,
+    mut cs: CS,
+  ) -> Result<AllocatedNum<G::Scalar>, SynthesisError> {
+    let bits = self.ro.get_challenge(cs.namespace(|| "get challenge"))?;
+    let c = le_bits_to_num(cs.namespace(|| "challenge"), bits)?;
+    self.ro = PoseidonROGadget::new(self.constants.clone());
+    self.ro.absorb(c.clone());
+    Ok(c)
+  }
+}
+
+/// A relaxed R1CS instance allocated in a circuit over `G::Scalar`, with its commitments in
+/// compressed form
+pub struct AllocatedSNARKInstance<G: Group> {
+  comm_W: AllocatedCompressedPoint<G>,
+  comm_L: Option<AllocatedCompressedPoint<G>>,
+  comm_E: AllocatedCompressedPoint<G>,
+  u: AllocatedNum<G::Scalar>,
+  X: Vec<AllocatedNum<G::Scalar>>,
+  // the commitments (comm_W, comm_L, comm_E), from which the prover computes the commitments
+  // that the verifier circuit takes as witnesses
+  comms: Option<(Commitment<G>, Option<Commitment<G>>, Commitment<G>)>,
+}
+
+impl<G: Group> AllocatedSNARKInstance<G> {
+  /// Allocates the given instance of the shape of `vk` as a witness of the circuit
+  pub fn alloc<CS: ConstraintSystem<G::Scalar>>(
+    mut cs: CS,
+    vk: &VerifierKey<G>,
+    inst: Option<&RelaxedR1CSInstance<G>>,
+  ) -> Result<Self, SynthesisError> {
+    let comm_W = AllocatedCompressedPoint::alloc(
+      cs.namespace(|| "allocate comm_W"),
+      inst.map(|inst| &inst.comm_W),
+      BN_LIMB_WIDTH,
+      BN_N_LIMBS,
+    )?;
+    let comm_L = if vk.S.lookup.is_some() {
+      Some(AllocatedCompressedPoint::alloc(
+        cs.namespace(|| "allocate comm_L"),
+        inst.and_then(|inst| inst.comm_L.as_ref()),
+        BN_LIMB_WIDTH,
+        BN_N_LIMBS,
+      )?)
+    } else {
+      None
+    };
+    let comm_E = AllocatedCompressedPoint::alloc(
+      cs.namespace(|| "allocate comm_E"),
+      inst.map(|inst| &inst.comm_E),
+      BN_LIMB_WIDTH,
+      BN_N_LIMBS,
+    )?;
+    let u = alloc_num(cs.namespace(|| "allocate u"), inst.map(|inst| inst.u))?;
+    let X = (0..vk.S.num_io)
+      .map(|i| {
+        alloc_num(
+          cs.namespace(|| format!("allocate X[{}]", i)),
+          inst.and_then(|inst| inst.X.get(i).copied()),
+        )
+      })
+      .collect::<Result<Vec<_>, _>>()?;
+    Ok(Self {
+      comm_W,
+      comm_L,
+      comm_E,
+      u,
+      X,
+      comms: inst.map(|inst| (inst.comm_W, inst.comm_L, inst.comm_E)),
+    })
+  }
+
+  /// Makes `u` and `X` public inputs. The commitments are output with the values shared with
+  /// `IPAGroupCircuit`, by `AllocatedDeferredIPACheck::inputize`.
+  pub fn inputize<CS: ConstraintSystem<G::Scalar>>(
+    &self,
+    mut cs: CS,
+  ) -> Result<(), SynthesisError> {
+    self.u.inputize(cs.namespace(|| "output u"))?;
+    for (i, x) in self.X.iter().enumerate() {
+      x.inputize(cs.namespace(|| format!("output X[{}]", i)))?;
+    }
+    Ok(())
+  }
+}
+
+/// A proof of `RelaxedR1CSSNARK` with a `PoseidonTranscript` allocated in a circuit over
+/// `G::Scalar`. The polynomials of the sum-checks are allocated without their linear terms.
+pub struct AllocatedRelaxedR1CSSNARK<G: Group> {
+  polys_outer: Vec<Vec<AllocatedNum<G::Scalar>>>,
+  claims_outer: [AllocatedNum<G::Scalar>; 3],
+  polys_inner: Vec<Vec<AllocatedNum<G::Scalar>>>,
+  eval_E: AllocatedNum<G::Scalar>,
+  eval_W: AllocatedNum<G::Scalar>,
+  eval_L: Option<AllocatedNum<G::Scalar>>,
+  cross_term: AllocatedNum<G::Scalar>,
+  L_vec: Vec<AllocatedCompressedPoint<G>>,
+  R_vec: Vec<AllocatedCompressedPoint<G>>,
+  a_hat: AllocatedNum<G::Scalar>,
+}
+
+impl<G: Group> AllocatedRelaxedR1CSSNARK<G> {
+  /// Allocates the given proof for the shape of `vk` as a witness of the circuit
+  pub fn alloc<CS: ConstraintSystem<G::Scalar>>(
+    mut cs: CS,
+    vk: &VerifierKey<G>,
+    proof: Option<&RelaxedR1CSSNARK<G, PoseidonTranscript<G>>>,
+  ) -> Result<Self, SynthesisError> {
+    let (num_rounds_x, num_rounds_y, num_rounds_ipa) = (
+      (vk.S.num_cons as f64).log2() as usize,
+      (vk.S.num_vars as f64).log2() as usize + 1,
+      num_rounds_ipa(vk),
+    );
+    let polys_outer = alloc_sumcheck(
+      cs.namespace(|| "allocate outer sum-check"),
+      num_rounds_x,
+      3,
+      proof.map(|proof| &proof.sc_proof_outer),
+    )?;
+    let claims = proof.map(|proof| proof.claims_outer);
+    let claims_outer = [
+      alloc_num(cs.namespace(|| "allocate claim_Az"), claims.map(|c| c.0))?,
+      alloc_num(cs.namespace(|| "allocate claim_Bz"), claims.map(|c| c.1))?,
+      alloc_num(cs.namespace(|| "allocate claim_Cz"), claims.map(|c| c.2))?,
+    ];
+    let polys_inner = alloc_sumcheck(
+      cs.namespace(|| "allocate inner sum-check"),
+      num_rounds_y,
+      2,
+      proof.map(|proof| &proof.sc_proof_inner),
+    )?;
+    let eval_E = alloc_num(
+      cs.namespace(|| "allocate eval_E"),
+      proof.map(|proof| proof.eval_E),
+    )?;
+    let eval_W = alloc_num(
+      cs.namespace(|| "allocate eval_W"),
+      proof.map(|proof| proof.eval_W),
+    )?;
+    let eval_L = if vk.S.lookup.is_some() {
+      Some(alloc_num(
+        cs.namespace(|| "allocate eval_L"),
+        proof.and_then(|proof| proof.eval_L),
+      )?)
+    } else {
+      None
+    };
+    let cross_term = alloc_num(
+      cs.namespace(|| "allocate cross_term"),
+      proof.map(|proof| proof.nifs_ip.cross_term),
+    )?;
+    let mut alloc_points = |name: &str, num: usize, L: bool| {
+      (0..num)
+        .map(|i| {
+          let comm = proof.and_then(|proof| {
+            let vec = if L {
+              &proof.ipa.L_vec
+            } else {
+              &proof.ipa.R_vec
+            };
+            vec.get(i)?.decompress().ok()
+          });
+          AllocatedCompressedPoint::alloc(
+            cs.namespace(|| format!("allocate {}[{}]", name, i)),
+            comm.as_ref(),
+            BN_LIMB_WIDTH,
+            BN_N_LIMBS,
+          )
+        })
+        .collect::<Result<Vec<_>, _>>()
+    };
+    let L_vec = alloc_points("L", num_rounds_ipa, true)?;
+    let R_vec = alloc_points("R", num_rounds_ipa, false)?;
+    let a_hat = alloc_num(
+      cs.namespace(|| "allocate a_hat"),
+      proof.map(|proof| proof.ipa.a_hat),
+    )?;
+    Ok(Self {
+      polys_outer,
+      claims_outer,
+      polys_inner,
+      eval_E,
+      eval_W,
+      eval_L,
+      cross_term,
+      L_vec,
+      R_vec,
+      a_hat,
+    })
+  }
+
+  /// Verifies the proof for the instance `U` of the shape of `vk` in the circuit, except for
+  /// the group operations. The commitments that result from group operations are provided by
+  /// the prover. Returns them along with the other values that `IPAGroupCircuit` shares with
+  /// this circuit, and the round challenges of the IPA that determine the folded generator.
+  pub fn verify<CS: ConstraintSystem<G::Scalar>>(
+    &self,
+    mut cs: CS,
+    vk: &VerifierKey<G>,
+    U: &AllocatedSNARKInstance<G>,
+  ) -> Result<AllocatedDeferredIPACheck<G>, SynthesisError> {
+    // as natively, the inner product instances for E and W must have the same size
+    if vk.S.lookup.is_none() && vk.S.num_vars != vk.S.num_cons {
+      return Err(SynthesisError::Unsatisfiable);
+    }
+    let one = alloc_constant(cs.namespace(|| "one"), G::Scalar::one())?;
+    let zero = alloc_constant(cs.namespace(|| "zero"), G::Scalar::zero())?;
+
+    // absorb the digest of the public parameters, the shape and the instance
+    let mut transcript =
+      PoseidonTranscriptGadget::<G>::new(cs.namespace(|| "transcript"), b"RelaxedR1CSSNARK")?;
+    transcript.absorb_bytes(cs.namespace(|| "tag"), &vk.gens_r1cs.tag)?;
+    let pp_digest = alloc_constant(cs.namespace(|| "pp_digest"), vk.digest)?;
+    let S_digest = alloc_constant(cs.namespace(|| "S digest"), vk.S.get_digest())?;
+    transcript.absorb(&pp_digest);
+    transcript.absorb(&S_digest);
+    transcript.absorb_commitment(cs.namespace(|| "absorb comm_W"), &U.comm_W)?;
+    if let Some(comm_L) = &U.comm_L {
+      transcript.absorb_commitment(cs.namespace(|| "absorb comm_L"), comm_L)?;
+    }
+    transcript.absorb_commitment(cs.namespace(|| "absorb comm_E"), &U.comm_E)?;
+    transcript.absorb(&U.u);
+    for x in &U.X {
+      transcript.absorb(x);
+    }
+
+    // outer sum-check
+    let tau = (0..self.polys_outer.len())
+      .map(|i| transcript.squeeze(cs.namespace(|| format!("tau[{}]", i))))
+      .collect::<Result<Vec<_>, _>>()?;
+    let (claim_outer_final, r_x) = verify_sumcheck(
+      cs.namespace(|| "outer sum-check"),
+      &mut transcript,
+      &zero,
+      &self.polys_outer,
+    )?;
+
+    // verify claim_outer_final
+    let [claim_Az, claim_Bz, claim_Cz] = &self.claims_outer;
+    let taus_bound_rx = eval_eq(cs.namespace(|| "eq(tau, r_x)"), &tau, &r_x, &one)?;
+    let AzBz = claim_Az.mul(cs.namespace(|| "Az * Bz"), claim_Bz)?;
+    let uCz = U.u.mul(cs.namespace(|| "u * Cz"), claim_Cz)?;
+    cs.enforce(
+      || "check claim_outer_final",
+      |lc| lc + taus_bound_rx.get_variable(),
+      |lc| lc + AzBz.get_variable() - uCz.get_variable() - self.eval_E.get_variable(),
+      |lc| lc + claim_outer_final.get_variable(),
+    );
+
+    for e in self.claims_outer.iter().chain([&self.eval_E]) {
+      transcript.absorb(e);
+    }
+
+    // inner sum-check
+    let r_ABC = ["r_A", "r_B", "r_C"]
+      .iter()
+      .map(|name| transcript.squeeze(cs.namespace(|| *name)))
+      .collect::<Result<Vec<_>, _>>()?;
+    let claim_inner_joint = inner_product(
+      cs.namespace(|| "claim_inner_joint"),
+      &r_ABC,
+      &self.claims_outer,
+    )?;
+    let (claim_inner_final, r_y) = verify_sumcheck(
+      cs.namespace(|| "inner sum-check"),
+      &mut transcript,
+      &claim_inner_joint,
+      &self.polys_inner,
+    )?;
+
+    let evals_rx = eq_evals(cs.namespace(|| "evals_rx"), &r_x, &one)?;
+    let evals_ry = eq_evals(cs.namespace(|| "evals_ry"), &r_y[1..], &one)?;
+
+    // verify claim_inner_final
+    let eval_X = {
+      let poly_X = iter::once(&U.u)
+        .chain(U.X.iter())
+        .enumerate()
+        .collect::<Vec<_>>();
+      eval_sparse(cs.namespace(|| "eval_X"), &poly_X, &r_y[1..], &one)?
+    };
+    // with a lookup argument, eval_W and eval_L are the evaluations of the halves of W at r_y[2..]
+    let eval_W = match &self.eval_L {
+      Some(eval_L) => interpolate(
+        cs.namespace(|| "eval_W with eval_L"),
+        &r_y[1],
+        &self.eval_W,
+        eval_L,
+      )?,
+      None => self.eval_W.clone(),
+    };
+    let eval_Z = interpolate(cs.namespace(|| "eval_Z"), &r_y[0], &eval_W, &eval_X)?;
+
+    // as natively, the entries of a matrix for W and for (u, X) are accumulated separately,
+    // and the products of the evaluation tables are shared by the entries of A, B and C
+    let num_vars = vk.S.num_vars;
+    let mut products: HashMap<(usize, usize), AllocatedNum<G::Scalar>> = HashMap::new();
+    let mut evals_M = Vec::new();
+    for (name, M) in [("A", &vk.S.A), ("B", &vk.S.B), ("C", &vk.S.C)] {
+      let mut cs = cs.namespace(|| format!("evaluate {}", name));
+      let mut terms = [Vec::new(), Vec::new()];
+      for (row, col, val) in M.iter() {
+        let (half, col) = if *col < num_vars {
+          (0, *col)
+        } else {
+          (1, *col - num_vars)
+        };
+        let product = match products.get(&(*row, col)) {
+          Some(product) => product.clone(),
+          None => {
+            let product = evals_rx[*row].mul(
+              cs.namespace(|| format!("eq(r_x, {}) * eq(r_y, {})", row, col)),
+              &evals_ry[col],
+            )?;
+            products.insert((*row, col), product.clone());
+            product
+          }
+        };
+        terms[half].push((*val, product));
+      }
+      let eval_W = linear_sum(cs.namespace(|| "eval_W"), &terms[0])?;
+      let eval_X = linear_sum(cs.namespace(|| "eval_X"), &terms[1])?;
+      evals_M.push(interpolate(
+        cs.namespace(|| "eval"),
+        &r_y[0],
+        &eval_W,
+        &eval_X,
+      )?);
+    }
+    let eval_M = inner_product(cs.namespace(|| "joint eval"), &r_ABC, &evals_M)?;
+    cs.enforce(
+      || "check claim_inner_final",
+      |lc| lc + eval_M.get_variable(),
+      |lc| lc + eval_Z.get_variable(),
+      |lc| lc + claim_inner_final.get_variable(),
+    );
+
+    // with a lookup argument, both halves of W are opened at r_y[2..] with a random linear
+    // combination, whose commitment comm_W + rho * comm_L is provided by the prover, and the
+    // public vectors of the inner product instances are padded to the same length
+    transcript.absorb(&self.eval_W);
+    let n = ipa_size(vk);
+    let (lookup, comm_W, b_W, c_W) = match (&U.comm_L, &self.eval_L) {
+      (Some(comm_L), Some(eval_L)) => {
+        transcript.absorb(eval_L);
+        let rho = transcript.squeeze(cs.namespace(|| "rho"))?;
+        let comm_W_rho_value = U
+          .comms
+          .and_then(|(comm_W, comm_L, _)| Some(comm_W + comm_L? * rho.get_value()?));
+        let comm_W_rho = AllocatedCompressedPoint::alloc(
+          cs.namespace(|| "allocate comm_W + rho * comm_L"),
+          comm_W_rho_value.as_ref(),
+          BN_LIMB_WIDTH,
+          BN_N_LIMBS,
+        )?;
+        let b_W = eq_evals(cs.namespace(|| "evals_ry_lo"), &r_y[2..], &one)?;
+        let c_W = mul_add(
+          cs.namespace(|| "eval_W + rho * eval_L"),
+          &self.eval_W,
+          &rho,
+          eval_L,
+        )?;
+        (
+          Some((comm_L.clone(), comm_W_rho.clone(), rho)),
+          comm_W_rho,
+          b_W,
+          c_W,
+        )
+      }
+      (None, None) => (None, U.comm_W.clone(), evals_ry, self.eval_W.clone()),
+      _ => return Err(SynthesisError::Unsatisfiable),
+    };
+    let pad = |b: Vec<AllocatedNum<G::Scalar>>| -> Vec<AllocatedNum<G::Scalar>> {
+      let len = b.len();
+      b.into_iter()
+        .chain(iter::repeat_n(zero.clone(), n - len))
+        .collect()
+    };
+    let (b_E, b_W) = (pad(evals_rx), pad(b_W));
+
+    // fold the inner product instances for E and W, as `NIFSForInnerProduct::verify`
+    transcript.absorb_bytes(
+      cs.namespace(|| "NIFS protocol name"),
+      b"NIFSForInnerProduct",
+    )?;
+    transcript.absorb_commitment(cs.namespace(|| "absorb U1.comm_a_vec"), &U.comm_E)?;
+    for e in &b_E {
+      transcript.absorb(e);
+    }
+    transcript.absorb_commitment(cs.namespace(|| "absorb U2.comm_a_vec"), &comm_W)?;
+    for e in &b_W {
+      transcript.absorb(e);
+    }
+    transcript.absorb(&self.cross_term);
+    let r_fold = transcript.squeeze(cs.namespace(|| "r_fold"))?;
+
+    let b_vec = b_E
+      .iter()
+      .zip(b_W.iter())
+      .enumerate()
+      .map(|(i, (b1, b2))| {
+        mul_add(
+          cs.namespace(|| format!("fold b_vec[{}]", i)),
+          b1,
+          &r_fold,
+          b2,
+        )
+      })
+      .collect::<Result<Vec<_>, _>>()?;
+    let c = {
+      let r_square = r_fold.square(cs.namespace(|| "r_fold^2"))?;
+      let c = mul_add(
+        cs.namespace(|| "eval_E + r_fold^2 * c_W"),
+        &self.eval_E,
+        &r_square,
+        &c_W,
+      )?;
+      mul_add(cs.namespace(|| "fold c"), &c, &r_fold, &self.cross_term)?
+    };
+    let comm_fold_value = U.comms.and_then(|(comm_W, comm_L, comm_E)| {
+      let comm_W = match (comm_L, &lookup) {
+        (Some(comm_L), Some((_, _, rho))) => comm_W + comm_L * rho.get_value()?,
+        _ => comm_W,
+      };
+      Some(comm_E + comm_W * r_fold.get_value()?)
+    });
+    let comm_fold = AllocatedCompressedPoint::alloc(
+      cs.namespace(|| "allocate comm_fold"),
+      comm_fold_value.as_ref(),
+      BN_LIMB_WIDTH,
+      BN_N_LIMBS,
+    )?;
+
+    // derive the challenges of the IPA, as `InnerProductArgument::verify`
+    transcript.absorb_bytes(
+      cs.namespace(|| "IPA protocol name"),
+      b"inner product argument",
+    )?;
+    transcript.absorb_commitment(cs.namespace(|| "absorb comm_a_vec"), &comm_fold)?;
+    for e in &b_vec {
+      transcript.absorb(e);
+    }
+    transcript.absorb(&c);
+    let r_c = transcript.squeeze(cs.namespace(|| "r_c"))?;
+    let r = self
+      .L_vec
+      .iter()
+      .zip(self.R_vec.iter())
+      .enumerate()
+      .map(|(i, (L, R))| {
+        transcript.absorb_commitment(cs.namespace(|| format!("absorb L[{}]", i)), L)?;
+        transcript.absorb_commitment(cs.namespace(|| format!("absorb R[{}]", i)), R)?;
+        transcript.squeeze(cs.namespace(|| format!("challenge_r[{}]", i)))
+      })
+      .collect::<Result<Vec<_>, _>>()?;
+
+    let (r_square, r_inverse_square, s) = ipa_tensor(cs.namespace(|| "s"), &r, n, &one)?;
+    let b_hat = inner_product(cs.namespace(|| "b_hat"), &b_vec, &s)?;
+
+    // the IPA checks comm_fold + c * r_c * g_c + sum_i (r_i^2 * L_i + r_i^-2 * R_i)
+    //   = a_hat * G_hat + a_hat * b_hat * r_c * g_c,
+    // so the two multiples of g_c are combined into (a_hat * b_hat - c) * r_c
+    let ab = self.a_hat.mul(cs.namespace(|| "a_hat * b_hat"), &b_hat)?;
+    let d = AllocatedNum::alloc(cs.namespace(|| "(a_hat * b_hat - c) * r_c"), || {
+      let (ab, c, r_c) = (
+        ab.get_value().ok_or(SynthesisError::AssignmentMissing)?,
+        c.get_value().ok_or(SynthesisError::AssignmentMissing)?,
+        r_c.get_value().ok_or(SynthesisError::AssignmentMissing)?,
+      );
+      Ok((ab - c) * r_c)
+    })?;
+    cs.enforce(
+      || "check (a_hat * b_hat - c) * r_c",
+      |lc| lc + ab.get_variable() - c.get_variable(),
+      |lc| lc + r_c.get_variable(),
+      |lc| lc + d.get_variable(),
+    );
+
+    // the values shared with `IPAGroupCircuit`, in the order of its public IO
+    let mut points = vec![U.comm_W.clone(), U.comm_E.clone()];
+    let mut scalars = Vec::new();
+    if let Some((comm_L, comm_W_rho, rho)) = lookup {
+      points.extend([comm_L, comm_W_rho]);
+      scalars.push(rho);
+    }
+    points.push(comm_fold);
+    points.extend(self.L_vec.iter().cloned());
+    points.extend(self.R_vec.iter().cloned());
+    scalars.push(r_fold);
+    scalars.extend(r_square);
+    scalars.extend(r_inverse_square);
+    scalars.extend([self.a_hat.clone(), d]);
+
+    Ok(AllocatedDeferredIPACheck { points, scalars, r })
+  }
+}
+
+/// The commitments and scalars with which the verifier circuit of `RelaxedR1CSSNARK` defers
+/// its group operations to `IPAGroupCircuit`, along with the round challenges of the IPA
+pub struct AllocatedDeferredIPACheck<G: Group> {
+  points: Vec<AllocatedCompressedPoint<G>>,
+  scalars: Vec<AllocatedNum<G::Scalar>>,
+  r: Vec<AllocatedNum<G::Scalar>>,
+}
+
+impl<G: Group> AllocatedDeferredIPACheck<G> {
+  /// Makes public inputs of the values shared with `IPAGroupCircuit`, as limbs, followed by
+  /// the round challenges of the IPA
+  pub fn inputize<CS: ConstraintSystem<G::Scalar>>(
+    &self,
+    mut cs: CS,
+  ) -> Result<(), SynthesisError> {
+    for (i, p) in self.points.iter().enumerate() {
+      let mut cs = cs.namespace(|| format!("output point {}", i));
+      // the limbs are not range checked here: they are equal to those of the group circuit
+      for (j, limb) in p.x.limbs.iter().enumerate() {
+        inputize_lc(
+          cs.namespace(|| format!("limb {}", j)),
+          limb.clone(),
+          p.x.limb_values.as_ref().map(|v| v[j]),
+        )?;
+      }
+      inputize_lc(cs.namespace(|| "flags"), p.flags(), p.flags_value())?;
+    }
+    for (i, s) in self.scalars.iter().enumerate() {
+      let mut cs = cs.namespace(|| format!("output scalar {}", i));
+      let bits = s.to_bits_le_strict(cs.namespace(|| "bits"))?;
+      inputize_limbs(cs.namespace(|| "limbs"), &bits)?;
+    }
+    for (i, r_i) in self.r.iter().enumerate() {
+      r_i.inputize(cs.namespace(|| format!("output r[{}]", i)))?;
+    }
+    Ok(())
+  }
+}
+
+/// The verifier circuit of `RelaxedR1CSSNARK` over `G::Scalar`. Its public IO are `u` and `X`
+/// of the instance, the values shared with `IPAGroupCircuit`, and the round challenges of the IPA.
+pub struct SNARKVerifierCircuit<'a, G: Group> {
+  vk: &'a VerifierKey<G>,
+  inputs: Option<(
+    &'a RelaxedR1CSInstance<G>,
+    &'a RelaxedR1CSSNARK<G, PoseidonTranscript<G>>,
+  )>,
+}
+
+impl<'a, G: Group> SNARKVerifierCircuit<'a, G> {
+  /// Creates the verifier circuit for the shape of `vk`, with an instance and its proof
+  pub fn new(
+    vk: &'a VerifierKey<G>,
+    inputs: Option<(
+      &'a RelaxedR1CSInstance<G>,
+      &'a RelaxedR1CSSNARK<G, PoseidonTranscript<G>>,
+    )>,
+  ) -> Self {
+    Self { vk, inputs }
+  }
+}
+
+impl<G: Group> Circuit<G::Scalar> for SNARKVerifierCircuit<'_, G> {
+  fn synthesize<CS: ConstraintSystem<G::Scalar>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+    let U = AllocatedSNARKInstance::alloc(
+      cs.namespace(|| "U"),
+      self.vk,
+      self.inputs.map(|inputs| inputs.0),
+    )?;
+    U.inputize(cs.namespace(|| "output U"))?;
+    let proof = AllocatedRelaxedR1CSSNARK::alloc(
+      cs.namespace(|| "proof"),
+      self.vk,
+      self.inputs.map(|inputs| inputs.1),
+    )?;
+    let deferred = proof.verify(cs.namespace(|| "verify"), self.vk, &U)?;
+    deferred.inputize(cs.namespace(|| "output deferred check"))?;
+
+    // R1CSShape requires an even number of public IO, so the IO is padded with a zero
+    if num_verifier_io(self.vk) > 1 + self.vk.S.num_io + num_shared_io(self.vk) + deferred.r.len() {
+      let zero = cs.alloc_input(|| "padding", || Ok(G::Scalar::zero()))?;
+      cs.enforce(
+        || "padding is zero",
+        |lc| lc + zero,
+        |lc| lc + CS::one(),
+        |lc| lc,
+      );
+    }
+    Ok(())
+  }
+}
+
+/// The inputs of `IPAGroupCircuit`, which the prover computes natively from an instance and
+/// its proof
+pub struct IPAGroupInputs<G: Group> {
+  comm_W: Commitment<G>,
+  comm_E: Commitment<G>,
+  // comm_L and rho with a lookup argument
+  lookup: Option<(Commitment<G>, G::Scalar)>,
+  r_fold: G::Scalar,
+  comm_fold: Commitment<G>,
+  L_vec: Vec<Commitment<G>>,
+  R_vec: Vec<Commitment<G>>,
+  r_square: Vec<G::Scalar>,
+  r_inverse_square: Vec<G::Scalar>,
+  a_hat: G::Scalar,
+  d: G::Scalar,
+  G_hat: Commitment<G>,
+}
+
+impl<G: Group> IPAGroupInputs<G> {
+  /// Runs the verifier of `snark` for the instance `U` up to its group operations, and
+  /// returns the inputs of the circuit that checks them
+  pub fn new(
+    vk: &VerifierKey<G>,
+    U: &RelaxedR1CSInstance<G>,
+    snark: &RelaxedR1CSSNARK<G, PoseidonTranscript<G>>,
+  ) -> Result<Self, NovaError> {
+    let mut transcript = new_transcript::<G, PoseidonTranscript<G>>(&vk.gens_r1cs);
+    let (r_U, n, rho, r_fold) = snark.reduce_to_ipa(vk, U, &mut transcript)?;
+    let (r_c, r) = snark.ipa.challenges(n, &r_U, &mut transcript)?;
+    let challenges = IPAChallenges::new(&r, n)?;
+    let b_hat = ipa_inner_product(&r_U.b_vec, &challenges.s);
+    let decompress = |comms: &[CompressedCommitment<G::CompressedGroupElement>]| {
+      comms
+        .iter()
+        .map(|comm| comm.decompress())
+        .collect::<Result<Vec<_>, _>>()
+    };
+
+    Ok(Self {
+      comm_W: U.comm_W,
+      comm_E: U.comm_E,
+      lookup: U.comm_L.zip(rho),
+      r_fold,
+      comm_fold: r_U.comm_a_vec,
+      L_vec: decompress(&snark.ipa.L_vec)?,
+      R_vec: decompress(&snark.ipa.R_vec)?,
+      r_square: challenges.r_square,
+      r_inverse_square: challenges.r_inverse_square,
+      a_hat: snark.ipa.a_hat,
+      d: (snark.ipa.a_hat * b_hat - r_U.c) * r_c,
+      G_hat: challenges.s.commit(&vk.gens_r1cs.gens),
+    })
+  }
+}
+
+/// The circuit over `G::Base` that checks the group operations of the verifier of
+/// `RelaxedR1CSSNARK` natively. Its public IO are the values shared with `SNARKVerifierCircuit`
+/// and the folded generator `G_hat`.
+pub struct IPAGroupCircuit<'a, G: Group> {
+  vk: &'a VerifierKey<G>,
+  inputs: Option<IPAGroupInputs<G>>,
+}
+
+impl<'a, G: Group> IPAGroupCircuit<'a, G> {
+  /// Creates the group circuit for the shape of `vk`
+  pub fn new(vk: &'a VerifierKey<G>, inputs: Option<IPAGroupInputs<G>>) -> Self {
+    Self { vk, inputs }
+  }
+
+  /// Allocates a point given in compressed form and outputs the limbs of its x-coordinate
+  /// and its flags
+  fn alloc_point<CS: ConstraintSystem<G::Base>>(
+    mut cs: CS,
+    comm: Option<&Commitment<G>>,
+  ) -> Result<AllocatedPoint<G::Base>, SynthesisError> {
+    let (point, flags) =
+      CycleFoldCircuit::<G>::alloc_compressed_point(cs.namespace(|| "point"), comm)?;
+    let x_bits = point.x.to_bits_le_strict(cs.namespace(|| "x bits"))?;
+    inputize_limbs(cs.namespace(|| "output x"), &x_bits)?;
+    let flags_value = comm.map(|comm| {
+      let (_, sign, is_infinity) = compress_point(comm);
+      G::Base::from(sign as u64 + 2 * is_infinity as u64)
+    });
+    inputize_lc(cs.namespace(|| "output flags"), flags, flags_value)?;
+    Ok(point)
+  }
+
+  /// Allocates the `num_bits` low-order bits of a scalar and outputs their limbs
+  fn alloc_scalar<CS: ConstraintSystem<G::Base>>(
+    mut cs: CS,
+    value: Option<G::Scalar>,
+    num_bits: usize,
+  ) -> Result<Vec<AllocatedBit>, SynthesisError> {
+    let value_bits = value.map(|value| value.to_le_bits());
+    let bits = (0..num_bits)
+      .map(|i| {
+        AllocatedBit::alloc(
+          cs.namespace(|| format!("bit {}", i)),
+          value_bits.as_ref().map(|bits| bits[i]),
+        )
+      })
+      .collect::<Result<Vec<_>, _>>()?;
+    let booleans = bits.iter().cloned().map(Boolean::from).collect::<Vec<_>>();
+    inputize_limbs(cs.namespace(|| "output limbs"), &booleans)?;
+    Ok(bits)
+  }
+}
+
+impl<G: Group> Circuit<G::Base> for IPAGroupCircuit<'_, G> {
+  fn synthesize<CS: ConstraintSystem<G::Base>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+    let inputs = self.inputs.as_ref();
+    let num_rounds = num_rounds_ipa(self.vk);
+    let has_lookup = self.vk.S.lookup.is_some();
+
+    // allocate and output the shared points, then the shared scalars, then G_hat
+    let comm_W = Self::alloc_point(
+      cs.namespace(|| "comm_W"),
+      inputs.map(|inputs| &inputs.comm_W),
+    )?;
+    let comm_E = Self::alloc_point(
+      cs.namespace(|| "comm_E"),
+      inputs.map(|inputs| &inputs.comm_E),
+    )?;
+    let lookup_points = if has_lookup {
+      let comm_L = inputs.and_then(|inputs| inputs.lookup.map(|(comm_L, _)| comm_L));
+      let comm_W_rho = inputs.and_then(|inputs| {
+        let (comm_L, rho) = inputs.lookup?;
+        Some(inputs.comm_W + comm_L * rho)
+      });
+      Some((
+        Self::alloc_point(cs.namespace(|| "comm_L"), comm_L.as_ref())?,
+        Self::alloc_point(cs.namespace(|| "comm_W_rho"), comm_W_rho.as_ref())?,
+      ))
+    } else {
+      None
+    };
+    let comm_fold = Self::alloc_point(
+      cs.namespace(|| "comm_fold"),
+      inputs.map(|inputs| &inputs.comm_fold),
+    )?;
+    let mut alloc_points = |name: &str, L: bool| {
+      (0..num_rounds)
+        .map(|i| {
+          let comm = inputs.and_then(|inputs| {
+            if L {
+              inputs.L_vec.get(i)
+            } else {
+              inputs.R_vec.get(i)
+            }
+          });
+          Self::alloc_point(cs.namespace(|| format!("{}[{}]", name, i)), comm)
+        })
+        .collect::<Result<Vec<_>, _>>()
+    };
+    let L_vec = alloc_points("L", true)?;
+    let R_vec = alloc_points("R", false)?;
+
+    let num_bits = G::Scalar::NUM_BITS as usize;
+    let rho = if has_lookup {
+      Some(Self::alloc_scalar(
+        cs.namespace(|| "rho"),
+        inputs.and_then(|inputs| inputs.lookup.map(|(_, rho)| rho)),
+        NUM_CHALLENGE_BITS,
+      )?)
+    } else {
+      None
+    };
+    let r_fold = Self::alloc_scalar(
+      cs.namespace(|| "r_fold"),
+      inputs.map(|inputs| inputs.r_fold),
+      NUM_CHALLENGE_BITS,
+    )?;
+    let mut alloc_scalars = |name: &str, square: bool| {
+      (0..num_rounds)
+        .map(|i| {
+          let value = inputs.and_then(|inputs| {
+            if square {
+              inputs.r_square.get(i).copied()
+            } else {
+              inputs.r_inverse_square.get(i).copied()
+            }
+          });
+          Self::alloc_scalar(cs.namespace(|| format!("{}[{}]", name, i)), value, num_bits)
+        })
+        .collect::<Result<Vec<_>, _>>()
+    };
+    let r_square = alloc_scalars("r_square", true)?;
+    let r_inverse_square = alloc_scalars("r_inverse_square", false)?;
+    let a_hat = Self::alloc_scalar(
+      cs.namespace(|| "a_hat"),
+      inputs.map(|inputs| inputs.a_hat),
+      num_bits,
+    )?;
+    let d = Self::alloc_scalar(
+      cs.namespace(|| "d"),
+      inputs.map(|inputs| inputs.d),
+      num_bits,
+    )?;
+
+    let G_hat = Self::alloc_point(cs.namespace(|| "G_hat"), inputs.map(|inputs| &inputs.G_hat))?;
+
+    // with a lookup argument, check comm_W_rho = comm_W + rho * comm_L
+    let comm_W = match (lookup_points, rho) {
+      (Some((comm_L, comm_W_rho)), Some(rho)) => {
+        let rho_L = comm_L.scalar_mul_windowed(cs.namespace(|| "rho * comm_L"), rho, WINDOW)?;
+        let sum = comm_W.add(cs.namespace(|| "comm_W + rho * comm_L"), &rho_L)?;
+        sum.enforce_equal(cs.namespace(|| "check comm_W_rho"), &comm_W_rho);
+        comm_W_rho
+      }
+      _ => comm_W,
+    };
+
+    // check comm_fold = comm_E + r_fold * comm_W
+    let r_W = comm_W.scalar_mul_windowed(cs.namespace(|| "r_fold * comm_W"), r_fold, WINDOW)?;
+    let sum = comm_E.add(cs.namespace(|| "comm_E + r_fold * comm_W"), &r_W)?;
+    sum.enforce_equal(cs.namespace(|| "check comm_fold"), &comm_fold);
+
+    // check comm_fold + sum_i (r_i^2 * L_i + r_i^-2 * R_i) = a_hat * G_hat + d * g_c
+    let mut lhs = comm_fold;
+    for (i, (P, bits)) in L_vec
+      .iter()
+      .zip(r_square)
+      .chain(R_vec.iter().zip(r_inverse_square))
+      .enumerate()
+    {
+      let term = P.scalar_mul_windowed(cs.namespace(|| format!("term {}", i)), bits, WINDOW)?;
+      lhs = lhs.add(cs.namespace(|| format!("add term {}", i)), &term)?;
+    }
+    let g_c = [G::Scalar::one()].commit(&self.vk.gens_ipa).comm;
+    let a_G_hat = G_hat.scalar_mul_windowed(cs.namespace(|| "a_hat * G_hat"), a_hat, WINDOW)?;
+    let d_g_c = AllocatedPoint::scalar_mul_fixed_base(cs.namespace(|| "d * g_c"), &g_c, d)?;
+    let rhs = a_G_hat.add(cs.namespace(|| "a_hat * G_hat + d * g_c"), &d_g_c)?;
+    lhs.enforce_equal(cs.namespace(|| "check IPA"), &rhs);
+
+    Ok(())
+  }
+}
+
+/// The check of the folded generator of the IPA that remains after `SNARKVerifierCircuit` and
+/// `IPAGroupCircuit`. As in Halo, the folded generator `G_hat = <s, G>` only depends on the
+/// round challenges of the IPA, so checks of several proofs can be batched or accumulated.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DeferredIPACheck<G: Group> {
+  r: Vec<G::Scalar>,
+  G_hat: Vec<BigInt>,
+}
+
+impl<G: Group> DeferredIPACheck<G> {
+  /// Checks that the public IO of `SNARKVerifierCircuit` and `IPAGroupCircuit` for the shape of
+  /// `vk` agree on the values that they share, and returns the check that remains
+  pub fn from_io(
+    vk: &VerifierKey<G>,
+    io_verifier: &[G::Scalar],
+    io_group: &[G::Base],
+  ) -> Result<Self, NovaError> {
+    let num_rounds = num_rounds_ipa(vk);
+    let num_shared = num_shared_io(vk);
+    let num_instance = 1 + vk.S.num_io;
+    NovaError::check_lengths(&[
+      (num_verifier_io(vk), io_verifier.len()),
+      (num_shared + BN_N_LIMBS + 1, io_group.len()),
+    ])?;
+    let r = &io_verifier[num_instance + num_shared..num_instance + num_shared + num_rounds];
+    if io_verifier[num_instance + num_shared + num_rounds..]
+      .iter()
+      .any(|e| !bool::from(e.is_zero()))
+    {
+      return Err(NovaError::InvalidIPA {
+        check: IPAFailure::DeferredIO,
+      });
+    }
+
+    // the shared values are limbs, which are equal as integers
+    let shared_verifier = io_verifier[num_instance..num_instance + num_shared]
+      .iter()
+      .map(f_to_nat);
+    let shared_group = io_group[..num_shared].iter().map(f_to_nat);
+    if !shared_verifier.eq(shared_group) {
+      return Err(NovaError::InvalidIPA {
+        check: IPAFailure::DeferredIO,
+      });
+    }
+
+    Ok(Self {
+      r: r.to_vec(),
+      G_hat: io_group[num_shared..].iter().map(f_to_nat).collect(),
+    })
+  }
+
+  /// Checks that `G_hat` is the inner product of the generators of `vk` with the tensor of
+  /// the round challenges
+  pub fn verify(&self, vk: &VerifierKey<G>) -> Result<(), NovaError> {
+    let challenges = IPAChallenges::new(&self.r, ipa_size(vk))?;
+    let G_hat = challenges.s.commit(&vk.gens_r1cs.gens);
+    if point_limbs(&G_hat) == self.G_hat {
+      Ok(())
+    } else {
+      Err(NovaError::InvalidIPA {
+        check: IPAFailure::FoldedGenerator,
+      })
+    }
+  }
+}
+
+/// Returns the size of the vectors of the IPA for the shape of `vk`, as `RelaxedR1CSSNARK::verify`
+fn ipa_size<G: Group>(vk: &VerifierKey<G>) -> usize {
+  if vk.S.lookup.is_some() {
+    max(vk.S.num_vars / 2, vk.S.num_cons)
+  } else {
+    max(vk.S.num_vars, vk.S.num_cons)
+  }
+}
+
+/// Returns the number of rounds of the IPA for the shape of `vk`
+fn num_rounds_ipa<G: Group>(vk: &VerifierKey<G>) -> usize {
+  (ipa_size(vk) as f64).log2() as usize
+}
+
+/// Returns the number of public IO with which the two circuits output the values they share
+fn num_shared_io<G: Group>(vk: &VerifierKey<G>) -> usize {
+  let num_rounds = num_rounds_ipa(vk);
+  let (num_points, num_scalars) = if vk.S.lookup.is_some() {
+    (5 + 2 * num_rounds, 4 + 2 * num_rounds)
+  } else {
+    (3 + 2 * num_rounds, 3 + 2 * num_rounds)
+  };
+  num_points * (BN_N_LIMBS + 1) + num_scalars * BN_N_LIMBS
+}
+
+/// Returns the number of public IO of `SNARKVerifierCircuit`, which is padded to be even
+fn num_verifier_io<G: Group>(vk: &VerifierKey<G>) -> usize {
+  let num_io = 1 + vk.S.num_io + num_shared_io(vk) + num_rounds_ipa(vk);
+  num_io + num_io % 2
+}
+
+/// Returns the limbs of a number as the circuits output them
+fn limbs<F: PrimeField>(f: &F) -> Vec<BigInt> {
+  let n = f_to_nat(f);
+  let mask = (BigInt::from(1) << BN_LIMB_WIDTH) - 1;
+  (0..BN_N_LIMBS)
+    .map(|i| (&n >> (i * BN_LIMB_WIDTH)) & &mask)
+    .collect()
+}
+
+/// Returns the limbs of the x-coordinate of a point followed by `sign + 2 * is_infinity`
+fn point_limbs<G: Group>(comm: &Commitment<G>) -> Vec<BigInt> {
+  let (x, sign, is_infinity) = compress_point(comm);
+  let mut limbs = limbs(&x);
+  limbs.push(BigInt::from(sign as u64 + 2 * is_infinity as u64));
+  limbs
+}
+
+/// Makes the linear combination `lc`, whose value is `value`, a public input
+fn inputize_lc<F: PrimeField, CS: ConstraintSystem<F>>(
+  mut cs: CS,
+  lc: LinearCombination<F>,
+  value: Option<F>,
+) -> Result<(), SynthesisError> {
+  let input = cs.alloc_input(
+    || "input",
+    || value.ok_or(SynthesisError::AssignmentMissing),
+  )?;
+  cs.enforce(
+    || "input = lc",
+    |_| lc,
+    |lc| lc + CS::one(),
+    |lc| lc + input,
+  );
+  Ok(())
+}
+
+/// Makes public inputs of the `BN_N_LIMBS` limbs of `BN_LIMB_WIDTH` bits of the number whose
+/// little-endian bits are `bits`
+fn inputize_limbs<F: PrimeField, CS: ConstraintSystem<F>>(
+  mut cs: CS,
+  bits: &[Boolean],
+) -> Result<(), SynthesisError> {
+  assert!(bits.len() <= BN_LIMB_WIDTH * BN_N_LIMBS);
+  for i in 0..BN_N_LIMBS {
+    let chunk = bits.iter().skip(i * BN_LIMB_WIDTH).take(BN_LIMB_WIDTH);
+    let mut lc = LinearCombination::zero();
+    let mut value = Some(F::zero());
+    let mut coeff = F::one();
+    for bit in chunk {
+      lc = lc + &bit.lc(CS::one(), coeff);
+      value = value.and_then(|v| Some(if bit.get_value()? { v + coeff } else { v }));
+      coeff = coeff.double();
+    }
+    inputize_lc(cs.namespace(|| format!("limb {}", i)), lc, value)?;
+  }
+  Ok(())
+}
+
+/// Allocates a number whose value is `value`, or zero when synthesizing the shape
+fn alloc_num<F: PrimeField, CS: ConstraintSystem<F>>(
+  mut cs: CS,
+  value: Option<F>,
+) -> Result<AllocatedNum<F>, SynthesisError> {
+  AllocatedNum::alloc(cs.namespace(|| "alloc"), || {
+    Ok(value.unwrap_or_else(F::zero))
+  })
+}
+
+/// Allocates the polynomials of a sum-check without their linear terms
+fn alloc_sumcheck<G: Group, CS: ConstraintSystem<G::Scalar>>(
+  mut cs: CS,
+  num_rounds: usize,
+  degree: usize,
+  proof: Option<&SumcheckProof<G>>,
+) -> Result<Vec<Vec<AllocatedNum<G::Scalar>>>, SynthesisError> {
+  (0..num_rounds)
+    .map(|i| {
+      (0..degree)
+        .map(|k| {
+          alloc_num(
+            cs.namespace(|| format!("allocate coefficient {} of poly {}", k, i)),
+            proof.and_then(|proof| {
+              proof
+                .compressed_polys
+                .get(i)?
+                .coeffs_except_linear_term
+                .get(k)
+                .copied()
+            }),
+          )
+        })
+        .collect::<Result<Vec<_>, _>>()
+    })
+    .collect()
+}
+
+/// Verifies a sum-check for `claim` whose polynomials are given without their linear terms,
+/// as `SumcheckProof::verify`. Returns the final claim and the challenges.
+fn verify_sumcheck<G: Group, CS: ConstraintSystem<G::Scalar>>(
+  mut cs: CS,
+  transcript: &mut PoseidonTranscriptGadget<G>,
+  claim: &AllocatedNum<G::Scalar>,
+  polys: &[Vec<AllocatedNum<G::Scalar>>],
+) -> Result<(AllocatedNum<G::Scalar>, Vec<AllocatedNum<G::Scalar>>), SynthesisError> {
+  let mut e = claim.clone();
+  let mut r = Vec::new();
+  for (i, poly) in polys.iter().enumerate() {
+    let mut cs = cs.namespace(|| format!("round {}", i));
+
+    // the linear term is such that poly(0) + poly(1) = e, as in `CompressedUniPoly::decompress`
+    let terms = iter::once((G::Scalar::one(), e.clone()))
+      .chain(iter::once((-G::Scalar::from(2u64), poly[0].clone())))
+      .chain(poly[1..].iter().map(|c| (-G::Scalar::one(), c.clone())))
+      .collect::<Vec<_>>();
+    let linear_term = linear_sum(cs.namespace(|| "linear term"), &terms)?;
+    let coeffs = [&poly[0..1], &[linear_term], &poly[1..]].concat();
+
+    transcript.absorb_bytes(cs.namespace(|| "UniPoly_begin"), b"UniPoly_begin")?;
+    for c in &coeffs {
+      transcript.absorb(c);
+    }
+    transcript.absorb_bytes(cs.namespace(|| "UniPoly_end"), b"UniPoly_end")?;
+    let r_i = transcript.squeeze(cs.namespace(|| "challenge"))?;
+
+    // evaluate the poly at r_i with Horner's method
+    let mut eval = coeffs[coeffs.len() - 1].clone();
+    for (k, c) in coeffs.iter().rev().skip(1).enumerate() {
+      eval = mul_add(cs.namespace(|| format!("horner {}", k)), c, &eval, &r_i)?;
+    }
+    e = eval;
+    r.push(r_i);
+  }
+  Ok((e, r))
+}
+
+/// Allocates `sum_i c_i * x_i`
+fn linear_sum<F: PrimeField, CS: ConstraintSystem<F>>(
+  mut cs: CS,
+  terms: &[(F, AllocatedNum<F>)],
+) -> Result<AllocatedNum<F>, SynthesisError> {
+  let res = AllocatedNum::alloc(cs.namespace(|| "alloc"), || {
+    terms.iter().try_fold(F::zero(), |acc, (c, x)| {
+      Ok(acc + *c * x.get_value().ok_or(SynthesisError::AssignmentMissing)?)
+    })
+  })?;
+  cs.enforce(
+    || "check sum",
+    |lc| {
+      terms
+        .iter()
+        .fold(lc, |lc, (c, x)| lc + (*c, x.get_variable()))
+    },
+    |lc| lc + CS::one(),
+    |lc| lc + res.get_variable(),
+  );
+  Ok(res)
+}
+
+/// Allocates `sum_i a_i * b_i` for non-empty `a` and `b`
+fn inner_product<F: PrimeField, CS: ConstraintSystem<F>>(
+  mut cs: CS,
+  a: &[AllocatedNum<F>],
+  b: &[AllocatedNum<F>],
+) -> Result<AllocatedNum<F>, SynthesisError> {
+  let mut acc = a[0].mul(cs.namespace(|| "product 0"), &b[0])?;
+  for (i, (a_i, b_i)) in a.iter().zip(b.iter()).enumerate().skip(1) {
+    acc = mul_add(cs.namespace(|| format!("product {}", i)), &acc, a_i, b_i)?;
+  }
+  Ok(acc)
+}
+
+/// Allocates `(1 - r) * a + r * b`
+fn interpolate<F: PrimeField, CS: ConstraintSystem<F>>(
+  mut cs: CS,
+  r: &AllocatedNum<F>,
+  a: &AllocatedNum<F>,
+  b: &AllocatedNum<F>,
+) -> Result<AllocatedNum<F>, SynthesisError> {
+  let res = AllocatedNum::alloc(cs.namespace(|| "alloc"), || {
+    let (r, a, b) = (
+      r.get_value().ok_or(SynthesisError::AssignmentMissing)?,
+      a.get_value().ok_or(SynthesisError::AssignmentMissing)?,
+      b.get_value().ok_or(SynthesisError::AssignmentMissing)?,
+    );
+    Ok(a + r * (b - a))
+  })?;
+  cs.enforce(
+    || "check interpolation",
+    |lc| lc + r.get_variable(),
+    |lc| lc + b.get_variable() - a.get_variable(),
+    |lc| lc + res.get_variable() - a.get_variable(),
+  );
+  Ok(res)
+}
+
+/// Allocates the evaluations of `eq(r, .)` over the Boolean hypercube, as `EqPolynomial::evals`
+fn eq_evals<F: PrimeField, CS: ConstraintSystem<F>>(
+  mut cs: CS,
+  r: &[AllocatedNum<F>],
+  one: &AllocatedNum<F>,
+) -> Result<Vec<AllocatedNum<F>>, SynthesisError> {
+  let mut evals = vec![one.clone()];
+  for (j, r_j) in r.iter().rev().enumerate() {
+    let mut cs = cs.namespace(|| format!("variable {}", j));
+    let right = evals
+      .iter()
+      .enumerate()
+      .map(|(i, x)| x.mul(cs.namespace(|| format!("right {}", i)), r_j))
+      .collect::<Result<Vec<_>, _>>()?;
+    let left = evals
+      .iter()
+      .zip(right.iter())
+      .enumerate()
+      .map(|(i, (x, y))| {
+        linear_sum(
+          cs.namespace(|| format!("left {}", i)),
+          &[(F::one(), x.clone()), (-F::one(), y.clone())],
+        )
+      })
+      .collect::<Result<Vec<_>, _>>()?;
+    evals = [left, right].concat();
+  }
+  Ok(evals)
+}
+
+/// Evaluates at `r` the sparse polynomial with the entries `Z`, as `SparsePolynomial::evaluate`
+fn eval_sparse<F: PrimeField, CS: ConstraintSystem<F>>(
+  mut cs: CS,
+  Z: &[(usize, &AllocatedNum<F>)],
+  r: &[AllocatedNum<F>],
+  one: &AllocatedNum<F>,
+) -> Result<AllocatedNum<F>, SynthesisError> {
+  let max_index = Z.iter().map(|(i, _)| *i).max().unwrap_or(0);
+  let num_vars_Z = (usize::BITS - max_index.leading_zeros()) as usize;
+  if num_vars_Z > r.len() {
+    return Err(SynthesisError::Unsatisfiable);
+  }
+
+  // the high-order variables are zero for every entry
+  let (r_hi, r_lo) = r.split_at(r.len() - num_vars_Z);
+  let mut common = one.clone();
+  for (i, r_i) in r_hi.iter().enumerate() {
+    let res = AllocatedNum::alloc(cs.namespace(|| format!("common {}", i)), || {
+      let (c, r) = (
+        common
+          .get_value()
+          .ok_or(SynthesisError::AssignmentMissing)?,
+        r_i.get_value().ok_or(SynthesisError::AssignmentMissing)?,
+      );
+      Ok(c * (F::one() - r))
+    })?;
+    cs.enforce(
+      || format!("check common {}", i),
+      |lc| lc + common.get_variable(),
+      |lc| lc + CS::one() - r_i.get_variable(),
+      |lc| lc + res.get_variable(),
+    );
+    common = res;
+  }
+
+  let chis = eq_evals(cs.namespace(|| "chis"), r_lo, one)?;
+  let (chis_Z, zs): (Vec<_>, Vec<_>) = Z
+    .iter()
+    .map(|(i, z)| (chis[*i].clone(), (*z).clone()))
+    .unzip();
+  let sum = inner_product(cs.namespace(|| "sum"), &chis_Z, &zs)?;
+  common.mul(cs.namespace(|| "eval"), &sum)
+}
+
+/// Allocates `r_square`, `r_inverse_square` and `s` of `IPAChallenges` for the round
+/// challenges `r` and vectors of size `n`, with the inverses of the challenges as witnesses
+#[allow(clippy::type_complexity)]
+fn ipa_tensor<F: PrimeField, CS: ConstraintSystem<F>>(
+  mut cs: CS,
+  r: &[AllocatedNum<F>],
+  n: usize,
+  one: &AllocatedNum<F>,
+) -> Result<
+  (
+    Vec<AllocatedNum<F>>,
+    Vec<AllocatedNum<F>>,
+    Vec<AllocatedNum<F>>,
+  ),
+  SynthesisError,
+> {
+  let r_inverse = r
+    .iter()
+    .enumerate()
+    .map(|(i, r_i)| {
+      let inv = AllocatedNum::alloc(cs.namespace(|| format!("r_inverse[{}]", i)), || {
+        let r = r_i.get_value().ok_or(SynthesisError::AssignmentMissing)?;
+        // a zero challenge has no inverse and leaves the constraint unsatisfied
+        Ok(Option::from(r.invert()).unwrap_or_else(F::zero))
+      })?;
+      cs.enforce(
+        || format!("check r_inverse[{}]", i),
+        |lc| lc + r_i.get_variable(),
+        |lc| lc + inv.get_variable(),
+        |lc| lc + CS::one(),
+      );
+      Ok(inv)
+    })
+    .collect::<Result<Vec<_>, SynthesisError>>()?;
+  let r_square = r
+    .iter()
+    .enumerate()
+    .map(|(i, r_i)| r_i.square(cs.namespace(|| format!("r_square[{}]", i))))
+    .collect::<Result<Vec<_>, _>>()?;
+  let r_inverse_square = r_inverse
+    .iter()
+    .enumerate()
+    .map(|(i, r_i)| r_i.square(cs.namespace(|| format!("r_inverse_square[{}]", i))))
+    .collect::<Result<Vec<_>, _>>()?;
+
+  let mut s0 = one.clone();
+  for (i, r_inverse_i) in r_inverse.iter().enumerate() {
+    s0 = s0.mul(cs.namespace(|| format!("s[0] product {}", i)), r_inverse_i)?;
+  }
+  let mut s = vec![s0];
+  for i in 1..n {
+    let pos_in_r = (31 - (i as u32).leading_zeros()) as usize;
+    let s_i = s[i - (1 << pos_in_r)].mul(
+      cs.namespace(|| format!("s[{}]", i)),
+      &r_square[(r.len() - 1) - pos_in_r],
+    )?;
+    s.push(s_i);
+  }
+  Ok((r_square, r_inverse_square, s))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    bellperson::{
+      r1cs::{NovaShape, NovaWitness},
+      shape_cs::ShapeCS,
+      solver::SatisfyingAssignment,
+    },
+    lookup::tests::{synthesize_add, table},
+    nifs::NIFS,
+    r1cs::{R1CSGens, R1CSShape, RelaxedR1CSWitness},
+    snark::{ProverKeyTrait, RelaxedR1CSSNARKTrait, VerifierKeyTrait},
+    spartan_with_ipa_pc::{tests::folded_cubic_instance, ProverKey},
+    traits::HashFuncTrait,
+  };
+
+  type G1 = pasta_curves::pallas::Point;
+  type G2 = pasta_curves::vesta::Point;
+  type S = pasta_curves::pallas::Scalar;
+  type B = pasta_curves::pallas::Base;
+
+  fn shape<G: Group, C: Circuit<G::Scalar>>(circuit: C) -> (R1CSShape<G>, R1CSGens<G>) {
+    let mut cs: ShapeCS<G> = ShapeCS::new();
+    circuit.synthesize(&mut cs).unwrap();
+    (cs.r1cs_shape(), cs.r1cs_gens())
+  }
+
+  /// Returns the public IO of `circuit` if its witness satisfies `shape`
+  fn public_io<G: Group, C: Circuit<G::Scalar>>(
+    (shape, gens): &(R1CSShape<G>, R1CSGens<G>),
+    circuit: C,
+  ) -> Result<Vec<G::Scalar>, NovaError> {
+    let mut cs: SatisfyingAssignment<G> = SatisfyingAssignment::new();
+    circuit
+      .synthesize(&mut cs)
+      .map_err(|_| NovaError::UnSat { constraint: None })?;
+    let (inst, witness) = cs.r1cs_instance_and_witness(shape, gens)?;
+    shape.is_sat(gens, &inst, &witness)?;
+    Ok(inst.X)
+  }
+
+  /// Checks that both circuits accept a valid proof, and that the remaining check passes
+  fn check_circuits(
+    vk: &VerifierKey<G1>,
+    U: &RelaxedR1CSInstance<G1>,
+    snark: &RelaxedR1CSSNARK<G1, PoseidonTranscript<G1>>,
+  ) -> ((R1CSShape<G1>, R1CSGens<G1>), (R1CSShape<G2>, R1CSGens<G2>)) {
+    let shape_verifier = shape(SNARKVerifierCircuit::new(vk, None));
+    let shape_group = shape::<G2, _>(IPAGroupCircuit::new(vk, None));
+
+    let io_verifier = public_io(
+      &shape_verifier,
+      SNARKVerifierCircuit::new(vk, Some((U, snark))),
+    )
+    .unwrap();
+    let inputs = IPAGroupInputs::new(vk, U, snark).unwrap();
+    let io_group = public_io(&shape_group, IPAGroupCircuit::new(vk, Some(inputs))).unwrap();
+
+    // the verifier circuit outputs the instance first
+    assert_eq!(io_verifier[0], U.u);
+    assert_eq!(io_verifier[1..=U.X.len()], U.X[..]);
+
+    let deferred = DeferredIPACheck::from_io(vk, &io_verifier, &io_group).unwrap();
+    assert!(deferred.verify(vk).is_ok());
+
+    // the two circuits must agree on the values they share
+    let mut bad_io_group = io_group.clone();
+    bad_io_group[0] += B::one();
+    assert!(DeferredIPACheck::from_io(vk, &io_verifier, &bad_io_group).is_err());
+
+    // the folded generator must match the round challenges
+    let mut bad_io_verifier = io_verifier.clone();
+    bad_io_verifier[1 + U.X.len() + num_shared_io(vk)] += S::one();
+    let deferred = DeferredIPACheck::from_io(vk, &bad_io_verifier, &io_group).unwrap();
+    assert!(deferred.verify(vk).is_err());
+
+    (shape_verifier, shape_group)
+  }
+
+  #[test]
+  fn test_spartan_verifier_circuit() {
+    let (pk, vk, U, W) = folded_cubic_instance();
+    let snark = RelaxedR1CSSNARK::<G1, PoseidonTranscript<G1>>::prove(&pk, &U, &W).unwrap();
+    assert!(snark.verify(&vk, &U).is_ok());
+    let (shape_verifier, shape_group) = check_circuits(&vk, &U, &snark);
+
+    // a tampered opening of the IPA does not satisfy the group circuit
+    let mut bad = snark;
+    bad.ipa.a_hat += S::one();
+    let inputs = IPAGroupInputs::new(&vk, &U, &bad).unwrap();
+    assert!(public_io(&shape_group, IPAGroupCircuit::new(&vk, Some(inputs))).is_err());
+
+    // a tampered evaluation does not satisfy the verifier circuit
+    bad.ipa.a_hat -= S::one();
+    bad.eval_W += S::one();
+    assert!(public_io(
+      &shape_verifier,
+      SNARKVerifierCircuit::new(&vk, Some((&U, &bad)))
+    )
+    .is_err());
+  }
+
+  #[test]
+  fn test_spartan_verifier_circuit_with_lookup() {
+    let mut cs: ShapeCS<G1> = ShapeCS::new();
+    let queries = synthesize_add(&mut cs, None, None).unwrap();
+    let shape = cs.r1cs_shape_with_lookup(&table(), &queries);
+    let gens = R1CSGens::new(shape.num_cons, shape.num_vars);
+    let ro_consts = <<G1 as Group>::HashFunc as HashFuncTrait<B, S>>::Constants::new();
+
+    let mut r_U = RelaxedR1CSInstance::default(&gens, &shape);
+    let mut r_W = RelaxedR1CSWitness::default(&shape);
+    for (x, y) in [(1, 2), (0, 15)] {
+      let mut cs: SatisfyingAssignment<G1> = SatisfyingAssignment::new();
+      let _ = synthesize_add(&mut cs, Some(x), Some(y));
+      let (U, W) = cs.r1cs_instance_and_witness(&shape, &gens).unwrap();
+      let (_nifs, (U, W)) = NIFS::prove(
+        &gens,
+        &ro_consts,
+        &shape.get_digest(),
+        &shape,
+        &r_U,
+        &r_W,
+        &U,
+        &W,
+      )
+      .unwrap();
+      r_U = U;
+      r_W = W;
+    }
+
+    let shape_padded = shape.pad();
+    let r_W = r_W.pad(&shape_padded);
+    let pk = ProverKey::new(&gens, &shape_padded, &shape.get_digest());
+    let vk = VerifierKey::new(&gens, &shape_padded, &shape.get_digest());
+    let snark = RelaxedR1CSSNARK::<G1, PoseidonTranscript<G1>>::prove(&pk, &r_U, &r_W).unwrap();
+    assert!(snark.verify(&vk, &r_U).is_ok());
+    let (shape_verifier, _) = check_circuits(&vk, &r_U, &snark);
+
+    // a tampered evaluation of the second half of the witness is rejected
+    let mut bad = snark;
+    bad.eval_L = bad.eval_L.map(|eval_L| eval_L + S::one());
+    assert!(public_io(
+      &shape_verifier,
+      SNARKVerifierCircuit::new(&vk, Some((&r_U, &bad)))
+    )
+    .is_err());
+  }
+}
@@ -1,11 +1,12 @@
 #![allow(clippy::too_many_arguments)]
 use crate::commitments::{CommitGens, CommitTrait, Commitment, CompressedCommitment};
-use crate::errors::NovaError;
-use crate::traits::{AppendToTranscriptTrait, ChallengeTrait, Group};
+use crate::errors::{IPAFailure, NovaError};
+use crate::parallel::prelude::*;
+use crate::serde_utils::scalar;
+use crate::traits::{AppendToTranscriptTrait, Group, TranscriptEngineTrait};
 use core::iter;
 use ff::Field;
-use merlin::Transcript;
-use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
 use std::marker::PhantomData;
 
 pub fn inner_product<T>(a: &[T], b: &[T]) -> T
@@ -19,19 +20,89 @@ where
     .reduce(T::zero, |x, y| x + y)
 }
 
+/// The squares of the challenges of the rounds of an inner product argument and of their
+/// inverses, with the vector `s` of their tensor structure
+pub struct IPAChallenges<F: Field> {
+  pub(crate) r_square: Vec<F>,
+  pub(crate) r_inverse_square: Vec<F>,
+  pub(crate) s: Vec<F>,
+}
+
+impl<F: Field> IPAChallenges<F> {
+  /// Computes the challenges of an argument over vectors of size `n` from its round challenges
+  pub fn new(r: &[F], n: usize) -> Result<Self, NovaError> {
+    let batch_invert = |v: &[F]| -> Result<Vec<F>, NovaError> {
+      let mut products = vec![F::zero(); v.len()];
+      let mut acc = F::one();
+
+      for i in 0..v.len() {
+        products[i] = acc;
+        acc *= v[i];
+      }
+
+      // we can compute an inversion only if acc is non-zero
+      if acc == F::zero() {
+        return Err(NovaError::InvalidIPA {
+          check: IPAFailure::ZeroChallenge,
+        });
+      }
+
+      // compute the inverse once for all entries
+      acc = acc.invert().unwrap();
+
+      let mut inv = vec![F::zero(); v.len()];
+      for i in 0..v.len() {
+        let tmp = acc * v[v.len() - 1 - i];
+        inv[v.len() - 1 - i] = products[v.len() - 1 - i] * acc;
+        acc = tmp;
+      }
+
+      Ok(inv)
+    };
+    // precompute scalars necessary for verification
+    let r_square: Vec<F> = r.par_iter().map(|r_i| r_i.square()).collect();
+    let r_inverse = batch_invert(r)?;
+    let r_inverse_square: Vec<F> = r_inverse.par_iter().map(|r_i| r_i.square()).collect();
+
+    // compute the vector with the tensor structure
+    let s = {
+      let mut s = vec![F::zero(); n];
+      s[0] = {
+        let mut v = F::one();
+        for r_inverse_i in &r_inverse {
+          v *= r_inverse_i;
+        }
+        v
+      };
+      for i in 1..n {
+        let pos_in_r = (31 - (i as u32).leading_zeros()) as usize;
+        s[i] = s[i - (1 << pos_in_r)] * r_square[(r.len() - 1) - pos_in_r];
+      }
+      s
+    };
+
+    Ok(Self {
+      r_square,
+      r_inverse_square,
+      s,
+    })
+  }
+}
+
 /// An inner product instance consists of a commitment to a vector `a` and another vector `b`
 /// and the claim that c = <a, b>.
 pub struct InnerProductInstance<G: Group> {
-  comm_a_vec: Commitment<G>,
-  b_vec: Vec<G::Scalar>,
-  c: G::Scalar,
+  pub(crate) comm_a_vec: Commitment<G>,
+  pub(crate) b_vec: Vec<G::Scalar>,
+  pub(crate) c: G::Scalar,
 }
 
 impl<G: Group> InnerProductInstance<G> {
-  pub fn new(comm_a_vec: &Commitment<G>, b_vec: &[G::Scalar], c: &G::Scalar) -> Self {
+  // takes ownership of b_vec, which is typically a large table computed only for this instance
+  pub fn new(comm_a_vec: &Commitment<G>, b_vec: Vec<G::Scalar>, c: &G::Scalar) -> Self {
     InnerProductInstance {
       comm_a_vec: *comm_a_vec,
-      b_vec: b_vec.to_vec(),
+      b_vec,
       c: *c,
     }
   }
@@ -41,17 +112,12 @@ pub struct InnerProductWitness<G: Group> {
   a_vec: Vec<G::Scalar>,
 }
 
-impl<G: Group> InnerProductWitness<G> {
-  pub fn new(a_vec: &[G::Scalar]) -> Self {
-    InnerProductWitness {
-      a_vec: a_vec.to_vec(),
-    }
-  }
-}
-
 /// A non-interactive folding scheme (NIFS) for inner product relations
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound = "")]
 pub struct NIFSForInnerProduct<G: Group> {
-  cross_term: G::Scalar,
+  #[serde(with = "scalar")]
+  pub(crate) cross_term: G::Scalar,
 }
 
 impl<G: Group> NIFSForInnerProduct<G> {
@@ -59,37 +125,37 @@ impl<G: Group> NIFSForInnerProduct<G> {
     b"NIFSForInnerProduct"
   }
 
-  pub fn prove(
+  // the witnesses are borrowed as slices so that the caller's vectors are not copied before folding
+  pub fn prove<T: TranscriptEngineTrait<G>>(
     U1: &InnerProductInstance<G>,
-    W1: &InnerProductWitness<G>,
+    W1: &[G::Scalar],
     U2: &InnerProductInstance<G>,
-    W2: &InnerProductWitness<G>,
-    transcript: &mut Transcript,
+    W2: &[G::Scalar],
+    transcript: &mut T,
   ) -> (Self, InnerProductInstance<G>, InnerProductWitness<G>) {
-    transcript.append_message(b"protocol-name", Self::protocol_name());
+    transcript.absorb_bytes(b"protocol-name", Self::protocol_name());
 
     // add the two commitments and two public vectors to the transcript
     U1.comm_a_vec
       .append_to_transcript(b"U1_comm_a_vec", transcript);
-    U1.b_vec.append_to_transcript(b"U1_b_vec", transcript);
+    transcript.absorb_scalars(b"U1_b_vec", &U1.b_vec);
     U2.comm_a_vec
       .append_to_transcript(b"U2_comm_a_vec", transcript);
-    U2.b_vec.append_to_transcript(b"U2_b_vec", transcript);
+    transcript.absorb_scalars(b"U2_b_vec", &U2.b_vec);
 
     // compute the cross-term
-    let cross_term = inner_product(&W1.a_vec, &U2.b_vec) + inner_product(&W2.a_vec, &U1.b_vec);
+    let cross_term = inner_product(W1, &U2.b_vec) + inner_product(W2, &U1.b_vec);
 
     // add the cross-term to the transcript
-    cross_term.append_to_transcript(b"cross_term", transcript);
+    transcript.absorb_scalar(b"cross_term", &cross_term);
 
     // obtain a random challenge
-    let r = G::Scalar::challenge(b"r", transcript);
+    let r = transcript.squeeze(b"r");
 
     // fold the vectors and their inner product
     let a_vec = W1
-      .a_vec
       .par_iter()
-      .zip(W2.a_vec.par_iter())
+      .zip(W2.par_iter())
       .map(|(x1, x2)| *x1 + r * x2)
       .collect::<Vec<G::Scalar>>();
     let b_vec = U1
@@ -112,29 +178,28 @@ impl<G: Group> NIFSForInnerProduct<G> {
     (NIFSForInnerProduct { cross_term }, U, W)
   }
 
-  pub fn verify(
+  /// Returns the folded instance along with the challenge with which it is folded
+  pub fn verify<T: TranscriptEngineTrait<G>>(
     &self,
     U1: &InnerProductInstance<G>,
     U2: &InnerProductInstance<G>,
-    transcript: &mut Transcript,
-  ) -> InnerProductInstance<G> {
-    transcript.append_message(b"protocol-name", Self::protocol_name());
+    transcript: &mut T,
+  ) -> (InnerProductInstance<G>, G::Scalar) {
+    transcript.absorb_bytes(b"protocol-name", Self::protocol_name());
 
     // add the two commitments and two public vectors to the transcript
     U1.comm_a_vec
       .append_to_transcript(b"U1_comm_a_vec", transcript);
-    U1.b_vec.append_to_transcript(b"U1_b_vec", transcript);
+    transcript.absorb_scalars(b"U1_b_vec", &U1.b_vec);
     U2.comm_a_vec
       .append_to_transcript(b"U2_comm_a_vec", transcript);
-    U2.b_vec.append_to_transcript(b"U2_b_vec", transcript);
+    transcript.absorb_scalars(b"U2_b_vec", &U2.b_vec);
 
     // add the cross-term to the transcript
-    self
-      .cross_term
-      .append_to_transcript(b"cross_term", transcript);
+    transcript.absorb_scalar(b"cross_term", &self.cross_term);
 
     // obtain a random challenge
-    let r = G::Scalar::challenge(b"r", transcript);
+    let r = transcript.squeeze(b"r");
 
     // fold the vectors and their inner product
     let b_vec = U1
@@ -146,20 +211,25 @@ impl<G: Group> NIFSForInnerProduct<G> {
     let c = U1.c + r * r * U2.c + r * self.cross_term;
     let comm_a_vec = U1.comm_a_vec + U2.comm_a_vec * r;
 
-    InnerProductInstance {
-      comm_a_vec,
-      b_vec,
-      c,
-    }
+    (
+      InnerProductInstance {
+        comm_a_vec,
+        b_vec,
+        c,
+      },
+      r,
+    )
   }
 }
 
 /// An inner product argument
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound = "")]
 pub struct InnerProductArgument<G: Group> {
-  L_vec: Vec<CompressedCommitment<G::CompressedGroupElement>>,
-  R_vec: Vec<CompressedCommitment<G::CompressedGroupElement>>,
-  a_hat: G::Scalar,
+  pub(crate) L_vec: Vec<CompressedCommitment<G::CompressedGroupElement>>,
+  pub(crate) R_vec: Vec<CompressedCommitment<G::CompressedGroupElement>>,
+  #[serde(with = "scalar")]
+  pub(crate) a_hat: G::Scalar,
   _p: PhantomData<G>,
 }
 
@@ -168,32 +238,38 @@ impl<G: Group> InnerProductArgument<G> {
     b"inner product argument"
   }
 
-  pub fn prove(
+  pub fn prove<T: TranscriptEngineTrait<G>>(
     gens: &CommitGens<G>,
     gens_c: &CommitGens<G>,
     U: &InnerProductInstance<G>,
     W: &InnerProductWitness<G>,
-    transcript: &mut Transcript,
+    transcript: &mut T,
   ) -> Result<Self, NovaError> {
-    transcript.append_message(b"protocol-name", Self::protocol_name());
+    transcript.absorb_bytes(b"protocol-name", Self::protocol_name());
 
     if U.b_vec.len() != W.a_vec.len() {
-      return Err(NovaError::InvalidInputLength);
+      return Err(NovaError::InvalidInputLength {
+        expected: W.a_vec.len(),
+        actual: U.b_vec.len(),
+      });
     }
 
     U.comm_a_vec.append_to_transcript(b"comm_a_vec", transcript);
-    U.b_vec.append_to_transcript(b"b_vec", transcript);
-    U.c.append_to_transcript(b"c", transcript);
+    transcript.absorb_scalars(b"b_vec", &U.b_vec);
+    transcript.absorb_scalar(b"c", &U.c);
 
     // sample a random base for commiting to the inner product
-    let r = G::Scalar::challenge(b"r", transcript);
+    let r = transcript.squeeze(b"r");
     let gens_c = gens_c.scale(&r);
 
-    // a closure that executes a step of the recursive inner product argument
+    // a closure that executes a step of the recursive inner product argument; it reads the
+    // generators in place rather than copying their halves, since the generators are the
+    // largest vector that the prover holds. The generators may outnumber the vectors, e.g.,
+    // when W is split for a lookup argument, in which case only the first ones are used
     let prove_inner = |a_vec: &[G::Scalar],
                        b_vec: &[G::Scalar],
                        gens: &CommitGens<G>,
-                       transcript: &mut Transcript|
+                       transcript: &mut T|
      -> Result<
       (
         CompressedCommitment<G::CompressedGroupElement>,
@@ -205,30 +281,17 @@ impl<G: Group> InnerProductArgument<G> {
       NovaError,
     > {
       let n = a_vec.len();
-      let (gens_L, gens_R) = gens.split_at(n / 2);
 
       let c_L = inner_product(&a_vec[0..n / 2], &b_vec[n / 2..n]);
       let c_R = inner_product(&a_vec[n / 2..n], &b_vec[0..n / 2]);
 
-      let L = a_vec[0..n / 2]
-        .iter()
-        .chain(iter::once(&c_L))
-        .copied()
-        .collect::<Vec<G::Scalar>>()
-        .commit(&gens_R.combine(&gens_c))
-        .compress();
-      let R = a_vec[n / 2..n]
-        .iter()
-        .chain(iter::once(&c_R))
-        .copied()
-        .collect::<Vec<G::Scalar>>()
-        .commit(&gens_L.combine(&gens_c))
-        .compress();
+      let L = (gens.commit_at(n / 2, &a_vec[0..n / 2]) + [c_L].commit(&gens_c)).compress();
+      let R = (gens.commit_at(0, &a_vec[n / 2..n]) + [c_R].commit(&gens_c)).compress();
 
       L.append_to_transcript(b"L", transcript);
       R.append_to_transcript(b"R", transcript);
 
-      let r = G::Scalar::challenge(b"challenge_r", transcript);
+      let r = transcript.squeeze(b"challenge_r");
       let r_inverse = r.invert().unwrap();
 
       // fold the left half and the right half
@@ -244,7 +307,7 @@ impl<G: Group> InnerProductArgument<G> {
         .map(|(b_L, b_R)| *b_L * r_inverse + r * *b_R)
         .collect::<Vec<G::Scalar>>();
 
-      let gens_folded = gens.fold(&r_inverse, &r);
+      let gens_folded = gens.fold_prefix(n, &r_inverse, &r);
 
       Ok((L, R, a_vec_folded, b_vec_folded, gens_folded))
     };
@@ -253,127 +316,102 @@ impl<G: Group> InnerProductArgument<G> {
     let mut L_vec: Vec<CompressedCommitment<G::CompressedGroupElement>> = Vec::new();
     let mut R_vec: Vec<CompressedCommitment<G::CompressedGroupElement>> = Vec::new();
 
-    // we create mutable copies of vectors and generators
-    let mut a_vec = W.a_vec.to_vec();
-    let mut b_vec = U.b_vec.to_vec();
-    let mut gens = gens.clone();
+    // the first round reads the inputs in place; later rounds own the folded vectors and generators
+    let mut folded: Option<(Vec<G::Scalar>, Vec<G::Scalar>, CommitGens<G>)> = None;
     for _i in 0..(U.b_vec.len() as f64).log2() as usize {
-      let (L, R, a_vec_folded, b_vec_folded, gens_folded) =
-        prove_inner(&a_vec, &b_vec, &gens, transcript)?;
+      let (L, R, a_vec_folded, b_vec_folded, gens_folded) = match &folded {
+        Some((a_vec, b_vec, gens)) => prove_inner(a_vec, b_vec, gens, transcript)?,
+        None => prove_inner(&W.a_vec, &U.b_vec, gens, transcript)?,
+      };
       L_vec.push(L);
       R_vec.push(R);
 
-      a_vec = a_vec_folded;
-      b_vec = b_vec_folded;
-      gens = gens_folded;
+      folded = Some((a_vec_folded, b_vec_folded, gens_folded));
     }
 
+    let a_hat = match &folded {
+      Some((a_vec, _, _)) => a_vec[0],
+      None => W.a_vec[0],
+    };
+
     Ok(InnerProductArgument {
       L_vec,
       R_vec,
-      a_hat: a_vec[0],
+      a_hat,
       _p: Default::default(),
     })
   }
 
-  pub fn verify(
+  /// Derives the challenges of the argument for the instance `U` of vectors of size `n`: the
+  /// challenge that scales the generator of the claim, and the challenges of the rounds
+  pub fn challenges<T: TranscriptEngineTrait<G>>(
     &self,
-    gens: &CommitGens<G>,
-    gens_c: &CommitGens<G>,
     n: usize,
     U: &InnerProductInstance<G>,
-    transcript: &mut Transcript,
-  ) -> Result<(), NovaError> {
-    transcript.append_message(b"protocol-name", Self::protocol_name());
-    if U.b_vec.len() != n
-      || n != (1 << self.L_vec.len())
-      || self.L_vec.len() != self.R_vec.len()
-      || self.L_vec.len() >= 32
-    {
-      return Err(NovaError::InvalidInputLength);
+    transcript: &mut T,
+  ) -> Result<(G::Scalar, Vec<G::Scalar>), NovaError> {
+    transcript.absorb_bytes(b"protocol-name", Self::protocol_name());
+    if self.L_vec.len() != self.R_vec.len() || self.L_vec.len() >= 32 {
+      return Err(NovaError::InvalidIPA {
+        check: IPAFailure::NumRounds,
+      });
     }
+    NovaError::check_lengths(&[(n, U.b_vec.len()), (1 << self.L_vec.len(), n)])?;
 
     U.comm_a_vec.append_to_transcript(b"comm_a_vec", transcript);
-    U.b_vec.append_to_transcript(b"b_vec", transcript);
-    U.c.append_to_transcript(b"c", transcript);
+    transcript.absorb_scalars(b"b_vec", &U.b_vec);
+    transcript.absorb_scalar(b"c", &U.c);
 
     // sample a random base for commiting to the inner product
-    let r = G::Scalar::challenge(b"r", transcript);
-    let gens_c = gens_c.scale(&r);
-
-    let P = U.comm_a_vec + [U.c].commit(&gens_c);
-
-    let batch_invert = |v: &[G::Scalar]| -> Result<Vec<G::Scalar>, NovaError> {
-      let mut products = vec![G::Scalar::zero(); v.len()];
-      let mut acc = G::Scalar::one();
-
-      for i in 0..v.len() {
-        products[i] = acc;
-        acc *= v[i];
-      }
-
-      // we can compute an inversion only if acc is non-zero
-      if acc == G::Scalar::zero() {
-        return Err(NovaError::InvalidInputLength);
-      }
-
-      // compute the inverse once for all entries
-      acc = acc.invert().unwrap();
-
-      let mut inv = vec![G::Scalar::zero(); v.len()];
-      for i in 0..v.len() {
-        let tmp = acc * v[v.len() - 1 - i];
-        inv[v.len() - 1 - i] = products[v.len() - 1 - i] * acc;
-        acc = tmp;
-      }
-
-      Ok(inv)
-    };
+    let r_c = transcript.squeeze(b"r");
 
     // compute a vector of public coins using self.L_vec and self.R_vec
     let r = (0..self.L_vec.len())
       .map(|i| {
         self.L_vec[i].append_to_transcript(b"L", transcript);
         self.R_vec[i].append_to_transcript(b"R", transcript);
-        G::Scalar::challenge(b"challenge_r", transcript)
+        transcript.squeeze(b"challenge_r")
       })
       .collect::<Vec<G::Scalar>>();
 
-    // precompute scalars necessary for verification
-    let r_square: Vec<G::Scalar> = (0..self.L_vec.len())
-      .into_par_iter()
-      .map(|i| r[i] * r[i])
-      .collect();
-    let r_inverse = batch_invert(&r)?;
-    let r_inverse_square: Vec<G::Scalar> = (0..self.L_vec.len())
-      .into_par_iter()
-      .map(|i| r_inverse[i] * r_inverse[i])
-      .collect();
+    Ok((r_c, r))
+  }
 
-    // compute the vector with the tensor structure
-    let s = {
-      let mut s = vec![G::Scalar::zero(); n];
-      s[0] = {
-        let mut v = G::Scalar::one();
-        for r_inverse_i in &r_inverse {
-          v *= r_inverse_i;
-        }
-        v
-      };
-      for i in 1..n {
-        let pos_in_r = (31 - (i as u32).leading_zeros()) as usize;
-        s[i] = s[i - (1 << pos_in_r)] * r_square[(self.L_vec.len() - 1) - pos_in_r];
-      }
-      s
-    };
+  pub fn verify<T: TranscriptEngineTrait<G>>(
+    &self,
+    gens: &CommitGens<G>,
+    gens_c: &CommitGens<G>,
+    n: usize,
+    U: &InnerProductInstance<G>,
+    transcript: &mut T,
+  ) -> Result<(), NovaError> {
+    let (r_c, r) = self.challenges(n, U, transcript)?;
+    let challenges = IPAChallenges::new(&r, n)?;
+    let b_hat = inner_product(&U.b_vec, &challenges.s);
+    self.verify_commitments(gens, gens_c, &U.comm_a_vec, &U.c, &r_c, &challenges, &b_hat)
+  }
+
+  /// Checks the commitments of the argument for the commitment `comm_a_vec` and the claim `c`,
+  /// given the challenges of the transcript and the inner product `b_hat` of the public vector
+  /// with the tensor of the challenges. This is the part of `verify` with group operations.
+  pub fn verify_commitments(
+    &self,
+    gens: &CommitGens<G>,
+    gens_c: &CommitGens<G>,
+    comm_a_vec: &Commitment<G>,
+    c: &G::Scalar,
+    r_c: &G::Scalar,
+    challenges: &IPAChallenges<G::Scalar>,
+    b_hat: &G::Scalar,
+  ) -> Result<(), NovaError> {
+    let gens_c = gens_c.scale(r_c);
+    let P = *comm_a_vec + [*c].commit(&gens_c);
 
     let gens_hat = {
-      let c = s.commit(gens).compress();
+      let c = challenges.s.commit(gens).compress();
       CommitGens::reinterpret_commitments_as_gens(&[c])?
     };
 
-    let b_hat = inner_product(&U.b_vec, &s);
-
     let P_hat = {
       let gens_folded = {
         let gens_L = CommitGens::reinterpret_commitments_as_gens(&self.L_vec)?;
@@ -381,9 +419,10 @@ impl<G: Group> InnerProductArgument<G> {
         let gens_P = CommitGens::reinterpret_commitments_as_gens(&[P.compress()])?;
         gens_L.combine(&gens_R).combine(&gens_P)
       };
-      r_square
+      challenges
+        .r_square
         .iter()
-        .chain(r_inverse_square.iter())
+        .chain(challenges.r_inverse_square.iter())
         .chain(iter::once(&G::Scalar::one()))
         .copied()
         .collect::<Vec<G::Scalar>>()
@@ -393,7 +432,9 @@ impl<G: Group> InnerProductArgument<G> {
     if P_hat == [self.a_hat, self.a_hat * b_hat].commit(&gens_hat.combine(&gens_c)) {
       Ok(())
     } else {
-      Err(NovaError::InvalidIPA)
+      Err(NovaError::InvalidIPA {
+        check: IPAFailure::Commitments,
+      })
     }
   }
 }
@@ -1,73 +1,126 @@
 //! This module implements RelaxedR1CSSNARKTrait using a Spartan variant
 //! instantiated with an IPA-based polynomial commitment scheme
+mod circuit;
 mod ipa;
-mod polynomial;
 mod sumcheck;
 
+pub use circuit::{
+  AllocatedDeferredIPACheck, AllocatedRelaxedR1CSSNARK, AllocatedSNARKInstance, DeferredIPACheck,
+  IPAGroupCircuit, IPAGroupInputs, SNARKVerifierCircuit,
+};
+
 use super::{
   commitments::CommitGens,
-  errors::NovaError,
+  errors::{NovaError, SumcheckFailure},
+  parallel::{self, prelude::*},
   r1cs::{R1CSGens, R1CSShape, RelaxedR1CSInstance, RelaxedR1CSWitness},
+  serde_utils::{option_scalar, scalar, scalar_triple},
   snark::{ProverKeyTrait, RelaxedR1CSSNARKTrait, VerifierKeyTrait},
-  traits::{AppendToTranscriptTrait, ChallengeTrait, Group},
+  traits::{AppendToTranscriptTrait, Group, TranscriptEngineTrait},
+  transcript::MerlinTranscript,
 };
-use core::cmp::max;
+use crate::polynomial::{EqPolynomial, MultilinearPolynomial, SparsePolynomial};
+use core::{cmp::max, marker::PhantomData};
 use ff::Field;
-use ipa::{InnerProductArgument, InnerProductInstance, InnerProductWitness, NIFSForInnerProduct};
+use ipa::{
+  inner_product, InnerProductArgument, InnerProductInstance, InnerProductWitness,
+  NIFSForInnerProduct,
+};
 use itertools::concat;
-use merlin::Transcript;
-use polynomial::{EqPolynomial, MultilinearPolynomial, SparsePolynomial};
-use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
 use sumcheck::SumcheckProof;
 
+/// Initializes the transcript of the SNARK for the domain-separation tag of the generators.
+/// Merlin absorbs the label of a transcript as a "dom-sep" message, so the tag extends it.
+fn new_transcript<G: Group, T: TranscriptEngineTrait<G>>(gens: &R1CSGens<G>) -> T {
+  let mut transcript = T::new(b"RelaxedR1CSSNARK");
+  transcript.absorb_bytes(b"dom-sep", &gens.tag);
+  transcript
+}
+
 /// A type that represents the prover's key
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
 pub struct ProverKey<G: Group> {
   gens_r1cs: R1CSGens<G>,
   gens_ipa: CommitGens<G>,
   S: R1CSShape<G>,
+  #[serde(with = "scalar")]
+  digest: G::Scalar,
 }
 
 impl<G: Group> ProverKeyTrait<G> for ProverKey<G> {
-  fn new(gens: &R1CSGens<G>, S: &R1CSShape<G>) -> Self {
+  fn new(gens: &R1CSGens<G>, S: &R1CSShape<G>, digest: &G::Scalar) -> Self {
     ProverKey {
       gens_r1cs: gens.clone(),
-      gens_ipa: CommitGens::new(b"ipa", 1),
+      gens_ipa: CommitGens::new(&[b"ipa", &gens.tag[..]].concat(), 1),
       S: S.clone(),
+      digest: *digest,
     }
   }
 }
 
 /// A type that represents the verifier's key
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
 pub struct VerifierKey<G: Group> {
   gens_r1cs: R1CSGens<G>,
   gens_ipa: CommitGens<G>,
   S: R1CSShape<G>,
+  #[serde(with = "scalar")]
+  digest: G::Scalar,
 }
 
 impl<G: Group> VerifierKeyTrait<G> for VerifierKey<G> {
-  fn new(gens: &R1CSGens<G>, S: &R1CSShape<G>) -> Self {
+  fn new(gens: &R1CSGens<G>, S: &R1CSShape<G>, digest: &G::Scalar) -> Self {
     VerifierKey {
       gens_r1cs: gens.clone(),
-      gens_ipa: CommitGens::new(b"ipa", 1),
+      gens_ipa: CommitGens::new(&[b"ipa", &gens.tag[..]].concat(), 1),
       S: S.clone(),
+      digest: *digest,
     }
   }
 }
 
 /// A succinct proof of knowledge of a witness to a relaxed R1CS instance
 /// The proof is produced using Spartan's combination of the sum-check and
-/// the commitment to a vector viewed as a polynomial commitment
-pub struct RelaxedR1CSSNARK<G: Group> {
+/// the commitment to a vector viewed as a polynomial commitment.
+/// The challenges are derived with the transcript `T`, which is Merlin by default.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct RelaxedR1CSSNARK<G: Group, T: TranscriptEngineTrait<G> = MerlinTranscript<G>> {
   sc_proof_outer: SumcheckProof<G>,
+  #[serde(with = "scalar_triple")]
   claims_outer: (G::Scalar, G::Scalar, G::Scalar),
   sc_proof_inner: SumcheckProof<G>,
+  #[serde(with = "scalar")]
   eval_E: G::Scalar,
+  #[serde(with = "scalar")]
   eval_W: G::Scalar,
+  #[serde(with = "option_scalar")]
+  eval_L: Option<G::Scalar>, // evaluation of the second half of W for shapes with a lookup argument
   nifs_ip: NIFSForInnerProduct<G>,
   ipa: InnerProductArgument<G>,
+  _p: PhantomData<T>,
 }
 
-impl<G: Group> RelaxedR1CSSNARKTrait<G> for RelaxedR1CSSNARK<G> {
+// a proof does not depend on the state of a transcript, so it is compared without bounds on `T`
+impl<G: Group, T: TranscriptEngineTrait<G>> PartialEq for RelaxedR1CSSNARK<G, T> {
+  fn eq(&self, other: &Self) -> bool {
+    self.sc_proof_outer == other.sc_proof_outer
+      && self.claims_outer == other.claims_outer
+      && self.sc_proof_inner == other.sc_proof_inner
+      && self.eval_E == other.eval_E
+      && self.eval_W == other.eval_W
+      && self.eval_L == other.eval_L
+      && self.nifs_ip == other.nifs_ip
+      && self.ipa == other.ipa
+  }
+}
+
+impl<G: Group, T: TranscriptEngineTrait<G>> Eq for RelaxedR1CSSNARK<G, T> {}
+
+impl<G: Group, T: TranscriptEngineTrait<G>> RelaxedR1CSSNARKTrait<G> for RelaxedR1CSSNARK<G, T> {
   type ProverKey = ProverKey<G>;
   type VerifierKey = VerifierKey<G>;
 
@@ -77,7 +130,7 @@ impl<G: Group> RelaxedR1CSSNARKTrait<G> for RelaxedR1CSSNARK<G> {
     U: &RelaxedR1CSInstance<G>,
     W: &RelaxedR1CSWitness<G>,
   ) -> Result<Self, NovaError> {
-    let mut transcript = Transcript::new(b"RelaxedR1CSSNARK");
+    let mut transcript: T = new_transcript(&pk.gens_r1cs);
 
     debug_assert!(pk.S.is_sat_relaxed(&pk.gens_r1cs, U, W).is_ok());
 
@@ -87,7 +140,8 @@ impl<G: Group> RelaxedR1CSSNARKTrait<G> for RelaxedR1CSSNARK<G> {
     assert_eq!(pk.S.num_io.next_power_of_two(), pk.S.num_io);
     assert!(pk.S.num_io < pk.S.num_vars);
 
-    // append the R1CSShape and RelaxedR1CSInstance to the transcript
+    // append the digest of the public parameters, the R1CSShape and RelaxedR1CSInstance to the transcript
+    transcript.absorb_scalar(b"pp_digest", &pk.digest);
     pk.S.append_to_transcript(b"S", &mut transcript);
     U.append_to_transcript(b"U", &mut transcript);
 
@@ -96,12 +150,12 @@ impl<G: Group> RelaxedR1CSSNARKTrait<G> for RelaxedR1CSSNARK<G> {
 
     let (num_rounds_x, num_rounds_y) = (
       (pk.S.num_cons as f64).log2() as usize,
-      ((pk.S.num_vars as f64).log2() as usize + 1) as usize,
+      (pk.S.num_vars as f64).log2() as usize + 1,
     );
 
     // outer sum-check
     let tau = (0..num_rounds_x)
-      .map(|_i| G::Scalar::challenge(b"challenge_tau", &mut transcript))
+      .map(|_i| transcript.squeeze(b"challenge_tau"))
       .collect();
 
     let mut poly_tau = MultilinearPolynomial::new(EqPolynomial::new(tau).evals());
@@ -138,23 +192,23 @@ impl<G: Group> RelaxedR1CSSNARKTrait<G> for RelaxedR1CSSNARK<G> {
     // claims from the end of sum-check
     let (claim_Az, claim_Bz): (G::Scalar, G::Scalar) = (claims_outer[1], claims_outer[2]);
 
-    claim_Az.append_to_transcript(b"claim_Az", &mut transcript);
-    claim_Bz.append_to_transcript(b"claim_Bz", &mut transcript);
+    transcript.absorb_scalar(b"claim_Az", &claim_Az);
+    transcript.absorb_scalar(b"claim_Bz", &claim_Bz);
     let claim_Cz = poly_Cz.evaluate(&r_x);
     let eval_E = MultilinearPolynomial::new(W.E.clone()).evaluate(&r_x);
-    claim_Cz.append_to_transcript(b"claim_Cz", &mut transcript);
-    eval_E.append_to_transcript(b"eval_E", &mut transcript);
+    transcript.absorb_scalar(b"claim_Cz", &claim_Cz);
+    transcript.absorb_scalar(b"eval_E", &eval_E);
 
     // inner sum-check
-    let r_A = G::Scalar::challenge(b"challenge_rA", &mut transcript);
-    let r_B = G::Scalar::challenge(b"challenge_rB", &mut transcript);
-    let r_C = G::Scalar::challenge(b"challenge_rC", &mut transcript);
+    let r_A = transcript.squeeze(b"challenge_rA");
+    let r_B = transcript.squeeze(b"challenge_rB");
+    let r_C = transcript.squeeze(b"challenge_rC");
     let claim_inner_joint = r_A * claim_Az + r_B * claim_Bz + r_C * claim_Cz;
 
-    let poly_ABC = {
-      // compute the initial evaluation table for R(\tau, x)
-      let evals_rx = EqPolynomial::new(r_x.clone()).evals();
+    // compute the evaluation table for eq(r_x, .), which is shared by the inner sum-check and the IPA
+    let evals_rx = EqPolynomial::new(r_x.clone()).evals();
 
+    let poly_ABC = {
       // Bounds "row" variables of (A, B, C) matrices viewed as 2d multilinear polynomials
       let compute_eval_table_sparse =
         |S: &R1CSShape<G>, rx: &[G::Scalar]| -> (Vec<G::Scalar>, Vec<G::Scalar>, Vec<G::Scalar>) {
@@ -166,14 +220,14 @@ impl<G: Group> RelaxedR1CSSNARKTrait<G> for RelaxedR1CSSNARK<G> {
             }
           };
 
-          let (A_evals, (B_evals, C_evals)) = rayon::join(
+          let (A_evals, (B_evals, C_evals)) = parallel::join(
             || {
               let mut A_evals: Vec<G::Scalar> = vec![G::Scalar::zero(); 2 * S.num_vars];
               inner(&S.A, &mut A_evals);
               A_evals
             },
             || {
-              rayon::join(
+              parallel::join(
                 || {
                   let mut B_evals: Vec<G::Scalar> = vec![G::Scalar::zero(); 2 * S.num_vars];
                   inner(&S.B, &mut B_evals);
@@ -218,20 +272,25 @@ impl<G: Group> RelaxedR1CSSNARKTrait<G> for RelaxedR1CSSNARK<G> {
       &mut transcript,
     );
 
-    let eval_W = MultilinearPolynomial::new(W.W.clone()).evaluate(&r_y[1..]);
-    eval_W.append_to_transcript(b"eval_W", &mut transcript);
-
-    let (nifs_ip, r_U, r_W) = NIFSForInnerProduct::prove(
-      &InnerProductInstance::new(&U.comm_E, &EqPolynomial::new(r_x).evals(), &eval_E),
-      &InnerProductWitness::new(&W.E),
-      &InnerProductInstance::new(
-        &U.comm_W,
-        &EqPolynomial::new(r_y[1..].to_vec()).evals(),
-        &eval_W,
-      ),
-      &InnerProductWitness::new(&W.W),
-      &mut transcript,
-    );
+    let (eval_W, eval_L, nifs_ip, r_U, r_W) = if pk.S.lookup.is_some() {
+      prove_evals_with_lookup(U, W, evals_rx, &eval_E, &r_y, &mut transcript)?
+    } else {
+      let eval_W = MultilinearPolynomial::new(W.W.clone()).evaluate(&r_y[1..]);
+      transcript.absorb_scalar(b"eval_W", &eval_W);
+
+      let (nifs_ip, r_U, r_W) = NIFSForInnerProduct::prove(
+        &InnerProductInstance::new(&U.comm_E, evals_rx, &eval_E),
+        &W.E,
+        &InnerProductInstance::new(
+          &U.comm_W,
+          EqPolynomial::new(r_y[1..].to_vec()).evals(),
+          &eval_W,
+        ),
+        &W.W,
+        &mut transcript,
+      );
+      (eval_W, None, nifs_ip, r_U, r_W)
+    };
 
     let ipa = InnerProductArgument::prove(
       &pk.gens_r1cs.gens,
@@ -246,34 +305,56 @@ impl<G: Group> RelaxedR1CSSNARKTrait<G> for RelaxedR1CSSNARK<G> {
       claims_outer: (claim_Az, claim_Bz, claim_Cz),
       sc_proof_inner,
       eval_W,
+      eval_L,
       eval_E,
       nifs_ip,
       ipa,
+      _p: PhantomData,
     })
   }
 
   /// verifies a proof of satisfiability of a RelaxedR1CS instance
   fn verify(&self, vk: &Self::VerifierKey, U: &RelaxedR1CSInstance<G>) -> Result<(), NovaError> {
-    let mut transcript = Transcript::new(b"RelaxedR1CSSNARK");
+    let mut transcript: T = new_transcript(&vk.gens_r1cs);
+    let (r_U, n, _rho, _r_fold) = self.reduce_to_ipa(vk, U, &mut transcript)?;
 
-    // append the R1CSShape and RelaxedR1CSInstance to the transcript
-    vk.S.append_to_transcript(b"S", &mut transcript);
-    U.append_to_transcript(b"U", &mut transcript);
+    self
+      .ipa
+      .verify(&vk.gens_r1cs.gens, &vk.gens_ipa, n, &r_U, &mut transcript)?;
+
+    Ok(())
+  }
+}
+
+impl<G: Group, T: TranscriptEngineTrait<G>> RelaxedR1CSSNARK<G, T> {
+  /// Runs the verifier up to the inner product argument: checks the sum-checks, folds the inner
+  /// product instances for E and W, and returns the folded instance with the size of its vectors,
+  /// the challenge that combines the halves of W with a lookup argument, and the folding challenge
+  pub(crate) fn reduce_to_ipa(
+    &self,
+    vk: &VerifierKey<G>,
+    U: &RelaxedR1CSInstance<G>,
+    transcript: &mut T,
+  ) -> Result<(InnerProductInstance<G>, usize, Option<G::Scalar>, G::Scalar), NovaError> {
+    // append the digest of the public parameters, the R1CSShape and RelaxedR1CSInstance to the transcript
+    transcript.absorb_scalar(b"pp_digest", &vk.digest);
+    vk.S.append_to_transcript(b"S", transcript);
+    U.append_to_transcript(b"U", transcript);
 
     let (num_rounds_x, num_rounds_y) = (
       (vk.S.num_cons as f64).log2() as usize,
-      ((vk.S.num_vars as f64).log2() as usize + 1) as usize,
+      (vk.S.num_vars as f64).log2() as usize + 1,
     );
 
     // outer sum-check
     let tau = (0..num_rounds_x)
-      .map(|_i| G::Scalar::challenge(b"challenge_tau", &mut transcript))
+      .map(|_i| transcript.squeeze(b"challenge_tau"))
       .collect::<Vec<G::Scalar>>();
 
     let (claim_outer_final, r_x) =
       self
         .sc_proof_outer
-        .verify(G::Scalar::zero(), num_rounds_x, 3, &mut transcript)?;
+        .verify(G::Scalar::zero(), num_rounds_x, 3, transcript)?;
 
     // verify claim_outer_final
     let (claim_Az, claim_Bz, claim_Cz) = self.claims_outer;
@@ -281,34 +362,34 @@ impl<G: Group> RelaxedR1CSSNARKTrait<G> for RelaxedR1CSSNARK<G> {
     let claim_outer_final_expected =
       taus_bound_rx * (claim_Az * claim_Bz - U.u * claim_Cz - self.eval_E);
     if claim_outer_final != claim_outer_final_expected {
-      return Err(NovaError::InvalidSumcheckProof);
+      return Err(NovaError::InvalidSumcheckProof {
+        check: SumcheckFailure::FinalClaim,
+      });
     }
 
-    self
-      .claims_outer
-      .0
-      .append_to_transcript(b"claim_Az", &mut transcript);
-    self
-      .claims_outer
-      .1
-      .append_to_transcript(b"claim_Bz", &mut transcript);
-    self
-      .claims_outer
-      .2
-      .append_to_transcript(b"claim_Cz", &mut transcript);
-    self.eval_E.append_to_transcript(b"eval_E", &mut transcript);
+    transcript.absorb_scalar(b"claim_Az", &self.claims_outer.0);
+    transcript.absorb_scalar(b"claim_Bz", &self.claims_outer.1);
+    transcript.absorb_scalar(b"claim_Cz", &self.claims_outer.2);
+    transcript.absorb_scalar(b"eval_E", &self.eval_E);
 
     // inner sum-check
-    let r_A = G::Scalar::challenge(b"challenge_rA", &mut transcript);
-    let r_B = G::Scalar::challenge(b"challenge_rB", &mut transcript);
-    let r_C = G::Scalar::challenge(b"challenge_rC", &mut transcript);
+    let r_A = transcript.squeeze(b"challenge_rA");
+    let r_B = transcript.squeeze(b"challenge_rB");
+    let r_C = transcript.squeeze(b"challenge_rC");
     let claim_inner_joint =
       r_A * self.claims_outer.0 + r_B * self.claims_outer.1 + r_C * self.claims_outer.2;
 
     let (claim_inner_final, r_y) =
       self
         .sc_proof_inner
-        .verify(claim_inner_joint, num_rounds_y, 2, &mut transcript)?;
+        .verify(claim_inner_joint, num_rounds_y, 2, transcript)?;
+
+    // compute evaluation tables for eq(r_x, .) and eq(r_y[1..], .) once and share them
+    // across the evaluations of (A, B, C) and the folding of the IPA instances
+    let (evals_rx, evals_ry) = parallel::join(
+      || EqPolynomial::new(r_x.clone()).evals(),
+      || EqPolynomial::new(r_y[1..].to_vec()).evals(),
+    );
 
     // verify claim_inner_final
     let eval_Z = {
@@ -322,60 +403,547 @@ impl<G: Group> RelaxedR1CSSNARKTrait<G> for RelaxedR1CSSNARK<G> {
             .collect::<Vec<(usize, G::Scalar)>>(),
         );
         SparsePolynomial::new((vk.S.num_vars as f64).log2() as usize, poly_X)
-          .evaluate(&r_y[1..].to_vec())
+          .evaluate_with_table(&evals_ry)
       };
-      (G::Scalar::one() - r_y[0]) * self.eval_W + r_y[0] * eval_X
+      // with a lookup argument, eval_W and eval_L are the evaluations of the halves of W at r_y[2..]
+      let eval_W = match (&vk.S.lookup, &U.comm_L, &self.eval_L) {
+        (None, None, None) => self.eval_W,
+        (Some(_), Some(_), Some(eval_L)) => {
+          (G::Scalar::one() - r_y[1]) * self.eval_W + r_y[1] * eval_L
+        }
+        _ => return Err(NovaError::InvalidLookup),
+      };
+      (G::Scalar::one() - r_y[0]) * eval_W + r_y[0] * eval_X
     };
 
-    let evaluate_as_sparse_polynomial = |S: &R1CSShape<G>,
-                                         r_x: &[G::Scalar],
-                                         r_y: &[G::Scalar]|
-     -> (G::Scalar, G::Scalar, G::Scalar) {
-      let evaluate_with_table =
-        |M: &[(usize, usize, G::Scalar)], T_x: &[G::Scalar], T_y: &[G::Scalar]| -> G::Scalar {
-          (0..M.len())
-            .map(|i| {
-              let (row, col, val) = M[i];
-              T_x[row] * T_y[col] * val
-            })
-            .fold(G::Scalar::zero(), |acc, x| acc + x)
-        };
-
-      let T_x = EqPolynomial::new(r_x.to_vec()).evals();
-      let T_y = EqPolynomial::new(r_y.to_vec()).evals();
-      let eval_A_r = evaluate_with_table(&S.A, &T_x, &T_y);
-      let eval_B_r = evaluate_with_table(&S.B, &T_x, &T_y);
-      let eval_C_r = evaluate_with_table(&S.C, &T_x, &T_y);
-      (eval_A_r, eval_B_r, eval_C_r)
+    // evaluates a sparse matrix M at (r_x, r_y) in time linear in the number of its entries.
+    // Since eq(r_y, col) = (1 - r_y[0]) * eq(r_y[1..], col) for col < num_vars and
+    // eq(r_y, col) = r_y[0] * eq(r_y[1..], col - num_vars) otherwise, we accumulate the two halves
+    // separately and combine them at the end, which avoids materializing eq(r_y, .)
+    let evaluate_as_sparse_polynomial = |M: &[(usize, usize, G::Scalar)]| -> G::Scalar {
+      let num_vars = vk.S.num_vars;
+      let (eval_W, eval_X) = M.iter().fold(
+        (G::Scalar::zero(), G::Scalar::zero()),
+        |(eval_W, eval_X), (row, col, val)| {
+          if *col < num_vars {
+            (eval_W + evals_rx[*row] * evals_ry[*col] * val, eval_X)
+          } else {
+            (
+              eval_W,
+              eval_X + evals_rx[*row] * evals_ry[*col - num_vars] * val,
+            )
+          }
+        },
+      );
+      (G::Scalar::one() - r_y[0]) * eval_W + r_y[0] * eval_X
     };
 
-    let (eval_A_r, eval_B_r, eval_C_r) = evaluate_as_sparse_polynomial(&vk.S, &r_x, &r_y);
+    let (eval_A_r, (eval_B_r, eval_C_r)) = parallel::join(
+      || evaluate_as_sparse_polynomial(&vk.S.A),
+      || {
+        parallel::join(
+          || evaluate_as_sparse_polynomial(&vk.S.B),
+          || evaluate_as_sparse_polynomial(&vk.S.C),
+        )
+      },
+    );
     let claim_inner_final_expected = (r_A * eval_A_r + r_B * eval_B_r + r_C * eval_C_r) * eval_Z;
     if claim_inner_final != claim_inner_final_expected {
-      return Err(NovaError::InvalidSumcheckProof);
+      return Err(NovaError::InvalidSumcheckProof {
+        check: SumcheckFailure::FinalClaim,
+      });
     }
 
     // verify eval_W and eval_E
-    self.eval_W.append_to_transcript(b"eval_W", &mut transcript); //eval_E is already in the transcript
-
-    let r_U = self.nifs_ip.verify(
-      &InnerProductInstance::new(&U.comm_E, &EqPolynomial::new(r_x).evals(), &self.eval_E),
-      &InnerProductInstance::new(
-        &U.comm_W,
-        &EqPolynomial::new(r_y[1..].to_vec()).evals(),
-        &self.eval_W,
+    transcript.absorb_scalar(b"eval_W", &self.eval_W); //eval_E is already in the transcript
+
+    // with a lookup argument, both halves of W are opened at r_y[2..] with a random
+    // linear combination, and the instances for E and W are padded to the same length
+    let (rho, U_E, U_W, n) = match (&U.comm_L, &self.eval_L) {
+      (Some(comm_L), Some(eval_L)) => {
+        transcript.absorb_scalar(b"eval_L", eval_L);
+        let rho = transcript.squeeze(b"challenge_rho");
+        let n = max(vk.S.num_vars / 2, vk.S.num_cons);
+        (
+          Some(rho),
+          InnerProductInstance::new(&U.comm_E, pad(evals_rx, n), &self.eval_E),
+          InnerProductInstance::new(
+            &(U.comm_W + *comm_L * rho),
+            pad(EqPolynomial::new(r_y[2..].to_vec()).evals(), n),
+            &(self.eval_W + rho * eval_L),
+          ),
+          n,
+        )
+      }
+      _ => (
+        None,
+        InnerProductInstance::new(&U.comm_E, evals_rx, &self.eval_E),
+        InnerProductInstance::new(&U.comm_W, evals_ry, &self.eval_W),
+        max(vk.S.num_vars, vk.S.num_cons),
       ),
+    };
+
+    let (r_U, r_fold) = self.nifs_ip.verify(&U_E, &U_W, transcript);
+    Ok((r_U, n, rho, r_fold))
+  }
+
+  /// produces the same proof as `prove`, but never materializes eq(tau, .), Cz, or the
+  /// concatenated assignment z during the outer sum-check, and recycles the buffers of the
+  /// outer sum-check for the tables of the inner sum-check and the IPA
+  fn prove_low_memory(
+    pk: &ProverKey<G>,
+    U: &RelaxedR1CSInstance<G>,
+    W: &RelaxedR1CSWitness<G>,
+  ) -> Result<Self, NovaError> {
+    let mut transcript: T = new_transcript(&pk.gens_r1cs);
+
+    debug_assert!(pk.S.is_sat_relaxed(&pk.gens_r1cs, U, W).is_ok());
+
+    // sanity check that R1CSShape has certain size characteristics
+    assert_eq!(pk.S.num_cons.next_power_of_two(), pk.S.num_cons);
+    assert_eq!(pk.S.num_vars.next_power_of_two(), pk.S.num_vars);
+    assert_eq!(pk.S.num_io.next_power_of_two(), pk.S.num_io);
+    assert!(pk.S.num_io < pk.S.num_vars);
+
+    if W.W.len() != pk.S.num_vars {
+      return Err(NovaError::InvalidWitnessLength {
+        expected: pk.S.num_vars,
+        actual: W.W.len(),
+      });
+    }
+    if W.E.len() != pk.S.num_cons {
+      return Err(NovaError::InvalidWitnessLength {
+        expected: pk.S.num_cons,
+        actual: W.E.len(),
+      });
+    }
+    if U.X.len() != pk.S.num_io {
+      return Err(NovaError::InvalidInputLength {
+        expected: pk.S.num_io,
+        actual: U.X.len(),
+      });
+    }
+
+    // append the digest of the public parameters, the R1CSShape and RelaxedR1CSInstance to the transcript
+    transcript.absorb_scalar(b"pp_digest", &pk.digest);
+    pk.S.append_to_transcript(b"S", &mut transcript);
+    U.append_to_transcript(b"U", &mut transcript);
+
+    // reads an entry of the full satisfying assignment z = (W.W, U.u, U.X) in place
+    let num_vars = pk.S.num_vars;
+    let z = |i: usize| -> G::Scalar {
+      if i < num_vars {
+        W.W[i]
+      } else if i == num_vars {
+        U.u
+      } else {
+        U.X[i - num_vars - 1]
+      }
+    };
+
+    let (num_rounds_x, num_rounds_y) = (
+      (pk.S.num_cons as f64).log2() as usize,
+      (num_vars as f64).log2() as usize + 1,
+    );
+
+    // outer sum-check
+    let tau = (0..num_rounds_x)
+      .map(|_i| transcript.squeeze(b"challenge_tau"))
+      .collect::<Vec<G::Scalar>>();
+
+    let (mut poly_Az, mut poly_Bz, mut poly_uCz_E) = {
+      let sparse_matrix_vec_product = |M: &[(usize, usize, G::Scalar)]| -> Vec<G::Scalar> {
+        let mut Mz = vec![G::Scalar::zero(); pk.S.num_cons];
+        for (row, col, val) in M {
+          Mz[*row] += *val * z(*col);
+        }
+        Mz
+      };
+
+      let (Az, (Bz, mut uCz_E)) = parallel::join(
+        || sparse_matrix_vec_product(&pk.S.A),
+        || {
+          parallel::join(
+            || sparse_matrix_vec_product(&pk.S.B),
+            || sparse_matrix_vec_product(&pk.S.C),
+          )
+        },
+      );
+
+      // Cz is overwritten in place with u * Cz + E, so the outer sum-check holds three vectors
+      // where the standard prover holds four; Cz(r_x) is recomputed from C below
+      uCz_E
+        .par_iter_mut()
+        .zip(W.E.par_iter())
+        .for_each(|(c, e)| *c = U.u * *c + e);
+
+      (
+        MultilinearPolynomial::new(Az),
+        MultilinearPolynomial::new(Bz),
+        MultilinearPolynomial::new(uCz_E),
+      )
+    };
+
+    let comb_func_outer = |poly_B_comp: &G::Scalar,
+                           poly_C_comp: &G::Scalar,
+                           poly_D_comp: &G::Scalar|
+     -> G::Scalar { *poly_B_comp * *poly_C_comp - *poly_D_comp };
+    let (sc_proof_outer, r_x, claims_outer) = SumcheckProof::prove_cubic_with_eq_and_additive_term(
+      &G::Scalar::zero(), // claim is zero
+      num_rounds_x,
+      &tau,
+      &mut poly_Az,
+      &mut poly_Bz,
+      &mut poly_uCz_E,
+      comb_func_outer,
+      &mut transcript,
+    );
+
+    // claims from the end of sum-check
+    let (claim_Az, claim_Bz): (G::Scalar, G::Scalar) = (claims_outer[1], claims_outer[2]);
+
+    transcript.absorb_scalar(b"claim_Az", &claim_Az);
+    transcript.absorb_scalar(b"claim_Bz", &claim_Bz);
+
+    // the tables of the outer sum-check are fully bound, so their buffers are reused from here on
+    let mut evals_rx = poly_Az.into_vec();
+    EqPolynomial::new(r_x).evals_into(&mut evals_rx);
+
+    // Cz(r_x) = \sum_{(row, col, val) in C} eq(r_x, row) * val * z[col]
+    let claim_Cz = pk
+      .S
+      .C
+      .par_iter()
+      .map(|(row, col, val)| evals_rx[*row] * val * z(*col))
+      .reduce(G::Scalar::zero, |x, y| x + y);
+    let eval_E = inner_product(&W.E, &evals_rx);
+    transcript.absorb_scalar(b"claim_Cz", &claim_Cz);
+    transcript.absorb_scalar(b"eval_E", &eval_E);
+
+    // inner sum-check
+    let r_A = transcript.squeeze(b"challenge_rA");
+    let r_B = transcript.squeeze(b"challenge_rB");
+    let r_C = transcript.squeeze(b"challenge_rC");
+    let claim_inner_joint = r_A * claim_Az + r_B * claim_Bz + r_C * claim_Cz;
+
+    // bounds "row" variables of r_A * A + r_B * B + r_C * C in a single table
+    let mut poly_ABC = {
+      let mut evals = reuse_buffer(poly_Bz.into_vec(), 2 * num_vars);
+      evals.resize(2 * num_vars, G::Scalar::zero());
+      for (M, r_M) in [(&pk.S.A, r_A), (&pk.S.B, r_B), (&pk.S.C, r_C)] {
+        for (row, col, val) in M {
+          evals[*col] += r_M * evals_rx[*row] * val;
+        }
+      }
+      MultilinearPolynomial::new(evals)
+    };
+
+    let mut poly_z = {
+      let mut evals = reuse_buffer(poly_uCz_E.into_vec(), 2 * num_vars);
+      evals.extend_from_slice(&W.W);
+      evals.push(U.u);
+      evals.extend_from_slice(&U.X);
+      evals.resize(2 * num_vars, G::Scalar::zero());
+      MultilinearPolynomial::new(evals)
+    };
+
+    let comb_func = |poly_A_comp: &G::Scalar, poly_B_comp: &G::Scalar| -> G::Scalar {
+      *poly_A_comp * *poly_B_comp
+    };
+    let (sc_proof_inner, r_y, _claims_inner) = SumcheckProof::prove_quad(
+      &claim_inner_joint,
+      num_rounds_y,
+      &mut poly_ABC,
+      &mut poly_z,
+      comb_func,
       &mut transcript,
     );
+    drop(poly_z);
+
+    let (eval_W, eval_L, nifs_ip, r_U, r_W) = if pk.S.lookup.is_some() {
+      prove_evals_with_lookup(U, W, evals_rx, &eval_E, &r_y, &mut transcript)?
+    } else {
+      let mut evals_ry = poly_ABC.into_vec();
+      EqPolynomial::new(r_y[1..].to_vec()).evals_into(&mut evals_ry);
+
+      let eval_W = inner_product(&W.W, &evals_ry);
+      transcript.absorb_scalar(b"eval_W", &eval_W);
+
+      let (nifs_ip, r_U, r_W) = NIFSForInnerProduct::prove(
+        &InnerProductInstance::new(&U.comm_E, evals_rx, &eval_E),
+        &W.E,
+        &InnerProductInstance::new(&U.comm_W, evals_ry, &eval_W),
+        &W.W,
+        &mut transcript,
+      );
+      (eval_W, None, nifs_ip, r_U, r_W)
+    };
 
-    self.ipa.verify(
-      &vk.gens_r1cs.gens,
-      &vk.gens_ipa,
-      max(vk.S.num_vars, vk.S.num_cons),
+    let ipa = InnerProductArgument::prove(
+      &pk.gens_r1cs.gens,
+      &pk.gens_ipa,
       &r_U,
+      &r_W,
       &mut transcript,
     )?;
 
+    Ok(RelaxedR1CSSNARK {
+      sc_proof_outer,
+      claims_outer: (claim_Az, claim_Bz, claim_Cz),
+      sc_proof_inner,
+      eval_W,
+      eval_L,
+      eval_E,
+      nifs_ip,
+      ipa,
+      _p: PhantomData,
+    })
+  }
+}
+
+/// Empties `buf` for reuse as a vector of length `n`. A buffer that is too small is freed before
+/// a new one is allocated, rather than grown, so that the old and new buffers are never live at once
+fn reuse_buffer<F: Field>(mut buf: Vec<F>, n: usize) -> Vec<F> {
+  buf.clear();
+  if buf.capacity() < n {
+    drop(buf);
+    Vec::with_capacity(n)
+  } else {
+    buf
+  }
+}
+
+/// Pads a vector with zeros to length `n`
+fn pad<F: Field>(mut v: Vec<F>, n: usize) -> Vec<F> {
+  v.resize(n, F::zero());
+  v
+}
+
+/// With a lookup argument, the two halves of W are committed separately. The prover evaluates
+/// each of them at r_y[2..] and opens both commitments with a random linear combination.
+/// Since the halves of W may be shorter than E, the instances for E and W are padded to the same length.
+fn prove_evals_with_lookup<G: Group, T: TranscriptEngineTrait<G>>(
+  U: &RelaxedR1CSInstance<G>,
+  W: &RelaxedR1CSWitness<G>,
+  evals_rx: Vec<G::Scalar>,
+  eval_E: &G::Scalar,
+  r_y: &[G::Scalar],
+  transcript: &mut T,
+) -> Result<
+  (
+    G::Scalar,
+    Option<G::Scalar>,
+    NIFSForInnerProduct<G>,
+    InnerProductInstance<G>,
+    InnerProductWitness<G>,
+  ),
+  NovaError,
+> {
+  let comm_L = U.comm_L.ok_or(NovaError::InvalidLookup)?;
+  let (W1, W2) = W.W.split_at(W.W.len() / 2);
+  let evals_ry = EqPolynomial::new(r_y[2..].to_vec()).evals();
+  let (eval_W, eval_L) = parallel::join(
+    || inner_product(W1, &evals_ry),
+    || inner_product(W2, &evals_ry),
+  );
+  transcript.absorb_scalar(b"eval_W", &eval_W);
+  transcript.absorb_scalar(b"eval_L", &eval_L);
+
+  let rho = transcript.squeeze(b"challenge_rho");
+  let W_comb = W1
+    .par_iter()
+    .zip(W2.par_iter())
+    .map(|(w1, w2)| *w1 + rho * w2)
+    .collect::<Vec<G::Scalar>>();
+
+  let n = max(W.E.len(), W_comb.len());
+  let (nifs_ip, r_U, r_W) = NIFSForInnerProduct::prove(
+    &InnerProductInstance::new(&U.comm_E, pad(evals_rx, n), eval_E),
+    &pad(W.E.clone(), n),
+    &InnerProductInstance::new(
+      &(U.comm_W + comm_L * rho),
+      pad(evals_ry, n),
+      &(eval_W + rho * eval_L),
+    ),
+    &pad(W_comb, n),
+    transcript,
+  );
+
+  Ok((eval_W, Some(eval_L), nifs_ip, r_U, r_W))
+}
+
+/// A variant of `RelaxedR1CSSNARK` with a prover tuned for large instances (e.g., 2^22 constraints).
+/// Its prover binds variables in place, reads z from the witness rather than copying it, and reuses
+/// the buffers of the outer sum-check in the inner sum-check. For a shape with `num_vars == num_cons`,
+/// its peak allocation on top of the witness is about 5 vectors of `num_cons` scalars, against about
+/// 16 for `RelaxedR1CSSNARK`, i.e., 3.2x less (`cargo bench --bench spartan-memory` reports both).
+/// The peak is reached in the inner sum-check, whose tables have `2 * num_vars` entries. Proving
+/// takes about as long, and proofs are identical to those of `RelaxedR1CSSNARK` and are verified
+/// the same way.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct LowMemoryRelaxedR1CSSNARK<G: Group, T: TranscriptEngineTrait<G> = MerlinTranscript<G>> {
+  snark: RelaxedR1CSSNARK<G, T>,
+}
+
+impl<G: Group, T: TranscriptEngineTrait<G>> RelaxedR1CSSNARKTrait<G>
+  for LowMemoryRelaxedR1CSSNARK<G, T>
+{
+  type ProverKey = ProverKey<G>;
+  type VerifierKey = VerifierKey<G>;
+
+  /// produces a succinct proof of satisfiability of a RelaxedR1CS instance
+  fn prove(
+    pk: &Self::ProverKey,
+    U: &RelaxedR1CSInstance<G>,
+    W: &RelaxedR1CSWitness<G>,
+  ) -> Result<Self, NovaError> {
+    Ok(LowMemoryRelaxedR1CSSNARK {
+      snark: RelaxedR1CSSNARK::prove_low_memory(pk, U, W)?,
+    })
+  }
+
+  /// verifies a proof of satisfiability of a RelaxedR1CS instance
+  fn verify(&self, vk: &Self::VerifierKey, U: &RelaxedR1CSInstance<G>) -> Result<(), NovaError> {
+    self.snark.verify(vk, U)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    bellperson::{
+      r1cs::{NovaShape, NovaWitness},
+      shape_cs::ShapeCS,
+      solver::SatisfyingAssignment,
+    },
+    nifs::NIFS,
+    traits::{HashFuncConstantsTrait, HashFuncTrait},
+  };
+  use ::bellperson::{gadgets::num::AllocatedNum, ConstraintSystem, SynthesisError};
+  use ff::PrimeField;
+
+  type S = pasta_curves::pallas::Scalar;
+  type G = pasta_curves::pallas::Point;
+
+  fn synthesize_cubic<Scalar: PrimeField, CS: ConstraintSystem<Scalar>>(
+    cs: &mut CS,
+    x_val: Option<Scalar>,
+  ) -> Result<(), SynthesisError> {
+    // `x^3 + x + 5 = y`, where `x` and `y` are respectively the input and output
+    let x = AllocatedNum::alloc(cs.namespace(|| "x"), || Ok(x_val.unwrap()))?;
+    x.inputize(cs.namespace(|| "x is input"))?;
+
+    let x_sq = x.square(cs.namespace(|| "x_sq"))?;
+    let x_cu = x_sq.mul(cs.namespace(|| "x_cu"), &x)?;
+    let y = AllocatedNum::alloc(cs.namespace(|| "y"), || {
+      Ok(x_cu.get_value().unwrap() + x.get_value().unwrap() + Scalar::from(5u64))
+    })?;
+    y.inputize(cs.namespace(|| "y is output"))?;
+
+    cs.enforce(
+      || "y = x^3 + x + 5",
+      |lc| lc + x_cu.get_variable() + x.get_variable() + (Scalar::from(5u64), CS::one()),
+      |lc| lc + CS::one(),
+      |lc| lc + y.get_variable(),
+    );
+
+    // the IPA folds E with W, so we allocate unconstrained variables until the padded
+    // number of variables matches the padded number of constraints
+    for i in 0..4 {
+      AllocatedNum::alloc(cs.namespace(|| format!("filler {}", i)), || {
+        Ok(x_val.unwrap() + Scalar::from(i))
+      })?;
+    }
+
     Ok(())
   }
+
+  /// Returns the keys for the padded shape of `synthesize_cubic` and a satisfying running
+  /// instance, which folds two instances so that it has a non-trivial u and E
+  pub(super) fn folded_cubic_instance() -> (
+    ProverKey<G>,
+    VerifierKey<G>,
+    RelaxedR1CSInstance<G>,
+    RelaxedR1CSWitness<G>,
+  ) {
+    let mut cs: ShapeCS<G> = ShapeCS::new();
+    let _ = synthesize_cubic(&mut cs, None);
+    let shape = cs.r1cs_shape();
+    let gens = cs.r1cs_gens();
+    let ro_consts = <<G as Group>::HashFunc as HashFuncTrait<
+      <G as Group>::Base,
+      <G as Group>::Scalar,
+    >>::Constants::new();
+
+    let mut r_U = RelaxedR1CSInstance::default(&gens, &shape);
+    let mut r_W = RelaxedR1CSWitness::default(&shape);
+    for x in [5, 135] {
+      let mut cs: SatisfyingAssignment<G> = SatisfyingAssignment::new();
+      let _ = synthesize_cubic(&mut cs, Some(S::from(x)));
+      let (U, W) = cs.r1cs_instance_and_witness(&shape, &gens).unwrap();
+      let (_nifs, (U, W)) = NIFS::prove(
+        &gens,
+        &ro_consts,
+        &shape.get_digest(),
+        &shape,
+        &r_U,
+        &r_W,
+        &U,
+        &W,
+      )
+      .unwrap();
+      r_U = U;
+      r_W = W;
+    }
+
+    let shape_padded = shape.pad();
+    let r_W = r_W.pad(&shape_padded);
+    assert!(shape_padded.is_sat_relaxed(&gens, &r_U, &r_W).is_ok());
+
+    let pk = ProverKey::new(&gens, &shape_padded, &shape.get_digest());
+    let vk = VerifierKey::new(&gens, &shape_padded, &shape.get_digest());
+    (pk, vk, r_U, r_W)
+  }
+
+  #[test]
+  fn test_low_memory_prover() {
+    let (pk, vk, r_U, r_W) = folded_cubic_instance();
+
+    let snark = RelaxedR1CSSNARK::<G>::prove(&pk, &r_U, &r_W).unwrap();
+    let snark_low_memory = LowMemoryRelaxedR1CSSNARK::<G>::prove(&pk, &r_U, &r_W).unwrap();
+
+    // both provers produce the same proof, which verifies
+    assert_eq!(snark, snark_low_memory.snark);
+    assert!(snark_low_memory.verify(&vk, &r_U).is_ok());
+  }
+
+  #[test]
+  fn test_tag() {
+    let (pk, vk, r_U, r_W) = folded_cubic_instance();
+
+    // a proof under a tag only verifies with the generators of that tag,
+    // which the IPA generator and the transcript depend on
+    let tagged = |gens: &R1CSGens<G>, tag: &[u8]| R1CSGens {
+      gens: gens.gens.clone(),
+      tag: tag.to_vec(),
+    };
+    let pk_a = ProverKey::new(&tagged(&pk.gens_r1cs, b"app-a"), &pk.S, &pk.digest);
+    let vk_a = VerifierKey::new(&tagged(&vk.gens_r1cs, b"app-a"), &vk.S, &vk.digest);
+    let vk_b = VerifierKey::new(&tagged(&vk.gens_r1cs, b"app-b"), &vk.S, &vk.digest);
+    assert_ne!(vk_a.gens_ipa.to_bytes(), vk_b.gens_ipa.to_bytes());
+
+    let snark = RelaxedR1CSSNARK::<G>::prove(&pk_a, &r_U, &r_W).unwrap();
+    assert!(snark.verify(&vk_a, &r_U).is_ok());
+    assert!(snark.verify(&vk_b, &r_U).is_err());
+    assert!(snark.verify(&vk, &r_U).is_err());
+
+    // the IPA generator of vk_a with the transcript of vk_b is still rejected
+    let vk_b = VerifierKey {
+      gens_ipa: vk_a.gens_ipa.clone(),
+      ..vk_b
+    };
+    assert!(snark.verify(&vk_b, &r_U).is_err());
+  }
 }
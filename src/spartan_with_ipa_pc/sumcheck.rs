@@ -1,32 +1,36 @@
 #![allow(clippy::too_many_arguments)]
 #![allow(clippy::type_complexity)]
-use super::polynomial::MultilinearPolynomial;
-use crate::errors::NovaError;
-use crate::traits::{AppendToTranscriptTrait, ChallengeTrait, Group};
+use crate::errors::{NovaError, SumcheckFailure};
+use crate::parallel::prelude::*;
+use crate::polynomial::{EqPolynomial, MultilinearPolynomial};
+use crate::serde_utils::scalars;
+use crate::traits::{AppendToTranscriptTrait, Group, TranscriptEngineTrait};
 use core::marker::PhantomData;
 use ff::Field;
-use merlin::Transcript;
-use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound = "")]
 pub struct SumcheckProof<G: Group> {
-  compressed_polys: Vec<CompressedUniPoly<G>>,
+  pub(crate) compressed_polys: Vec<CompressedUniPoly<G>>,
 }
 
 impl<G: Group> SumcheckProof<G> {
-  pub fn verify(
+  pub fn verify<T: TranscriptEngineTrait<G>>(
     &self,
     claim: G::Scalar,
     num_rounds: usize,
     degree_bound: usize,
-    transcript: &mut Transcript,
+    transcript: &mut T,
   ) -> Result<(G::Scalar, Vec<G::Scalar>), NovaError> {
     let mut e = claim;
     let mut r: Vec<G::Scalar> = Vec::new();
 
     // verify that there is a univariate polynomial for each round
     if self.compressed_polys.len() != num_rounds {
-      return Err(NovaError::InvalidSumcheckProof);
+      return Err(NovaError::InvalidSumcheckProof {
+        check: SumcheckFailure::NumRounds,
+      });
     }
 
     for i in 0..self.compressed_polys.len() {
@@ -34,19 +38,23 @@ impl<G: Group> SumcheckProof<G> {
 
       // verify degree bound
       if poly.degree() != degree_bound {
-        return Err(NovaError::InvalidSumcheckProof);
+        return Err(NovaError::InvalidSumcheckProof {
+          check: SumcheckFailure::Degree { round: i },
+        });
       }
 
       // check if G_k(0) + G_k(1) = e
       if poly.eval_at_zero() + poly.eval_at_one() != e {
-        return Err(NovaError::InvalidSumcheckProof);
+        return Err(NovaError::InvalidSumcheckProof {
+          check: SumcheckFailure::RoundSum { round: i },
+        });
       }
 
       // append the prover's message to the transcript
       poly.append_to_transcript(b"poly", transcript);
 
       //derive the verifier's challenge for the next round
-      let r_i = G::Scalar::challenge(b"challenge_nextround", transcript);
+      let r_i = transcript.squeeze(b"challenge_nextround");
 
       r.push(r_i);
 
@@ -57,16 +65,17 @@ impl<G: Group> SumcheckProof<G> {
     Ok((e, r))
   }
 
-  pub fn prove_quad<F>(
+  pub fn prove_quad<F, T>(
     claim: &G::Scalar,
     num_rounds: usize,
     poly_A: &mut MultilinearPolynomial<G::Scalar>,
     poly_B: &mut MultilinearPolynomial<G::Scalar>,
     comb_func: F,
-    transcript: &mut Transcript,
+    transcript: &mut T,
   ) -> (Self, Vec<G::Scalar>, Vec<G::Scalar>)
   where
     F: Fn(&G::Scalar, &G::Scalar) -> G::Scalar + Sync,
+    T: TranscriptEngineTrait<G>,
   {
     let mut r: Vec<G::Scalar> = Vec::new();
     let mut polys: Vec<CompressedUniPoly<G>> = Vec::new();
@@ -101,7 +110,7 @@ impl<G: Group> SumcheckProof<G> {
       poly.append_to_transcript(b"poly", transcript);
 
       //derive the verifier's challenge for the next round
-      let r_i = G::Scalar::challenge(b"challenge_nextround", transcript);
+      let r_i = transcript.squeeze(b"challenge_nextround");
       r.push(r_i);
       polys.push(poly.compress());
 
@@ -122,7 +131,7 @@ impl<G: Group> SumcheckProof<G> {
     )
   }
 
-  pub fn prove_cubic_with_additive_term<F>(
+  pub fn prove_cubic_with_additive_term<F, T>(
     claim: &G::Scalar,
     num_rounds: usize,
     poly_A: &mut MultilinearPolynomial<G::Scalar>,
@@ -130,10 +139,11 @@ impl<G: Group> SumcheckProof<G> {
     poly_C: &mut MultilinearPolynomial<G::Scalar>,
     poly_D: &mut MultilinearPolynomial<G::Scalar>,
     comb_func: F,
-    transcript: &mut Transcript,
+    transcript: &mut T,
   ) -> (Self, Vec<G::Scalar>, Vec<G::Scalar>)
   where
     F: Fn(&G::Scalar, &G::Scalar, &G::Scalar, &G::Scalar) -> G::Scalar + Sync,
+    T: TranscriptEngineTrait<G>,
   {
     let mut r: Vec<G::Scalar> = Vec::new();
     let mut polys: Vec<CompressedUniPoly<G>> = Vec::new();
@@ -193,7 +203,7 @@ impl<G: Group> SumcheckProof<G> {
       poly.append_to_transcript(b"poly", transcript);
 
       //derive the verifier's challenge for the next round
-      let r_i = G::Scalar::challenge(b"challenge_nextround", transcript);
+      let r_i = transcript.squeeze(b"challenge_nextround");
       r.push(r_i);
       polys.push(poly.compress());
 
@@ -215,6 +225,104 @@ impl<G: Group> SumcheckProof<G> {
       vec![poly_A[0], poly_B[0], poly_C[0], poly_D[0]],
     )
   }
+
+  // proves \sum_x eq(tau, x) * comb_func(A(x), B(x), C(x)) = claim, producing the same proof as
+  // prove_cubic_with_additive_term with poly_A = eq(tau, .), but without materializing eq(tau, .):
+  // eq factors over the bound variables are accumulated into a scalar, the factor of the variable
+  // being bound is linear, and the remaining ones are read from O(sqrt(n))-sized split tables
+  pub fn prove_cubic_with_eq_and_additive_term<F, T>(
+    claim: &G::Scalar,
+    num_rounds: usize,
+    tau: &[G::Scalar],
+    poly_A: &mut MultilinearPolynomial<G::Scalar>,
+    poly_B: &mut MultilinearPolynomial<G::Scalar>,
+    poly_C: &mut MultilinearPolynomial<G::Scalar>,
+    comb_func: F,
+    transcript: &mut T,
+  ) -> (Self, Vec<G::Scalar>, Vec<G::Scalar>)
+  where
+    F: Fn(&G::Scalar, &G::Scalar, &G::Scalar) -> G::Scalar + Sync,
+    T: TranscriptEngineTrait<G>,
+  {
+    assert_eq!(tau.len(), num_rounds);
+    let mut r: Vec<G::Scalar> = Vec::new();
+    let mut polys: Vec<CompressedUniPoly<G>> = Vec::new();
+    let mut claim_per_round = *claim;
+    let mut eq_bound = G::Scalar::one();
+
+    for j in 0..num_rounds {
+      let poly = {
+        let len = poly_A.len() / 2;
+        let eq_rest = EqPolynomial::new(tau[j + 1..].to_vec()).split_evals();
+        assert_eq!(eq_rest.len(), len);
+
+        // sums of eq(tau[j+1..], i) * comb_func over the remaining variables,
+        // with the variable being bound set to 0, 2, and 3
+        let [sum_0, sum_2, sum_3] = eq_rest.weighted_sum(|i| {
+          let eval_point_0 = comb_func(&poly_A[i], &poly_B[i], &poly_C[i]);
+
+          let poly_A_bound_point = poly_A[len + i] + poly_A[len + i] - poly_A[i];
+          let poly_B_bound_point = poly_B[len + i] + poly_B[len + i] - poly_B[i];
+          let poly_C_bound_point = poly_C[len + i] + poly_C[len + i] - poly_C[i];
+          let eval_point_2 = comb_func(
+            &poly_A_bound_point,
+            &poly_B_bound_point,
+            &poly_C_bound_point,
+          );
+
+          let poly_A_bound_point = poly_A_bound_point + poly_A[len + i] - poly_A[i];
+          let poly_B_bound_point = poly_B_bound_point + poly_B[len + i] - poly_B[i];
+          let poly_C_bound_point = poly_C_bound_point + poly_C[len + i] - poly_C[i];
+          let eval_point_3 = comb_func(
+            &poly_A_bound_point,
+            &poly_B_bound_point,
+            &poly_C_bound_point,
+          );
+          [eval_point_0, eval_point_2, eval_point_3]
+        });
+
+        // eq(tau_j, t) = (1 - tau_j) + t * (2 * tau_j - 1)
+        let eq_j_0 = G::Scalar::one() - tau[j];
+        let eq_j_step = tau[j] + tau[j] - G::Scalar::one();
+        let eq_j_2 = eq_j_0 + eq_j_step + eq_j_step;
+        let eq_j_3 = eq_j_2 + eq_j_step;
+
+        let eval_point_0 = eq_bound * eq_j_0 * sum_0;
+        let evals = vec![
+          eval_point_0,
+          claim_per_round - eval_point_0,
+          eq_bound * eq_j_2 * sum_2,
+          eq_bound * eq_j_3 * sum_3,
+        ];
+        UniPoly::from_evals(&evals)
+      };
+
+      // append the prover's message to the transcript
+      poly.append_to_transcript(b"poly", transcript);
+
+      //derive the verifier's challenge for the next round
+      let r_i = transcript.squeeze(b"challenge_nextround");
+      r.push(r_i);
+      polys.push(poly.compress());
+
+      // Set up next round
+      claim_per_round = poly.evaluate(&r_i);
+      eq_bound *= r_i * tau[j] + (G::Scalar::one() - r_i) * (G::Scalar::one() - tau[j]);
+
+      // bound all tables to the verifier's challenege
+      poly_A.bound_poly_var_top(&r_i);
+      poly_B.bound_poly_var_top(&r_i);
+      poly_C.bound_poly_var_top(&r_i);
+    }
+
+    (
+      SumcheckProof {
+        compressed_polys: polys,
+      },
+      r,
+      vec![eq_bound, poly_A[0], poly_B[0], poly_C[0]],
+    )
+  }
 }
 
 // ax^2 + bx + c stored as vec![a,b,c]
@@ -226,9 +334,11 @@ pub struct UniPoly<G: Group> {
 
 // ax^2 + bx + c stored as vec![a,c]
 // ax^3 + bx^2 + cx + d stored as vec![a,c,d]
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound = "")]
 pub struct CompressedUniPoly<G: Group> {
-  coeffs_except_linear_term: Vec<G::Scalar>,
+  #[serde(with = "scalars")]
+  pub(crate) coeffs_except_linear_term: Vec<G::Scalar>,
   _p: PhantomData<G>,
 }
 
@@ -320,12 +430,14 @@ impl<G: Group> CompressedUniPoly<G> {
   }
 }
 
-impl<G: Group> AppendToTranscriptTrait for UniPoly<G> {
-  fn append_to_transcript(&self, label: &'static [u8], transcript: &mut Transcript) {
-    transcript.append_message(label, b"UniPoly_begin");
-    for i in 0..self.coeffs.len() {
-      self.coeffs[i].append_to_transcript(b"coeff", transcript);
-    }
-    transcript.append_message(label, b"UniPoly_end");
+impl<G: Group> AppendToTranscriptTrait<G> for UniPoly<G> {
+  fn append_to_transcript<T: TranscriptEngineTrait<G>>(
+    &self,
+    label: &'static [u8],
+    transcript: &mut T,
+  ) {
+    transcript.absorb_bytes(label, b"UniPoly_begin");
+    transcript.absorb_scalars(b"coeff", &self.coeffs);
+    transcript.absorb_bytes(label, b"UniPoly_end");
   }
 }
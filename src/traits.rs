@@ -1,10 +1,11 @@
 //! This module defines various traits required by the users of the library to implement.
+use crate::{commitments::Commitment, errors::NovaError, security::SecurityConfig};
 use bellperson::{gadgets::num::AllocatedNum, ConstraintSystem, SynthesisError};
 use core::{
   fmt::Debug,
   ops::{Add, AddAssign, Mul, MulAssign, Sub, SubAssign},
 };
-use ff::{PrimeField, PrimeFieldBits};
+use ff::{Field, PrimeField, PrimeFieldBits};
 use merlin::Transcript;
 use num_bigint::BigInt;
 
@@ -50,14 +51,34 @@ pub trait Group:
   /// Produces a preprocessed element
   fn preprocessed(&self) -> Self::PreprocessedGroupElement;
 
-  /// Produce a vector of group elements using a static label
-  fn from_label(label: &'static [u8], n: usize) -> Vec<Self::PreprocessedGroupElement>;
+  /// Compresses a preprocessed element
+  fn compress_preprocessed(p: &Self::PreprocessedGroupElement) -> Self::CompressedGroupElement;
+
+  /// Produce a vector of group elements using a label
+  fn from_label(label: &[u8], n: usize) -> Vec<Self::PreprocessedGroupElement>;
 
   /// Returns the affine coordinates (x, y, infinty) for the point
   fn to_coordinates(&self) -> (Self::Base, Self::Base, bool);
 
   /// Returns the order of the group as a big integer
   fn get_order() -> BigInt;
+
+  /// Returns `(A, B)` for the short Weierstrass equation y^2 = x^3 + A * x + B of the curve.
+  /// The default assumes A = 0, as for the pasta curves and other curves with an efficient
+  /// endomorphism, and derives B from a point of the curve; curves with A != 0 must override it.
+  fn get_curve_params() -> (Self::Base, Self::Base) {
+    curve_params_with_zero_a::<Self>()
+  }
+
+  /// Returns `(zeta, lambda)` such that mapping (x, y) to (zeta * x, y) multiplies a point by lambda
+  fn get_endomorphism() -> (Self::Base, Self::Scalar);
+}
+
+/// Returns `(0, B)` for a curve y^2 = x^3 + B, where B is derived from a point of the curve
+pub(crate) fn curve_params_with_zero_a<G: Group>() -> (G::Base, G::Base) {
+  let p = G::vartime_multiscalar_mul(&[G::Scalar::one()], &G::from_label(b"curve params", 1)[..1]);
+  let (x, y, _) = p.to_coordinates();
+  (G::Base::zero(), y.square() - x.square() * x)
 }
 
 /// Represents a compressed version of a group element
@@ -70,12 +91,45 @@ pub trait CompressedGroup: Clone + Copy + Debug + Eq + Sized + Send + Sync + 'st
 
   /// Returns a byte array representing the compressed group element
   fn as_bytes(&self) -> &[u8];
+
+  /// Reads a compressed group element from the bytes returned by `as_bytes`
+  fn from_bytes(bytes: &[u8]) -> Option<Self>;
+}
+
+/// A transcript that absorbs the messages of a public-coin protocol and derives its
+/// challenges with the Fiat-Shamir transform
+pub trait TranscriptEngineTrait<G: Group>: Send + Sync {
+  /// Initializes a transcript for the protocol with the provided label
+  fn new(label: &'static [u8]) -> Self;
+
+  /// Absorbs a message made of bytes, such as the name of a sub-protocol
+  fn absorb_bytes(&mut self, label: &'static [u8], bytes: &[u8]);
+
+  /// Absorbs a scalar
+  fn absorb_scalar(&mut self, label: &'static [u8], s: &G::Scalar);
+
+  /// Absorbs a commitment
+  fn absorb_commitment(&mut self, label: &'static [u8], comm: &Commitment<G>);
+
+  /// Derives a challenge from the messages absorbed so far
+  fn squeeze(&mut self, label: &'static [u8]) -> G::Scalar;
+
+  /// Absorbs the scalars one after the other under the same label
+  fn absorb_scalars(&mut self, label: &'static [u8], scalars: &[G::Scalar]) {
+    for s in scalars {
+      self.absorb_scalar(label, s);
+    }
+  }
 }
 
 /// A helper trait to append different types to the transcript
-pub trait AppendToTranscriptTrait {
+pub trait AppendToTranscriptTrait<G: Group> {
   /// appends the value to the transcript under the provided label
-  fn append_to_transcript(&self, label: &'static [u8], transcript: &mut Transcript);
+  fn append_to_transcript<T: TranscriptEngineTrait<G>>(
+    &self,
+    label: &'static [u8],
+    transcript: &mut T,
+  );
 }
 
 /// A helper trait to absorb different objects in RO
@@ -106,12 +160,26 @@ pub trait HashFuncTrait<Base, Scalar> {
 
   /// Returns a hash of the internal state
   fn get_hash(&self) -> Scalar;
+
+  /// Returns the security configuration of the constants of the hash function
+  fn config(&self) -> &SecurityConfig;
 }
 
 /// A helper trait that defines the constants associated with a hash function
-pub trait HashFuncConstantsTrait<Base> {
+pub trait HashFuncConstantsTrait<Base>: Sized {
   /// produces constants/parameters associated with the hash function
-  fn new() -> Self;
+  fn new() -> Self {
+    Self::new_with_config(&SecurityConfig::default())
+  }
+
+  /// produces constants/parameters associated with the hash function for `config`
+  fn new_with_config(config: &SecurityConfig) -> Self;
+
+  /// Returns the security configuration for which the constants were produced
+  fn config(&self) -> &SecurityConfig;
+
+  /// Returns the constants as bytes, which the digest of public parameters binds
+  fn to_bytes(&self) -> Vec<u8>;
 }
 
 /// A helper trait for types with a group operation.
@@ -151,18 +219,28 @@ pub trait StepCircuit<F: PrimeField>: Send + Sync + Clone {
 
   /// Execute the circuit for a computation step and return output
   fn compute(&self, z: &F) -> F;
-}
 
-impl<F: PrimeField> AppendToTranscriptTrait for F {
-  fn append_to_transcript(&self, label: &'static [u8], transcript: &mut Transcript) {
-    transcript.append_message(label, self.to_repr().as_ref());
+  /// Execute the circuit for a computation step like `compute`, but return an error instead of
+  /// panicking if the step cannot be executed, as when a witness of the step is missing.
+  /// The prover calls this method, which calls `compute` by default.
+  fn try_compute(&self, z: &F) -> Result<F, NovaError> {
+    Ok(self.compute(z))
   }
-}
 
-impl<F: PrimeField> AppendToTranscriptTrait for [F] {
-  fn append_to_transcript(&self, label: &'static [u8], transcript: &mut Transcript) {
-    for s in self {
-      s.append_to_transcript(label, transcript);
-    }
+  /// Returns the fixed table in which the step circuit looks up values, which is empty by default
+  fn lookup_table(&self) -> Vec<F> {
+    Vec::new()
+  }
+
+  /// Sythesize the circuit for a computation step like `synthesize`, and push to `lookups`
+  /// the variables that must be in the lookup table. The variables must be the same in every step,
+  /// and `setup` panics if the table is empty but variables are pushed.
+  fn synthesize_with_lookups<CS: ConstraintSystem<F>>(
+    &self,
+    cs: &mut CS,
+    z: AllocatedNum<F>,
+    _lookups: &mut Vec<AllocatedNum<F>>,
+  ) -> Result<AllocatedNum<F>, SynthesisError> {
+    self.synthesize(cs, z)
   }
 }
@@ -0,0 +1,176 @@
+//! This module implements the transcripts with which the compressed SNARK derives its challenges.
+//! `MerlinTranscript` hashes byte encodings with Keccak through Merlin. `PoseidonTranscript`
+//! absorbs field elements in a chain of Poseidon hashes over `G::Scalar`, so that a circuit over
+//! `G::Scalar` can recompute the challenges with native field operations.
+//!
+//! `PoseidonTranscript` models Poseidon as a random oracle and truncates its challenges to 128
+//! bits. It absorbs the label of the protocol and the bytes of its messages but no other
+//! labels, so its domain separation rests on the fixed order of the messages. The encoding of
+//! bytes and commitments into field elements is specific to this crate, and another
+//! implementation must reproduce it, along with the constants of neptune, to recompute the
+//! challenges. `ChallengeTrait` remains for the code paths that only use Merlin.
+use crate::{
+  commitments::{compressed_point_elements, Commitment},
+  poseidon::{PoseidonRO, ROConstantsCircuit},
+  traits::{
+    ChallengeTrait, CompressedGroup, Group, HashFuncConstantsTrait, HashFuncTrait,
+    TranscriptEngineTrait,
+  },
+};
+use core::{fmt, iter, marker::PhantomData};
+use ff::PrimeField;
+use merlin::Transcript;
+
+/// A transcript built on Merlin, whose challenges are derived with Keccak
+pub struct MerlinTranscript<G: Group> {
+  transcript: Transcript,
+  _p: PhantomData<G>,
+}
+
+impl<G: Group> fmt::Debug for MerlinTranscript<G> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str("MerlinTranscript")
+  }
+}
+
+impl<G: Group> TranscriptEngineTrait<G> for MerlinTranscript<G> {
+  fn new(label: &'static [u8]) -> Self {
+    Self {
+      transcript: Transcript::new(label),
+      _p: PhantomData,
+    }
+  }
+
+  fn absorb_bytes(&mut self, label: &'static [u8], bytes: &[u8]) {
+    self.transcript.append_message(label, bytes);
+  }
+
+  fn absorb_scalar(&mut self, label: &'static [u8], s: &G::Scalar) {
+    self.transcript.append_message(label, s.to_repr().as_ref());
+  }
+
+  fn absorb_commitment(&mut self, label: &'static [u8], comm: &Commitment<G>) {
+    self
+      .transcript
+      .append_message(label, comm.comm.compress().as_bytes());
+  }
+
+  fn squeeze(&mut self, label: &'static [u8]) -> G::Scalar {
+    G::Scalar::challenge(label, &mut self.transcript)
+  }
+}
+
+/// The number of bytes packed in a field element, which is below the capacity of the scalar
+/// fields of the supported curves
+const BYTES_PER_ELEMENT: usize = 31;
+
+/// Encodes bytes as their number followed by the bytes packed into field elements, the way
+/// `PoseidonTranscript` absorbs them
+pub(crate) fn bytes_to_elements<F: PrimeField>(bytes: &[u8]) -> Vec<F> {
+  iter::once(F::from(bytes.len() as u64))
+    .chain(bytes.chunks(BYTES_PER_ELEMENT).map(|chunk| {
+      let mut repr = F::Repr::default();
+      repr.as_mut()[..chunk.len()].copy_from_slice(chunk);
+      F::from_repr(repr).unwrap()
+    }))
+    .collect()
+}
+
+/// A transcript made of a chain of Poseidon hashes over `G::Scalar`: each challenge is the hash
+/// of the previous challenge and of the elements absorbed since. Labels of scalars, commitments
+/// and challenges are not absorbed, since the order of the messages is fixed by the protocol.
+pub struct PoseidonTranscript<G: Group> {
+  constants: ROConstantsCircuit<G::Scalar>,
+  ro: PoseidonRO<G::Scalar, G::Scalar>,
+}
+
+impl<G: Group> fmt::Debug for PoseidonTranscript<G> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str("PoseidonTranscript")
+  }
+}
+
+impl<G: Group> TranscriptEngineTrait<G> for PoseidonTranscript<G> {
+  fn new(label: &'static [u8]) -> Self {
+    let constants = ROConstantsCircuit::<G::Scalar>::new();
+    let mut transcript = Self {
+      ro: PoseidonRO::new(constants.clone()),
+      constants,
+    };
+    transcript.absorb_bytes(b"protocol-label", label);
+    transcript
+  }
+
+  /// Absorbs the number of bytes followed by the bytes packed into field elements
+  fn absorb_bytes(&mut self, _label: &'static [u8], bytes: &[u8]) {
+    for e in bytes_to_elements(bytes) {
+      self.ro.absorb(e);
+    }
+  }
+
+  fn absorb_scalar(&mut self, _label: &'static [u8], s: &G::Scalar) {
+    self.ro.absorb(*s);
+  }
+
+  /// Absorbs the commitment in compressed form, the way `NIMFSTranscript` does
+  fn absorb_commitment(&mut self, _label: &'static [u8], comm: &Commitment<G>) {
+    for e in compressed_point_elements(comm) {
+      self.ro.absorb(e);
+    }
+  }
+
+  fn squeeze(&mut self, _label: &'static [u8]) -> G::Scalar {
+    let c = self.ro.get_challenge();
+    self.ro = PoseidonRO::new(self.constants.clone());
+    self.ro.absorb(c);
+    c
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::commitments::{CommitGens, CommitTrait};
+
+  type G = pasta_curves::pallas::Point;
+  type S = pasta_curves::pallas::Scalar;
+
+  fn challenges<T: TranscriptEngineTrait<G>>(label: &'static [u8], s: S) -> (S, S) {
+    let comm = [s, S::one()].commit(&CommitGens::<G>::new(b"test", 2));
+    let mut transcript = T::new(label);
+    transcript.absorb_bytes(b"protocol-name", b"test");
+    transcript.absorb_scalar(b"s", &s);
+    transcript.absorb_commitment(b"comm", &comm);
+    let c1 = transcript.squeeze(b"c1");
+    transcript.absorb_scalars(b"v", &[s, s + S::one()]);
+    (c1, transcript.squeeze(b"c2"))
+  }
+
+  fn test_transcript_with<T: TranscriptEngineTrait<G>>() {
+    let s = S::from(7u64);
+    let (c1, c2) = challenges::<T>(b"label", s);
+    assert_ne!(c1, c2);
+    assert_eq!(challenges::<T>(b"label", s), (c1, c2));
+    assert_ne!(challenges::<T>(b"other label", s).0, c1);
+    assert_ne!(challenges::<T>(b"label", S::from(8u64)).0, c1);
+  }
+
+  #[test]
+  fn test_transcripts() {
+    test_transcript_with::<MerlinTranscript<G>>();
+    test_transcript_with::<PoseidonTranscript<G>>();
+  }
+
+  #[test]
+  fn test_merlin_transcript_matches_merlin() {
+    let s = S::from(7u64);
+    let mut transcript = Transcript::new(b"label");
+    transcript.append_message(b"protocol-name", b"test");
+    transcript.append_message(b"s", s.to_repr().as_ref());
+
+    let mut engine = <MerlinTranscript<G> as TranscriptEngineTrait<G>>::new(b"label");
+    engine.absorb_bytes(b"protocol-name", b"test");
+    engine.absorb_scalar(b"s", &s);
+    assert_eq!(engine.squeeze(b"c"), S::challenge(b"c", &mut transcript));
+  }
+}